@@ -0,0 +1,36 @@
+//! Scrub a note with the default rules and print the text and the redaction count.
+//!
+//! cargo run --example basic_scrub
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use clinical_scrubber::config::ScrubberConfig;
+use clinical_scrubber::{ScrubMode, Scrubber};
+
+const NOTE: &str = include_str!("../tests/fixtures/sample_note.txt");
+
+fn run(note: &str) -> Result<String> {
+    let scrubber = Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard)?;
+    let (text, stats) = scrubber.scrub(note, &HashSet::new());
+    Ok(format!("{}\n{} redactions\n", text, stats.total()))
+}
+
+fn main() -> Result<()> {
+    print!("{}", run(NOTE)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_the_sample_note() {
+        let output = run(NOTE).expect("run");
+        assert!(output.starts_with("Patient [PERSON] [PERSON] ([MRN]) was seen on [DATE]."));
+        assert!(output.contains("Call [PHONE] or email [EMAIL] with results."));
+        assert!(!output.contains("maria.lopez"));
+        assert!(output.ends_with("8 redactions\n"));
+    }
+}
@@ -0,0 +1,60 @@
+//! Scrub many documents on several threads with one shared `Scrubber`, keeping input order.
+//!
+//! cargo run --example batch_parallel
+
+use std::collections::HashSet;
+use std::thread;
+
+use anyhow::Result;
+use clinical_scrubber::config::ScrubberConfig;
+use clinical_scrubber::{ScrubMode, Scrubber};
+
+const NOTE: &str = include_str!("../tests/fixtures/sample_note.txt");
+const THREADS: usize = 4;
+
+fn run(documents: &[&str]) -> Result<Vec<String>> {
+    let scrubber = Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard)?;
+    let skip = HashSet::new();
+    let chunk = documents.len().div_ceil(THREADS).max(1);
+    let scrubbed = thread::scope(|scope| {
+        let workers: Vec<_> = documents
+            .chunks(chunk)
+            .map(|batch| {
+                let (scrubber, skip) = (&scrubber, &skip);
+                scope.spawn(move || {
+                    batch
+                        .iter()
+                        .map(|document| scrubber.scrub(document, skip).0)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("worker thread"))
+            .collect()
+    });
+    Ok(scrubbed)
+}
+
+fn main() -> Result<()> {
+    let documents: Vec<&str> = NOTE.lines().collect();
+    for (index, text) in run(&documents)?.iter().enumerate() {
+        println!("{}: {}", index + 1, text);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_document_order() {
+        let documents: Vec<&str> = NOTE.lines().cycle().take(30).collect();
+        let scrubbed = run(&documents).expect("run");
+        assert_eq!(scrubbed.len(), 30);
+        assert_eq!(scrubbed[1], "Call [PHONE] or email [EMAIL] with results.");
+        assert_eq!(scrubbed[28], scrubbed[1]);
+    }
+}
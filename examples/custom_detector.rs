@@ -0,0 +1,54 @@
+//! Add a site-specific identifier as a custom category, give it its own token, and skip a
+//! built-in category.
+//!
+//! cargo run --example custom_detector
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use clinical_scrubber::config::{CustomPattern, ScrubberConfig};
+use clinical_scrubber::{Category, CategoryKey, ScrubMode, Scrubber};
+
+const NOTE: &str = include_str!("../tests/fixtures/sample_note.txt");
+
+fn run(note: &str) -> Result<String> {
+    let encounter: CategoryKey = "custom:encounter".parse().map_err(anyhow::Error::msg)?;
+    let mut config = ScrubberConfig {
+        custom_patterns: vec![CustomPattern {
+            name: "encounter".to_string(),
+            pattern: r"\bENC-\d{4}-\d{5}\b".to_string(),
+            token: None,
+            case_insensitive: false,
+        }],
+        ..Default::default()
+    };
+    config.tokens.insert(encounter.clone(), "[ENC]".to_string());
+    let scrubber = Scrubber::new(config, ScrubMode::Standard)?;
+
+    let skip = HashSet::from([CategoryKey::from(Category::Date)]);
+    let (text, stats) = scrubber.scrub(note, &skip);
+    Ok(format!(
+        "{}\n{}: {}\n",
+        text,
+        encounter,
+        stats.count_key(&encounter)
+    ))
+}
+
+fn main() -> Result<()> {
+    print!("{}", run(NOTE)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_encounters_and_keeps_dates() {
+        let output = run(NOTE).expect("run");
+        assert!(output.contains("[ENC] was closed by [PERSON]."));
+        assert!(output.contains("was seen on 03/14/2024."));
+        assert!(output.ends_with("custom:encounter: 1\n"));
+    }
+}
@@ -0,0 +1,48 @@
+//! Print one JSON line per redaction, with its category and its byte offsets in the input and
+//! the output, in the `--spans-out` format.
+//!
+//! cargo run --example spans_to_json
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use clinical_scrubber::config::ScrubberConfig;
+use clinical_scrubber::report::{render_span_json, SchemaVersion};
+use clinical_scrubber::{ScrubMode, Scrubber};
+
+const NOTE: &str = include_str!("../tests/fixtures/sample_note.txt");
+
+fn run(note: &str) -> Result<String> {
+    let scrubber = Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard)?;
+    let result = scrubber.scrub_detailed(note, &HashSet::new());
+    let mut out = String::new();
+    for span in &result.spans {
+        out.push_str(&render_span_json(span, SchemaVersion::V2)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn main() -> Result<()> {
+    print!("{}", run(NOTE)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emits_one_line_per_redaction() {
+        let output = run(NOTE).expect("run");
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("json line"))
+            .collect();
+        assert_eq!(lines.len(), 8);
+        let mrn = &lines[2];
+        assert_eq!(mrn["category"], "mrn");
+        let (start, end) = (mrn["start"].as_u64().unwrap(), mrn["end"].as_u64().unwrap());
+        assert_eq!(&NOTE[start as usize..end as usize], "MRN 4829130");
+    }
+}
@@ -0,0 +1,414 @@
+//! Offset alignment between the original input and the scrubbed output.
+//!
+//! Every stage that edits text (normalization, redaction, punctuation cleanup) goes through a
+//! `Rewriter`, which records how byte ranges of its input map onto its output. The per-stage
+//! maps are composed so callers can project spans from the original text onto the output.
+
+use std::ops::Range;
+
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SegmentKind {
+    /// Copied unchanged; offsets inside map one-to-one.
+    Identity,
+    /// Normalized or tidied (quotes, whitespace, punctuation); maps as a whole.
+    Rewritten,
+    /// Replaced by a redaction token.
+    Redacted,
+}
+
+/// A byte range of the original text and the output range it became.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct AlignedSegment {
+    pub original: Range<usize>,
+    pub output: Range<usize>,
+    pub kind: SegmentKind,
+}
+
+/// Ordered segments that cover both the original text and the output without gaps.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct Alignment {
+    segments: Vec<AlignedSegment>,
+}
+
+impl Alignment {
+    pub fn segments(&self) -> &[AlignedSegment] {
+        &self.segments
+    }
+
+    /// Maps a byte range of the original text onto the output. Returns `None` when the range
+    /// overlaps a redaction or lies outside the original text. Rewritten segments (normalized
+    /// punctuation or whitespace) are mapped as a whole.
+    pub fn project_span(&self, original: Range<usize>) -> Option<Range<usize>> {
+        if original.start > original.end {
+            return None;
+        }
+        let total = self.segments.last().map(|s| s.original.end).unwrap_or(0);
+        if original.end > total {
+            return None;
+        }
+        if self
+            .segments
+            .iter()
+            .filter(|s| s.original.start < original.end && original.start < s.original.end)
+            .any(|s| s.kind == SegmentKind::Redacted)
+        {
+            return None;
+        }
+        let start = self.project_offset(original.start, false)?;
+        let end = if original.is_empty() {
+            start
+        } else {
+            self.project_offset(original.end, true)?
+        };
+        Some(start..end.max(start))
+    }
+
+    /// Marks the redacted segments inside `output` as no longer redacted once `original` has
+    /// been written back there. A segment mapping exactly `original` onto `output` becomes
+    /// identity again; one merged with neighbouring rewrites stays mapped as a whole.
+    pub(crate) fn unredact(&mut self, original: Range<usize>, output: Range<usize>) {
+        for segment in &mut self.segments {
+            if segment.kind != SegmentKind::Redacted
+                || segment.output.start < output.start
+                || segment.output.end > output.end
+            {
+                continue;
+            }
+            segment.kind = if segment.original == original && output.len() == original.len() {
+                SegmentKind::Identity
+            } else {
+                SegmentKind::Rewritten
+            };
+        }
+    }
+
+    /// Maps a byte range of the output back onto the original text. Identity segments map
+    /// exactly; a range that touches any other segment widens to cover all of it.
+    pub(crate) fn original_range(&self, output: Range<usize>) -> Range<usize> {
+        let start = self
+            .segments
+            .iter()
+            .find(|s| s.output.start <= output.start && output.start < s.output.end)
+            .map_or(output.start, |s| match s.kind {
+                SegmentKind::Identity => s.original.start + (output.start - s.output.start),
+                _ => s.original.start,
+            });
+        let end = self
+            .segments
+            .iter()
+            .find(|s| s.output.start < output.end && output.end <= s.output.end)
+            .map_or(output.end, |s| match s.kind {
+                SegmentKind::Identity => s.original.start + (output.end - s.output.start),
+                _ => s.original.end,
+            });
+        start..end.max(start)
+    }
+
+    /// Projects a single offset. `is_end` picks the segment ending at `offset` rather than the
+    /// one starting there, so range ends stay inside the span being projected.
+    fn project_offset(&self, offset: usize, is_end: bool) -> Option<usize> {
+        let segment = self.segments.iter().find(|s| {
+            if is_end {
+                s.original.start < offset && offset <= s.original.end
+            } else {
+                s.original.start <= offset && offset < s.original.end
+            }
+        });
+        let Some(segment) = segment else {
+            // Offsets at the very end (or in an empty text) map to the end of the output.
+            return Some(self.segments.last().map(|s| s.output.end).unwrap_or(0));
+        };
+        Some(match segment.kind {
+            SegmentKind::Identity => segment.output.start + (offset - segment.original.start),
+            _ if is_end => segment.output.end,
+            _ => segment.output.start,
+        })
+    }
+
+    /// Chains `self` (A to B) with `next` (B to C) into a single A to C alignment. Walks the
+    /// shared middle text in intervals where both sides have a single segment; overlapping
+    /// non-identity segments from either side are merged into one output segment.
+    pub(crate) fn then(&self, next: &Alignment) -> Alignment {
+        let (first, second) = (&self.segments, &next.segments);
+        let mut out = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        let mut mid = 0;
+        let mut group: Option<(usize, usize, SegmentKind)> = None;
+
+        loop {
+            // Segments that are empty in the middle text sit at a single point.
+            if let Some(a) = first.get(i).filter(|a| a.output.is_empty()) {
+                match &mut group {
+                    Some((_, _, kind)) => *kind = merge_kind(*kind, a.kind),
+                    None => {
+                        let z = second.get(j).map(|b| output_at(b, mid)).unwrap_or(0);
+                        push(&mut out, a.original.clone(), z..z, a.kind);
+                    }
+                }
+                i += 1;
+                continue;
+            }
+            if let Some(b) = second.get(j).filter(|b| b.original.is_empty()) {
+                match &mut group {
+                    Some((_, _, kind)) => *kind = merge_kind(*kind, b.kind),
+                    None => {
+                        let x = first.get(i).map(|a| original_at(a, mid)).unwrap_or(0);
+                        push(&mut out, x..x, b.output.clone(), b.kind);
+                    }
+                }
+                j += 1;
+                continue;
+            }
+            let (Some(a), Some(b)) = (first.get(i), second.get(j)) else {
+                break;
+            };
+
+            let end = a.output.end.min(b.original.end);
+            if a.kind == SegmentKind::Identity && b.kind == SegmentKind::Identity {
+                let x = original_at(a, mid);
+                let z = output_at(b, mid);
+                let len = end - mid;
+                push(&mut out, x..x + len, z..z + len, SegmentKind::Identity);
+            } else {
+                let (_, _, kind) = group.get_or_insert((
+                    original_at(a, mid),
+                    output_at(b, mid),
+                    SegmentKind::Identity,
+                ));
+                *kind = merge_kind(*kind, merge_kind(a.kind, b.kind));
+            }
+            mid = end;
+
+            let a_done = a.output.end == mid;
+            let b_done = b.original.end == mid;
+            let a_open = a.kind != SegmentKind::Identity && !a_done;
+            let b_open = b.kind != SegmentKind::Identity && !b_done;
+            if !a_open && !b_open {
+                if let Some((x_start, z_start, kind)) = group.take() {
+                    let x_end = original_end_at(a, mid);
+                    let z_end = output_end_at(b, mid);
+                    push(&mut out, x_start..x_end, z_start..z_end, kind);
+                }
+            }
+            if a_done {
+                i += 1;
+            }
+            if b_done {
+                j += 1;
+            }
+        }
+        Alignment { segments: out }
+    }
+}
+
+fn merge_kind(a: SegmentKind, b: SegmentKind) -> SegmentKind {
+    match (a, b) {
+        (SegmentKind::Redacted, _) | (_, SegmentKind::Redacted) => SegmentKind::Redacted,
+        (SegmentKind::Identity, SegmentKind::Identity) => SegmentKind::Identity,
+        _ => SegmentKind::Rewritten,
+    }
+}
+
+/// Original offset for middle offset `mid`, taken as the start of a range.
+fn original_at(a: &AlignedSegment, mid: usize) -> usize {
+    match a.kind {
+        SegmentKind::Identity => a.original.start + (mid - a.output.start),
+        _ => a.original.start,
+    }
+}
+
+/// Original offset for middle offset `mid`, taken as the end of a range.
+fn original_end_at(a: &AlignedSegment, mid: usize) -> usize {
+    match a.kind {
+        SegmentKind::Identity => a.original.start + (mid - a.output.start),
+        _ => a.original.end,
+    }
+}
+
+/// Output offset for middle offset `mid`, taken as the start of a range.
+fn output_at(b: &AlignedSegment, mid: usize) -> usize {
+    match b.kind {
+        SegmentKind::Identity => b.output.start + (mid - b.original.start),
+        _ => b.output.start,
+    }
+}
+
+/// Output offset for middle offset `mid`, taken as the end of a range.
+fn output_end_at(b: &AlignedSegment, mid: usize) -> usize {
+    match b.kind {
+        SegmentKind::Identity => b.output.start + (mid - b.original.start),
+        _ => b.output.end,
+    }
+}
+
+fn push(
+    out: &mut Vec<AlignedSegment>,
+    original: Range<usize>,
+    output: Range<usize>,
+    kind: SegmentKind,
+) {
+    if original.is_empty() && output.is_empty() {
+        return;
+    }
+    if let Some(last) = out.last_mut() {
+        if last.kind == SegmentKind::Identity
+            && kind == SegmentKind::Identity
+            && last.original.end == original.start
+            && last.output.end == output.start
+        {
+            last.original.end = original.end;
+            last.output.end = output.end;
+            return;
+        }
+    }
+    out.push(AlignedSegment {
+        original,
+        output,
+        kind,
+    });
+}
+
+/// Builds an output string from a source string while recording the alignment between them.
+/// Edits must be applied in increasing source order.
+pub(crate) struct Rewriter<'a> {
+    source: &'a str,
+    output: String,
+    cursor: usize,
+    segments: Vec<AlignedSegment>,
+}
+
+impl<'a> Rewriter<'a> {
+    pub(crate) fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            output: String::with_capacity(source.len()),
+            cursor: 0,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Copies source text up to `end` unchanged.
+    pub(crate) fn copy_to(&mut self, end: usize) {
+        if end <= self.cursor {
+            return;
+        }
+        let start = self.output.len();
+        self.output.push_str(&self.source[self.cursor..end]);
+        push(
+            &mut self.segments,
+            self.cursor..end,
+            start..self.output.len(),
+            SegmentKind::Identity,
+        );
+        self.cursor = end;
+    }
+
+    /// Replaces `source[start..end]` with `text`. Text between the previous edit and `start`
+    /// is copied first. Replacing a range with identical text records it as unchanged.
+    pub(crate) fn replace(&mut self, start: usize, end: usize, text: &str, kind: SegmentKind) {
+        self.copy_to(start);
+        if &self.source[start..end] == text {
+            self.copy_to(end);
+            return;
+        }
+        let out_start = self.output.len();
+        self.output.push_str(text);
+        push(
+            &mut self.segments,
+            start..end,
+            out_start..self.output.len(),
+            kind,
+        );
+        self.cursor = end;
+    }
+
+    /// Length of the output built so far.
+    pub(crate) fn output_len(&self) -> usize {
+        self.output.len()
+    }
+
+    pub(crate) fn finish(mut self) -> (String, Alignment) {
+        self.copy_to(self.source.len());
+        (
+            self.output,
+            Alignment {
+                segments: self.segments,
+            },
+        )
+    }
+}
+
+/// Regex replacement that records its alignment; every replaced match is `Rewritten`.
+pub(crate) fn rewrite_all<F>(text: &str, regex: &Regex, mut replacement: F) -> (String, Alignment)
+where
+    F: FnMut(&Captures) -> String,
+{
+    let mut rewriter = Rewriter::new(text);
+    for caps in regex.captures_iter(text) {
+        let whole = caps.get(0).expect("whole match");
+        let replaced = replacement(&caps);
+        rewriter.replace(
+            whole.start(),
+            whole.end(),
+            &replaced,
+            SegmentKind::Rewritten,
+        );
+    }
+    rewriter.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(text: &str, start: usize, end: usize, token: &str) -> (String, Alignment) {
+        let mut rewriter = Rewriter::new(text);
+        rewriter.replace(start, end, token, SegmentKind::Redacted);
+        rewriter.finish()
+    }
+
+    #[test]
+    fn projects_spans_around_a_redaction() {
+        let text = "Pain in knee, called 555-111-2222 today.";
+        let (output, alignment) = redact(text, 21, 33, "[PHONE]");
+        assert_eq!(output, "Pain in knee, called [PHONE] today.");
+
+        // Before the redaction.
+        let before = alignment.project_span(8..12).expect("before");
+        assert_eq!(&output[before], "knee");
+        // Inside the redaction.
+        assert_eq!(alignment.project_span(25..28), None);
+        // After the redaction.
+        let after = alignment.project_span(34..39).expect("after");
+        assert_eq!(&output[after], "today");
+    }
+
+    #[test]
+    fn composes_rewrites_across_stages() {
+        let text = "Seen  by Dr. X on 3/4 .";
+        let spaces = Regex::new(r" {2,}").unwrap();
+        let (first, a) = rewrite_all(text, &spaces, |_| " ".to_string());
+        assert_eq!(first, "Seen by Dr. X on 3/4 .");
+        let (second, b) = redact(&first, 8, 13, "[PERSON]");
+        assert_eq!(second, "Seen by [PERSON] on 3/4 .");
+        let trailing = Regex::new(r" \.").unwrap();
+        let (third, c) = rewrite_all(&second, &trailing, |_| ".".to_string());
+        assert_eq!(third, "Seen by [PERSON] on 3/4.");
+
+        let alignment = a.then(&b).then(&c);
+        let on = alignment.project_span(15..17).expect("on");
+        assert_eq!(&third[on], "on");
+        assert_eq!(alignment.project_span(9..13), None);
+        let seen = alignment.project_span(0..4).expect("seen");
+        assert_eq!(&third[seen], "Seen");
+        let date = alignment.project_span(18..21).expect("date");
+        assert_eq!(&third[date], "3/4");
+        let last = alignment.segments().last().expect("segment");
+        assert_eq!(last.original.end, text.len());
+        assert_eq!(last.output.end, third.len());
+    }
+}
@@ -0,0 +1,93 @@
+//! The restricted audit log (`--audit-log`): one JSON line per redaction with the original
+//! text, for privacy-office spot checks.
+//!
+//! This is the only output that deliberately carries PHI. It is written only to a named file,
+//! never to stdout or stderr, and on Unix the file is created readable by its owner alone.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+
+use crate::report::{SchemaVersion, Versioned};
+use crate::spans::RedactionSpan;
+use crate::CategoryKey;
+
+/// One line of the audit log. Offsets are byte offsets into the input.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct AuditRecord<'a> {
+    pub source: &'a str,
+    pub category: &'a CategoryKey,
+    pub start: usize,
+    pub end: usize,
+    pub original: &'a str,
+    /// The entity number in a numbered token such as `[PERSON_2]`; together with `original`
+    /// this is the only place the number-to-text mapping is written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+/// Writes one record per span, each tagged with `version`, replacing any earlier log at
+/// `path`. `source` names the input file ("-" for stdin). Refuses "-" as the path, so the log
+/// cannot end up on stdout. `spans` must be every redaction, so scrub with
+/// `ScrubOptions::all_spans`.
+pub fn write_audit_log(
+    path: &Path,
+    source: &str,
+    input: &str,
+    spans: &[RedactionSpan],
+    version: SchemaVersion,
+) -> Result<()> {
+    if path == Path::new("-") {
+        return Err(anyhow!(
+            "--audit-log needs a file path; it is never written to stdout"
+        ));
+    }
+    let file = create_restricted(path)
+        .with_context(|| format!("failed to create audit log: {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    for span in spans {
+        let record = AuditRecord {
+            source,
+            category: &span.category,
+            start: span.original.start,
+            end: span.original.end,
+            original: &input[span.original.clone()],
+            index: span.index,
+        };
+        serde_json::to_writer(&mut writer, &Versioned::new(version, &record))
+            .map_err(std::io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"))
+            .with_context(|| format!("failed to write audit log: {}", path.display()))?;
+    }
+    writer
+        .flush()
+        .with_context(|| format!("failed to write audit log: {}", path.display()))
+}
+
+/// Creates (or truncates) `path` with owner-only permissions. An existing file is narrowed to
+/// 0600 too, since `mode` only applies to newly created files.
+#[cfg(unix)]
+fn create_restricted(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn create_restricted(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+}
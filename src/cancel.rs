@@ -0,0 +1,41 @@
+//! Cooperative cancellation for long scrubs.
+//!
+//! `Scrubber::scrub_cancellable` checks the token between detector passes and gives up with
+//! `Cancelled` instead of returning a partially redacted text. The token is cheap to clone, so
+//! a signal handler or another thread can hold one while the scrub runs.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every holder of this token to stop at its next check.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Returned when a scrub stopped because its token was cancelled. No output is produced.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("scrub cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
@@ -1,7 +1,22 @@
-use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::describe::Confidence;
+use crate::profiles::Regulation;
+use crate::CategoryKey;
+
+/// MRN digit counts detected when the config does not set `mrn_min_length`/`mrn_max_length`.
+pub const DEFAULT_MRN_MIN_LENGTH: usize = 6;
+pub const DEFAULT_MRN_MAX_LENGTH: usize = 10;
 
 /// Options that control how the scrubber behaves. Values are merged with sensible defaults.
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct ScrubberConfig {
     /// Additional person names to scrub (case-insensitive).
     #[serde(default)]
@@ -9,10 +24,876 @@ pub struct ScrubberConfig {
     /// Additional keywords or facility names to scrub (case-insensitive).
     #[serde(default)]
     pub keywords: Vec<String>,
+    /// Categories skipped on every run, as if given to `--skip`, e.g. `["relative-date",
+    /// "zip"]`. `--unskip` re-enables one for a single run.
+    #[serde(default)]
+    pub skip: Vec<CategoryKey>,
+    /// Extra terms and phrases that are never redacted as person names, e.g. "COPD
+    /// Exacerbation". Unlike `allowlist` they only stop person candidates.
+    #[serde(default)]
+    pub stoplist: Vec<String>,
+    /// Replaces the built-in name stoplist instead of adding to it; `stoplist` still adds.
+    #[serde(default)]
+    pub stoplist_replace: Option<Vec<String>>,
+    /// Extra titles that mark the next one or two capitalized words as a name, e.g. "Sheikh" or
+    /// "Charge Nurse". A trailing "." is optional in the text ("Cpl." also matches "Cpl").
+    #[serde(default)]
+    pub titles: Vec<String>,
+    /// Replaces the built-in titles (Dr., Mr., Rev., Father, ...) instead of adding to them;
+    /// `titles` still adds.
+    #[serde(default)]
+    pub titles_replace: Option<Vec<String>>,
+    /// Files of extra person names, one per line (`#` comments and blank lines skipped), added
+    /// to `names`. Relative paths are read from the config file's directory.
+    #[serde(default)]
+    pub names_files: Vec<PathBuf>,
+    /// Files of extra keywords, in the same format as `names_files`, added to `keywords`.
+    #[serde(default)]
+    pub keywords_files: Vec<PathBuf>,
+    /// Compiled size limit for each dictionary pattern, in MiB (default: 10). Raise it for
+    /// dictionaries of tens of thousands of entries.
+    #[serde(default)]
+    pub regex_size_limit_mb: Option<usize>,
+    /// Employer and school names to scrub (case-insensitive).
+    #[serde(default)]
+    pub employers: Vec<String>,
+    /// City, county and neighborhood names to scrub (case-insensitive).
+    #[serde(default)]
+    pub geo_places: Vec<String>,
+    /// Also scrub US state and territory names. Safe Harbor allows keeping them, so they are
+    /// kept by default.
+    #[serde(default)]
+    pub redact_states: bool,
+    /// Extra labels that introduce an insurance ID under Safe Harbor, e.g. "Medicaid". The
+    /// built-in labels (Member, Policy, Plan, Group, Subscriber, Payer, BCBS, Insurance) always
+    /// apply.
+    #[serde(default)]
+    pub insurance_labels: Vec<String>,
+    /// Extra labels that introduce a passport or immigration number under Safe Harbor, e.g.
+    /// "Green Card". The built-in labels (Passport, Visa, Alien Registration, A-Number) always
+    /// apply.
+    #[serde(default)]
+    pub govt_id_labels: Vec<String>,
+    /// Extra labels that introduce a certificate or legal document number under Safe Harbor,
+    /// e.g. "Guardianship No". The built-in labels (Case No, Case Number, Case #, Cert,
+    /// Certificate, Docket) always apply.
+    #[serde(default)]
+    pub certificate_labels: Vec<String>,
+    /// Extra labels that introduce a medical record number, e.g. "CSN", "FIN", "Enc #". The
+    /// built-in labels (MRN, Patient ID, Chart) always apply.
+    #[serde(default)]
+    pub mrn_labels: Vec<String>,
+    /// Regex for the identifier after an MRN label, e.g. `[A-Z]\d{6,}` to require a letter
+    /// prefix. Labels match regardless of case; this pattern does not, unless it says `(?i)`.
+    /// Defaults to four or more letters, digits, and dashes. A match without a digit is never
+    /// taken as an ID, so "MRN pending" stays.
+    #[serde(default)]
+    pub mrn_pattern: Option<String>,
+    /// Extra phrases redacted as relative dates, e.g. "the other day" or "a few weeks back".
+    /// Matched in any case; inner spaces match any whitespace.
+    #[serde(default)]
+    pub relative_date_phrases: Vec<String>,
     /// Overrides the minimum length for MRN detection (default: 6).
     #[serde(default)]
     pub mrn_min_length: Option<usize>,
     /// Overrides the maximum length for MRN detection (default: 10).
     #[serde(default)]
     pub mrn_max_length: Option<usize>,
+    /// Tunes detection for short standalone values such as form fields: lone first names and
+    /// month/day dates are redacted. Trades precision for recall on short strings.
+    #[serde(default)]
+    pub field_mode: bool,
+    /// Output line breaks as the tidying passes leave them: a final newline is dropped and a
+    /// line break before punctuation is joined. Off by default, so the output keeps the
+    /// input's final newline (`\n` or `\r\n`) and its line breaks.
+    #[serde(default)]
+    pub normalize_newlines: bool,
+    /// Date (YYYY-MM-DD) that ages are computed against. Defaults to today; set it for
+    /// reproducible output.
+    #[serde(default)]
+    pub reference_date: Option<String>,
+    /// How full dates ("03/14/2021", "Jan 5, 2020") are rendered. Defaults to the regulation's
+    /// mode, or `redact`.
+    #[serde(default)]
+    pub date_mode: Option<DateMode>,
+    /// How numeric dates such as "03/04/2021" are read when `date_mode` or `date_shift` needs
+    /// the date's value: `mdy`, `dmy`, or `auto` (the default), which reads a first number over
+    /// 12 as the day and is otherwise month first.
+    #[serde(default)]
+    pub date_order: DateOrder,
+    /// Shift every full date by the same number of days instead of redacting it; takes
+    /// precedence over `date_mode`. `--seed` sets or overrides the seed.
+    #[serde(default)]
+    pub date_shift: Option<DateShift>,
+    /// Seed for everything randomized, currently the `date_shift` offset when `date_shift`
+    /// gives no seed of its own. Unset, each scrubber draws one from OS entropy and reports it
+    /// as `seed` in the stats, so a run can be repeated.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// How cued birth years ("born in 1931") of patients over 89 are rendered. Birth years of
+    /// younger patients are left as-is.
+    #[serde(default)]
+    pub birth_year_mode: BirthYearMode,
+    /// How ages over 89 are rendered. Setting it also redacts them outside Safe Harbor mode.
+    #[serde(default)]
+    pub age_mode: Option<AgeMode>,
+    /// How MRNs embedded in composite identifiers ("SPEC-2024-4829130-01") are redacted.
+    #[serde(default)]
+    pub composite_ids: CompositeIdMode,
+    /// Regulatory profile that selects categories and rendering modes; other settings here
+    /// override it. Also accepted as `profile`.
+    #[serde(default, alias = "profile")]
+    pub regulation: Option<Regulation>,
+    /// How ZIP codes are rendered. Defaults to the regulation's mode, or `redact`.
+    #[serde(default)]
+    pub zip_mode: Option<ZipMode>,
+    /// How lat/long pairs are rendered: `"redact"` (default) or `{"round": {"places": 1}}` to
+    /// keep each component to that many decimal places.
+    #[serde(default)]
+    pub coordinate_mode: CoordinateMode,
+    /// Accept OCR look-alikes (O/o for 0, l/I for 1, S for 5) in values right after an SSN,
+    /// MRN, DOB, or phone label, when the corrected value fits the expected format. Never applied
+    /// to unlabeled text.
+    #[serde(default)]
+    pub ocr_tolerant: bool,
+    /// Rules below this confidence tier (`low`, `medium`, `high`) do not run. Defaults to
+    /// `low`, so every rule runs; `--min-confidence` overrides it.
+    #[serde(default)]
+    pub min_confidence: Confidence,
+    /// How three-digit ZIP prefixes in phrases like "the 606 area" or "area code 606" are
+    /// rendered.
+    #[serde(default)]
+    pub area_mode: AreaMode,
+    /// Which person matchers run: `strict` (name dictionary and titles), `balanced` (adds
+    /// first name + surname, relative and first-name cues), or `aggressive` (adds any run of
+    /// capitalized words; the default). `--person-mode` overrides it.
+    #[serde(default)]
+    pub person_mode: PersonMode,
+    /// How much context a bare first name in narrative text ("spoke with Kevin") needs before
+    /// it is redacted.
+    #[serde(default)]
+    pub first_name_alone: FirstNameMode,
+    /// Redactions per document that get a span record (default: 10000). Redactions past the
+    /// cap are still applied and counted; the stats report how many records were dropped.
+    #[serde(default)]
+    pub max_spans: Option<usize>,
+    /// Files that replace the built-in word lists wholesale.
+    #[serde(default)]
+    pub dictionaries: DictionaryPaths,
+    /// Site-specific identifiers, each redacted as its own `custom:<name>` category. They run
+    /// after the built-in detectors: a match replaces built-in redactions inside it, but not one
+    /// covering the whole match, such as a phone number or date.
+    #[serde(default)]
+    pub custom_patterns: Vec<CustomPattern>,
+    /// Replace a whole line or sentence with `[REDACTED_SEGMENT]` when token-level redaction
+    /// looks hopeless there, e.g. a pasted contact list.
+    #[serde(default)]
+    pub segment_fallback: Option<SegmentFallback>,
+    /// Replacement text per category, e.g. `{"custom:encounter-number": "[ENC]"}`. Overrides
+    /// every redaction in that category, including partial ones such as truncated ZIPs.
+    #[serde(default)]
+    pub tokens: BTreeMap<CategoryKey, String>,
+    /// `plain` (default) writes `[PERSON]` for every name; `numbered` writes `[PERSON_1]`,
+    /// `[PERSON_2]`, ..., giving repeats of the same text the same number within a document.
+    #[serde(default)]
+    pub token_style: TokenStyle,
+    /// Terms never redacted by any detector, such as the site's own name, matched
+    /// case-insensitively as whole words with any run of whitespace between words.
+    #[serde(default)]
+    pub allowlist: Vec<String>,
+}
+
+impl ScrubberConfig {
+    /// Reads a config file: `.yaml`/`.yml` as YAML, `.toml` as TOML, and `.json` or any other
+    /// extension as JSON. Every format fills the same fields, and parse errors carry the
+    /// parser's line and column.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let format = ConfigFormat::of(path);
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file: {}", path.display()))?;
+        let mut config = format.parse(&raw).with_context(|| {
+            format!(
+                "failed to parse config {}: {}",
+                format.name(),
+                path.display()
+            )
+        })?;
+        config
+            .validate()
+            .with_context(|| format!("invalid config: {}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for file in config
+            .names_files
+            .iter_mut()
+            .chain(config.keywords_files.iter_mut())
+        {
+            *file = dir.join(&*file);
+        }
+        Ok(config)
+    }
+
+    /// Checks values that parse but make no sense: an empty or inverted MRN length range, blank
+    /// or multi-line tokens, blank allowlist entries, and custom patterns without a name or
+    /// pattern. `Scrubber::new` calls it; patterns are compiled there.
+    pub fn validate(&self) -> Result<()> {
+        let mrn_min = self.mrn_min_length.unwrap_or(DEFAULT_MRN_MIN_LENGTH);
+        let mrn_max = self.mrn_max_length.unwrap_or(DEFAULT_MRN_MAX_LENGTH);
+        if mrn_min == 0 || mrn_min > mrn_max {
+            bail!(
+                "invalid MRN length range {}-{}: mrn_min_length must be at least 1 and no more \
+                 than mrn_max_length",
+                mrn_min,
+                mrn_max
+            );
+        }
+        for custom in &self.custom_patterns {
+            if custom.name.trim().is_empty() {
+                bail!(
+                    "custom_patterns entry with pattern {:?} has no name",
+                    custom.pattern
+                );
+            }
+            if custom.pattern.is_empty() {
+                bail!("custom pattern '{}' has an empty pattern", custom.name);
+            }
+        }
+        let custom_tokens = self.custom_patterns.iter().filter_map(|custom| {
+            let token = custom.token.as_ref()?;
+            Some((format!("custom:{}", custom.name), token))
+        });
+        let tokens = self
+            .tokens
+            .iter()
+            .map(|(category, token)| (category.to_string(), token))
+            .chain(custom_tokens);
+        for (category, token) in tokens {
+            if token.trim().is_empty() || token.contains(['\n', '\r']) {
+                bail!(
+                    "token override for {} must be non-empty and on one line, got {:?}",
+                    category,
+                    token
+                );
+            }
+        }
+        if self
+            .relative_date_phrases
+            .iter()
+            .any(|phrase| phrase.trim().is_empty())
+        {
+            bail!("relative_date_phrases entries must be non-empty");
+        }
+        if self.mrn_labels.iter().any(|label| label.trim().is_empty()) {
+            bail!("mrn_labels entries must be non-empty");
+        }
+        if self.mrn_pattern.as_deref().is_some_and(str::is_empty) {
+            bail!("mrn_pattern must be non-empty; leave it out for the default");
+        }
+        if self.allowlist.iter().any(|term| term.trim().is_empty()) {
+            bail!("allowlist entries must be non-empty");
+        }
+        let mut titles = self
+            .titles
+            .iter()
+            .chain(self.titles_replace.iter().flatten());
+        if titles.any(|title| title.trim().is_empty()) {
+            bail!("titles and titles_replace entries must be non-empty");
+        }
+        if self.titles_replace.as_ref().is_some_and(Vec::is_empty) && self.titles.is_empty() {
+            bail!("titles_replace must list at least one title");
+        }
+        if self.stoplist_replace.is_some() && self.dictionaries.name_stoplist.is_some() {
+            bail!("stoplist_replace and dictionaries.name_stoplist both replace the name stoplist; use one");
+        }
+        Ok(())
+    }
+
+    /// Reads each config file in turn and merges it over the ones before; no files gives the
+    /// default config.
+    pub fn from_files(paths: &[PathBuf]) -> Result<Self> {
+        paths.iter().try_fold(Self::default(), |merged, path| {
+            Ok(merged.merge(Self::from_file(path)?))
+        })
+    }
+
+    /// Layers `other` over `self`, as when a project config follows an org-wide one:
+    ///
+    /// - Lists (`names`, `keywords`, `employers`, `geo_places`, the label lists, `allowlist`,
+    ///   `stoplist`, `titles`, `relative_date_phrases`) are appended, skipping entries already present in any case.
+    ///   `names_files`, `keywords_files` and `skip` are appended too.
+    /// - `custom_patterns` are appended; a pattern with the name of an earlier one replaces it.
+    /// - `tokens` and `dictionaries` are merged per key, with `other` winning.
+    /// - Optional settings (`mrn_min_length`, `date_mode`, `regulation`, ...) are taken from
+    ///   `other` when it sets them.
+    /// - Flags (`redact_states`, `field_mode`, `ocr_tolerant`, `normalize_newlines`) are on if
+    ///   either config sets them.
+    /// - Other modes are taken from `other` unless it leaves them at their default, so a later
+    ///   file cannot reset a mode to its default.
+    pub fn merge(mut self, other: Self) -> Self {
+        for (list, more) in [
+            (&mut self.names, other.names),
+            (&mut self.keywords, other.keywords),
+            (&mut self.employers, other.employers),
+            (&mut self.geo_places, other.geo_places),
+            (&mut self.insurance_labels, other.insurance_labels),
+            (&mut self.govt_id_labels, other.govt_id_labels),
+            (&mut self.certificate_labels, other.certificate_labels),
+            (&mut self.mrn_labels, other.mrn_labels),
+            (&mut self.titles, other.titles),
+            (&mut self.relative_date_phrases, other.relative_date_phrases),
+            (&mut self.allowlist, other.allowlist),
+            (&mut self.stoplist, other.stoplist),
+        ] {
+            for entry in more {
+                if !list.iter().any(|seen| seen.eq_ignore_ascii_case(&entry)) {
+                    list.push(entry);
+                }
+            }
+        }
+        for (files, more) in [
+            (&mut self.names_files, other.names_files),
+            (&mut self.keywords_files, other.keywords_files),
+        ] {
+            for file in more {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+        for category in other.skip {
+            if !self.skip.contains(&category) {
+                self.skip.push(category);
+            }
+        }
+        for pattern in other.custom_patterns {
+            match self
+                .custom_patterns
+                .iter_mut()
+                .find(|seen| seen.name == pattern.name)
+            {
+                Some(seen) => *seen = pattern,
+                None => self.custom_patterns.push(pattern),
+            }
+        }
+        self.tokens.extend(other.tokens);
+
+        let dictionaries = other.dictionaries;
+        self.dictionaries.names = dictionaries.names.or(self.dictionaries.names);
+        self.dictionaries.first_names = dictionaries.first_names.or(self.dictionaries.first_names);
+        self.dictionaries.facility_terms = dictionaries
+            .facility_terms
+            .or(self.dictionaries.facility_terms);
+        self.dictionaries.name_stoplist = dictionaries
+            .name_stoplist
+            .or(self.dictionaries.name_stoplist);
+
+        self.mrn_min_length = other.mrn_min_length.or(self.mrn_min_length);
+        self.mrn_max_length = other.mrn_max_length.or(self.mrn_max_length);
+        self.mrn_pattern = other.mrn_pattern.or(self.mrn_pattern);
+        self.reference_date = other.reference_date.or(self.reference_date);
+        self.date_mode = other.date_mode.or(self.date_mode);
+        self.date_shift = other.date_shift.or(self.date_shift);
+        self.seed = other.seed.or(self.seed);
+        self.age_mode = other.age_mode.or(self.age_mode);
+        self.regulation = other.regulation.or(self.regulation);
+        self.zip_mode = other.zip_mode.or(self.zip_mode);
+        self.max_spans = other.max_spans.or(self.max_spans);
+        self.stoplist_replace = other.stoplist_replace.or(self.stoplist_replace);
+        self.titles_replace = other.titles_replace.or(self.titles_replace);
+        self.regex_size_limit_mb = other.regex_size_limit_mb.or(self.regex_size_limit_mb);
+        self.segment_fallback = other.segment_fallback.or(self.segment_fallback);
+
+        self.redact_states |= other.redact_states;
+        self.field_mode |= other.field_mode;
+        self.normalize_newlines |= other.normalize_newlines;
+        self.ocr_tolerant |= other.ocr_tolerant;
+
+        override_unless_default(&mut self.birth_year_mode, other.birth_year_mode);
+        override_unless_default(&mut self.date_order, other.date_order);
+        override_unless_default(&mut self.composite_ids, other.composite_ids);
+        override_unless_default(&mut self.coordinate_mode, other.coordinate_mode);
+        override_unless_default(&mut self.min_confidence, other.min_confidence);
+        override_unless_default(&mut self.area_mode, other.area_mode);
+        override_unless_default(&mut self.first_name_alone, other.first_name_alone);
+        override_unless_default(&mut self.person_mode, other.person_mode);
+        override_unless_default(&mut self.token_style, other.token_style);
+        self
+    }
+}
+
+/// The syntax of a config file, picked by its extension.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    fn of(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            Some("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "JSON",
+            ConfigFormat::Yaml => "YAML",
+            ConfigFormat::Toml => "TOML",
+        }
+    }
+
+    /// Parses `raw`, reducing each parser's error to its message and 1-based position. The
+    /// TOML error's own text is not used, since it quotes the offending line of the file.
+    fn parse(self, raw: &str) -> Result<ScrubberConfig> {
+        let (message, line, column) = match self {
+            ConfigFormat::Json => match serde_json::from_str(raw) {
+                Ok(config) => return Ok(config),
+                Err(err) => (err.to_string(), err.line(), err.column()),
+            },
+            ConfigFormat::Yaml => match serde_yaml::from_str(raw) {
+                Ok(config) => return Ok(config),
+                Err(err) => {
+                    let (line, column) = err
+                        .location()
+                        .map_or((0, 0), |location| (location.line(), location.column()));
+                    (err.to_string(), line, column)
+                }
+            },
+            ConfigFormat::Toml => match toml::from_str(raw) {
+                Ok(config) => return Ok(config),
+                Err(err) => {
+                    let (line, column) = err.span().map_or((0, 0), |span| {
+                        let before = &raw[..span.start];
+                        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+                        (
+                            before.matches('\n').count() + 1,
+                            before[line_start..].chars().count() + 1,
+                        )
+                    });
+                    (err.message().to_string(), line, column)
+                }
+            },
+        };
+        Err(parse_error(&message, line, column))
+    }
+}
+
+/// Adds the parser's position to `message` unless it is already there or unknown (line 0).
+/// serde's "unknown field" error also gets the closest valid key, so a misspelled `name` reads
+/// "did you mean `names`?" rather than failing with a list of every key.
+fn parse_error(message: &str, line: usize, column: usize) -> anyhow::Error {
+    let position = format!("line {} column {}", line, column);
+    let Some((_, rest)) = message.split_once("unknown field `") else {
+        return if line == 0 || message.contains(&position) {
+            anyhow!("{}", message)
+        } else {
+            anyhow!("{} at {}", message, position)
+        };
+    };
+    let mut quoted = rest.split('`').step_by(2);
+    let unknown = quoted.next().unwrap_or_default();
+    let closest = quoted
+        .map(|candidate| (edit_distance(unknown, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= candidate.len().max(3) / 3)
+        .min();
+    match closest {
+        Some((_, candidate)) => anyhow!(
+            "unknown key `{}` at {}; did you mean `{}`?",
+            unknown,
+            position,
+            candidate
+        ),
+        None => anyhow!("unknown key `{}` at {}", unknown, position),
+    }
+}
+
+/// Levenshtein distance in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn override_unless_default<T: Default + PartialEq>(setting: &mut T, value: T) {
+    if value != T::default() {
+        *setting = value;
+    }
+}
+
+/// A user-defined identifier pattern.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomPattern {
+    /// Category name without the `custom:` prefix: lowercase letters, digits, and dashes.
+    pub name: String,
+    /// Regular expression for the identifier; the whole match is redacted.
+    pub pattern: String,
+    /// Replacement text; defaults to the uppercased name in brackets. Same as a `tokens`
+    /// entry for `custom:<name>`, which may not also be given.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Match the pattern without regard to case.
+    #[serde(default)]
+    pub case_insensitive: bool,
+}
+
+/// When a segment is redacted whole. Either condition is enough.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SegmentFallback {
+    /// More than this many distinct categories redacted in one segment.
+    pub threshold: usize,
+    /// More than this fraction (0-1) of the segment's non-space characters redacted.
+    #[serde(default)]
+    pub density: Option<f64>,
+    #[serde(default)]
+    pub mode: SegmentMode,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SegmentMode {
+    #[default]
+    Line,
+    Sentence,
+}
+
+/// Optional replacements for the embedded lists under `data/`, in the same one-entry-per-line
+/// format. `names` and `keywords` above still extend whichever list is in effect.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DictionaryPaths {
+    #[serde(default)]
+    pub names: Option<PathBuf>,
+    #[serde(default)]
+    pub first_names: Option<PathBuf>,
+    #[serde(default)]
+    pub facility_terms: Option<PathBuf>,
+    #[serde(default)]
+    pub name_stoplist: Option<PathBuf>,
+}
+
+/// Moves dates by an offset derived from `seed`, so intervals within a document survive.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DateShift {
+    /// Defaults to the config's `seed`.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// The largest shift in either direction, in days. Defaults to 365.
+    #[serde(default)]
+    pub max_days: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateMode {
+    /// Replace the date with `[DATE]`.
+    #[default]
+    Redact,
+    /// Keep only the year, e.g. "03/14/2021" becomes "2021".
+    Year,
+    /// Replace the date with a token that keeps the year, e.g. `[DATE:2021]`.
+    Token,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateOrder {
+    /// Month first unless the first number is over 12, as in "31/12/2024".
+    #[default]
+    Auto,
+    /// Always month first; "31/12/2024" does not parse and is redacted as `[DATE]`.
+    Mdy,
+    /// Always day first; "12/31/2024" does not parse and is redacted as `[DATE]`.
+    Dmy,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BirthYearMode {
+    /// Replace the year with `[DATE]`.
+    #[default]
+    Redact,
+    /// Generalize the year to its decade, e.g. "1930s".
+    Decade,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AgeMode {
+    /// Replace the age with `[AGE_OVER_89]`.
+    #[default]
+    Redact,
+    /// Replace the age with "90+", e.g. "a 90+-year-old".
+    Generalize,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompositeIdMode {
+    /// Replace only the MRN segment, keeping the surrounding structure.
+    #[default]
+    Segment,
+    /// Replace the entire composite identifier.
+    Whole,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ZipMode {
+    /// Replace the ZIP code with `[ZIP]`.
+    #[default]
+    Redact,
+    /// Keep the first three digits, e.g. "60614-2201" becomes "606**".
+    Truncate,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CoordinateMode {
+    /// Replace the pair with `[COORD]`.
+    #[default]
+    Redact,
+    /// Round both components, keeping degree signs and hemisphere letters, e.g.
+    /// "41.8781° N, 87.6298° W" becomes "41.9° N, 87.6° W" at one place. One decimal place is
+    /// roughly 11 km of latitude, two roughly 1 km.
+    Round { places: u8 },
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AreaMode {
+    /// Treat the prefix as a ZIP code: `[ZIP]`, or kept as-is when ZIPs are truncated to three
+    /// digits anyway.
+    #[default]
+    Zip,
+    /// Replace the prefix with `[GEO]`.
+    Geo,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenStyle {
+    #[default]
+    Plain,
+    /// Bracket tokens carry an entity number, e.g. `[PERSON_2]`.
+    Numbered,
+}
+
+/// Person matchers by precision, lowest recall first; each level runs everything the level
+/// before it does.
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum PersonMode {
+    /// Names from the dictionary and names after a title ("Dr.", "Rev.").
+    Strict,
+    /// Also a common first name followed by a surname, and names after relationship or
+    /// first-name cues ("daughter Linda").
+    Balanced,
+    /// Also any two or three capitalized words in a row.
+    #[default]
+    Aggressive,
+}
+
+impl PersonMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Strict => "strict",
+            Self::Balanced => "balanced",
+            Self::Aggressive => "aggressive",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FirstNameMode {
+    /// Never redact a first name without a surname (outside field mode).
+    Off,
+    /// Redact a first name only after a cue such as "spoke with" or "daughter".
+    #[default]
+    CueRequired,
+    /// Redact any capitalized first name in narrative position.
+    Aggressive,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_yaml_and_toml_by_extension_and_json_under_any_other() {
+        let dir = std::env::temp_dir().join(format!("config-formats-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("dir");
+
+        let path = dir.join("site.conf");
+        fs::write(&path, r#"{"names": ["Quaxley"], "mrn_min_length": 7}"#).expect("write");
+        let config = ScrubberConfig::from_file(&path).expect("json");
+        assert_eq!(config.names, ["Quaxley"]);
+        assert_eq!(config.mrn_min_length, Some(7));
+
+        fs::write(&path, "{\n  \"names\": [\"Quaxley\",]\n}").expect("write");
+        let err = format!(
+            "{:#}",
+            ScrubberConfig::from_file(&path).expect_err("syntax")
+        );
+        assert!(err.contains("line 2 column"), "{}", err);
+
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let json = ScrubberConfig::from_file(&fixtures.join("scrubber_config.json")).expect("json");
+        let json = serde_json::to_value(&json).expect("value");
+        for name in ["scrubber_config.yaml", "scrubber_config.toml"] {
+            let config = ScrubberConfig::from_file(&fixtures.join(name)).expect(name);
+            assert_eq!(
+                serde_json::to_value(&config).expect("value"),
+                json,
+                "{}",
+                name
+            );
+        }
+
+        for (name, raw, position) in [
+            (
+                "site.YML",
+                "names:\n  - Quaxley\nmrn_min_length: [7]\n",
+                "line 3 column 17",
+            ),
+            ("site.yaml", "names: [Quaxley\n", "line 2 column 1"),
+            (
+                "site.toml",
+                "names = [\"Quaxley\"]\nmrn_min_length = \"7\"\n",
+                "line 2 column 18",
+            ),
+            ("site.toml", "names = [\"Quaxley\"\n", "line 2 column 1"),
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, raw).expect("write");
+            let err = format!("{:#}", ScrubberConfig::from_file(&path).expect_err(name));
+            assert!(err.contains(position), "{}: {}", name, err);
+            assert!(!err.contains("Quaxley"), "{}: {}", name, err);
+        }
+        fs::write(dir.join("site.toml"), "name = [\"Quaxley\"]\n").expect("write");
+        let err = format!(
+            "{:#}",
+            ScrubberConfig::from_file(&dir.join("site.toml")).expect_err("unknown key")
+        );
+        assert!(
+            err.contains("unknown key `name` at line 1 column 1; did you mean `names`?"),
+            "{}",
+            err
+        );
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn rejects_misspelled_keys_and_out_of_range_values_naming_the_file() {
+        let dir = std::env::temp_dir().join(format!("config-errors-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("dir");
+        let path = dir.join("site.json");
+
+        fs::write(&path, r#"{"name": ["Quaxley"]}"#).expect("write");
+        let err = format!(
+            "{:#}",
+            ScrubberConfig::from_file(&path).expect_err("unknown key")
+        );
+        assert!(err.contains(&path.display().to_string()), "{}", err);
+        assert!(err.contains("unknown key `name` at line 1 column 7; did you mean `names`?"));
+
+        fs::write(
+            &path,
+            r#"{"custom_patterns": [{"name": "csn", "patern": "x"}]}"#,
+        )
+        .expect("write");
+        let err = format!(
+            "{:#}",
+            ScrubberConfig::from_file(&path).expect_err("nested")
+        );
+        assert!(err.contains("did you mean `pattern`?"), "{}", err);
+
+        fs::write(&path, r#"{"zzzz": 1}"#).expect("write");
+        let err = format!(
+            "{:#}",
+            ScrubberConfig::from_file(&path).expect_err("unknown key")
+        );
+        assert!(
+            err.ends_with("unknown key `zzzz` at line 1 column 7"),
+            "{}",
+            err
+        );
+
+        fs::write(&path, r#"{"mrn_min_length": 12}"#).expect("write");
+        let err = format!("{:#}", ScrubberConfig::from_file(&path).expect_err("range"));
+        assert!(err.contains("invalid config"), "{}", err);
+        assert!(err.contains(&path.display().to_string()), "{}", err);
+        assert!(err.contains("invalid MRN length range 12-10"), "{}", err);
+        fs::remove_dir_all(&dir).ok();
+
+        let config = ScrubberConfig {
+            custom_patterns: vec![CustomPattern {
+                name: " ".to_string(),
+                pattern: "x".to_string(),
+                token: None,
+                case_insensitive: false,
+            }],
+            ..ScrubberConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn later_configs_append_lists_and_override_settings() {
+        let base: ScrubberConfig = serde_json::from_str(
+            r#"{"names": ["Quaxley", "Darrow"], "mrn_min_length": 6, "redact_states": true,
+                "zip_mode": "truncate", "tokens": {"person": "[NAME]", "date": "[WHEN]"},
+                "custom_patterns": [{"name": "csn", "pattern": "CSN\\d+"}]}"#,
+        )
+        .expect("base");
+        let project: ScrubberConfig = serde_json::from_str(
+            r#"{"names": ["darrow", "Zorbin"], "mrn_min_length": 8,
+                "first_name_alone": "off", "tokens": {"person": "<NAME>"},
+                "custom_patterns": [{"name": "csn", "pattern": "CSN-\\d+"},
+                                    {"name": "study", "pattern": "STU-\\d+"}]}"#,
+        )
+        .expect("project");
+
+        let merged = base.merge(project);
+        assert_eq!(merged.names, ["Quaxley", "Darrow", "Zorbin"]);
+        assert_eq!(merged.mrn_min_length, Some(8));
+        assert!(merged.redact_states);
+        assert_eq!(merged.zip_mode, Some(ZipMode::Truncate));
+        assert_eq!(merged.first_name_alone, FirstNameMode::Off);
+        assert_eq!(
+            merged.tokens.values().collect::<Vec<_>>(),
+            ["[WHEN]", "<NAME>"]
+        );
+        let patterns: Vec<(&str, &str)> = merged
+            .custom_patterns
+            .iter()
+            .map(|custom| (custom.name.as_str(), custom.pattern.as_str()))
+            .collect();
+        assert_eq!(patterns, [("csn", "CSN-\\d+"), ("study", "STU-\\d+")]);
+
+        let unchanged = merged.merge(ScrubberConfig::default());
+        assert_eq!(unchanged.mrn_min_length, Some(8));
+        assert_eq!(unchanged.first_name_alone, FirstNameMode::Off);
+    }
 }
@@ -0,0 +1,350 @@
+//! CSV scrubbing (`--format csv`): the named columns of each row are scrubbed and every other
+//! field, the header and the line endings are copied as they were.
+//!
+//! Fields follow RFC 4180: comma-separated, optionally double-quoted, with `""` for a quote
+//! and line breaks allowed inside quotes. The first row is the header; columns are chosen by
+//! header name or 0-based index. A scrubbed field keeps its quotes, and an unquoted one gains
+//! them only if its new text holds a comma, quote or line break. A row whose field count
+//! differs from the header's is an error, since a stray comma would shift free text into a
+//! column that is not scrubbed. Blank lines are copied through. Like `--stream`, rows are read
+//! and written one at a time.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+
+use anyhow::{Context, Result};
+
+use crate::cancel::CancellationToken;
+use crate::report::csv_field;
+use crate::scrubber::{ScrubOptions, ScrubStats, Scrubber};
+use crate::CategoryKey;
+
+/// A column named on the command line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl Column {
+    /// All digits is an index; anything else is a header name.
+    pub fn parse(text: &str) -> Self {
+        match text.parse() {
+            Ok(index) if text.bytes().all(|byte| byte.is_ascii_digit()) => Column::Index(index),
+            _ => Column::Name(text.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Name(name) => write!(f, "{:?}", name),
+            Column::Index(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+/// Which columns to scrub, and how.
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    pub columns: Vec<Column>,
+    /// Per-line counts are never collected, as they would number lines within a field.
+    pub scrub: ScrubOptions,
+}
+
+impl CsvOptions {
+    pub fn new(columns: &[String]) -> Self {
+        Self {
+            columns: columns.iter().map(|column| Column::parse(column)).collect(),
+            scrub: ScrubOptions::default(),
+        }
+    }
+}
+
+/// Scrubs the chosen columns of the CSV in `reader` into `writer` and returns the stats of
+/// all rows.
+pub fn scrub_csv(
+    scrubber: &Scrubber,
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+    csv: &CsvOptions,
+    skip: &HashSet<CategoryKey>,
+    cancel: &CancellationToken,
+) -> Result<ScrubStats> {
+    let options = ScrubOptions {
+        collect_lines: false,
+        ..csv.scrub
+    };
+    let mut unique: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+    let mut total = scrubber
+        .scrub_record("", skip, &options, cancel, &mut unique)?
+        .stats;
+    let mut record = RawRecord::default();
+    let mut number = 0;
+    // The header, and any blank lines before it, are held back until the columns are found,
+    // so a bad `--column` writes nothing.
+    let mut leading = Vec::new();
+    let header = loop {
+        number += 1;
+        if !record.read(&mut reader, number)? {
+            return Err(anyhow::anyhow!(
+                "the CSV input is empty; expected a header row"
+            ));
+        }
+        leading.extend_from_slice(&record.bytes);
+        if !record.is_blank() {
+            break record.decoded_fields(number)?;
+        }
+    };
+    let mut selected: Vec<usize> = Vec::new();
+    for column in &csv.columns {
+        let index = match column {
+            Column::Index(index) if *index < header.len() => *index,
+            Column::Index(index) => {
+                return Err(anyhow::anyhow!(
+                    "column {} is out of range; the header has {} columns (0 to {})",
+                    index,
+                    header.len(),
+                    header.len() - 1
+                ))
+            }
+            Column::Name(name) => {
+                header
+                    .iter()
+                    .position(|field| field == name)
+                    .with_context(|| {
+                        format!(
+                            "column {} is not in the header, which has: {}",
+                            column,
+                            header.join(", ")
+                        )
+                    })?
+            }
+        };
+        if !selected.contains(&index) {
+            selected.push(index);
+        }
+    }
+    writer.write_all(&leading)?;
+
+    while {
+        number += 1;
+        record.read(&mut reader, number)?
+    } {
+        if record.is_blank() {
+            writer.write_all(&record.bytes)?;
+            continue;
+        }
+        if record.fields.len() != header.len() {
+            return Err(anyhow::anyhow!(
+                "row {} has {} fields but the header has {}",
+                number,
+                record.fields.len(),
+                header.len()
+            ));
+        }
+        let mut at = 0;
+        for (index, range) in record.fields.iter().enumerate() {
+            writer.write_all(&record.bytes[at..range.start])?;
+            at = range.end;
+            if !selected.contains(&index) {
+                writer.write_all(&record.bytes[range.clone()])?;
+                continue;
+            }
+            let value = record.decode(range.clone(), number)?;
+            let result = scrubber.scrub_record(&value, skip, &options, cancel, &mut unique)?;
+            total += &result.stats;
+            let field = if record.bytes[range.start] == b'"' {
+                format!("\"{}\"", result.text.replace('"', "\"\""))
+            } else {
+                csv_field(&result.text)
+            };
+            writer.write_all(field.as_bytes())?;
+        }
+        writer.write_all(&record.bytes[at..])?;
+    }
+    writer.flush()?;
+    total.unique = unique
+        .iter()
+        .map(|(category, values)| (category.clone(), values.len()))
+        .collect();
+    Ok(total)
+}
+
+/// One row as read: its bytes, line ending included, and the raw range of each field.
+#[derive(Default)]
+struct RawRecord {
+    bytes: Vec<u8>,
+    fields: Vec<Range<usize>>,
+}
+
+impl RawRecord {
+    /// Reads the next row, across line breaks inside quotes. False at the end of the input.
+    fn read(&mut self, reader: &mut impl BufRead, number: usize) -> Result<bool> {
+        self.bytes.clear();
+        self.fields.clear();
+        let (mut at, mut start) = (0, 0);
+        let (mut quoted, mut closed) = (false, false);
+        loop {
+            let read = reader.read_until(b'\n', &mut self.bytes)?;
+            if read == 0 {
+                if self.bytes.is_empty() {
+                    return Ok(false);
+                }
+                if quoted {
+                    return Err(anyhow::anyhow!(
+                        "row {}: a quoted field is not closed before the end of the input",
+                        number
+                    ));
+                }
+                self.fields.push(start..self.bytes.len());
+                return Ok(true);
+            }
+            while at < self.bytes.len() {
+                let byte = self.bytes[at];
+                let line_end =
+                    byte == b'\n' || (byte == b'\r' && self.bytes.get(at + 1) == Some(&b'\n'));
+                if quoted {
+                    if byte == b'"' && self.bytes.get(at + 1) == Some(&b'"') {
+                        at += 1;
+                    } else if byte == b'"' {
+                        quoted = false;
+                        closed = true;
+                    }
+                } else if byte == b',' || line_end {
+                    self.fields.push(start..at);
+                    if line_end {
+                        return Ok(true);
+                    }
+                    start = at + 1;
+                    closed = false;
+                } else if closed {
+                    return Err(anyhow::anyhow!(
+                        "row {}, field {}: text after a closing quote",
+                        number,
+                        self.fields.len()
+                    ));
+                } else if byte == b'"' && at == start {
+                    quoted = true;
+                } else if byte == b'"' {
+                    return Err(anyhow::anyhow!(
+                        "row {}, field {}: a quote inside an unquoted field",
+                        number,
+                        self.fields.len()
+                    ));
+                }
+                at += 1;
+            }
+        }
+    }
+
+    /// A line with nothing but its line ending.
+    fn is_blank(&self) -> bool {
+        self.fields.len() == 1 && self.fields[0].is_empty()
+    }
+
+    /// A field's value, with its quotes removed and `""` read as `"`.
+    fn decode(&self, range: Range<usize>, number: usize) -> Result<String> {
+        let raw = std::str::from_utf8(&self.bytes[range])
+            .with_context(|| format!("row {} is not valid UTF-8", number))?;
+        Ok(match raw.strip_prefix('"') {
+            Some(inner) => inner
+                .strip_suffix('"')
+                .unwrap_or(inner)
+                .replace("\"\"", "\""),
+            None => raw.to_string(),
+        })
+    }
+
+    fn decoded_fields(&self, number: usize) -> Result<Vec<String>> {
+        self.fields
+            .iter()
+            .map(|range| self.decode(range.clone(), number))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScrubberConfig;
+    use crate::scrubber::ScrubMode;
+
+    fn scrub(input: &str, columns: &[&str]) -> Result<(String, ScrubStats)> {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let columns: Vec<String> = columns.iter().map(|column| column.to_string()).collect();
+        let mut output = Vec::new();
+        let stats = scrub_csv(
+            &scrubber,
+            input.as_bytes(),
+            &mut output,
+            &CsvOptions::new(&columns),
+            &HashSet::new(),
+            &CancellationToken::new(),
+        )?;
+        Ok((String::from_utf8(output).expect("utf-8"), stats))
+    }
+
+    #[test]
+    fn scrubs_quoted_fields_with_commas_and_line_breaks() {
+        let input = concat!(
+            "id,note_text,mrn,comments\r\n",
+            "1,\"Call Jane Doe, 312-555-0182,\nor \"\"email\"\" jane.doe@example.com\",\"MRN, 4829130\",ok\r\n",
+            "\r\n",
+            "2,Seen 03/14/2024,\"4829130\",\"Phone: 312-555-0182\"",
+        );
+        let (output, stats) = scrub(input, &["note_text", "3"]).expect("csv");
+        assert_eq!(
+            output,
+            concat!(
+                "id,note_text,mrn,comments\r\n",
+                "1,\"Call [PERSON], [PHONE],\nor \"\"email\"\" [EMAIL]\",\"MRN, 4829130\",ok\r\n",
+                "\r\n",
+                "2,Seen [DATE],\"4829130\",\"Phone: [PHONE]\"",
+            )
+        );
+        assert_eq!(stats.total(), 5);
+    }
+
+    #[test]
+    fn reports_missing_columns_and_ragged_rows() {
+        let message = |result: Result<(String, ScrubStats)>| result.expect_err("error").to_string();
+        assert_eq!(
+            message(scrub("id,text\n1,x\n", &["note_text"])),
+            "column \"note_text\" is not in the header, which has: id, text"
+        );
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let mut output = Vec::new();
+        scrub_csv(
+            &scrubber,
+            "\nid,text\n1,x\n".as_bytes(),
+            &mut output,
+            &CsvOptions::new(&["note_text".to_string()]),
+            &HashSet::new(),
+            &CancellationToken::new(),
+        )
+        .expect_err("missing column");
+        assert!(output.is_empty(), "{:?}", output);
+        assert_eq!(
+            message(scrub("id,text\n", &["2"])),
+            "column 2 is out of range; the header has 2 columns (0 to 1)"
+        );
+        assert_eq!(
+            message(scrub("", &["text"])),
+            "the CSV input is empty; expected a header row"
+        );
+        assert_eq!(
+            message(scrub("id,text\n1,a,b\n", &["text"])),
+            "row 2 has 3 fields but the header has 2"
+        );
+        assert_eq!(
+            message(scrub("id,text\n1,\"open\n", &["text"])),
+            "row 2: a quoted field is not closed before the end of the input"
+        );
+    }
+}
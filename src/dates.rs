@@ -0,0 +1,408 @@
+//! Calendar helpers shared by the date-aware detectors.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+
+use crate::config::DateOrder;
+
+/// A calendar date without a time zone.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct CivilDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl CivilDate {
+    /// Parses `YYYY-MM-DD`.
+    pub fn parse(value: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid date '{}': expected YYYY-MM-DD", value);
+        let mut parts = value.trim().splitn(3, '-');
+        let year: i32 = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(invalid)?;
+        let month: u32 = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(invalid)?;
+        let day: u32 = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(invalid)?;
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(invalid());
+        }
+        Ok(Self { year, month, day })
+    }
+
+    /// Today's date in UTC.
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        Self::from_days((seconds / 86_400) as i64)
+    }
+
+    /// Days since 1970-01-01; the inverse of `from_days`.
+    pub fn days(self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let month = i64::from(self.month);
+        let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+            + i64::from(self.day)
+            - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    /// The date `days` later (or earlier, when negative).
+    pub fn add_days(self, days: i64) -> Self {
+        Self::from_days(self.days() + days)
+    }
+
+    /// Converts days since 1970-01-01 into a civil date (Howard Hinnant's algorithm).
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Self { year, month, day }
+    }
+}
+
+/// A date as written in a note, with enough of its format to write another date the same way.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WrittenDate {
+    pub date: CivilDate,
+    format: DateFormat,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum DateFormat {
+    /// `YYYY-MM-DD`.
+    Iso,
+    /// "03/14/2021", "14-3-21".
+    Numeric {
+        separator: char,
+        day_first: bool,
+        padded: bool,
+        short_year: bool,
+    },
+    /// "01021987" (month first) or "19870102" (year first).
+    Compact { year_first: bool },
+    /// "Jan 5, 2020", "JANUARY 05 2020", or day first: "31 December 2024".
+    MonthName {
+        day_first: bool,
+        abbreviated: bool,
+        upper: bool,
+        padded: bool,
+        comma: bool,
+        short_year: bool,
+    },
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+impl WrittenDate {
+    /// Parses "03/14/2021", "3-14-21", "2021-03-14", "Jan 5, 2020", "5 January 2020" or a
+    /// compact "01021987" (or "19870102" when that is the only valid reading).
+    /// Numeric dates are read in `order`; under `DateOrder::Auto` they are month first unless
+    /// the first number can only be a day. A two-digit year belongs to the century that puts it
+    /// no later than `pivot_year`, so with a pivot of 2024, "24" is 2024 and "25" is 1925.
+    pub fn parse(value: &str, pivot_year: i32, order: DateOrder) -> Option<Self> {
+        let value = value.trim();
+        if let Ok(date) = CivilDate::parse(value) {
+            return Some(Self {
+                date,
+                format: DateFormat::Iso,
+            });
+        }
+        if value.len() == 8 && value.bytes().all(|b| b.is_ascii_digit()) {
+            return Self::parse_compact(value);
+        }
+        let parts: Vec<&str> = value
+            .split(|c: char| c == '/' || c == '-' || c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .collect();
+        let [first, second, year_part] = parts[..] else {
+            return None;
+        };
+        let short_year = year_part.len() == 2;
+        let year = match (year_part.len(), year_part.parse::<i32>().ok()?) {
+            (2, short) => {
+                let century = pivot_year - pivot_year.rem_euclid(100);
+                if century + short > pivot_year {
+                    century - 100 + short
+                } else {
+                    century + short
+                }
+            }
+            (4, full) => full,
+            _ => return None,
+        };
+        let named = match (month_from_name(first), month_from_name(second)) {
+            (Some(month), _) => Some((month, first, second, false)),
+            (None, Some(month)) => Some((month, second, first, true)),
+            (None, None) => None,
+        };
+        let (month, day, format) = match named {
+            Some((month, name, day, day_first)) => (
+                month,
+                day.parse().ok()?,
+                DateFormat::MonthName {
+                    day_first,
+                    abbreviated: name.len() < MONTH_NAMES[month as usize - 1].len(),
+                    upper: name.len() > 1 && name.chars().all(|c| c.is_ascii_uppercase()),
+                    padded: day.starts_with('0'),
+                    comma: value.contains(','),
+                    short_year,
+                },
+            ),
+            None => {
+                let (a, b): (u32, u32) = (first.parse().ok()?, second.parse().ok()?);
+                let day_first = match order {
+                    DateOrder::Auto => a > 12,
+                    DateOrder::Mdy => false,
+                    DateOrder::Dmy => true,
+                };
+                let (month, day) = if day_first { (b, a) } else { (a, b) };
+                let format = DateFormat::Numeric {
+                    separator: value[first.len()..].chars().next()?,
+                    day_first,
+                    padded: first.starts_with('0') || second.starts_with('0'),
+                    short_year,
+                };
+                (month, day, format)
+            }
+        };
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Self {
+            date: CivilDate { year, month, day },
+            format,
+        })
+    }
+
+    fn parse_compact(value: &str) -> Option<Self> {
+        let number = |range: std::ops::Range<usize>| value[range].parse::<u32>().ok();
+        let valid = |year: i32, month: u32, day: u32| {
+            (1..=12).contains(&month) && day != 0 && day <= days_in_month(year, month)
+        };
+        let (month, day, year) = (number(0..2)?, number(2..4)?, number(4..8)? as i32);
+        if valid(year, month, day) {
+            return Some(Self {
+                date: CivilDate { year, month, day },
+                format: DateFormat::Compact { year_first: false },
+            });
+        }
+        let (year, month, day) = (number(0..4)? as i32, number(4..6)?, number(6..8)?);
+        valid(year, month, day).then_some(Self {
+            date: CivilDate { year, month, day },
+            format: DateFormat::Compact { year_first: true },
+        })
+    }
+
+    /// Writes `date` in this date's format.
+    pub fn render(&self, date: CivilDate) -> String {
+        let year = |short_year: bool| {
+            if short_year {
+                format!("{:02}", date.year.rem_euclid(100))
+            } else {
+                date.year.to_string()
+            }
+        };
+        match self.format {
+            DateFormat::Iso => format!("{:04}-{:02}-{:02}", date.year, date.month, date.day),
+            DateFormat::Compact { year_first: false } => {
+                format!("{:02}{:02}{:04}", date.month, date.day, date.year)
+            }
+            DateFormat::Compact { year_first: true } => {
+                format!("{:04}{:02}{:02}", date.year, date.month, date.day)
+            }
+            DateFormat::Numeric {
+                separator,
+                day_first,
+                padded,
+                short_year,
+            } => {
+                let (first, second) = if day_first {
+                    (date.day, date.month)
+                } else {
+                    (date.month, date.day)
+                };
+                let width = if padded { 2 } else { 1 };
+                format!(
+                    "{:0width$}{sep}{:0width$}{sep}{}",
+                    first,
+                    second,
+                    year(short_year),
+                    width = width,
+                    sep = separator
+                )
+            }
+            DateFormat::MonthName {
+                day_first,
+                abbreviated,
+                upper,
+                padded,
+                comma,
+                short_year,
+            } => {
+                let name = MONTH_NAMES[date.month as usize - 1];
+                let name = if abbreviated { &name[..3] } else { name };
+                let name = if upper {
+                    name.to_ascii_uppercase()
+                } else {
+                    name.to_string()
+                };
+                let width = if padded { 2 } else { 1 };
+                let comma = if comma { "," } else { "" };
+                if day_first {
+                    format!(
+                        "{:0width$} {}{} {}",
+                        date.day,
+                        name,
+                        comma,
+                        year(short_year),
+                    )
+                } else {
+                    format!(
+                        "{} {:0width$}{} {}",
+                        name,
+                        date.day,
+                        comma,
+                        year(short_year),
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// The month a name or three-letter abbreviation ("Sept", "january") refers to.
+fn month_from_name(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let name = name.to_ascii_lowercase();
+    let prefix = name.get(..3)?;
+    MONTHS
+        .iter()
+        .position(|month| *month == prefix)
+        .map(|index| index as u32 + 1)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_rejects_dates() {
+        assert_eq!(
+            CivilDate::parse("2024-02-29").expect("leap day"),
+            CivilDate {
+                year: 2024,
+                month: 2,
+                day: 29
+            }
+        );
+        assert!(CivilDate::parse("2023-02-29").is_err());
+        assert!(CivilDate::parse("2024/01/01").is_err());
+    }
+
+    #[test]
+    fn parses_dates_as_written_in_notes() {
+        let parse = |value| {
+            WrittenDate::parse(value, 2024, DateOrder::Auto).map(|written| written.date.year)
+        };
+        assert_eq!(parse("03/14/2021"), Some(2021));
+        assert_eq!(parse("3-14-21"), Some(2021));
+        assert_eq!(parse("14/03/2021"), Some(2021));
+        assert_eq!(parse("1/2/24"), Some(2024));
+        assert_eq!(parse("1/2/25"), Some(1925));
+        assert_eq!(parse("2021-03-14"), Some(2021));
+        assert_eq!(parse("Jan 5, 2020"), Some(2020));
+        assert_eq!(parse("September 30 1931"), Some(1931));
+        assert_eq!(parse("01021987"), Some(1987));
+        assert_eq!(parse("19870102"), Some(1987));
+        assert_eq!(parse("Feb 30, 2020"), None);
+        assert_eq!(parse("13/14/2021"), None);
+    }
+
+    #[test]
+    fn reads_numeric_dates_in_the_configured_order() {
+        let month = |value, order| WrittenDate::parse(value, 2024, order).map(|w| w.date.month);
+        assert_eq!(month("03/04/2021", DateOrder::Auto), Some(3));
+        assert_eq!(month("03/04/2021", DateOrder::Mdy), Some(3));
+        assert_eq!(month("03/04/2021", DateOrder::Dmy), Some(4));
+        assert_eq!(month("31/12/2024", DateOrder::Auto), Some(12));
+        assert_eq!(month("31/12/2024", DateOrder::Mdy), None);
+        assert_eq!(month("31/12/2024", DateOrder::Dmy), Some(12));
+        assert_eq!(month("12/31/2024", DateOrder::Dmy), None);
+        assert_eq!(month("31 December 2024", DateOrder::Mdy), Some(12));
+        assert_eq!(month("05 Mar, 2024", DateOrder::Auto), Some(3));
+    }
+
+    #[test]
+    fn renders_shifted_dates_in_the_written_format() {
+        let shift = |value| {
+            let written = WrittenDate::parse(value, 2024, DateOrder::Auto).expect(value);
+            written.render(written.date.add_days(20))
+        };
+        assert_eq!(shift("03/14/2021"), "04/03/2021");
+        assert_eq!(shift("3-14-21"), "4-3-21");
+        assert_eq!(shift("25/12/2023"), "14/1/2024");
+        assert_eq!(shift("2021-03-14"), "2021-04-03");
+        assert_eq!(shift("Jan 5, 2020"), "Jan 25, 2020");
+        assert_eq!(shift("DECEMBER 20 2020"), "JANUARY 9 2021");
+        assert_eq!(shift("31 December 2024"), "20 January 2025");
+        assert_eq!(shift("05 Mar, 2024"), "25 Mar, 2024");
+    }
+
+    #[test]
+    fn converts_days_since_epoch() {
+        assert_eq!(
+            CivilDate::from_days(0),
+            CivilDate::parse("1970-01-01").unwrap()
+        );
+        assert_eq!(
+            CivilDate::from_days(19_723),
+            CivilDate::parse("2024-01-01").unwrap()
+        );
+        let leap_day = CivilDate::parse("2024-02-29").unwrap();
+        assert_eq!(CivilDate::from_days(leap_day.days()), leap_day);
+        assert_eq!(CivilDate::parse("1969-12-31").unwrap().days(), -1);
+    }
+}
@@ -0,0 +1,203 @@
+//! Reuse of earlier output for byte-identical inputs (`--dedupe-cache`).
+//!
+//! Entries map an input's content hash to the input and output files of the run that scrubbed
+//! it. A hit is trusted only after the earlier input is re-read and compared byte for byte and
+//! its output still exists. Anything else falls back to scrubbing again: a moved file, a hash
+//! collision, a cache written under other settings, or a cache file that does not parse.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::dictionaries::fnv1a_hex;
+
+/// One earlier scrub: where its input came from and where its output went.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DedupeEntry {
+    pub input: PathBuf,
+    pub output: PathBuf,
+}
+
+/// Documents seen so far, split by whether they were scrubbed or reused.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct DedupeCounts {
+    pub unique: usize,
+    pub duplicates: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    settings: String,
+    entries: BTreeMap<String, DedupeEntry>,
+}
+
+#[derive(Debug)]
+pub struct DedupeCache {
+    /// Identifies the rules and config the cached outputs were produced with.
+    settings: String,
+    entries: BTreeMap<String, DedupeEntry>,
+    counts: DedupeCounts,
+}
+
+/// Stable content hash used for cache keys and settings.
+pub fn content_hash(bytes: &[u8]) -> String {
+    fnv1a_hex(bytes)
+}
+
+impl DedupeCache {
+    /// An empty cache for outputs produced under `settings`.
+    pub fn new(settings: impl Into<String>) -> Self {
+        Self {
+            settings: settings.into(),
+            entries: BTreeMap::new(),
+            counts: DedupeCounts::default(),
+        }
+    }
+
+    /// Loads a cache file. A missing file gives an empty cache. An unreadable or corrupt file,
+    /// or one written under other settings, gives an empty cache and a warning.
+    pub fn load(path: &Path, settings: impl Into<String>) -> (Self, Option<String>) {
+        let mut cache = Self::new(settings);
+        let raw = match fs::read(path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return (cache, None),
+            Err(err) => {
+                let warning = format!("ignoring dedupe cache {}: {}", path.display(), err);
+                return (cache, Some(warning));
+            }
+        };
+        let warning = match serde_json::from_slice::<CacheFile>(&raw) {
+            Ok(file) if file.settings == cache.settings => {
+                cache.entries = file.entries;
+                None
+            }
+            Ok(_) => Some(format!(
+                "ignoring dedupe cache {}: written with different rules or config",
+                path.display()
+            )),
+            Err(err) => Some(format!("ignoring dedupe cache {}: {}", path.display(), err)),
+        };
+        (cache, warning)
+    }
+
+    /// Writes the cache beside `path` and renames it into place.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = CacheFile {
+            settings: self.settings.clone(),
+            entries: self.entries.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file).context("failed to serialize dedupe cache")?;
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        fs::write(&partial, json)
+            .and_then(|()| fs::rename(&partial, path))
+            .with_context(|| format!("failed to write dedupe cache: {}", path.display()))
+    }
+
+    /// The earlier scrub of a byte-identical input whose output still exists. Counts the
+    /// document as a duplicate on a hit and as unique otherwise.
+    pub fn find(&mut self, input: &[u8]) -> Option<DedupeEntry> {
+        let hit = self
+            .entries
+            .get(&content_hash(input))
+            .filter(|entry| entry.output.is_file())
+            .filter(|entry| fs::read(&entry.input).is_ok_and(|earlier| earlier == input))
+            .cloned();
+        if hit.is_some() {
+            self.counts.duplicates += 1;
+        } else {
+            self.counts.unique += 1;
+        }
+        hit
+    }
+
+    /// Records the scrub of `input`, replacing any earlier entry for the same content.
+    pub fn insert(&mut self, input: &[u8], entry: DedupeEntry) {
+        self.entries.insert(content_hash(input), entry);
+    }
+
+    pub fn counts(&self) -> DedupeCounts {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dedupe-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).expect("scratch dir");
+        dir
+    }
+
+    #[test]
+    fn reuses_verified_entries_only() {
+        let dir = scratch("verify");
+        let (input, output) = (dir.join("a.txt"), dir.join("a.out"));
+        fs::write(&input, "Jane Doe").expect("input");
+        fs::write(&output, "[PERSON]").expect("output");
+
+        let mut cache = DedupeCache::new("rules");
+        assert_eq!(cache.find(b"Jane Doe"), None);
+        let entry = DedupeEntry {
+            input: input.clone(),
+            output: output.clone(),
+        };
+        cache.insert(b"Jane Doe", entry.clone());
+        assert_eq!(cache.find(b"Jane Doe"), Some(entry));
+
+        // A different document filed under the same hash is a collision, not a hit.
+        cache.entries.insert(
+            content_hash(b"John Roe"),
+            DedupeEntry {
+                input: input.clone(),
+                output: output.clone(),
+            },
+        );
+        assert_eq!(cache.find(b"John Roe"), None);
+
+        fs::remove_file(&output).expect("remove output");
+        assert_eq!(cache.find(b"Jane Doe"), None);
+        assert_eq!(
+            cache.counts(),
+            DedupeCounts {
+                unique: 3,
+                duplicates: 1
+            }
+        );
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn bad_cache_files_start_empty() {
+        let dir = scratch("load");
+        let path = dir.join("cache.json");
+        let (cache, warning) = DedupeCache::load(&path, "rules");
+        assert!(cache.entries.is_empty() && warning.is_none());
+
+        let mut cache = DedupeCache::new("rules");
+        cache.insert(
+            b"x",
+            DedupeEntry {
+                input: "a".into(),
+                output: "b".into(),
+            },
+        );
+        cache.save(&path).expect("save");
+        assert_eq!(DedupeCache::load(&path, "rules").0.entries.len(), 1);
+
+        let (cache, warning) = DedupeCache::load(&path, "other rules");
+        assert!(cache.entries.is_empty());
+        assert!(warning.expect("warning").contains("different rules"));
+
+        fs::write(&path, "{not json").expect("corrupt");
+        let (cache, warning) = DedupeCache::load(&path, "rules");
+        assert!(cache.entries.is_empty() && warning.is_some());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
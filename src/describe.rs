@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{
+    AgeMode, AreaMode, BirthYearMode, CompositeIdMode, CoordinateMode, DateMode, DateOrder,
+    FirstNameMode, PersonMode, TokenStyle, ZipMode,
+};
+use crate::dictionaries::fnv1a_hex;
+use crate::profiles::Regulation;
+use crate::CategoryKey;
+
+/// Where a detector's pattern or dictionary came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleSource {
+    /// Compiled-in defaults only.
+    Builtin,
+    /// Defaults extended or overridden by the JSON config.
+    Config,
+}
+
+/// How likely a detector's matches are to be real identifiers. Ordered low to high, so
+/// `min_confidence` can drop every rule below a tier.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    Hash,
+    PartialOrd,
+    Ord,
+    ValueEnum,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Confidence {
+    /// Guesses from shape alone, e.g. any run of capitalized words.
+    #[default]
+    Low,
+    /// Right shape with weak context, e.g. bare digit runs or a surname after a common first
+    /// name.
+    Medium,
+    /// A distinctive format or an explicit label, e.g. SSNs, emails, "MRN:".
+    High,
+}
+
+impl Confidence {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+/// Why a detector will not run for the described invocation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DisabledBy {
+    /// The detector belongs to a profile (e.g. Safe Harbor) that is not enabled.
+    Profile,
+    /// Every category the detector emits was passed to `--skip`.
+    Skip,
+    /// The detector's confidence tier is below `--min-confidence`.
+    Confidence,
+    /// The detector is a person matcher above the configured `person_mode`.
+    PersonMode,
+}
+
+/// Letter classes the name and facility patterns were compiled with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CharacterClasses {
+    /// Any Unicode letter, so "José Peña" is matched whole.
+    Unicode,
+    /// ASCII letters only (the `ascii-only` feature); accented names are cut short or missed.
+    Ascii,
+}
+
+impl CharacterClasses {
+    pub const ACTIVE: Self = if cfg!(feature = "ascii-only") {
+        Self::Ascii
+    } else {
+        Self::Unicode
+    };
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Unicode => "unicode",
+            Self::Ascii => "ascii",
+        }
+    }
+}
+
+/// One detector in the order `Scrubber::scrub` runs it.
+#[derive(Debug, Serialize)]
+pub struct RuleDescription {
+    /// Position in the run order; earlier detectors claim text first.
+    pub priority: usize,
+    pub name: &'static str,
+    pub categories: Vec<CategoryKey>,
+    pub source: RuleSource,
+    pub confidence: Confidence,
+    /// Short description of the pattern, or the dictionary size.
+    pub summary: String,
+    pub disabled_by: Option<DisabledBy>,
+}
+
+/// The effective rule set for a scrubber plus skip list.
+#[derive(Debug, Serialize)]
+pub struct RuleSetDescription {
+    pub regulation: Option<Regulation>,
+    pub safe_harbor: bool,
+    pub field_mode: bool,
+    pub character_classes: CharacterClasses,
+    /// Content hash of each word list, keyed by list name.
+    pub dictionaries: BTreeMap<&'static str, String>,
+    pub rules: Vec<RuleDescription>,
+}
+
+impl RuleSetDescription {
+    pub fn rule(&self, name: &str) -> Option<&RuleDescription> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    /// Hash of the whole description: two runs with the same fingerprint applied the same
+    /// detectors, modes, and dictionaries.
+    pub fn fingerprint(&self) -> String {
+        let json = serde_json::to_vec(self).expect("rule description serializes");
+        fnv1a_hex(&json)
+    }
+
+    /// Renders a fixed-width table, one detector per line.
+    pub fn render_table(&self) -> String {
+        let mut out = format!(
+            "Regulation: {}, Safe Harbor: {}, field mode: {}, character classes: {}\n",
+            self.regulation.map(Regulation::name).unwrap_or("none"),
+            if self.safe_harbor { "on" } else { "off" },
+            if self.field_mode { "on" } else { "off" },
+            self.character_classes.name(),
+        );
+        if !self.dictionaries.is_empty() {
+            let versions: Vec<String> = self
+                .dictionaries
+                .iter()
+                .map(|(name, version)| format!("{}={}", name, version))
+                .collect();
+            out.push_str(&format!("Dictionaries: {}\n", versions.join(", ")));
+        }
+        out.push_str(&format!(
+            "{:<4} {:<20} {:<26} {:<8} {:<10} {:<10} {}\n",
+            "#", "rule", "categories", "source", "confidence", "status", "summary"
+        ));
+        for rule in &self.rules {
+            let categories: Vec<String> = rule.categories.iter().map(ToString::to_string).collect();
+            let source = match rule.source {
+                RuleSource::Builtin => "builtin",
+                RuleSource::Config => "config",
+            };
+            let status = match rule.disabled_by {
+                None => "active",
+                Some(DisabledBy::Profile) => "profile",
+                Some(DisabledBy::Skip) => "skipped",
+                Some(DisabledBy::Confidence) => "confidence",
+                Some(DisabledBy::PersonMode) => "person-mode",
+            };
+            out.push_str(&format!(
+                "{:<4} {:<20} {:<26} {:<8} {:<10} {:<10} {}\n",
+                rule.priority,
+                rule.name,
+                categories.join(","),
+                source,
+                rule.confidence.name(),
+                status,
+                rule.summary
+            ));
+        }
+        out
+    }
+}
+
+/// Every setting a scrubber resolved from its mode, regulation profile, merged configs, and
+/// overrides, as printed by `clinical-scrubber config`. Word lists appear as entry counts, never
+/// their contents, so the output can be attached to a ticket.
+#[derive(Clone, Debug, Serialize)]
+pub struct ResolvedConfig {
+    pub regulation: Option<Regulation>,
+    pub safe_harbor: bool,
+    pub field_mode: bool,
+    pub min_confidence: Confidence,
+    pub person_mode: PersonMode,
+    /// Categories that will be redacted, skipped, or left off by the profile or mode; filled in
+    /// for a skip list by `Scrubber::resolved_config`.
+    pub categories: CategoryStates,
+    /// Names from `custom_patterns`, in run order.
+    pub custom_patterns: Vec<String>,
+    /// Replacement text per category: config `tokens` plus each custom category's default.
+    pub tokens: BTreeMap<CategoryKey, String>,
+    pub token_style: TokenStyle,
+    pub mrn_length: MrnLength,
+    pub reference_date: String,
+    pub date_mode: DateMode,
+    pub date_order: DateOrder,
+    /// Whether dates are shifted; the offset itself is never shown.
+    pub date_shift: bool,
+    pub birth_year_mode: BirthYearMode,
+    pub age_mode: AgeMode,
+    pub zip_mode: ZipMode,
+    pub area_mode: AreaMode,
+    pub coordinate_mode: CoordinateMode,
+    pub composite_ids: CompositeIdMode,
+    pub first_name_alone: FirstNameMode,
+    pub redact_states: bool,
+    pub ocr_tolerant: bool,
+    pub max_spans: usize,
+    /// Entries in each word list after merging defaults, config lists, and list files.
+    pub dictionaries: BTreeMap<&'static str, usize>,
+}
+
+/// How each category will be treated, in report order.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CategoryStates {
+    pub enabled: Vec<CategoryKey>,
+    pub skipped: Vec<CategoryKey>,
+    pub not_enabled: Vec<CategoryKey>,
+}
+
+/// Digit counts a bare number needs to be redacted as an MRN.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct MrnLength {
+    pub min: usize,
+    pub max: usize,
+}
@@ -0,0 +1,220 @@
+//! `--diff`: a unified line diff between the input and the scrubbed text, for tuning configs.
+//!
+//! Lines are paired through the alignment rather than by a generic diff algorithm: every input
+//! newline that survives unchanged anchors an input line to its output line, so the diff is
+//! linear in the document and never pairs the wrong lines. Removed text on `-` lines goes
+//! through `PhiDisplay`, so redacted originals only appear with `--log-phi`.
+
+use std::ops::Range;
+
+use crate::alignment::{Alignment, SegmentKind};
+use crate::phi::PhiDisplay;
+use crate::scrubber::ScrubResult;
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiffOptions {
+    /// ANSI colors for hunk headers and changed lines.
+    pub color: bool,
+    /// Show redacted originals on `-` lines instead of the placeholder.
+    pub log_phi: bool,
+}
+
+enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Renders the diff from `input` to `result.text`; empty when nothing changed.
+pub fn render_diff(input: &str, result: &ScrubResult, options: DiffOptions) -> String {
+    let lines = diff_lines(input, result, options.log_phi);
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    let paint = |color: &str, text: String| {
+        if options.color {
+            format!("{}{}{}\n", color, text, RESET)
+        } else {
+            text + "\n"
+        }
+    };
+    let mut out = paint(RED, "--- original".to_string()) + &paint(GREEN, "+++ scrubbed".into());
+
+    // Group changes whose context would touch into one hunk.
+    let mut hunks: Vec<Range<usize>> = Vec::new();
+    for &index in &changed {
+        match hunks.last_mut() {
+            Some(last) if index - last.end <= 2 * CONTEXT => last.end = index + 1,
+            _ => hunks.push(index..index + 1),
+        }
+    }
+
+    for hunk in hunks {
+        let start = hunk.start.saturating_sub(CONTEXT);
+        let end = (hunk.end + CONTEXT).min(lines.len());
+        let (old_before, new_before) = counts(&lines[..start]);
+        let (old_len, new_len) = counts(&lines[start..end]);
+        out += &paint(
+            CYAN,
+            format!(
+                "@@ -{} +{} @@",
+                hunk_range(old_before, old_len),
+                hunk_range(new_before, new_len)
+            ),
+        );
+        for line in &lines[start..end] {
+            out += &match line {
+                DiffLine::Same(text) => format!(" {}\n", text),
+                DiffLine::Removed(text) => paint(RED, format!("-{}", text)),
+                DiffLine::Added(text) => paint(GREEN, format!("+{}", text)),
+            };
+        }
+    }
+    out
+}
+
+/// Splits both texts at the anchored newlines and compares them chunk by chunk.
+fn diff_lines(input: &str, result: &ScrubResult, log_phi: bool) -> Vec<DiffLine> {
+    let text = &result.text;
+    let anchors = input
+        .match_indices('\n')
+        .filter_map(|(at, _)| {
+            let output = result.project_span(at..at + 1)?;
+            (&text[output.clone()] == "\n").then_some((at, output.start))
+        })
+        .chain([(input.len(), text.len())]);
+
+    let mut lines = Vec::new();
+    let (mut old_at, mut new_at) = (0, 0);
+    for (old_end, new_end) in anchors {
+        let (mut old, mut new) = (&input[old_at..old_end], &text[new_at..new_end]);
+        if old_end == input.len() {
+            // A final newline ends the last line rather than starting another.
+            old = old.strip_suffix('\n').unwrap_or(old);
+            new = new.strip_suffix('\n').unwrap_or(new);
+            if old.is_empty() && new.is_empty() {
+                break;
+            }
+        }
+        if old == new {
+            lines.extend(split(old).map(DiffLine::Same));
+        } else {
+            let old_range = old_at..old_at + old.len();
+            let removed = masked(input, old_range, &result.alignment, log_phi);
+            lines.extend(split(&removed).map(DiffLine::Removed));
+            lines.extend(split(new).map(DiffLine::Added));
+        }
+        (old_at, new_at) = (old_end + 1, new_end + 1);
+    }
+    lines
+}
+
+/// `range` of the input with each redacted piece shown through `PhiDisplay`.
+fn masked(input: &str, range: Range<usize>, alignment: &Alignment, log_phi: bool) -> String {
+    let mut out = String::new();
+    for segment in alignment.segments() {
+        let start = segment.original.start.max(range.start);
+        let end = segment.original.end.min(range.end);
+        if start >= end {
+            continue;
+        }
+        if segment.kind == SegmentKind::Redacted {
+            out += &PhiDisplay::new(&input[start..end], log_phi).to_string();
+        } else {
+            out += &input[start..end];
+        }
+    }
+    out
+}
+
+fn split(chunk: &str) -> impl Iterator<Item = String> + '_ {
+    chunk
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+}
+
+/// Old-side and new-side line counts.
+fn counts(lines: &[DiffLine]) -> (usize, usize) {
+    lines.iter().fold((0, 0), |(old, new), line| match line {
+        DiffLine::Same(_) => (old + 1, new + 1),
+        DiffLine::Removed(_) => (old + 1, new),
+        DiffLine::Added(_) => (old, new + 1),
+    })
+}
+
+/// "start,len" with 1-based lines, as in `diff -u`; an empty range names the line before it.
+fn hunk_range(before: usize, len: usize) -> String {
+    let start = if len == 0 { before } else { before + 1 };
+    format!("{},{}", start, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::config::ScrubberConfig;
+    use crate::{ScrubMode, Scrubber};
+
+    #[test]
+    fn pairs_lines_through_crlf_and_splits_distant_changes_into_hunks() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let mut input = "Call 312-555-0182 today.\r\n".to_string();
+        for n in 1..=8 {
+            input += &format!("Vitals stable, check {}.\r\n", n);
+        }
+        input += "\r\nEmail jane.doe@example.com\r\n";
+        let result = scrubber.scrub_detailed(&input, &HashSet::new());
+
+        let options = DiffOptions {
+            color: false,
+            log_phi: true,
+        };
+        let diff = render_diff(&input, &result, options);
+        let expected = "\
+--- original
++++ scrubbed
+@@ -1,4 +1,4 @@
+-Call 312-555-0182 today.
++Call [PHONE] [REL_DATE].
+ Vitals stable, check 1.
+ Vitals stable, check 2.
+ Vitals stable, check 3.
+@@ -8,4 +8,4 @@
+ Vitals stable, check 7.
+ Vitals stable, check 8.
+\x20
+-Email jane.doe@example.com
++Email [EMAIL]
+";
+        assert_eq!(diff, expected);
+
+        let hidden = render_diff(&input, &result, DiffOptions::default());
+        assert!(hidden.contains("-Call «redacted» «redacted»."));
+        assert!(!hidden.contains("312-555-0182"));
+        assert_eq!(
+            render_diff(
+                "no PHI here\n",
+                &scrubber.scrub_detailed("no PHI here\n", &HashSet::new()),
+                options
+            ),
+            ""
+        );
+    }
+}
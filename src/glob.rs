@@ -0,0 +1,165 @@
+//! Wildcard expansion for input paths, for shells that pass patterns through unexpanded
+//! (cmd.exe, or a quoted `'notes/**/*.txt'`).
+//!
+//! `*` matches any run of characters within one path component, `?` matches one character and a
+//! `**` component matches any number of directories. Wildcards do not match a leading `.`, so
+//! hidden files and directories are left out unless the pattern spells the dot. Matches are
+//! regular files, returned sorted.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Whether `path` holds a wildcard and so needs [`expand`].
+pub fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+/// The files matching `pattern`, sorted. An empty result is not an error; the caller decides.
+pub fn expand(pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let separators: &[char] = if cfg!(windows) { &['/', '\\'] } else { &['/'] };
+    let parts: Vec<&str> = pattern.split(separators).collect();
+    let literal = parts.iter().take_while(|part| !is_pattern(part)).count();
+    let base = match parts[..literal].join("/") {
+        prefix if prefix.is_empty() && literal > 0 => PathBuf::from("/"),
+        prefix if prefix.is_empty() => PathBuf::from("."),
+        prefix => PathBuf::from(prefix),
+    };
+    let mut found = Vec::new();
+    let rest: Vec<&str> = parts[literal..]
+        .iter()
+        .copied()
+        .filter(|part| !part.is_empty())
+        .collect();
+    if rest.is_empty() {
+        if base.is_file() {
+            found.push(base);
+        }
+        return Ok(found);
+    }
+    walk(&base, literal == 0, &rest, &mut found)?;
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+/// Matches `parts` below `dir`. `relative` drops the `./` that a pattern without a directory
+/// would otherwise gain.
+fn walk(dir: &Path, relative: bool, parts: &[&str], found: &mut Vec<PathBuf>) -> io::Result<()> {
+    let Some((part, rest)) = parts.split_first() else {
+        return Ok(());
+    };
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let mut entries: Vec<(String, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = if relative {
+            PathBuf::from(&name)
+        } else {
+            dir.join(&name)
+        };
+        entries.push((name, path));
+    }
+    entries.sort();
+    if *part == "**" {
+        // Zero directories, then one more level with the `**` still in front.
+        walk(dir, relative, rest, found)?;
+        for (name, path) in &entries {
+            if !name.starts_with('.') && path.is_dir() {
+                walk(path, false, parts, found)?;
+            }
+        }
+        return Ok(());
+    }
+    for (name, path) in &entries {
+        if !matches(part, name) {
+            continue;
+        }
+        if rest.is_empty() {
+            if path.is_file() {
+                found.push(path.clone());
+            }
+        } else if path.is_dir() {
+            walk(path, false, rest, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// Matches one path component against one pattern component.
+fn matches(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was and how much of the name it has taken, for backtracking.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_within_one_component() {
+        assert!(matches("*.txt", "note.txt"));
+        assert!(matches("note?.txt", "note1.txt"));
+        assert!(matches("*a*b", "xaxxb"));
+        assert!(!matches("*.txt", "note.txt.bak"));
+        assert!(!matches("*.txt", ".hidden.txt"));
+        assert!(matches(".*", ".hidden"));
+    }
+
+    #[test]
+    fn double_star_descends_into_subdirectories() {
+        let dir = std::env::temp_dir().join(format!("glob-{}", std::process::id()));
+        fs::create_dir_all(dir.join("b/c")).expect("dirs");
+        fs::create_dir_all(dir.join(".git")).expect("dirs");
+        for file in ["a.txt", "a.md", "b/b.txt", "b/c/c.txt", ".git/x.txt"] {
+            fs::write(dir.join(file), "x").expect("file");
+        }
+        let root = dir.display().to_string();
+
+        let all = expand(&format!("{}/**/*.txt", root)).expect("expand");
+        assert_eq!(
+            all,
+            vec![
+                dir.join("a.txt"),
+                dir.join("b/b.txt"),
+                dir.join("b/c/c.txt")
+            ]
+        );
+        let top = expand(&format!("{}/*.txt", root)).expect("expand");
+        assert_eq!(top, vec![dir.join("a.txt")]);
+        assert!(expand(&format!("{}/*.csv", root))
+            .expect("expand")
+            .is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,328 @@
+//! JSON Lines scrubbing (`--format jsonl`): the named string fields of each record are
+//! scrubbed and everything else on the line, key order and spacing included, is copied as it
+//! was.
+//!
+//! Fields are dotted paths through nested objects (`payload.note.text`); a path that is
+//! missing or does not end at a string is left alone. Only the scrubbed values are
+//! re-encoded, with serde_json's escaping. A line that is not valid JSON is passed through
+//! unscrubbed and counted in `ScrubStats::parse_errors`. Like `--stream`, records are read
+//! and written one at a time.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, Write};
+use std::ops::Range;
+
+use anyhow::{Context, Result};
+
+use crate::cancel::CancellationToken;
+use crate::scrubber::{ScrubOptions, ScrubStats, Scrubber};
+use crate::CategoryKey;
+
+/// The key `attach_stats` adds to each record.
+pub const STATS_KEY: &str = "_scrub_stats";
+
+/// Which fields of a record to scrub, and how.
+#[derive(Clone, Debug)]
+pub struct JsonlOptions {
+    paths: Vec<Vec<String>>,
+    /// Add each record's redaction counts under `STATS_KEY`.
+    pub attach_stats: bool,
+    /// Per-line counts are never collected, as they would number lines within a field.
+    pub scrub: ScrubOptions,
+}
+
+impl JsonlOptions {
+    /// Splits each field at `.`; a path with an empty segment is an error.
+    pub fn new(fields: &[String], attach_stats: bool) -> Result<Self> {
+        let mut paths: Vec<Vec<String>> = Vec::new();
+        for field in fields {
+            let path: Vec<String> = field.split('.').map(str::to_string).collect();
+            if path.iter().any(String::is_empty) {
+                return Err(anyhow::anyhow!(
+                    "--field {:?}: expected a key or a dotted path such as payload.note.text",
+                    field
+                ));
+            }
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        Ok(Self {
+            paths,
+            attach_stats,
+            scrub: ScrubOptions::default(),
+        })
+    }
+}
+
+/// Scrubs each line of `reader` as a JSON record into `writer` and returns the stats of all
+/// records. `on_parse_error` hears the line number and parser error of each line passed
+/// through; the error names a position, never the text.
+pub fn scrub_jsonl(
+    scrubber: &Scrubber,
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+    jsonl: &JsonlOptions,
+    skip: &HashSet<CategoryKey>,
+    cancel: &CancellationToken,
+    mut on_parse_error: impl FnMut(usize, &serde_json::Error),
+) -> Result<ScrubStats> {
+    let options = ScrubOptions {
+        collect_lines: false,
+        ..jsonl.scrub
+    };
+    let mut unique: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+    let mut total = scrubber
+        .scrub_record("", skip, &options, cancel, &mut unique)?
+        .stats;
+    let mut line = Vec::new();
+    for number in 1.. {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let mut body = line.len();
+        if line.ends_with(b"\n") {
+            body -= 1;
+            if line[..body].ends_with(b"\r") {
+                body -= 1;
+            }
+        }
+        let record = &line[..body];
+        if record.iter().all(u8::is_ascii_whitespace) {
+            writer.write_all(&line)?;
+            continue;
+        }
+        if let Err(err) = serde_json::from_slice::<serde::de::IgnoredAny>(record) {
+            on_parse_error(number, &err);
+            total.parse_errors += 1;
+            writer.write_all(&line)?;
+            continue;
+        }
+
+        let mut stats = ScrubStats::default();
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+        for path in &jsonl.paths {
+            let Some(range) = find_string(record, 0, path) else {
+                continue;
+            };
+            let value: String =
+                serde_json::from_slice(&record[range.clone()]).context("string field")?;
+            let result = scrubber.scrub_record(&value, skip, &options, cancel, &mut unique)?;
+            stats += &result.stats;
+            edits.push((range, serde_json::to_string(&result.text)?));
+        }
+        edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        let mut out = record.to_vec();
+        for (range, value) in edits {
+            out.splice(range, value.into_bytes());
+        }
+        if jsonl.attach_stats {
+            attach_stats(&mut out, &stats)?;
+        }
+        writer.write_all(&out)?;
+        writer.write_all(&line[body..])?;
+        total += &stats;
+    }
+    writer.flush()?;
+    total.unique = unique
+        .iter()
+        .map(|(category, values)| (category.clone(), values.len()))
+        .collect();
+    Ok(total)
+}
+
+/// Adds `"_scrub_stats": {"total": n, "counts": {...}}` as the last key of a record that is
+/// an object, listing only the categories that fired.
+fn attach_stats(record: &mut Vec<u8>, stats: &ScrubStats) -> Result<()> {
+    let Some(close) = record.iter().rposition(|&byte| byte == b'}') else {
+        return Ok(());
+    };
+    let start = skip_ws(record, 0);
+    if record[start] != b'{' {
+        return Ok(());
+    }
+    let counts: serde_json::Map<String, serde_json::Value> = stats
+        .by_key()
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(category, count)| (category.to_string(), count.into()))
+        .collect();
+    let value = serde_json::json!({ "total": stats.total(), "counts": counts });
+    let empty = skip_ws(record, start + 1) == close;
+    let entry = format!(
+        "{}\"{}\":{}",
+        if empty { "" } else { "," },
+        STATS_KEY,
+        serde_json::to_string(&value)?
+    );
+    record.splice(close..close, entry.into_bytes());
+    Ok(())
+}
+
+// The scanner below walks text that has already parsed as JSON, so it does not re-check the
+// syntax.
+
+fn skip_ws(text: &[u8], mut at: usize) -> usize {
+    while text.get(at).is_some_and(u8::is_ascii_whitespace) {
+        at += 1;
+    }
+    at
+}
+
+/// The end of the string whose opening quote is at `at`.
+fn string_end(text: &[u8], mut at: usize) -> usize {
+    at += 1;
+    loop {
+        match text[at] {
+            b'\\' => at += 2,
+            b'"' => return at + 1,
+            _ => at += 1,
+        }
+    }
+}
+
+/// The end of the value starting at `at`.
+fn value_end(text: &[u8], at: usize) -> usize {
+    match text[at] {
+        b'"' => string_end(text, at),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut at = at;
+            loop {
+                match text[at] {
+                    b'"' => {
+                        at = string_end(text, at);
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return at + 1;
+                        }
+                    }
+                    _ => {}
+                }
+                at += 1;
+            }
+        }
+        _ => {
+            let mut at = at;
+            while text.get(at).is_some_and(|byte| {
+                !matches!(byte, b',' | b'}' | b']') && !byte.is_ascii_whitespace()
+            }) {
+                at += 1;
+            }
+            at
+        }
+    }
+}
+
+/// The byte range, quotes included, of the string at `path` in the object starting at `at`.
+/// With a repeated key the last one counts, as when parsing.
+fn find_string(text: &[u8], at: usize, path: &[String]) -> Option<Range<usize>> {
+    let mut at = skip_ws(text, at);
+    if text.get(at) != Some(&b'{') {
+        return None;
+    }
+    at = skip_ws(text, at + 1);
+    let mut found = None;
+    while text.get(at) == Some(&b'"') {
+        let key_end = string_end(text, at);
+        let key: String = serde_json::from_slice(&text[at..key_end]).ok()?;
+        let start = skip_ws(text, skip_ws(text, key_end) + 1);
+        let end = value_end(text, start);
+        if key == path[0] {
+            found = match path {
+                [_] => (text[start] == b'"').then_some(start..end),
+                [_, rest @ ..] => find_string(text, start, rest),
+                [] => None,
+            };
+        }
+        at = skip_ws(text, end);
+        if text.get(at) == Some(&b',') {
+            at = skip_ws(text, at + 1);
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScrubberConfig;
+    use crate::scrubber::ScrubMode;
+
+    fn scrub(input: &str, fields: &[&str], attach_stats: bool) -> (String, ScrubStats, Vec<usize>) {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let fields: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+        let jsonl = JsonlOptions::new(&fields, attach_stats).expect("fields");
+        let mut output = Vec::new();
+        let mut errors = Vec::new();
+        let stats = scrub_jsonl(
+            &scrubber,
+            input.as_bytes(),
+            &mut output,
+            &jsonl,
+            &HashSet::new(),
+            &CancellationToken::new(),
+            |line, _| errors.push(line),
+        )
+        .expect("jsonl");
+        (String::from_utf8(output).expect("utf-8"), stats, errors)
+    }
+
+    #[test]
+    fn scrubs_nested_fields_and_leaves_the_rest_of_the_line_alone() {
+        let input = concat!(
+            r#"{"note_id": 1, "payload": {"note": {"text": "Call 312-555-0182."}, "text": "312-555-0182"}}"#,
+            "\n",
+            r#"{"z":[1,{"a":"}"}],"payload":{"note":{"text":"Email \"jane.doe@example.com\"\n"}}}"#,
+            "\r\n",
+        );
+        let (output, stats, errors) = scrub(input, &["payload.note.text"], false);
+        assert_eq!(
+            output,
+            concat!(
+                r#"{"note_id": 1, "payload": {"note": {"text": "Call [PHONE]."}, "text": "312-555-0182"}}"#,
+                "\n",
+                r#"{"z":[1,{"a":"}"}],"payload":{"note":{"text":"Email \"[EMAIL]\"\n"}}}"#,
+                "\r\n",
+            )
+        );
+        assert_eq!(stats.total(), 2);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn passes_bad_lines_through_and_counts_them() {
+        let input = "{\"text\": \"Call 312-555-0182\"\nnot json 312-555-0182\n\n{\"text\": 5}\n";
+        let (output, stats, errors) = scrub(input, &["text"], false);
+        assert_eq!(output, input);
+        assert_eq!(errors, [1, 2]);
+        assert_eq!(stats.parse_errors, 2);
+        assert_eq!(stats.total(), 0);
+    }
+
+    #[test]
+    fn attaches_per_record_counts_as_the_last_key() {
+        let input = "{\"id\": 7, \"text\": \"Call 312-555-0182.\", \"author\": \"x\"}\n{}\n";
+        let (output, _, _) = scrub(input, &["text", "missing.path"], true);
+        assert_eq!(
+            output,
+            concat!(
+                r#"{"id": 7, "text": "Call [PHONE].", "author": "x","_scrub_stats":{"counts":{"phone":1},"total":1}}"#,
+                "\n",
+                r#"{"_scrub_stats":{"counts":{},"total":0}}"#,
+                "\n",
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_empty_path_segments() {
+        assert!(JsonlOptions::new(&["payload..text".to_string()], false).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+#[cfg(not(any(feature = "unicode", feature = "ascii-only")))]
+compile_error!("enable the `unicode` feature (on by default) or `ascii-only`; the name patterns need one of them");
+
+pub mod alignment;
+pub mod audit;
+pub mod cancel;
+pub mod config;
+pub mod csv;
+pub mod dates;
+pub mod dedupe;
+pub mod describe;
+pub mod dictionaries;
+pub mod diff;
+pub mod glob;
+pub mod jsonl;
+pub mod phi;
+pub mod preview;
+pub mod profiles;
+pub mod profiling;
+pub mod repl;
+pub mod report;
+pub mod review;
+pub mod scrubber;
+pub mod segmentation;
+pub mod spans;
+pub mod stream;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+
+use std::fmt;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub use scrubber::{ScrubMode, ScrubOptions, ScrubResult, ScrubStats, Scrubber};
+
+/// Built-in redaction categories. Reports list them in declaration order.
+#[derive(
+    Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Category {
+    Email,
+    Phone,
+    Fax,
+    Pager,
+    Date,
+    RelativeDate,
+    /// Ages over 89.
+    Age,
+    Ssn,
+    Mrn,
+    Account,
+    Zip,
+    Person,
+    Facility,
+    Employer,
+    Address,
+    /// Cities, counties and other subdivisions smaller than a state.
+    Geo,
+    Coordinate,
+    Url,
+    Handle,
+    Insurance,
+    Mbi,
+    License,
+    GovtId,
+    Certificate,
+    Vehicle,
+    Device,
+    Ip,
+    /// A whole line or sentence replaced by `segment_fallback`.
+    Segment,
+}
+
+impl Category {
+    /// Every category in the order reports list them.
+    pub const ALL: [Category; 28] = [
+        Category::Email,
+        Category::Phone,
+        Category::Fax,
+        Category::Pager,
+        Category::Date,
+        Category::RelativeDate,
+        Category::Age,
+        Category::Ssn,
+        Category::Mrn,
+        Category::Account,
+        Category::Zip,
+        Category::Person,
+        Category::Facility,
+        Category::Employer,
+        Category::Address,
+        Category::Geo,
+        Category::Coordinate,
+        Category::Url,
+        Category::Handle,
+        Category::Insurance,
+        Category::Mbi,
+        Category::License,
+        Category::GovtId,
+        Category::Certificate,
+        Category::Vehicle,
+        Category::Device,
+        Category::Ip,
+        Category::Segment,
+    ];
+
+    /// The kebab-case name accepted by `--skip` and used in JSON output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Email => "email",
+            Category::Phone => "phone",
+            Category::Fax => "fax",
+            Category::Pager => "pager",
+            Category::Date => "date",
+            Category::RelativeDate => "relative-date",
+            Category::Age => "age",
+            Category::Ssn => "ssn",
+            Category::Mrn => "mrn",
+            Category::Account => "account",
+            Category::Zip => "zip",
+            Category::Person => "person",
+            Category::Facility => "facility",
+            Category::Employer => "employer",
+            Category::Address => "address",
+            Category::Geo => "geo",
+            Category::Coordinate => "coordinate",
+            Category::Url => "url",
+            Category::Handle => "handle",
+            Category::Insurance => "insurance",
+            Category::Mbi => "mbi",
+            Category::License => "license",
+            Category::GovtId => "govt-id",
+            Category::Certificate => "certificate",
+            Category::Vehicle => "vehicle",
+            Category::Device => "device",
+            Category::Ip => "ip",
+            Category::Segment => "segment",
+        }
+    }
+}
+
+/// A built-in category or one declared by a `custom_patterns` config entry. Custom categories
+/// are written `custom:<name>` in `--skip`, config `tokens`, stats, and spans; built-ins keep
+/// their plain names. Built-ins sort first, in report order, then custom categories by name.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum CategoryKey {
+    Builtin(Category),
+    Custom(String),
+}
+
+impl CategoryKey {
+    const CUSTOM_PREFIX: &'static str = "custom:";
+
+    /// A custom category key, after checking the name is lowercase letters, digits, and dashes.
+    pub fn custom(name: &str) -> Result<Self, String> {
+        let valid = !name.is_empty()
+            && !name.starts_with('-')
+            && !name.ends_with('-')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if valid {
+            Ok(CategoryKey::Custom(name.to_string()))
+        } else {
+            Err(format!(
+                "invalid custom category name '{}': use lowercase letters, digits, and dashes",
+                name
+            ))
+        }
+    }
+
+    /// The token written for redactions in this category unless config `tokens` overrides it:
+    /// `[ENCOUNTER_NUMBER]` for `custom:encounter-number`. Built-in detectors choose their own
+    /// tokens, so built-ins have none here.
+    pub fn default_token(&self) -> Option<String> {
+        match self {
+            CategoryKey::Builtin(_) => None,
+            CategoryKey::Custom(name) => {
+                Some(format!("[{}]", name.to_ascii_uppercase().replace('-', "_")))
+            }
+        }
+    }
+}
+
+impl From<Category> for CategoryKey {
+    fn from(category: Category) -> Self {
+        CategoryKey::Builtin(category)
+    }
+}
+
+impl fmt::Display for CategoryKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CategoryKey::Builtin(category) => f.write_str(category.name()),
+            CategoryKey::Custom(name) => write!(f, "{}{}", Self::CUSTOM_PREFIX, name),
+        }
+    }
+}
+
+impl FromStr for CategoryKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(name) = value.strip_prefix(Self::CUSTOM_PREFIX) {
+            return Self::custom(name);
+        }
+        Category::from_str(value, false)
+            .map(CategoryKey::Builtin)
+            .map_err(|_| {
+                format!(
+                    "unknown category '{}' (expected one of {}, or custom:<name>)",
+                    value,
+                    Category::ALL.map(Category::name).join(", ")
+                )
+            })
+    }
+}
+
+impl Serialize for CategoryKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CategoryKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn category_keys_round_trip_through_strings() {
+        for value in ["relative-date", "custom:encounter-number"] {
+            let key: CategoryKey = value.parse().expect("parse");
+            assert_eq!(key.to_string(), value);
+            let json = serde_json::to_string(&key).expect("serialize");
+            assert_eq!(
+                serde_json::from_str::<CategoryKey>(&json).expect("parse"),
+                key
+            );
+        }
+        assert_eq!(
+            "ip".parse::<CategoryKey>(),
+            Ok(CategoryKey::Builtin(Category::Ip))
+        );
+        assert!("custom:".parse::<CategoryKey>().is_err());
+        assert!("custom:Encounter".parse::<CategoryKey>().is_err());
+        assert!("encounter-number".parse::<CategoryKey>().is_err());
+    }
+
+    #[test]
+    fn builtins_sort_before_custom_categories() {
+        let mut keys: Vec<CategoryKey> = vec![
+            CategoryKey::custom("zeta").unwrap(),
+            Category::Ip.into(),
+            CategoryKey::custom("alpha").unwrap(),
+            Category::Email.into(),
+        ];
+        keys.sort();
+        let names: Vec<String> = keys.iter().map(ToString::to_string).collect();
+        assert_eq!(names, ["email", "ip", "custom:alpha", "custom:zeta"]);
+    }
+
+    #[test]
+    fn custom_tokens_are_uppercased_names() {
+        let key = CategoryKey::custom("encounter-number").unwrap();
+        assert_eq!(key.default_token().as_deref(), Some("[ENCOUNTER_NUMBER]"));
+        assert_eq!(CategoryKey::from(Category::Email).default_token(), None);
+    }
+}
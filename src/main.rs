@@ -1,93 +1,2031 @@
-mod config;
-mod scrubber;
-
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "webhook")]
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use clinical_scrubber::audit;
+use clinical_scrubber::cancel::{CancellationToken, Cancelled};
+use clinical_scrubber::config::{PersonMode, ScrubberConfig};
+use clinical_scrubber::csv::{self, CsvOptions};
+use clinical_scrubber::dedupe::{self, DedupeCache, DedupeCounts, DedupeEntry};
+use clinical_scrubber::describe::Confidence;
+use clinical_scrubber::diff::{self, DiffOptions};
+use clinical_scrubber::glob;
+use clinical_scrubber::jsonl::{self, JsonlOptions};
+use clinical_scrubber::preview;
+use clinical_scrubber::profiles::Regulation;
+use clinical_scrubber::profiling;
+use clinical_scrubber::repl::{self, ReplSettings};
+use clinical_scrubber::report::aggregate::StatsReport;
+use clinical_scrubber::report::manifest::{self, FileStatus, Manifest, ManifestFile};
+use clinical_scrubber::report::{html, render_span_json, ReportOptions, SchemaVersion, Versioned};
+use clinical_scrubber::review::{self, Decisions, ReviewOptions};
+use clinical_scrubber::spans::RedactionSpan;
+use clinical_scrubber::stream::{self, StreamOptions};
+#[cfg(feature = "webhook")]
+use clinical_scrubber::webhook::{DocumentEvent, WebhookOptions, WebhookSender};
+use clinical_scrubber::{
+    Category, CategoryKey, ScrubMode, ScrubOptions, ScrubResult, ScrubStats, Scrubber,
+};
+
+/// `eprint!` that skips a closed stderr (e.g. `2>&1 | head`) instead of panicking. Nothing
+/// else could report the failure, and the text on stdout should still be written.
+macro_rules! stderr {
+    ($($arg:tt)*) => {
+        write_stderr(format_args!($($arg)*))
+    };
+}
 
-use config::ScrubberConfig;
-use scrubber::{ScrubStats, Scrubber};
+/// `eprintln!` counterpart of `stderr!`.
+macro_rules! stderrln {
+    ($($arg:tt)*) => {
+        write_stderr(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
 
 #[derive(Parser, Debug)]
 #[command(
     name = "clinical-scrubber",
     about = "CLI tool that redacts common PHI elements from clinical notes.",
     version,
-    author = ""
+    author = "",
+    after_help = "\
+Environment (ignored with --no-env):
+  CLINICAL_SCRUBBER_CONFIG   config file, used when no --config is given
+  CLINICAL_SCRUBBER_SKIP     comma-separated categories, used when no --skip or --only is given
+  CLINICAL_SCRUBBER_PROFILE  regulation profile, used when no --regulation is given
+  CLINICAL_SCRUBBER_NAMES    comma-separated names added to the config's names
+
+Precedence: command line, then environment, then config file, then built-in defaults."
 )]
 struct Args {
-    /// Optional input file. Use '-' to read from STDIN.
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Optional input file. Use '-' to read from STDIN. A pattern such as 'notes/**/*.txt'
+    /// is expanded here, for shells that leave it alone.
     #[arg(short, long)]
     input: Option<PathBuf>,
 
-    /// Optional output file. Use '-' to write to STDOUT.
+    /// More input files or patterns, read in sorted order. Several inputs need --output-dir or
+    /// --in-place.
+    #[arg(value_name = "FILE", conflicts_with = "input_dir")]
+    files: Vec<PathBuf>,
+
+    /// Optional output file. Use '-' to write to STDOUT. Takes a single input.
     #[arg(short, long)]
     output: Option<PathBuf>,
 
-    /// Path to JSON config that augments the default dictionaries.
-    #[arg(short, long)]
-    config: Option<PathBuf>,
+    /// Scrub every file in this directory (subdirectories are skipped unless --recursive),
+    /// writing each result to --output-dir under the same name. The rules are compiled once
+    /// for the whole batch. Files that look binary are skipped with a warning.
+    #[arg(long, conflicts_with = "input")]
+    input_dir: Option<PathBuf>,
 
-    /// Redaction categories to skip (e.g. --skip person --skip date).
-    #[arg(long, value_enum)]
-    skip: Vec<Category>,
+    /// With --input-dir, also scrub the files in its subdirectories, mirroring the folder
+    /// structure under --output-dir.
+    #[arg(long, requires = "input_dir")]
+    recursive: bool,
+
+    /// With --input-dir, scrub only files with these extensions (e.g. --ext txt,md,hl7).
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "EXT",
+        requires = "input_dir"
+    )]
+    ext: Vec<String>,
+
+    /// With --recursive, descend into symlinked directories and read symlinked files; they are
+    /// skipped otherwise.
+    #[arg(long, requires = "recursive")]
+    follow_symlinks: bool,
+
+    /// Write each input's result to this directory under the input's file name; created if
+    /// missing. Must not be the input directory.
+    #[arg(
+        long,
+        conflicts_with_all = ["output", "diff", "preview", "alignment_out", "spans_out", "audit_log"]
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// Replace each input with its scrubbed text, written to a temporary file beside it, synced
+    /// and renamed over it. --in-place=SUFFIX first copies the original to the input path plus
+    /// SUFFIX, e.g. --in-place=.bak.
+    #[arg(
+        long,
+        value_name = "SUFFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        conflicts_with_all = ["output", "output_dir", "dedupe", "dedupe_cache", "diff", "preview", "alignment_out", "spans_out", "audit_log"]
+    )]
+    in_place: Option<String>,
+
+    /// Read, scrub and write the input one record (line) at a time, so memory stays bounded
+    /// on very large files. Entities split across records, such as multi-line addresses, can
+    /// be missed.
+    #[arg(
+        long,
+        conflicts_with_all = ["output_dir", "in_place", "diff", "preview", "alignment_out", "spans_out", "audit_log", "dedupe_cache"]
+    )]
+    stream: bool,
+
+    /// With --stream, the text that ends a record: `newline`, `null` (NUL-terminated records,
+    /// as `xargs -0` reads), or text with \n, \r, \t, \0 and \\ escapes; e.g. '\n\n' for
+    /// paragraphs.
+    #[arg(
+        long,
+        value_name = "SEP",
+        requires = "stream",
+        default_value = "newline"
+    )]
+    record_separator: String,
+
+    /// With --stream, write each record's stats to stderr as one JSON line, `{"record": N,
+    /// "stats": {...}}`, as the record is written. N counts records from 1.
+    #[arg(long, requires = "stream")]
+    stats_per_record: bool,
+
+    /// With several inputs, stop at the first file that fails instead of reporting it and
+    /// continuing with the rest.
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// With several inputs, how many files to scrub at once; defaults to the number of CPUs.
+    /// Output and stats are the same for any value. --dedupe scrubs one file at a time.
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Keep running and scrub each file that appears or changes in --input-dir into
+    /// --output-dir, until Ctrl-C. Files ending in .tmp are skipped until renamed.
+    #[arg(long, requires_all = ["input_dir", "output_dir"])]
+    watch: bool,
+
+    /// With --watch, milliseconds between looks at the input directory. A file is scrubbed
+    /// once it is unchanged across one interval.
+    #[arg(long, value_name = "MS", default_value_t = 1000, requires = "watch")]
+    poll_interval: u64,
+
+    /// With --watch, move each scrubbed input into a processed/ folder inside --input-dir.
+    #[arg(long, requires = "watch")]
+    move_processed: bool,
+
+    /// Output shape: the scrubbed text, or a self-contained HTML page with each redaction
+    /// highlighted by category and the stats table as a legend. jsonl reads one JSON record
+    /// per line and scrubs only the --field values; csv scrubs only the --column fields.
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// With --format jsonl, a string field to scrub: a key, or a dotted path into nested
+    /// objects such as payload.note.text. Repeatable.
+    #[arg(long, value_name = "PATH")]
+    field: Vec<String>,
+
+    /// With --format jsonl, add each record's redaction counts under "_scrub_stats".
+    #[arg(long)]
+    attach_stats: bool,
+
+    /// With --format csv, a column to scrub: a header name, or a 0-based index. Repeatable.
+    #[arg(long, value_name = "NAME|INDEX")]
+    column: Vec<String>,
+
+    /// Print a unified diff from the input to the scrubbed text to stdout instead of the text.
+    /// Removed PHI shows as a placeholder unless --log-phi is set.
+    #[arg(long, conflicts_with_all = ["output", "format", "dedupe_cache"])]
+    diff: bool,
+
+    /// Color the diff with ANSI escapes when stdout is a terminal.
+    #[arg(long, requires = "diff")]
+    diff_color: bool,
+
+    /// Print the original text to stdout with each match highlighted instead of replaced,
+    /// then the usual stats. Shows PHI, so it needs --log-phi.
+    #[arg(
+        long,
+        requires = "log_phi",
+        conflicts_with_all = ["output", "format", "diff", "dedupe_cache"]
+    )]
+    preview: bool,
+
+    /// Mark --preview matches as «CATEGORY:text» instead of with ANSI colors. Colors are also
+    /// off when stdout is not a terminal.
+    #[arg(long, requires = "preview")]
+    no_color: bool,
+
+    /// Path to JSON config that augments the default dictionaries. Repeat to layer configs;
+    /// later files append to lists and override settings (see `ScrubberConfig::merge`).
+    #[arg(short, long, global = true)]
+    config: Vec<PathBuf>,
+
+    /// Print the effective config, after merging every --config and applying command-line
+    /// overrides, as JSON and exit. Lists are printed in full; the `config` subcommand shows
+    /// the resolved settings instead.
+    #[arg(long)]
+    print_config: bool,
+
+    /// Redaction categories to skip (e.g. --skip person,date --skip custom:encounter).
+    #[arg(long, global = true, value_delimiter = ',')]
+    skip: Vec<CategoryKey>,
+
+    /// Redact only these categories and skip every other (e.g. --only ssn,mrn,phone). Also
+    /// runs any of them that the config's `skip` list turns off, and the age rule outside
+    /// Safe Harbor mode.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "CATEGORY",
+        conflicts_with = "skip"
+    )]
+    only: Vec<CategoryKey>,
+
+    /// Run a category that the config's `skip` list turns off, for this run only (e.g.
+    /// --unskip zip). `--unskip age` also runs the age rule outside Safe Harbor mode.
+    #[arg(
+        long,
+        visible_alias = "enable",
+        value_name = "CATEGORY",
+        value_delimiter = ','
+    )]
+    unskip: Vec<CategoryKey>,
+
+    /// Allow original matched text in diagnostic output (repl annotations and the like). Off by
+    /// default, so stderr never carries PHI.
+    #[arg(long, global = true)]
+    log_phi: bool,
+
+    /// Exit with status 3 after writing the output and the summary if anything was redacted,
+    /// or only if one of the listed categories was (e.g. --fail-on-phi person,ssn).
+    #[arg(long, value_delimiter = ',', num_args = 0..)]
+    fail_on_phi: Option<Vec<CategoryKey>>,
+
+    /// Scrub in full and report the stats, but write no scrubbed text (or diff) anywhere and
+    /// print nothing to stdout except spans asked for with --spans-out -. With --fail-on-phi
+    /// this only detects.
+    #[arg(long, conflicts_with_all = ["output", "in_place", "dedupe", "dedupe_cache", "manifest"])]
+    dry_run: bool,
 
     /// Suppress redaction summary.
     #[arg(long)]
     quiet: bool,
 
-    /// Emit redaction stats as JSON to stderr.
-    #[arg(long)]
+    /// Add per-rule counts and per-line redaction counts ("line 42: person 2, phone 1") to the
+    /// summary, and a `lines` array to --stats-json.
+    #[arg(long, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Emit redaction stats as JSON instead of the human summary; same as --stats-format json.
+    #[arg(long, conflicts_with = "stats_format")]
     stats_json: bool,
 
-    /// Enable additional HIPAA Safe Harbor redactions (IDs, licenses, IPs, etc.).
+    /// Shape of the stats report: the human summary, JSON, or `file,category,count` CSV rows
+    /// for spreadsheets.
+    #[arg(long, value_enum, default_value = "text")]
+    stats_format: StatsFormat,
+
+    /// With --stats-format csv, list every category, including those with no redactions.
     #[arg(long)]
+    csv_all: bool,
+
+    /// Write the summary (or --stats-json) to this file, replaced atomically, instead of
+    /// stderr. '-' keeps it on stderr.
+    #[arg(long, conflicts_with = "quiet")]
+    stats_output: Option<PathBuf>,
+
+    /// Time each detector and print the slowest first to stderr; the timings are also included
+    /// in --stats-json.
+    #[arg(long)]
+    profile_detectors: bool,
+
+    /// Shape of every JSON output (stats, spans, alignment, audit log, rule description, webhook
+    /// events).
+    #[arg(long, value_enum, default_value = "1")]
+    schema_version: SchemaVersion,
+
+    /// Enable additional HIPAA Safe Harbor redactions (IDs, licenses, IPs, ages over 89, etc.)
+    /// and truncate ZIP codes to three digits.
+    #[arg(long, global = true)]
     safe_harbor: bool,
+
+    /// Regulatory profile selecting categories and rendering modes; --skip and config settings
+    /// still apply on top.
+    #[arg(long, visible_alias = "profile", value_enum, global = true)]
+    regulation: Option<Regulation>,
+
+    /// Drop rules below this confidence tier; overrides the config's `min_confidence`. Each
+    /// rule's tier is listed by --describe-rules.
+    #[arg(long, value_enum)]
+    min_confidence: Option<Confidence>,
+
+    /// Which person matchers run, from `strict` (dictionary and titled names) to `aggressive`
+    /// (any run of capitalized words, the default); overrides the config's `person_mode`.
+    #[arg(long, value_enum)]
+    person_mode: Option<PersonMode>,
+
+    /// Ignore the CLINICAL_SCRUBBER_* environment variables, for reproducible runs.
+    #[arg(long)]
+    no_env: bool,
+
+    /// Never redact this term (repeatable); added to the config's `allowlist`.
+    #[arg(long = "allow", value_name = "TERM")]
+    allow: Vec<String>,
+
+    /// Seed for everything randomized, overriding the config's `seed` and its `date_shift`
+    /// seed; the same seed, input and config give the same output. It does not turn on date
+    /// shifting, which only the config's `date_shift` does.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Treat input as a short standalone value (form field); trades precision for recall.
+    #[arg(long, global = true)]
+    field_mode: bool,
+
+    /// Drop the input's final newline and join line breaks before punctuation, as output did
+    /// before line endings were kept; sets the config's `normalize_newlines`.
+    #[arg(long)]
+    normalize_newlines: bool,
+
+    /// Print the active rule set (as a table or JSON) and exit without scrubbing.
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "table")]
+    describe_rules: Option<DescribeFormat>,
+
+    /// Write a JSON mapping of input byte ranges to output byte ranges to this file.
+    #[arg(long)]
+    alignment_out: Option<PathBuf>,
+
+    /// Write one JSON line per redaction (category, input range, output range) to this file,
+    /// up to the config's `max_spans`, or every one when --audit-log is also given. '-' writes
+    /// them to stdout, with --dry-run.
+    #[arg(long)]
+    spans_out: Option<PathBuf>,
+
+    /// Write one JSON line per redaction, including the original text, to this restricted
+    /// file (mode 0600 on Unix) for audits. Off by default; "-" is rejected.
+    #[arg(long)]
+    audit_log: Option<PathBuf>,
+
+    /// Add this file's stats to a JSON report holding every file's stats and their total,
+    /// keyed by the --input path. Reruns on the same file replace its entry.
+    #[arg(long)]
+    stats_report: Option<PathBuf>,
+
+    /// With several inputs, write a JSON manifest of the run to this file at the end: each
+    /// input and output with its SHA-256 and stats, the resolved config's hash, the tool
+    /// version and the run's start and end. Written atomically.
+    #[arg(long, value_name = "PATH")]
+    manifest: Option<PathBuf>,
+
+    /// With several inputs, scrub each distinct document once: a byte-identical repeat gets a
+    /// copy of the first one's output. The summary counts unique and duplicate documents, and
+    /// --manifest names the file each duplicate repeats.
+    #[arg(long, conflicts_with = "stats_report")]
+    dedupe: bool,
+
+    /// Reuse the output of an earlier run on a byte-identical input instead of scrubbing it
+    /// again, tracked across runs in this JSON file; implies --dedupe. Needs --input and
+    /// --output files, or --output-dir.
+    #[arg(long, conflicts_with_all = ["alignment_out", "spans_out", "audit_log", "stats_report"])]
+    dedupe_cache: Option<PathBuf>,
+
+    /// Before writing the output, ask on the terminal about each redaction made by a
+    /// --review-rule below high confidence, showing it with a few words on each side. Answers
+    /// are y (redact), n (keep), a (always redact that text) or v (never redact it); higher
+    /// confidence redactions are made without asking. Needs an --input file and a terminal.
+    #[arg(
+        long,
+        conflicts_with_all = ["stream", "input_dir", "output_dir", "in_place", "watch", "dedupe_cache"]
+    )]
+    interactive: bool,
+
+    /// With --interactive, the rules to ask about, as `<category>.<rule>` from
+    /// --describe-rules.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "RULE",
+        requires = "interactive",
+        default_values_t = review::DEFAULT_RULES.map(String::from)
+    )]
+    review_rule: Vec<String>,
+
+    /// With --interactive, write the session's always and never answers to this file as a
+    /// config snippet (`names`, `keywords` and `allowlist`) to pass with --config later.
+    #[arg(long, value_name = "PATH", requires = "interactive")]
+    save_decisions: Option<PathBuf>,
+
+    /// POST a JSON event (stats and metadata, never text) to this https:// or http:// URL per
+    /// document; in a batch, once for each file scrubbed.
+    #[cfg(feature = "webhook")]
+    #[arg(long)]
+    webhook_url: Option<String>,
+
+    /// Header sent with each webhook request, e.g. "Authorization: Bearer abc123". Needs an
+    /// https:// URL, or a loopback host over http://, so it never travels in cleartext.
+    #[cfg(feature = "webhook")]
+    #[arg(long, requires = "webhook_url")]
+    webhook_auth_header: Option<String>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, ValueEnum)]
-enum Category {
-    Email,
-    Phone,
-    Date,
-    RelativeDate,
-    Ssn,
-    Mrn,
-    Zip,
-    Person,
-    Facility,
-    Address,
-    Coordinate,
-    Url,
-    Insurance,
-    License,
-    Vehicle,
-    Device,
-    Ip,
-}
-
-fn main() -> Result<()> {
-    let args = Args::parse();
-    let skip: HashSet<Category> = args.skip.into_iter().collect();
-
-    let config = load_config(args.config.as_ref())?;
-    let scrubber = Scrubber::new(config, args.safe_harbor)?;
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Preview redactions line by line while tuning a config; type :help for commands.
+    Repl,
+    /// Print the fully resolved settings (categories, tokens, modes, word list sizes) as JSON
+    /// and exit. Word lists are counted, never listed.
+    Config,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Html,
+    Jsonl,
+    Csv,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum StatsFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum DescribeFormat {
+    Table,
+    Json,
+}
+
+/// Exit status after SIGINT/SIGTERM, following the shell's 128 + SIGINT convention.
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Exit status when `--fail-on-phi` finds redactions, distinct from clap's usage error (2).
+const EXIT_PHI_FOUND: i32 = 3;
+
+/// Redactions that `--fail-on-phi` turns into a failing exit status.
+#[derive(Debug)]
+struct PhiFound(usize);
+
+impl std::fmt::Display for PhiFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} redaction(s) found; failing per --fail-on-phi",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for PhiFound {}
+
+/// A batch stopped by Ctrl-C after finishing `done` of its `total` files.
+#[derive(Debug)]
+struct BatchInterrupted {
+    done: usize,
+    total: usize,
+}
+
+impl std::fmt::Display for BatchInterrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "interrupted after {} of {} files; their output was kept and the rest were not started",
+            self.done, self.total
+        )
+    }
+}
+
+impl std::error::Error for BatchInterrupted {}
+
+fn main() {
+    match run() {
+        // A closed downstream pipe (e.g. `| head`) just means nobody wants more output.
+        Err(err) if is_broken_pipe(&err) => {}
+        Err(err) if err.is::<Cancelled>() => {
+            stderrln!("interrupted; no output written");
+            process::exit(EXIT_INTERRUPTED);
+        }
+        Err(err) if err.is::<BatchInterrupted>() => {
+            stderrln!("{}", err);
+            process::exit(EXIT_INTERRUPTED);
+        }
+        Err(err) if err.is::<PhiFound>() => {
+            stderrln!("{}", err);
+            process::exit(EXIT_PHI_FOUND);
+        }
+        Err(err) => {
+            // The report `fn main() -> Result` would print, without its panic on a closed stderr.
+            stderrln!("Error: {:?}", err);
+            process::exit(1);
+        }
+        Ok(()) => {}
+    }
+}
+
+fn write_stderr(args: std::fmt::Arguments) {
+    let mut stderr = io::stderr().lock();
+    stderr.write_fmt(args).and_then(|()| stderr.flush()).ok();
+}
+
+fn run() -> Result<()> {
+    let started = SystemTime::now();
+    let mut args = Args::parse();
+    let env_names = if args.no_env {
+        Vec::new()
+    } else {
+        apply_env(&mut args, |name| std::env::var(name).ok())?
+    };
+    let is_file = |path: &PathBuf| path != std::path::Path::new("-");
+    if let (Some(output), Some(stats)) = (&args.output, &args.stats_output) {
+        if is_file(output) && is_file(stats) && resolved(output) == resolved(stats) {
+            return Err(anyhow::anyhow!(
+                "--output and --stats-output both point at {}; pick separate files",
+                output.display()
+            ));
+        }
+    }
+    check_format_args(&args)?;
+    if args.audit_log.as_deref() == Some(std::path::Path::new("-")) {
+        return Err(anyhow::anyhow!(
+            "--audit-log needs a file path; it is never written to stdout"
+        ));
+    }
+    if args.spans_out.as_deref() == Some(std::path::Path::new("-")) && !args.dry_run {
+        return Err(anyhow::anyhow!(
+            "--spans-out - needs --dry-run, since stdout otherwise carries the scrubbed text"
+        ));
+    }
+    let mode = if args.safe_harbor {
+        ScrubMode::SafeHarbor
+    } else {
+        ScrubMode::Standard
+    };
+
+    if let Some(Command::Repl) = args.command {
+        if !args.only.is_empty() {
+            return Err(anyhow::anyhow!(
+                "--only is not available in the repl; use --skip there"
+            ));
+        }
+        let skip = args.skip.iter().cloned().collect();
+        let settings = ReplSettings {
+            config_paths: args.config,
+            mode,
+            field_mode: args.field_mode,
+            regulation: args.regulation,
+            skip,
+            names: env_names,
+            log_phi: args.log_phi,
+        };
+        return repl::run(settings, io::stdin().lock(), io::stdout().lock());
+    }
+
+    let mut config = ScrubberConfig::from_files(&args.config)?;
+    config.field_mode |= args.field_mode;
+    config.normalize_newlines |= args.normalize_newlines;
+    if args.regulation.is_some() {
+        config.regulation = args.regulation;
+    }
+    if let Some(min_confidence) = args.min_confidence {
+        config.min_confidence = min_confidence;
+    }
+    if let Some(person_mode) = args.person_mode {
+        config.person_mode = person_mode;
+    }
+    config.names.extend(env_names.iter().cloned());
+    config.allowlist.extend(args.allow.iter().cloned());
+    config
+        .skip
+        .retain(|category| !args.unskip.contains(category) && !args.only.contains(category));
+    let age = CategoryKey::Builtin(Category::Age);
+    if args.only.contains(&age) || args.unskip.contains(&age) {
+        config.age_mode = Some(config.age_mode.unwrap_or_default());
+    }
+    if let Some(seed) = args.seed {
+        // Seeds what the config randomizes; it never turns date shifting on.
+        config.seed = Some(seed);
+        if let Some(shift) = config.date_shift.as_mut() {
+            shift.seed = None;
+        }
+    }
+    if args.print_config {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+        return Ok(());
+    }
+    let merged = serde_json::to_value(&config)?;
+    let scrubber = match Scrubber::new(config, mode) {
+        Err(err) if !args.config.is_empty() => {
+            let paths: Vec<String> = args
+                .config
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            return Err(err.context(format!("invalid config: {}", paths.join(", "))));
+        }
+        scrubber => scrubber?,
+    };
+    for warning in scrubber.token_warnings() {
+        stderrln!("warning: {}", warning);
+    }
+    let skip = resolve_skip(&args, &scrubber.categories())?;
+    // The merged config has the word lists themselves; the resolved one adds the mode and the
+    // command line's skips, which change output too.
+    let config_sha256 = manifest::sha256_hex(&serde_json::to_vec(&(
+        &merged,
+        scrubber.resolved_config(&skip),
+    ))?);
+    let not_enabled = scrubber.report_options(&skip).not_enabled;
+    for (flag, named) in [("--only", &args.only), ("--unskip", &args.unskip)] {
+        if let Some(category) = named.iter().find(|category| not_enabled.contains(category)) {
+            return Err(anyhow::anyhow!(
+                "{} {}: none of its detectors run with this mode and config; categories only \
+                 Safe Harbor redacts need --safe-harbor",
+                flag,
+                category
+            ));
+        }
+    }
+
+    if let Some(Command::Config) = args.command {
+        let resolved = scrubber.resolved_config(&skip);
+        let rendered =
+            serde_json::to_string_pretty(&Versioned::new(args.schema_version, &resolved))
+                .context("failed to serialize resolved config")?;
+        return write_stdout(&(rendered + "\n"));
+    }
+
+    if let Some(format) = args.describe_rules {
+        let description = scrubber.describe(&skip);
+        let rendered = match format {
+            DescribeFormat::Table => description.render_table(),
+            DescribeFormat::Json => {
+                serde_json::to_string_pretty(&Versioned::new(args.schema_version, &description))
+                    .context("failed to serialize rule description")?
+                    + "\n"
+            }
+        };
+        return write_stdout(&rendered);
+    }
+
+    let mut files = input_files(&args)?;
+    let batch = args.input_dir.is_some() || args.output_dir.is_some() || args.in_place.is_some();
+    if !batch && files.len() > 1 {
+        return Err(anyhow::anyhow!(
+            "{} inputs need --output-dir or --in-place; --output takes a single input",
+            files.len()
+        ));
+    }
+    if !batch {
+        if args.manifest.is_some() {
+            return Err(anyhow::anyhow!(
+                "--manifest needs several inputs: --input-dir, --output-dir or --in-place"
+            ));
+        }
+        if args.dedupe {
+            return Err(anyhow::anyhow!(
+                "--dedupe needs several inputs: --input-dir or --output-dir; \
+                 use --dedupe-cache to reuse output across runs"
+            ));
+        }
+        args.input = files.pop().or(args.input);
+    }
+    if args.interactive {
+        check_interactive(&args)?;
+    }
+
+    let mut dedupe = match &args.dedupe_cache {
+        Some(path) => Some(open_dedupe_cache(
+            path, &args, &env_names, &scrubber, &skip,
+        )?),
+        // Kept for this run only, so every entry was made under the same settings.
+        None if args.dedupe => Some(DedupeCache::new(String::new())),
+        None => None,
+    };
+
+    #[cfg(feature = "webhook")]
+    let mut webhook = match &args.webhook_url {
+        Some(url) => Some(WebhookSender::start(WebhookOptions {
+            auth_header: args.webhook_auth_header.clone(),
+            ..WebhookOptions::new(url.as_str())
+        })?),
+        None => None,
+    };
+
+    let cancel = CancellationToken::new();
+    interrupt::install(&cancel);
+
+    if args.watch || batch {
+        // Ctrl-C stops the batch between files. Scrubs get a token of their own, so the files
+        // in flight are finished rather than abandoned.
+        let scrub_cancel = CancellationToken::new();
+        let batch = Batch {
+            args: &args,
+            scrubber: &scrubber,
+            skip: &skip,
+            cancel: &scrub_cancel,
+            interrupt: &cancel,
+            started,
+            config_sha256: &config_sha256,
+            #[cfg(feature = "webhook")]
+            fingerprint: webhook
+                .as_ref()
+                .map(|_| scrubber.describe(&skip).fingerprint())
+                .unwrap_or_default(),
+            #[cfg(feature = "webhook")]
+            webhook: Mutex::new(webhook.take()),
+        };
+        if args.watch {
+            return batch.watch(dedupe.as_mut());
+        }
+        let mut binary = Vec::new();
+        let jobs = batch_jobs(&args, files, &mut binary)?;
+        for path in &binary {
+            stderrln!("warning: skipping {}: looks binary", path.display());
+        }
+        return batch.run(&jobs, &binary, dedupe.as_mut());
+    }
+
+    if args.stream || matches!(args.format, OutputFormat::Jsonl | OutputFormat::Csv) {
+        #[cfg(feature = "webhook")]
+        let started = Instant::now();
+        let stats = stream_document(&args, &scrubber, &skip, &cancel)?;
+        #[cfg(feature = "webhook")]
+        let webhook = webhook.take().map(|webhook| (webhook, started.elapsed()));
+        return report_document(
+            &args,
+            &scrubber,
+            &skip,
+            &stats,
+            #[cfg(feature = "webhook")]
+            webhook,
+        );
+    }
 
     let input = read_input(args.input.as_ref())?;
-    let (scrubbed, stats) = scrubber.scrub(&input, &skip);
-    write_output(args.output.as_ref(), &scrubbed)?;
+    if let Some(earlier) = dedupe
+        .as_mut()
+        .and_then(|cache| cache.find(input.as_bytes()))
+    {
+        reuse_output(&earlier, args.output.as_ref().expect("checked"))?;
+        if !args.quiet {
+            stderrln!(
+                "duplicate of {}: reused {}",
+                earlier.input.display(),
+                earlier.output.display()
+            );
+        }
+        return Ok(());
+    }
+    #[cfg(feature = "webhook")]
+    let started = Instant::now();
+    let options = ScrubOptions {
+        collect_timings: args.profile_detectors,
+        collect_lines: args.verbose,
+        all_spans: args.audit_log.is_some(),
+    };
+    let mut result = scrubber.scrub_with_options(&input, &skip, &options, &cancel)?;
+    if args.interactive {
+        review_detections(&args, &input, &mut result)?;
+    }
+    #[cfg(feature = "webhook")]
+    let elapsed = started.elapsed();
+    if args.dry_run {
+        // Nothing to write; the summary below still reports what was found.
+    } else if args.preview {
+        let color = !args.no_color && io::stdout().is_terminal();
+        write_stdout(&preview::render_preview(&input, &result.spans, color))?;
+    } else if args.diff {
+        let options = DiffOptions {
+            color: args.diff_color && io::stdout().is_terminal(),
+            log_phi: args.log_phi,
+        };
+        write_stdout(&diff::render_diff(&input, &result, options))?;
+    } else {
+        match args.format {
+            OutputFormat::Html => write_output(args.output.as_ref(), &html::render_html(&result))?,
+            // jsonl and csv are scrubbed by `stream_document`.
+            OutputFormat::Text | OutputFormat::Jsonl | OutputFormat::Csv => {
+                write_output(args.output.as_ref(), &result.text)?
+            }
+        }
+    }
+
+    if let (Some(cache), Some(path)) = (dedupe.as_mut(), args.dedupe_cache.as_ref()) {
+        cache.insert(
+            input.as_bytes(),
+            DedupeEntry {
+                input: absolute(args.input.as_ref().expect("checked")),
+                output: absolute(args.output.as_ref().expect("checked")),
+            },
+        );
+        cache.save(path)?;
+    }
+
+    if let Some(path) = args.alignment_out.as_ref() {
+        let json = serde_json::to_string(&Versioned::new(args.schema_version, &result.alignment))
+            .context("failed to serialize alignment")?;
+        fs::write(path, json)
+            .with_context(|| format!("failed to write alignment file: {}", path.display()))?;
+    }
+
+    if let Some(path) = args.spans_out.as_ref() {
+        write_spans(path, &result.spans, args.schema_version)?;
+    }
+
+    if let Some(path) = args.audit_log.as_ref() {
+        let source = args
+            .input
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |path| path.display().to_string());
+        audit::write_audit_log(path, &source, &input, &result.spans, args.schema_version)?;
+    }
+
+    #[cfg(feature = "webhook")]
+    let webhook = webhook.take().map(|webhook| (webhook, elapsed));
+    report_document(
+        &args,
+        &scrubber,
+        &skip,
+        &result.stats,
+        #[cfg(feature = "webhook")]
+        webhook,
+    )
+}
+
+/// `--stream` and `--format jsonl`: scrubs the input record by record into the output, written
+/// beside it and renamed into place at the end as `write_output` does.
+fn stream_document(
+    args: &Args,
+    scrubber: &Scrubber,
+    skip: &HashSet<CategoryKey>,
+    cancel: &CancellationToken,
+) -> Result<ScrubStats> {
+    if args.format == OutputFormat::Html {
+        return Err(anyhow::anyhow!(
+            "--stream writes text; --format html needs the whole document"
+        ));
+    }
+    let options = ScrubOptions {
+        collect_timings: args.profile_detectors,
+        collect_lines: args.verbose,
+        ..ScrubOptions::default()
+    };
+    let stream_options = StreamOptions {
+        scrub: options,
+        ..StreamOptions::new(unescape_separator(&args.record_separator)?)
+    };
+    let reader: Box<dyn BufRead> = match args.input.as_ref() {
+        Some(path) if path != Path::new("-") => {
+            Box::new(BufReader::new(fs::File::open(path).with_context(|| {
+                format!("failed to read input file: {}", path.display())
+            })?))
+        }
+        _ => Box::new(io::stdin().lock()),
+    };
+    let source = args
+        .input
+        .as_ref()
+        .map_or_else(|| "-".to_string(), |path| path.display().to_string());
+    let stream = |writer: &mut dyn Write| match args.format {
+        OutputFormat::Jsonl => jsonl::scrub_jsonl(
+            scrubber,
+            reader,
+            writer,
+            &jsonl_options(args, options)?,
+            skip,
+            cancel,
+            |line, err| warn_unparsed(&source, line, err),
+        ),
+        OutputFormat::Csv => csv::scrub_csv(
+            scrubber,
+            reader,
+            writer,
+            &csv_options(args, options),
+            skip,
+            cancel,
+        ),
+        _ => stream::scrub_stream(
+            scrubber,
+            reader,
+            writer,
+            &stream_options,
+            skip,
+            cancel,
+            |record, stats| report_record(args, record, stats),
+        ),
+    };
+    match args.output.as_ref() {
+        _ if args.dry_run => stream(&mut io::sink()),
+        Some(path) if path != Path::new("-") => {
+            let mut partial = path.clone().into_os_string();
+            partial.push(".partial");
+            let partial = PathBuf::from(partial);
+            let file = fs::File::create(&partial)
+                .with_context(|| format!("failed to create output file: {}", path.display()))?;
+            let mut writer = BufWriter::new(file);
+            let written = stream(&mut writer).and_then(|stats| {
+                let file = writer.into_inner().map_err(|err| err.into_error())?;
+                file.sync_all()?;
+                Ok(stats)
+            });
+            let stats = match written {
+                Ok(stats) => stats,
+                Err(err) => {
+                    fs::remove_file(&partial).ok();
+                    return Err(err);
+                }
+            };
+            fs::rename(&partial, path)
+                .with_context(|| format!("failed to write output file: {}", path.display()))?;
+            Ok(stats)
+        }
+        _ => stream(&mut BufWriter::new(io::stdout().lock())),
+    }
+}
+
+/// `--stats-per-record`: one JSON line on stderr for a record of a `--stream`.
+fn report_record(args: &Args, record: usize, stats: &ScrubStats) -> Result<()> {
+    if !args.stats_per_record {
+        return Ok(());
+    }
+    let line = serde_json::json!({
+        "record": record,
+        "stats": stats.to_json_value(args.schema_version)?,
+    });
+    let mut stderr = io::stderr().lock();
+    writeln!(stderr, "{}", line)?;
+    stderr.flush()?;
+    Ok(())
+}
+
+/// `--field` and `--attach-stats` go with `--format jsonl` and `--column` with `--format csv`.
+/// Each format needs something to scrub and cannot produce the whole-document outputs.
+fn check_format_args(args: &Args) -> Result<()> {
+    if args.format != OutputFormat::Jsonl && (!args.field.is_empty() || args.attach_stats) {
+        return Err(anyhow::anyhow!(
+            "--field and --attach-stats need --format jsonl"
+        ));
+    }
+    if args.format != OutputFormat::Csv && !args.column.is_empty() {
+        return Err(anyhow::anyhow!("--column needs --format csv"));
+    }
+    let name = match args.format {
+        OutputFormat::Jsonl if args.field.is_empty() => {
+            return Err(anyhow::anyhow!(
+                "--format jsonl needs at least one --field to scrub"
+            ))
+        }
+        OutputFormat::Csv if args.column.is_empty() => {
+            return Err(anyhow::anyhow!(
+                "--format csv needs at least one --column to scrub"
+            ))
+        }
+        OutputFormat::Jsonl => "jsonl",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Text | OutputFormat::Html => return Ok(()),
+    };
+    let conflicts = [
+        (args.stream, "--stream"),
+        (args.diff, "--diff"),
+        (args.preview, "--preview"),
+        (args.alignment_out.is_some(), "--alignment-out"),
+        (args.spans_out.is_some(), "--spans-out"),
+        (args.audit_log.is_some(), "--audit-log"),
+        (args.dedupe_cache.is_some(), "--dedupe-cache"),
+        (args.interactive, "--interactive"),
+    ];
+    if let Some((_, flag)) = conflicts.iter().find(|(set, _)| *set) {
+        return Err(anyhow::anyhow!(
+            "--format {} cannot be combined with {}",
+            name,
+            flag
+        ));
+    }
+    jsonl_options(args, ScrubOptions::default()).map(|_| ())
+}
+
+/// `--interactive` asks on stderr and reads answers from stdin, so the text must come from a
+/// file and both must be a terminal.
+fn check_interactive(args: &Args) -> Result<()> {
+    if args
+        .input
+        .as_deref()
+        .is_none_or(|path| path == Path::new("-"))
+    {
+        return Err(anyhow::anyhow!(
+            "--interactive reads answers from stdin, so it needs an --input file"
+        ));
+    }
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Err(anyhow::anyhow!("--interactive needs a terminal"));
+    }
+    Ok(())
+}
+
+/// Puts `--interactive` answers into `result` and saves them for `--save-decisions`.
+fn review_detections(args: &Args, input: &str, result: &mut ScrubResult) -> Result<()> {
+    let options = ReviewOptions {
+        rules: args.review_rule.clone(),
+        ..ReviewOptions::default()
+    };
+    let mut decisions = Decisions::default();
+    review::review(
+        input,
+        result,
+        &options,
+        &mut decisions,
+        io::stdin().lock(),
+        io::stderr().lock(),
+    )?;
+    if let Some(path) = &args.save_decisions {
+        let snippet = serde_json::to_string_pretty(&decisions.to_config_snippet())
+            .context("failed to serialize decisions")?;
+        fs::write(path, snippet + "\n")
+            .with_context(|| format!("failed to write decisions: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn jsonl_options(args: &Args, scrub: ScrubOptions) -> Result<JsonlOptions> {
+    let mut options = JsonlOptions::new(&args.field, args.attach_stats)?;
+    options.scrub = scrub;
+    Ok(options)
+}
+
+fn csv_options(args: &Args, scrub: ScrubOptions) -> CsvOptions {
+    CsvOptions {
+        scrub,
+        ..CsvOptions::new(&args.column)
+    }
+}
+
+/// Warns about a `--format jsonl` line that is not JSON, naming where the parser stopped but
+/// none of the line's text.
+fn warn_unparsed(source: &str, line: usize, err: &serde_json::Error) {
+    stderrln!(
+        "warning: {}:{}: not valid JSON ({}); passed through unscrubbed",
+        source,
+        line,
+        err
+    );
+}
+
+/// Reads a `--record-separator`: `newline`, `null`, or text with `\n`, `\r`, `\t`, `\0` and
+/// `\\` escapes.
+fn unescape_separator(text: &str) -> Result<Vec<u8>> {
+    match text {
+        "newline" => return Ok(b"\n".to_vec()),
+        "null" => return Ok(b"\0".to_vec()),
+        _ => {}
+    }
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        out.push(match chars.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            other => {
+                return Err(anyhow::anyhow!(
+                    "--record-separator {:?}: unknown escape \\{}",
+                    text,
+                    other.map(String::from).unwrap_or_default()
+                ))
+            }
+        });
+    }
+    if out.is_empty() {
+        return Err(anyhow::anyhow!("--record-separator must not be empty"));
+    }
+    Ok(out.into_bytes())
+}
+
+/// The reports on one scrubbed document: the stats report and summary, detector timings, the
+/// webhook event, and the `--fail-on-phi` check.
+fn report_document(
+    args: &Args,
+    scrubber: &Scrubber,
+    skip: &HashSet<CategoryKey>,
+    stats: &ScrubStats,
+    #[cfg(feature = "webhook")] webhook: Option<(WebhookSender, Duration)>,
+) -> Result<()> {
+    if let Some(path) = args.stats_report.as_ref() {
+        let (mut report, warning) = StatsReport::load(path);
+        if let Some(warning) = warning {
+            stderrln!("warning: {}", warning);
+        }
+        let source = args
+            .input
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |path| path.display().to_string());
+        report.insert(source, stats);
+        report.save(path, args.schema_version)?;
+    }
 
     if !args.quiet {
-        report_stats(&stats, args.stats_json)?;
+        let source = args
+            .input
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |path| path.display().to_string());
+        let rendered = match (args.stats_json, args.stats_format) {
+            (true, _) | (false, StatsFormat::Json) => {
+                stats.render_json(args.schema_version)? + "\n"
+            }
+            (false, StatsFormat::Csv) => stats.render_csv(&source, args.csv_all),
+            (false, StatsFormat::Text) => stats.render_human(&ReportOptions {
+                rules: args.verbose,
+                ..scrubber.report_options(skip)
+            }),
+        };
+        report_stats(&rendered, args.stats_output.as_ref())?;
+    }
+
+    if args.profile_detectors {
+        stderr!("{}", profiling::render_table(&stats.detector_timings));
+    }
+
+    #[cfg(feature = "webhook")]
+    if let Some((mut webhook, elapsed)) = webhook {
+        let document = args
+            .input
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |path| path.display().to_string());
+        let fingerprint = scrubber.describe(skip).fingerprint();
+        webhook.send(&DocumentEvent::new(
+            &document,
+            stats,
+            args.schema_version,
+            &fingerprint,
+            elapsed,
+        )?)?;
+        finish_webhook(webhook);
+    }
+
+    if let Some(categories) = &args.fail_on_phi {
+        let found = if categories.is_empty() {
+            stats.total()
+        } else {
+            categories
+                .iter()
+                .map(|category| stats.count_key(category))
+                .sum()
+        };
+        if found > 0 {
+            return Err(PhiFound(found).into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits for the webhook's queued events and, if any were lost, says how many.
+#[cfg(feature = "webhook")]
+fn finish_webhook(webhook: WebhookSender) {
+    let report = webhook.finish();
+    if report.failed > 0 || report.dropped > 0 {
+        stderrln!(
+            "webhook: {} delivered, {} failed, {} dropped",
+            report.delivered,
+            report.failed,
+            report.dropped
+        );
+    }
+}
+
+/// The files named by `--input` and the positional arguments, with patterns expanded, in sorted
+/// order and without repeats. Empty when the input is stdin.
+fn input_files(args: &Args) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for arg in args.input.iter().chain(&args.files) {
+        let text = arg.to_string_lossy();
+        if arg.exists() || !glob::is_pattern(&text) {
+            files.push(arg.clone());
+            continue;
+        }
+        let matches = glob::expand(&text).with_context(|| format!("failed to expand {}", text))?;
+        if matches.is_empty() {
+            return Err(anyhow::anyhow!("no files match {}", text));
+        }
+        files.extend(matches);
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Pairs each batch input with where its result goes: over the input with `--in-place`, else
+/// into `--output-dir` under the input's file name.
+fn batch_jobs(
+    args: &Args,
+    files: Vec<PathBuf>,
+    binary: &mut Vec<PathBuf>,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let inputs = match &args.input_dir {
+        Some(input_dir) => {
+            let mut inputs = Vec::new();
+            list_inputs(args, input_dir, &mut HashSet::new(), &mut inputs, binary)?;
+            inputs.sort();
+            inputs
+        }
+        None if files.is_empty() => {
+            return Err(anyhow::anyhow!(
+                "--output-dir and --in-place need input files or --input-dir"
+            ))
+        }
+        None => files,
+    };
+    if inputs.iter().any(|input| input == Path::new("-")) {
+        return Err(anyhow::anyhow!(
+            "stdin ('-') cannot be read as one of a batch of inputs"
+        ));
+    }
+    if args.in_place.is_some() {
+        return Ok(inputs
+            .into_iter()
+            .map(|input| (input.clone(), input))
+            .collect());
+    }
+    let Some(output_dir) = &args.output_dir else {
+        return Err(anyhow::anyhow!(
+            "--input-dir needs --output-dir or --in-place"
+        ));
+    };
+    if !args.dry_run {
+        fs::create_dir_all(output_dir).with_context(|| {
+            format!(
+                "failed to create output directory: {}",
+                output_dir.display()
+            )
+        })?;
     }
+    if let Some(input_dir) = &args.input_dir {
+        if absolute(input_dir) == absolute(output_dir) {
+            return Err(anyhow::anyhow!(
+                "--input-dir and --output-dir both point at {}; pick separate directories",
+                input_dir.display()
+            ));
+        }
+    }
+    let mut jobs: Vec<(PathBuf, PathBuf)> = Vec::with_capacity(inputs.len());
+    let mut writers: HashMap<PathBuf, usize> = HashMap::new();
+    for input in inputs {
+        let Some(name) = input.file_name() else {
+            return Err(anyhow::anyhow!("not a file: {}", input.display()));
+        };
+        let relative = args
+            .input_dir
+            .as_ref()
+            .and_then(|input_dir| input.strip_prefix(input_dir).ok())
+            .unwrap_or(Path::new(name));
+        let output = output_dir.join(relative);
+        if absolute(&output) == absolute(&input) {
+            return Err(anyhow::anyhow!(
+                "--output-dir would overwrite the input {}; use --in-place for that",
+                input.display()
+            ));
+        }
+        if let Some(&earlier) = writers.get(&output) {
+            return Err(anyhow::anyhow!(
+                "{} and {} would both be written to {}",
+                jobs[earlier].0.display(),
+                input.display(),
+                output.display()
+            ));
+        }
+        writers.insert(output.clone(), jobs.len());
+        jobs.push((input, output));
+    }
+    if !args.dry_run {
+        let parents: HashSet<&Path> = jobs
+            .iter()
+            .filter_map(|(_, output)| output.parent())
+            .collect();
+        for parent in parents {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("failed to create output directory: {}", parent.display())
+            })?;
+        }
+    }
+    Ok(jobs)
+}
 
+/// Adds the files in `dir` to `inputs`, and those that look binary to `binary`, keeping only
+/// `--ext` matches. With `--recursive` it descends into subdirectories other than the output
+/// directory and, with `--move-processed`, the processed folder. Symlinks are skipped when
+/// recursing unless `--follow-symlinks`; `visited` stops a link cycle.
+fn list_inputs(
+    args: &Args,
+    dir: &Path,
+    visited: &mut HashSet<PathBuf>,
+    inputs: &mut Vec<PathBuf>,
+    binary: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if !visited.insert(fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf())) {
+        return Ok(());
+    }
+    let entries: Vec<PathBuf> = fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<_>>()
+        })
+        .with_context(|| format!("failed to read input directory: {}", dir.display()))?;
+    let processed = args
+        .input_dir
+        .as_ref()
+        .filter(|_| args.move_processed)
+        .map(|input_dir| input_dir.join("processed"));
+    let excluded: Vec<PathBuf> = args
+        .output_dir
+        .iter()
+        .chain(processed.iter())
+        .map(|dir| absolute(dir))
+        .collect();
+    for path in entries {
+        let linked = fs::symlink_metadata(&path).is_ok_and(|meta| meta.file_type().is_symlink());
+        if args.recursive && linked && !args.follow_symlinks {
+            continue;
+        }
+        if path.is_dir() {
+            if args.recursive && !excluded.contains(&absolute(&path)) {
+                list_inputs(args, &path, visited, inputs, binary)?;
+            }
+            continue;
+        }
+        let wanted = args.ext.is_empty()
+            || path.extension().is_some_and(|extension| {
+                args.ext
+                    .iter()
+                    .any(|ext| extension.eq_ignore_ascii_case(ext.trim_start_matches('.')))
+            });
+        if !path.is_file() || !wanted {
+            continue;
+        }
+        if looks_binary(&path) {
+            binary.push(path);
+        } else {
+            inputs.push(path);
+        }
+    }
     Ok(())
 }
 
+/// Whether the first 8 KB of `path` hold a NUL byte, as text never does. A file that cannot
+/// be read is left for the scrub to report.
+fn looks_binary(path: &Path) -> bool {
+    let mut head = Vec::with_capacity(8192);
+    fs::File::open(path)
+        .and_then(|file| file.take(8192).read_to_end(&mut head))
+        .is_ok_and(|_| head.contains(&0))
+}
+
+/// What became of one file in a batch, with its hashes when `--manifest` asks for them.
+enum BatchOutcome {
+    Scrubbed(Box<ScrubStats>, Option<Digests>),
+    /// A byte-identical input was scrubbed before; its output was copied.
+    Reused(DedupeEntry, Option<Digests>),
+    Failed(anyhow::Error),
+}
+
+/// Where a file's output went, and the SHA-256 of its input as read and its output as
+/// written.
+struct Digests {
+    output: PathBuf,
+    input_sha256: String,
+    output_sha256: String,
+}
+
+/// One file's line in the text summary of a batch.
+fn outcome_summary(outcome: &BatchOutcome) -> String {
+    match outcome {
+        BatchOutcome::Scrubbed(stats, _) => match stats.total() {
+            1 => "1 redaction".to_string(),
+            count => format!("{} redactions", count),
+        },
+        BatchOutcome::Reused(earlier, _) => format!(
+            "duplicate of {}, reused its output",
+            earlier.input.display()
+        ),
+        BatchOutcome::Failed(_) => "failed".to_string(),
+    }
+}
+
+/// Several inputs (`--input-dir`, or several files or a pattern with `--output-dir` or
+/// `--in-place`): every file scrubbed by the one `Scrubber`, reported per file and in total.
+struct Batch<'a> {
+    args: &'a Args,
+    scrubber: &'a Scrubber,
+    skip: &'a HashSet<CategoryKey>,
+    cancel: &'a CancellationToken,
+    /// Cancelled by the first Ctrl-C: no further file starts, and the ones being scrubbed
+    /// finish.
+    interrupt: &'a CancellationToken,
+    /// When the run started, and the hash of its resolved config, for `--manifest`.
+    started: SystemTime,
+    config_sha256: &'a str,
+    /// Fingerprint of the rules, named by each webhook event.
+    #[cfg(feature = "webhook")]
+    fingerprint: String,
+    /// `--webhook-url`: one sender for every file of the batch, until `finish` takes it.
+    #[cfg(feature = "webhook")]
+    webhook: Mutex<Option<WebhookSender>>,
+}
+
+impl Batch<'_> {
+    /// Scrubs each input into its output. A file that fails is reported and the rest still
+    /// run, unless `--fail-fast`; the batch then exits with an error naming how many failed.
+    /// After an interrupt the files already written are kept and reported, the manifest is
+    /// marked `interrupted`, and the run ends with `BatchInterrupted`. `skipped` are the
+    /// inputs passed over as binary.
+    fn run(
+        &self,
+        jobs: &[(PathBuf, PathBuf)],
+        skipped: &[PathBuf],
+        mut dedupe: Option<&mut DedupeCache>,
+    ) -> Result<()> {
+        let args = self.args;
+        let mut outcomes = Vec::with_capacity(jobs.len());
+        for (index, result) in self.scrub_all(jobs, dedupe.as_deref_mut()) {
+            let outcome = match result {
+                Ok(outcome) => outcome,
+                Err(err) if args.fail_fast => return Err(err),
+                Err(err) => BatchOutcome::Failed(err),
+            };
+            if let BatchOutcome::Failed(err) = &outcome {
+                stderrln!("error: {:#}", err);
+            }
+            outcomes.push((jobs[index].0.display().to_string(), outcome));
+        }
+        let interrupted = outcomes.len() < jobs.len() && self.interrupt.is_cancelled();
+        self.finish(&outcomes, skipped, dedupe, interrupted)?;
+        if interrupted {
+            return Err(BatchInterrupted {
+                done: outcomes.len(),
+                total: jobs.len(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Scrubs each file that appears or changes in `--input-dir` until the first Ctrl-C, then
+    /// reports the files as `run` does. A file is picked up once its size and modification
+    /// time hold still for one poll, so a copy in progress is not read half written; `.tmp`
+    /// files are left for the writer to rename. The file in flight is finished before the
+    /// watch stops.
+    fn watch(&self, mut dedupe: Option<&mut DedupeCache>) -> Result<()> {
+        let args = self.args;
+        let interrupt = self.interrupt;
+        let input_dir = args
+            .input_dir
+            .as_ref()
+            .expect("--watch requires --input-dir");
+        let interval = Duration::from_millis(args.poll_interval);
+        // Size and modification time: as seen at the last poll, and as last scrubbed.
+        let mut seen: HashMap<PathBuf, (u64, Option<SystemTime>)> = HashMap::new();
+        let mut done: HashMap<PathBuf, (u64, Option<SystemTime>)> = HashMap::new();
+        let mut warned: HashSet<PathBuf> = HashSet::new();
+        let mut outcomes = Vec::new();
+        if !args.quiet {
+            stderrln!(
+                "watching {} every {} ms; Ctrl-C to stop",
+                input_dir.display(),
+                args.poll_interval
+            );
+        }
+        while !interrupt.is_cancelled() {
+            let mut ready = Vec::new();
+            let mut current = HashMap::new();
+            let mut binary = Vec::new();
+            for (input, output) in batch_jobs(args, Vec::new(), &mut binary)? {
+                if input
+                    .extension()
+                    .is_some_and(|extension| extension == "tmp")
+                {
+                    continue;
+                }
+                let Ok(metadata) = fs::metadata(&input) else {
+                    continue;
+                };
+                let stamp = (metadata.len(), metadata.modified().ok());
+                if seen.get(&input) == Some(&stamp) && done.get(&input) != Some(&stamp) {
+                    ready.push((input.clone(), output));
+                }
+                current.insert(input, stamp);
+            }
+            seen = current;
+            for path in binary {
+                if warned.insert(path.clone()) {
+                    stderrln!("warning: skipping {}: looks binary", path.display());
+                }
+            }
+
+            for (index, result) in self.scrub_all(&ready, dedupe.as_deref_mut()) {
+                let input = &ready[index].0;
+                let outcome = match result {
+                    Ok(outcome) => outcome,
+                    Err(err) if args.fail_fast => return Err(err),
+                    Err(err) => BatchOutcome::Failed(err),
+                };
+                match &outcome {
+                    BatchOutcome::Failed(err) => stderrln!("error: {:#}", err),
+                    outcome if !args.quiet => {
+                        stderrln!("{}: {}", input.display(), outcome_summary(outcome))
+                    }
+                    _ => {}
+                }
+                done.insert(input.clone(), seen[input]);
+                let failed = matches!(outcome, BatchOutcome::Failed(_));
+                if args.move_processed && !failed && !args.dry_run {
+                    let relative = input
+                        .strip_prefix(input_dir)
+                        .expect("listed from the input dir");
+                    let target = input_dir.join("processed").join(relative);
+                    let processed = target.parent().expect("a file in processed/");
+                    fs::create_dir_all(processed).with_context(|| {
+                        format!("failed to create directory: {}", processed.display())
+                    })?;
+                    fs::rename(input, &target).with_context(|| {
+                        format!("failed to move {} to {}", input.display(), target.display())
+                    })?;
+                    done.remove(input);
+                }
+                outcomes.push((input.display().to_string(), outcome));
+            }
+
+            let resume = Instant::now() + interval;
+            while !interrupt.is_cancelled() && Instant::now() < resume {
+                thread::sleep(Duration::from_millis(20).min(interval));
+            }
+        }
+        let mut skipped: Vec<PathBuf> = warned.into_iter().collect();
+        skipped.sort();
+        self.finish(&outcomes, &skipped, dedupe, false)
+    }
+
+    /// Saves the dedupe cache, prints and records the stats of a finished batch (with the
+    /// inputs `skipped` as binary in the manifest), waits for its webhook events, and fails if
+    /// any file did or, with `--fail-on-phi`, if PHI was found. An `interrupted` batch is
+    /// reported and recorded the same way, but the caller decides how it ends.
+    fn finish(
+        &self,
+        outcomes: &[(String, BatchOutcome)],
+        skipped: &[PathBuf],
+        dedupe: Option<&mut DedupeCache>,
+        interrupted: bool,
+    ) -> Result<()> {
+        let args = self.args;
+        if let (Some(cache), Some(path)) = (dedupe.as_deref(), args.dedupe_cache.as_ref()) {
+            cache.save(path)?;
+        }
+        let dedupe = dedupe.map(|cache| cache.counts());
+        let mut total = ScrubStats::default();
+        for (_, outcome) in outcomes {
+            if let BatchOutcome::Scrubbed(stats, _) = outcome {
+                total += stats;
+            }
+        }
+        if let Some(path) = args.stats_report.as_ref() {
+            let (mut report, warning) = StatsReport::load(path);
+            if let Some(warning) = warning {
+                stderrln!("warning: {}", warning);
+            }
+            for (file, outcome) in outcomes {
+                if let BatchOutcome::Scrubbed(stats, _) = outcome {
+                    report.insert(file.clone(), stats);
+                }
+            }
+            report.save(path, args.schema_version)?;
+        }
+        if let Some(path) = args.manifest.as_ref() {
+            self.manifest(outcomes, skipped, &total, dedupe, interrupted)?
+                .save(path)?;
+        }
+        if !args.quiet {
+            let rendered = self.render_stats(outcomes, &total, dedupe)?;
+            report_stats(&rendered, args.stats_output.as_ref())?;
+        }
+        if args.profile_detectors {
+            stderr!("{}", profiling::render_table(&total.detector_timings));
+        }
+        #[cfg(feature = "webhook")]
+        if let Some(webhook) = self.webhook.lock().expect("webhook").take() {
+            finish_webhook(webhook);
+        }
+        if interrupted {
+            return Ok(());
+        }
+
+        let failed = outcomes
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, BatchOutcome::Failed(_)))
+            .count();
+        if failed > 0 {
+            return Err(anyhow::anyhow!(
+                "{} of {} files failed",
+                failed,
+                outcomes.len()
+            ));
+        }
+        if let Some(categories) = &args.fail_on_phi {
+            let found = if categories.is_empty() {
+                total.total()
+            } else {
+                categories
+                    .iter()
+                    .map(|category| total.count_key(category))
+                    .sum()
+            };
+            if found > 0 {
+                return Err(PhiFound(found).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Scrubs the jobs on `--jobs` threads, or one at a time with a dedupe cache, since which
+    /// of two identical files is scrubbed and which reuses it must not depend on timing. The
+    /// results come back in job order. After an interrupt, a cancellation, or any failure with
+    /// `--fail-fast`, no further jobs start; files already being scrubbed are finished.
+    fn scrub_all(
+        &self,
+        jobs: &[(PathBuf, PathBuf)],
+        mut dedupe: Option<&mut DedupeCache>,
+    ) -> Vec<(usize, Result<BatchOutcome>)> {
+        let stops = |result: &Result<BatchOutcome>| {
+            result
+                .as_ref()
+                .is_err_and(|err| err.is::<Cancelled>() || self.args.fail_fast)
+        };
+        let threads = match dedupe {
+            Some(_) => 1,
+            None => self
+                .args
+                .jobs
+                .or_else(|| thread::available_parallelism().ok())
+                .map_or(1, NonZeroUsize::get),
+        };
+        if threads == 1 || jobs.len() < 2 {
+            let mut results = Vec::with_capacity(jobs.len());
+            for (index, (input, output)) in jobs.iter().enumerate() {
+                if self.interrupt.is_cancelled() {
+                    break;
+                }
+                let result = self.scrub_file(input, output, dedupe.as_deref_mut());
+                let stop = stops(&result);
+                results.push((index, result));
+                if stop {
+                    break;
+                }
+            }
+            return results;
+        }
+
+        let next = AtomicUsize::new(0);
+        let stopped = AtomicBool::new(false);
+        let mut results: Vec<(usize, Result<BatchOutcome>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.min(jobs.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+                        while !stopped.load(Ordering::Relaxed) && !self.interrupt.is_cancelled() {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some((input, output)) = jobs.get(index) else {
+                                break;
+                            };
+                            let result = self.scrub_file(input, output, None);
+                            if stops(&result) {
+                                stopped.store(true, Ordering::Relaxed);
+                            }
+                            results.push((index, result));
+                        }
+                        results
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("batch worker"))
+                .collect()
+        });
+        results.sort_by_key(|(index, _)| *index);
+        results
+    }
+
+    fn scrub_file(
+        &self,
+        input: &Path,
+        output: &Path,
+        dedupe: Option<&mut DedupeCache>,
+    ) -> Result<BatchOutcome> {
+        let args = self.args;
+        let text = fs::read_to_string(input)
+            .with_context(|| format!("failed to read input file: {}", input.display()))?;
+        let output = output.to_path_buf();
+        // Hashed after writing, as the bytes on disk; an in-place run has replaced the input
+        // by then, so its hash is taken from the text read.
+        let digests = |output: &Path| -> Result<Option<Digests>> {
+            if args.manifest.is_none() {
+                return Ok(None);
+            }
+            let written = fs::read(output)
+                .with_context(|| format!("failed to read output file: {}", output.display()))?;
+            Ok(Some(Digests {
+                output: output.to_path_buf(),
+                input_sha256: manifest::sha256_hex(text.as_bytes()),
+                output_sha256: manifest::sha256_hex(&written),
+            }))
+        };
+        if let Some(cache) = dedupe {
+            if let Some(earlier) = cache.find(text.as_bytes()) {
+                reuse_output(&earlier, &output)?;
+                return Ok(BatchOutcome::Reused(earlier, digests(&output)?));
+            }
+            let stats = self.scrub_text(input, &text, &output)?;
+            cache.insert(
+                text.as_bytes(),
+                DedupeEntry {
+                    input: absolute(input),
+                    output: absolute(&output),
+                },
+            );
+            return Ok(BatchOutcome::Scrubbed(Box::new(stats), digests(&output)?));
+        }
+        let stats = self.scrub_text(input, &text, &output)?;
+        Ok(BatchOutcome::Scrubbed(Box::new(stats), digests(&output)?))
+    }
+
+    fn scrub_text(&self, input: &Path, text: &str, output: &PathBuf) -> Result<ScrubStats> {
+        let args = self.args;
+        #[cfg(feature = "webhook")]
+        let started = Instant::now();
+        let options = ScrubOptions {
+            collect_timings: args.profile_detectors,
+            ..ScrubOptions::default()
+        };
+        let (rendered, stats) = match args.format {
+            OutputFormat::Jsonl => {
+                let mut out = Vec::new();
+                let stats = jsonl::scrub_jsonl(
+                    self.scrubber,
+                    text.as_bytes(),
+                    &mut out,
+                    &jsonl_options(args, options)?,
+                    self.skip,
+                    self.cancel,
+                    |line, err| warn_unparsed(&input.display().to_string(), line, err),
+                )?;
+                (String::from_utf8(out).context("jsonl output")?, stats)
+            }
+            OutputFormat::Csv => {
+                let mut out = Vec::new();
+                let stats = csv::scrub_csv(
+                    self.scrubber,
+                    text.as_bytes(),
+                    &mut out,
+                    &csv_options(args, options),
+                    self.skip,
+                    self.cancel,
+                )?;
+                (String::from_utf8(out).context("csv output")?, stats)
+            }
+            format => {
+                let result =
+                    self.scrubber
+                        .scrub_with_options(text, self.skip, &options, self.cancel)?;
+                let rendered = match format {
+                    OutputFormat::Html => html::render_html(&result),
+                    _ => result.text,
+                };
+                (rendered, result.stats)
+            }
+        };
+        if !args.dry_run {
+            match args.in_place.as_deref() {
+                Some(suffix) if !suffix.is_empty() => {
+                    let mut backup = input.as_os_str().to_owned();
+                    backup.push(suffix);
+                    fs::copy(input, &backup)
+                        .with_context(|| format!("failed to back up {}", input.display()))?;
+                }
+                _ => {}
+            }
+            write_output(Some(output), &rendered)?;
+        }
+        #[cfg(feature = "webhook")]
+        if let Some(webhook) = self.webhook.lock().expect("webhook").as_mut() {
+            webhook.send(&DocumentEvent::new(
+                &input.display().to_string(),
+                &stats,
+                args.schema_version,
+                &self.fingerprint,
+                started.elapsed(),
+            )?)?;
+        }
+        Ok(stats)
+    }
+
+    /// The `--manifest` record of a finished batch. Failed and skipped files list no output.
+    fn manifest(
+        &self,
+        outcomes: &[(String, BatchOutcome)],
+        skipped: &[PathBuf],
+        total: &ScrubStats,
+        dedupe: Option<DedupeCounts>,
+        interrupted: bool,
+    ) -> Result<Manifest> {
+        let version = self.args.schema_version;
+        let mut files = outcomes
+            .iter()
+            .map(|(input, outcome)| {
+                let (status, stats, digests, duplicate_of) = match outcome {
+                    BatchOutcome::Scrubbed(stats, digests) => (
+                        FileStatus::Scrubbed,
+                        Some(stats.to_json_value(version)?),
+                        digests.as_ref(),
+                        None,
+                    ),
+                    BatchOutcome::Reused(earlier, digests) => (
+                        FileStatus::Reused,
+                        None,
+                        digests.as_ref(),
+                        Some(earlier.input.display().to_string()),
+                    ),
+                    BatchOutcome::Failed(_) => (FileStatus::Failed, None, None, None),
+                };
+                Ok(ManifestFile {
+                    input: input.clone(),
+                    input_sha256: digests.map(|digests| digests.input_sha256.clone()),
+                    output: digests.map(|digests| digests.output.display().to_string()),
+                    output_sha256: digests.map(|digests| digests.output_sha256.clone()),
+                    status,
+                    duplicate_of,
+                    stats,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        files.extend(skipped.iter().map(|input| ManifestFile {
+            input: input.display().to_string(),
+            input_sha256: None,
+            output: None,
+            output_sha256: None,
+            status: FileStatus::Skipped,
+            duplicate_of: None,
+            stats: None,
+        }));
+        Ok(Manifest {
+            dedupe,
+            interrupted,
+            ..Manifest::new(
+                version,
+                self.started,
+                self.config_sha256.to_string(),
+                files,
+                total.to_json_value(version)?,
+            )
+        })
+    }
+
+    /// One line per file and the totals as text; every file's stats and the total as JSON; or
+    /// every file's CSV rows under one header.
+    fn render_stats(
+        &self,
+        outcomes: &[(String, BatchOutcome)],
+        total: &ScrubStats,
+        dedupe: Option<DedupeCounts>,
+    ) -> Result<String> {
+        let args = self.args;
+        let scrubbed = || {
+            outcomes.iter().filter_map(|(file, outcome)| match outcome {
+                BatchOutcome::Scrubbed(stats, _) => Some((file, stats)),
+                _ => None,
+            })
+        };
+        Ok(match (args.stats_json, args.stats_format) {
+            (true, _) | (false, StatsFormat::Json) => {
+                let files = scrubbed()
+                    .map(|(file, stats)| {
+                        Ok((file.clone(), stats.to_json_value(args.schema_version)?))
+                    })
+                    .collect::<Result<serde_json::Map<_, _>>>()?;
+                let failed: Vec<&String> = outcomes
+                    .iter()
+                    .filter(|(_, outcome)| matches!(outcome, BatchOutcome::Failed(_)))
+                    .map(|(file, _)| file)
+                    .collect();
+                let mut report = serde_json::json!({
+                    "files": files,
+                    "failed": failed,
+                    "total": total.to_json_value(args.schema_version)?,
+                });
+                if let Some(counts) = dedupe {
+                    report["dedupe"] = serde_json::to_value(counts)?;
+                }
+                serde_json::to_string_pretty(&report).context("failed to serialize batch stats")?
+                    + "\n"
+            }
+            (false, StatsFormat::Csv) => {
+                let mut out = String::from("file,category,count\n");
+                for (file, stats) in scrubbed() {
+                    for row in stats.render_csv(file, args.csv_all).lines().skip(1) {
+                        out.push_str(row);
+                        out.push('\n');
+                    }
+                }
+                out
+            }
+            (false, StatsFormat::Text) => {
+                let mut out = String::new();
+                for (file, outcome) in outcomes {
+                    out.push_str(&format!("{}: {}\n", file, outcome_summary(outcome)));
+                }
+                if let Some(counts) = dedupe {
+                    out.push_str(&format!(
+                        "Documents: {} unique, {} duplicates\n",
+                        counts.unique, counts.duplicates
+                    ));
+                }
+                out.push_str(&format!("Total for {} files:\n", outcomes.len()));
+                out + &total.render_human(&ReportOptions {
+                    rules: args.verbose,
+                    ..self.scrubber.report_options(self.skip)
+                })
+            }
+        })
+    }
+}
+
+/// Loads the dedupe cache for the current rules and config. Entries are only reused by runs
+/// whose rule description, config files and environment names are identical.
+fn open_dedupe_cache(
+    path: &std::path::Path,
+    args: &Args,
+    env_names: &[String],
+    scrubber: &Scrubber,
+    skip: &HashSet<CategoryKey>,
+) -> Result<DedupeCache> {
+    let is_file = |path: Option<&PathBuf>| path.is_some_and(|p| p != std::path::Path::new("-"));
+    let files = is_file(args.input.as_ref()) && is_file(args.output.as_ref());
+    if !files && args.output_dir.is_none() {
+        return Err(anyhow::anyhow!(
+            "--dedupe-cache needs --input and --output files, or --output-dir"
+        ));
+    }
+    let mut settings = scrubber.describe(skip).fingerprint().into_bytes();
+    if args.format == OutputFormat::Html {
+        settings.extend(b"html");
+    }
+    for config in &args.config {
+        settings.extend(
+            fs::read(config)
+                .with_context(|| format!("failed to read config file: {}", config.display()))?,
+        );
+    }
+    for name in env_names {
+        settings.extend(name.as_bytes());
+        settings.push(b'\n');
+    }
+    let (cache, warning) = DedupeCache::load(path, dedupe::content_hash(&settings));
+    if let Some(warning) = warning {
+        stderrln!("warning: {}", warning);
+    }
+    Ok(cache)
+}
+
+/// Copies an earlier output to `output`, unless it already is that file.
+fn reuse_output(earlier: &DedupeEntry, output: &PathBuf) -> Result<()> {
+    if absolute(output) == earlier.output {
+        return Ok(());
+    }
+    let contents = fs::read_to_string(&earlier.output).with_context(|| {
+        format!(
+            "failed to read earlier output: {}",
+            earlier.output.display()
+        )
+    })?;
+    write_output(Some(output), &contents)
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
 fn read_input(path: Option<&PathBuf>) -> Result<String> {
     match path {
         Some(p) if p == std::path::Path::new("-") => read_from_stdin(),
@@ -98,6 +2036,9 @@ fn read_input(path: Option<&PathBuf>) -> Result<String> {
 }
 
 fn read_from_stdin() -> Result<String> {
+    if io::stdin().is_terminal() {
+        stderrln!("reading from stdin, press Ctrl-D to finish");
+    }
     let mut buffer = String::new();
     io::stdin()
         .read_to_string(&mut buffer)
@@ -107,97 +2048,213 @@ fn read_from_stdin() -> Result<String> {
 
 fn write_output(path: Option<&PathBuf>, contents: &str) -> Result<()> {
     match path {
-        Some(p) if p == std::path::Path::new("-") => {
-            io::stdout()
-                .write_all(contents.as_bytes())
-                .context("failed to write to STDOUT")?;
-        }
-        Some(p) => {
-            let mut file = fs::File::create(p)
+        Some(p) if p != std::path::Path::new("-") => {
+            // Write and sync beside the target, then rename, so neither an aborted run nor a
+            // crash leaves a truncated output file.
+            let mut partial = p.clone().into_os_string();
+            partial.push(".partial");
+            let partial = PathBuf::from(partial);
+            let mut file = fs::File::create(&partial)
                 .with_context(|| format!("failed to create output file: {}", p.display()))?;
-            file.write_all(contents.as_bytes())
-                .with_context(|| format!("failed to write output file: {}", p.display()))?;
-        }
-        None => {
-            io::stdout()
+            if let Err(err) = file
                 .write_all(contents.as_bytes())
-                .context("failed to write to STDOUT")?;
+                .and_then(|()| file.sync_all())
+            {
+                fs::remove_file(&partial).ok();
+                return Err(err)
+                    .with_context(|| format!("failed to write output file: {}", p.display()));
+            }
+            fs::rename(&partial, p)
+                .with_context(|| format!("failed to write output file: {}", p.display()))
         }
+        _ => write_stdout(contents),
     }
+}
 
-    Ok(())
+/// Writes and flushes stdout, so anything reported on stderr afterwards follows the text.
+fn write_stdout(contents: &str) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(contents.as_bytes())
+        .and_then(|()| stdout.flush())
+        .context("failed to write to STDOUT")
 }
 
-fn load_config(path: Option<&PathBuf>) -> Result<ScrubberConfig> {
-    match path {
-        Some(p) => {
-            let raw = fs::read_to_string(p)
-                .with_context(|| format!("failed to read config file: {}", p.display()))?;
-            let config: ScrubberConfig = serde_json::from_str(&raw)
-                .with_context(|| format!("failed to parse config JSON: {}", p.display()))?;
-            Ok(config)
-        }
-        None => Ok(ScrubberConfig::default()),
+/// Streams spans as JSON lines rather than serializing the whole list at once.
+fn write_spans(path: &PathBuf, spans: &[RedactionSpan], version: SchemaVersion) -> Result<()> {
+    let mut writer: BufWriter<Box<dyn Write>> = if path == Path::new("-") {
+        BufWriter::new(Box::new(io::stdout().lock()))
+    } else {
+        let file = fs::File::create(path)
+            .with_context(|| format!("failed to create spans file: {}", path.display()))?;
+        BufWriter::new(Box::new(file))
+    };
+    for span in spans {
+        writer
+            .write_all(render_span_json(span, version)?.as_bytes())
+            .and_then(|()| writer.write_all(b"\n"))
+            .with_context(|| format!("failed to write spans file: {}", path.display()))?;
     }
+    writer
+        .flush()
+        .with_context(|| format!("failed to write spans file: {}", path.display()))
 }
 
-fn report_stats(stats: &ScrubStats, as_json: bool) -> Result<()> {
-    if as_json {
-        let payload = serde_json::to_string_pretty(stats).context("failed to serialize stats")?;
-        eprintln!("{}", payload);
-    } else {
-        eprintln!("Redactions applied: {}", stats.total());
-        if stats.emails > 0 {
-            eprintln!("  emails   : {}", stats.emails);
-        }
-        if stats.phones > 0 {
-            eprintln!("  phones   : {}", stats.phones);
-        }
-        if stats.dates > 0 {
-            eprintln!("  dates    : {}", stats.dates);
-        }
-        if stats.ssn > 0 {
-            eprintln!("  ssn          : {}", stats.ssn);
-        }
-        if stats.mrn > 0 {
-            eprintln!("  mrn          : {}", stats.mrn);
-        }
-        if stats.zip_codes > 0 {
-            eprintln!("  zip codes    : {}", stats.zip_codes);
-        }
-        if stats.persons > 0 {
-            eprintln!("  persons      : {}", stats.persons);
-        }
-        if stats.facilities > 0 {
-            eprintln!("  facilities   : {}", stats.facilities);
-        }
-        if stats.addresses > 0 {
-            eprintln!("  addresses    : {}", stats.addresses);
-        }
-        if stats.coordinates > 0 {
-            eprintln!("  coordinates  : {}", stats.coordinates);
-        }
-        if stats.urls > 0 {
-            eprintln!("  urls         : {}", stats.urls);
-        }
-        if stats.insurance_ids > 0 {
-            eprintln!("  insurance    : {}", stats.insurance_ids);
+/// SIGINT/SIGTERM handling: the first signal cancels the token, which stops a single scrub at
+/// its next check and lets a batch finish the files in flight without starting more; a second
+/// one exits immediately.
+#[cfg(unix)]
+mod interrupt {
+    use std::sync::OnceLock;
+
+    use clinical_scrubber::cancel::CancellationToken;
+
+    const SIGINT: i32 = 2;
+    const SIGTERM: i32 = 15;
+
+    static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        fn _exit(status: i32) -> !;
+    }
+
+    extern "C" fn on_signal(_: i32) {
+        if let Some(token) = TOKEN.get() {
+            if token.is_cancelled() {
+                // SAFETY: `_exit` is async-signal-safe.
+                unsafe { _exit(super::EXIT_INTERRUPTED) };
+            }
+            token.cancel();
         }
-        if stats.licenses > 0 {
-            eprintln!("  licenses     : {}", stats.licenses);
+    }
+
+    pub fn install(token: &CancellationToken) {
+        if TOKEN.set(token.clone()).is_ok() {
+            // SAFETY: `on_signal` only touches atomics and calls `_exit`.
+            unsafe {
+                signal(SIGINT, on_signal);
+                signal(SIGTERM, on_signal);
+            }
         }
-        if stats.vehicles > 0 {
-            eprintln!("  vehicles     : {}", stats.vehicles);
+    }
+}
+
+#[cfg(not(unix))]
+mod interrupt {
+    use clinical_scrubber::cancel::CancellationToken;
+
+    pub fn install(_: &CancellationToken) {}
+}
+
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|io_err| io_err.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
+
+/// Fills in the settings the command line left unset from the `CLINICAL_SCRUBBER_*`
+/// variables (see `--help`), and returns the extra names from `CLINICAL_SCRUBBER_NAMES`.
+fn apply_env(args: &mut Args, var: impl Fn(&str) -> Option<String>) -> Result<Vec<String>> {
+    let list = |name: &str| -> Vec<String> {
+        var(name)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    if args.config.is_empty() {
+        args.config.extend(
+            var("CLINICAL_SCRUBBER_CONFIG")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+        );
+    }
+    if args.skip.is_empty() && args.only.is_empty() {
+        args.skip = list("CLINICAL_SCRUBBER_SKIP")
+            .iter()
+            .map(|category| category.parse().map_err(anyhow::Error::msg))
+            .collect::<Result<_>>()
+            .context("invalid CLINICAL_SCRUBBER_SKIP")?;
+    }
+    if args.regulation.is_none() {
+        if let Some(profile) = var("CLINICAL_SCRUBBER_PROFILE").filter(|value| !value.is_empty()) {
+            args.regulation = Some(
+                Regulation::from_str(&profile, true)
+                    .map_err(anyhow::Error::msg)
+                    .context("invalid CLINICAL_SCRUBBER_PROFILE")?,
+            );
         }
-        if stats.devices > 0 {
-            eprintln!("  devices      : {}", stats.devices);
+    }
+    Ok(list("CLINICAL_SCRUBBER_NAMES"))
+}
+
+/// The categories this run skips: those named by `--skip`, or with `--only`, every category
+/// it does not name. Custom categories must be defined by the config.
+fn resolve_skip(args: &Args, categories: &[CategoryKey]) -> Result<HashSet<CategoryKey>> {
+    let (flag, named) = if args.only.is_empty() {
+        ("--skip", &args.skip)
+    } else {
+        ("--only", &args.only)
+    };
+    if let Some(unknown) = named.iter().find(|category| !categories.contains(category)) {
+        return Err(anyhow::anyhow!(
+            "{} {}: no custom_patterns entry defines it",
+            flag,
+            unknown
+        ));
+    }
+    Ok(if args.only.is_empty() {
+        args.skip.iter().cloned().collect()
+    } else {
+        categories
+            .iter()
+            .filter(|category| !args.only.contains(category))
+            .cloned()
+            .collect()
+    })
+}
+
+/// Writes the rendered stats to stderr or to `path`.
+fn report_stats(rendered: &str, path: Option<&PathBuf>) -> Result<()> {
+    match path {
+        Some(path) if path != std::path::Path::new("-") => {
+            let mut partial = path.as_os_str().to_owned();
+            partial.push(".partial");
+            let partial = PathBuf::from(partial);
+            fs::write(&partial, rendered)
+                .and_then(|()| fs::rename(&partial, path))
+                .with_context(|| format!("failed to write stats file: {}", path.display()))
         }
-        if stats.ip_addresses > 0 {
-            eprintln!("  ip addresses : {}", stats.ip_addresses);
+        _ => {
+            let mut stderr = io::stderr().lock();
+            stderr
+                .write_all(rendered.as_bytes())
+                .and_then(|()| stderr.flush())
+                .context("failed to write to STDERR")
         }
-        if stats.relative_dates > 0 {
-            eprintln!("  relative dates: {}", stats.relative_dates);
+    }
+}
+
+/// `path` with its directory resolved, so two spellings of a file that may not exist yet
+/// compare equal.
+fn resolved(path: &std::path::Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() {
+                std::path::Path::new(".")
+            } else {
+                parent
+            };
+            fs::canonicalize(parent).map_or_else(|_| path.to_path_buf(), |dir| dir.join(name))
         }
+        _ => path.to_path_buf(),
     }
-    Ok(())
 }
@@ -0,0 +1,61 @@
+//! The single way to show original text outside the scrubbed output.
+//!
+//! Stderr and interactive output end up in centralized logs, so any code path that could echo
+//! matched or input text (previews, explanations, debug output) formats it through
+//! `PhiDisplay`. It prints a placeholder unless the run opted in with `--log-phi`.
+
+use std::fmt;
+
+/// Shown in place of original text when PHI logging is off.
+pub const PLACEHOLDER: &str = "«redacted»";
+
+/// Original text that renders as `PLACEHOLDER` unless `reveal` is set. `Debug` quotes the text
+/// when revealed and prints the bare placeholder otherwise.
+#[derive(Clone, Copy)]
+pub struct PhiDisplay<'a> {
+    text: &'a str,
+    reveal: bool,
+}
+
+impl<'a> PhiDisplay<'a> {
+    /// `log_phi` is the run's `--log-phi` setting.
+    pub fn new(text: &'a str, log_phi: bool) -> Self {
+        Self {
+            text,
+            reveal: log_phi,
+        }
+    }
+}
+
+impl fmt::Display for PhiDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.reveal {
+            f.write_str(self.text)
+        } else {
+            f.write_str(PLACEHOLDER)
+        }
+    }
+}
+
+impl fmt::Debug for PhiDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.reveal {
+            fmt::Debug::fmt(self.text, f)
+        } else {
+            f.write_str(PLACEHOLDER)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hides_text_unless_revealed() {
+        let hidden = PhiDisplay::new("Jane Doe", false);
+        assert_eq!(format!("{} {:?}", hidden, hidden), "«redacted» «redacted»");
+        let shown = PhiDisplay::new("Jane Doe", true);
+        assert_eq!(format!("{} {:?}", shown, shown), "Jane Doe \"Jane Doe\"");
+    }
+}
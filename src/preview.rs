@@ -0,0 +1,74 @@
+//! `--preview`: the original text with every match marked in place, for checking a config
+//! before trusting it. Nothing is replaced; matches come from the span records, so redactions
+//! past `max_spans` are not marked.
+
+use crate::spans::RedactionSpan;
+use crate::{Category, CategoryKey};
+
+/// Foreground colors cycled through the built-in categories; shown in inverse video.
+const PALETTE: [u8; 6] = [31, 32, 33, 34, 35, 36];
+/// Custom categories share one color.
+const CUSTOM_COLOR: u8 = 95;
+const RESET: &str = "\x1b[0m";
+
+/// Renders `input` with each span's text highlighted by category color, or wrapped as
+/// `«PERSON:text»` when `color` is off.
+pub fn render_preview(input: &str, spans: &[RedactionSpan], color: bool) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut cursor = 0;
+    for span in spans {
+        out.push_str(&input[cursor..span.original.start]);
+        let text = &input[span.original.clone()];
+        if color {
+            out.push_str(&format!(
+                "\x1b[7;{}m{}{}",
+                category_color(&span.category),
+                text,
+                RESET
+            ));
+        } else {
+            out.push_str(&format!(
+                "«{}:{}»",
+                span.category.to_string().to_uppercase(),
+                text
+            ));
+        }
+        cursor = span.original.end;
+    }
+    out.push_str(&input[cursor..]);
+    out
+}
+
+fn category_color(category: &CategoryKey) -> u8 {
+    match category {
+        CategoryKey::Builtin(category) => {
+            let index = Category::ALL
+                .iter()
+                .position(|candidate| candidate == category)
+                .unwrap_or(0);
+            PALETTE[index % PALETTE.len()]
+        }
+        CategoryKey::Custom(_) => CUSTOM_COLOR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::config::ScrubberConfig;
+    use crate::{ScrubMode, Scrubber};
+
+    #[test]
+    fn colors_matches_by_category_and_leaves_the_rest_alone() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Call  312-555-0182 or jane.doe@example.com.";
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        assert_eq!(
+            render_preview(input, &result.spans, true),
+            "Call  \x1b[7;32m312-555-0182\x1b[0m or \x1b[7;31mjane.doe@example.com\x1b[0m."
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Named regulatory profiles that bundle which categories run and how they are rendered.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{DateMode, ZipMode};
+use crate::Category;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Regulation {
+    /// The built-in defaults: Safe Harbor-only identifiers off, ZIPs and dates redacted.
+    Standard,
+    /// HIPAA Safe Harbor: all 18 identifier types.
+    SafeHarbor,
+    /// HIPAA Limited Data Set: direct identifiers removed; dates and ZIP geography kept.
+    LimitedDataset,
+    /// GDPR-style: every identifier category, including online identifiers.
+    Strict,
+}
+
+impl Regulation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Regulation::Standard => "standard",
+            Regulation::SafeHarbor => "safe-harbor",
+            Regulation::LimitedDataset => "limited-dataset",
+            Regulation::Strict => "strict",
+        }
+    }
+}
+
+/// What a regulation turns on and off. Explicit user settings (`--skip`, `zip_mode` in the
+/// config) are applied on top.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegulationProfile {
+    pub regulation: Regulation,
+    /// Whether the Safe Harbor-only detectors (IDs, licenses, vehicles, devices, IPs) run.
+    pub safe_harbor: bool,
+    /// Categories the profile leaves unredacted.
+    pub disabled: Vec<Category>,
+    pub zip_mode: ZipMode,
+    pub date_mode: DateMode,
+}
+
+impl RegulationProfile {
+    pub fn for_regulation(regulation: Regulation) -> Self {
+        match regulation {
+            Regulation::Standard => Self {
+                regulation,
+                safe_harbor: false,
+                disabled: Vec::new(),
+                zip_mode: ZipMode::Redact,
+                date_mode: DateMode::Redact,
+            },
+            // Safe Harbor allows the year of a date; dates of patients over 89 are still
+            // redacted whole.
+            Regulation::SafeHarbor => Self {
+                regulation,
+                safe_harbor: true,
+                disabled: Vec::new(),
+                zip_mode: ZipMode::Redact,
+                date_mode: DateMode::Year,
+            },
+            Regulation::LimitedDataset => Self {
+                regulation,
+                safe_harbor: true,
+                disabled: vec![
+                    Category::Date,
+                    Category::RelativeDate,
+                    Category::Age,
+                    Category::Geo,
+                ],
+                zip_mode: ZipMode::Truncate,
+                date_mode: DateMode::Redact,
+            },
+            Regulation::Strict => Self {
+                regulation,
+                safe_harbor: true,
+                disabled: Vec::new(),
+                zip_mode: ZipMode::Redact,
+                date_mode: DateMode::Redact,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_matrix() {
+        let profile = RegulationProfile::for_regulation(Regulation::Standard);
+        assert!(!profile.safe_harbor);
+        assert!(profile.disabled.is_empty());
+        assert_eq!(
+            (profile.zip_mode, profile.date_mode),
+            (ZipMode::Redact, DateMode::Redact)
+        );
+    }
+
+    #[test]
+    fn safe_harbor_matrix() {
+        let profile = RegulationProfile::for_regulation(Regulation::SafeHarbor);
+        assert!(profile.safe_harbor);
+        assert!(profile.disabled.is_empty());
+        assert_eq!(profile.zip_mode, ZipMode::Redact);
+        assert_eq!(profile.date_mode, DateMode::Year);
+    }
+
+    #[test]
+    fn limited_dataset_matrix() {
+        let profile = RegulationProfile::for_regulation(Regulation::LimitedDataset);
+        assert!(profile.safe_harbor);
+        assert_eq!(
+            profile.disabled,
+            vec![
+                Category::Date,
+                Category::RelativeDate,
+                Category::Age,
+                Category::Geo
+            ]
+        );
+        assert_eq!(profile.zip_mode, ZipMode::Truncate);
+    }
+
+    #[test]
+    fn strict_matrix() {
+        let profile = RegulationProfile::for_regulation(Regulation::Strict);
+        assert!(profile.safe_harbor);
+        assert!(profile.disabled.is_empty());
+        assert_eq!(profile.zip_mode, ZipMode::Redact);
+    }
+}
@@ -0,0 +1,76 @@
+//! Per-detector wall time and match counts, collected when `ScrubOptions::collect_timings` is
+//! set (`--profile-detectors`).
+
+use serde::{Deserialize, Serialize};
+
+/// Time one detector pass took on one document.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DetectorTiming {
+    /// The rule name from `--describe-rules`; `custom:<name>` for custom patterns.
+    pub detector: String,
+    pub elapsed_us: u64,
+    /// Redactions the pass made.
+    pub matches: usize,
+}
+
+/// Renders timings slowest first, with each detector's share of the total.
+pub fn render_table(timings: &[DetectorTiming]) -> String {
+    let mut sorted: Vec<&DetectorTiming> = timings.iter().collect();
+    sorted.sort_by(|a, b| {
+        b.elapsed_us
+            .cmp(&a.elapsed_us)
+            .then_with(|| a.detector.cmp(&b.detector))
+    });
+    let total: u64 = timings.iter().map(|timing| timing.elapsed_us).sum();
+    let mut out = format!(
+        "Detector timings: {:.3} ms total\n{:<24} {:>10} {:>6} {:>8}\n",
+        total as f64 / 1000.0,
+        "detector",
+        "ms",
+        "share",
+        "matches"
+    );
+    for timing in sorted {
+        let share = if total == 0 {
+            0.0
+        } else {
+            timing.elapsed_us as f64 * 100.0 / total as f64
+        };
+        out.push_str(&format!(
+            "{:<24} {:>10.3} {:>5.1}% {:>8}\n",
+            timing.detector,
+            timing.elapsed_us as f64 / 1000.0,
+            share,
+            timing.matches
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_slowest_first() {
+        let timings = [
+            DetectorTiming {
+                detector: "email".to_string(),
+                elapsed_us: 250,
+                matches: 1,
+            },
+            DetectorTiming {
+                detector: "capital-sequence".to_string(),
+                elapsed_us: 750,
+                matches: 0,
+            },
+        ];
+        let expected = "\
+Detector timings: 1.000 ms total
+detector                         ms  share  matches
+capital-sequence              0.750  75.0%        0
+email                         0.250  25.0%        1
+";
+        assert_eq!(render_table(&timings), expected);
+    }
+}
@@ -0,0 +1,279 @@
+//! Interactive preview for tuning configs (`clinical_scrubber repl`).
+//!
+//! Each input line (or a block opened with `<<` and closed by a lone `.`) is scrubbed and
+//! printed with one annotation per redaction, naming the rule that made it and what that rule
+//! looks for. Lines starting with `:` adjust the live scrubber;
+//! a config that fails to build is reported and the previous scrubber stays in use.
+
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+use crate::config::ScrubberConfig;
+use crate::phi::PhiDisplay;
+use crate::profiles::Regulation;
+use crate::{CategoryKey, ScrubMode, Scrubber};
+
+const HELP: &str = "\
+:skip <category>     stop redacting a category (e.g. person, custom:encounter)
+:unskip <category>   redact it again
+:profile <name>      switch regulation: standard, safe-harbor, limited-dataset, strict, or none
+:reload-config       re-read the config files
+:quit                leave
+<<                   start a multi-line block; end it with a line containing only .
+";
+
+/// Starting state for a session; `:profile` and `:skip` change it as the session goes.
+#[derive(Debug, Default)]
+pub struct ReplSettings {
+    /// Configs merged in order; re-read by `:reload-config`.
+    pub config_paths: Vec<PathBuf>,
+    pub mode: ScrubMode,
+    pub field_mode: bool,
+    /// Overrides the config's `regulation` when set.
+    pub regulation: Option<Regulation>,
+    pub skip: HashSet<CategoryKey>,
+    /// Extra names added to the config's `names` (`CLINICAL_SCRUBBER_NAMES`).
+    pub names: Vec<String>,
+    /// Show the original text of each redaction instead of a placeholder (`--log-phi`).
+    pub log_phi: bool,
+}
+
+struct Session {
+    settings: ReplSettings,
+    scrubber: Scrubber,
+}
+
+impl Session {
+    fn build(settings: &ReplSettings) -> Result<Scrubber> {
+        let mut config = ScrubberConfig::from_files(&settings.config_paths)?;
+        config.field_mode |= settings.field_mode;
+        config.names.extend(settings.names.iter().cloned());
+        if settings.regulation.is_some() {
+            config.regulation = settings.regulation;
+        }
+        Scrubber::new(config, settings.mode)
+    }
+
+    fn rebuild(&mut self) -> Result<()> {
+        self.scrubber = Self::build(&self.settings)?;
+        Ok(())
+    }
+
+    fn warn(&self, output: &mut impl Write) -> Result<()> {
+        for warning in self.scrubber.token_warnings() {
+            writeln!(output, "warning: {}", warning)?;
+        }
+        Ok(())
+    }
+
+    fn parse_category(&self, value: &str) -> Result<CategoryKey> {
+        let category: CategoryKey = value.parse().map_err(|err: String| anyhow!(err))?;
+        if !self.scrubber.categories().contains(&category) {
+            return Err(anyhow!("no custom_patterns entry defines {}", category));
+        }
+        Ok(category)
+    }
+
+    fn switch_profile(&mut self, value: &str) -> Result<()> {
+        let regulation = match value {
+            "none" => None,
+            name => Some(
+                <Regulation as clap::ValueEnum>::from_str(name, true)
+                    .map_err(|_| anyhow!("unknown profile: {}", name))?,
+            ),
+        };
+        let previous = std::mem::replace(&mut self.settings.regulation, regulation);
+        self.rebuild()
+            .inspect_err(|_| self.settings.regulation = previous)
+    }
+
+    /// Applies a `:` command. Returns false on `:quit`.
+    fn command(&mut self, line: &str, output: &mut impl Write) -> Result<bool> {
+        let mut words = line.split_whitespace();
+        let result = match (words.next(), words.next()) {
+            (Some(":quit" | ":q"), None) => return Ok(false),
+            (Some(":help"), None) => {
+                output.write_all(HELP.as_bytes())?;
+                Ok(())
+            }
+            (Some(":skip"), Some(value)) => self.parse_category(value).map(|category| {
+                self.settings.skip.insert(category);
+            }),
+            (Some(":unskip"), Some(value)) => self.parse_category(value).map(|category| {
+                self.settings.skip.remove(&category);
+            }),
+            (Some(":profile"), Some(value)) => self.switch_profile(value),
+            (Some(":reload-config"), None) => self.rebuild(),
+            _ => Err(anyhow!("unknown command: {} (try :help)", line.trim())),
+        };
+        match result {
+            Ok(()) if line.starts_with(":help") => {}
+            Ok(()) => {
+                writeln!(output, "ok")?;
+                self.warn(output)?;
+            }
+            Err(err) => writeln!(output, "error: {:#}", err)?,
+        }
+        Ok(true)
+    }
+
+    fn preview(&self, text: &str, output: &mut impl Write) -> Result<()> {
+        let result = self.scrubber.scrub_detailed(text, &self.settings.skip);
+        let description = self.scrubber.describe(&self.settings.skip);
+        writeln!(output, "{}", result.text)?;
+        for span in &result.spans {
+            write!(
+                output,
+                "  {} <- {} by {}",
+                &result.text[span.output.clone()],
+                span.category,
+                span.rule
+            )?;
+            if let Some(rule) = description.rule(span.rule) {
+                write!(output, " ({})", rule.summary)?;
+            }
+            writeln!(
+                output,
+                " {:?}",
+                PhiDisplay::new(&text[span.original.clone()], self.settings.log_phi)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs a session until `:quit` or end of input. Fails only if the initial config does not
+/// build or the streams fail.
+pub fn run(settings: ReplSettings, input: impl BufRead, mut output: impl Write) -> Result<()> {
+    let scrubber = Session::build(&settings)?;
+    let mut session = Session { settings, scrubber };
+    session.warn(&mut output)?;
+    let mut lines = input.lines();
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        if line.starts_with(':') {
+            if !session.command(&line, &mut output)? {
+                break;
+            }
+        } else if line.trim() == "<<" {
+            let mut block = Vec::new();
+            for line in lines.by_ref() {
+                let line = line?;
+                if line == "." {
+                    break;
+                }
+                block.push(line);
+            }
+            session.preview(&block.join("\n"), &mut output)?;
+        } else if !line.trim().is_empty() {
+            session.preview(&line, &mut output)?;
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(script: &str, settings: ReplSettings) -> String {
+        let mut output = Vec::new();
+        run(settings, script.as_bytes(), &mut output).expect("session");
+        String::from_utf8(output).expect("utf8")
+    }
+
+    #[test]
+    fn previews_lines_and_blocks_with_annotations() {
+        let output = session(
+            "Email jane.doe@example.com\n<<\nSeen 03/14/2024\nby phone 312-555-0182\n.\n:quit\nnot reached\n",
+            ReplSettings {
+                log_phi: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            output,
+            "> Email [EMAIL]\n  [EMAIL] <- email by email (user@domain.tld) \"jane.doe@example.com\"\n\
+             > Seen [DATE]\nby phone [PHONE]\n  \
+             [DATE] <- date by date (numeric, ISO, and month-name dates) \"03/14/2024\"\n  \
+             [PHONE] <- phone by phone-label (Ph/Fax/Pager labels attribute the next number on \
+             the line) \"312-555-0182\"\n\
+             > "
+        );
+    }
+
+    #[test]
+    fn commands_adjust_the_live_scrubber() {
+        let output = session(
+            ":skip date\nSeen 03/14/2024\n:unskip date\n:profile limited-dataset\nSeen 03/14/2024\n:skip nonsense\n:profile bogus\n",
+            ReplSettings::default(),
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "> ok");
+        assert_eq!(lines[1], "> Seen 03/14/2024");
+        assert_eq!(lines[2], "> ok");
+        assert_eq!(lines[3], "> ok");
+        assert_eq!(lines[4], "> Seen 03/14/2024");
+        assert!(lines[5].starts_with("> error: unknown category 'nonsense'"));
+        assert_eq!(lines[6], "> error: unknown profile: bogus");
+    }
+
+    /// Yields `chunks` one read at a time and runs `between` before the last one, so a test
+    /// can change files mid-session.
+    struct Staged<F: FnMut()> {
+        chunks: Vec<&'static [u8]>,
+        between: F,
+    }
+
+    impl<F: FnMut()> std::io::Read for Staged<F> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.chunks.is_empty() {
+                return Ok(0);
+            }
+            if self.chunks.len() == 1 {
+                (self.between)();
+            }
+            let chunk = self.chunks.remove(0);
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn reload_reports_a_broken_config_and_keeps_going() {
+        let path = std::env::temp_dir().join(format!("repl-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"names": ["Zelda Quartermaine"]}"#).expect("config");
+        let settings = ReplSettings {
+            config_paths: vec![path.clone()],
+            ..Default::default()
+        };
+        let input = Staged {
+            chunks: vec![
+                b"Zelda Quartermaine called\n",
+                b":reload-config\nZelda Quartermaine called\n",
+            ],
+            between: || std::fs::write(&path, "{not json").expect("config"),
+        };
+        let mut output = Vec::new();
+        run(settings, std::io::BufReader::new(input), &mut output).expect("session");
+        std::fs::remove_file(&path).ok();
+
+        let output = String::from_utf8(output).expect("utf8");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "> [PERSON] called", "{}", output);
+        assert!(
+            lines[2].starts_with("> error: failed to parse config JSON"),
+            "{}",
+            output
+        );
+        assert_eq!(lines[3], "> [PERSON] called");
+    }
+}
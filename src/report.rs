@@ -0,0 +1,433 @@
+pub mod aggregate;
+pub mod html;
+pub mod manifest;
+pub mod v1;
+pub mod v2;
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::config::PersonMode;
+use crate::scrubber::ScrubStats;
+use crate::spans::RedactionSpan;
+use crate::{Category, CategoryKey};
+
+/// Shape of the JSON artifacts (stats, spans, alignment, audit log, rule description, webhook
+/// events).
+/// Every artifact carries its `schema_version`; typed structs for each live in `v1` and `v2`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum SchemaVersion {
+    /// Flat per-category stats counters.
+    #[default]
+    #[value(name = "1")]
+    V1,
+    /// Stats counts keyed by category name; flat span offsets.
+    #[value(name = "2")]
+    V2,
+}
+
+impl SchemaVersion {
+    pub fn number(self) -> u32 {
+        match self {
+            SchemaVersion::V1 => v1::SCHEMA_VERSION,
+            SchemaVersion::V2 => v2::SCHEMA_VERSION,
+        }
+    }
+}
+
+/// Adds `schema_version` to artifacts whose shape is the same in every version.
+#[derive(Serialize)]
+pub struct Versioned<'a, T: Serialize> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub artifact: &'a T,
+}
+
+impl<'a, T: Serialize> Versioned<'a, T> {
+    pub fn new(version: SchemaVersion, artifact: &'a T) -> Self {
+        Self {
+            schema_version: version.number(),
+            artifact,
+        }
+    }
+}
+
+/// How each category was configured for the run being reported.
+#[derive(Debug, Default)]
+pub struct ReportOptions {
+    /// Categories with at least one detector that ran.
+    pub active: Vec<CategoryKey>,
+    /// Categories passed to `--skip`.
+    pub skipped: Vec<CategoryKey>,
+    /// Categories whose detectors all require a mode that is off (e.g. Safe Harbor).
+    pub not_enabled: Vec<CategoryKey>,
+    /// Break the counts down by rule (`--verbose`).
+    pub rules: bool,
+    /// The `person_mode` in effect and the person matchers it turned off, listed with the
+    /// per-rule counts.
+    pub person_mode: PersonMode,
+    pub person_rules_off: Vec<&'static str>,
+}
+
+impl ScrubStats {
+    /// Human-readable summary: one row per category that fired, followed by the active
+    /// categories that found nothing, the categories that did not run, the per-rule counts when
+    /// asked for, and the per-line counts when they were collected.
+    pub fn render_human(&self, options: &ReportOptions) -> String {
+        let mut out = format!("Redactions applied: {}\n", self.total());
+        for (category, count) in self.by_key() {
+            if count > 0 {
+                out.push_str(&format!("  {:<15}: {}", label(&category), count));
+                if let Some(unique) = self.unique.get(&category.to_string()) {
+                    let mentions = if count == 1 { "mention" } else { "mentions" };
+                    out.push_str(&format!(" {}, {} unique", mentions, unique));
+                }
+                match self.low_confidence.get(&category.to_string()) {
+                    Some(low) => out.push_str(&format!(" ({} low-confidence)\n", low)),
+                    None => out.push('\n'),
+                }
+            }
+        }
+
+        let quiet: Vec<CategoryKey> = options
+            .active
+            .iter()
+            .filter(|category| self.count_key(category) == 0)
+            .cloned()
+            .collect();
+        for (heading, categories) in [
+            ("No matches", quiet.as_slice()),
+            ("Skipped", options.skipped.as_slice()),
+            ("Not enabled", options.not_enabled.as_slice()),
+        ] {
+            if !categories.is_empty() {
+                out.push_str(&format!("{}: {}\n", heading, names(categories)));
+            }
+        }
+
+        if self.parse_errors > 0 {
+            out.push_str(&format!(
+                "Parse errors: {} (passed through unscrubbed)\n",
+                self.parse_errors
+            ));
+        }
+
+        if options.rules && !(self.rules.is_empty() && options.person_rules_off.is_empty()) {
+            out.push_str("By rule:\n");
+            for (rule, count) in &self.rules {
+                out.push_str(&format!("  {}: {}\n", rule, count));
+            }
+            if !options.person_rules_off.is_empty() {
+                out.push_str(&format!(
+                    "  off by person_mode {}: {}\n",
+                    options.person_mode.name(),
+                    options.person_rules_off.join(", ")
+                ));
+            }
+        }
+
+        for line in self.lines.chunk_by(|a, b| a.line == b.line) {
+            let counts: Vec<String> = line
+                .iter()
+                .map(|entry| format!("{} {}", entry.category, entry.count))
+                .collect();
+            out.push_str(&format!("  line {}: {}\n", line[0].line, counts.join(", ")));
+        }
+        out
+    }
+
+    /// `file,category,count` rows, header first: one per category that fired, or one per
+    /// category when `all` is set. Rows carry the file name so runs can be appended into one
+    /// sheet.
+    pub fn render_csv(&self, file: &str, all: bool) -> String {
+        let mut out = String::from("file,category,count\n");
+        for (category, count) in self.by_key() {
+            if all || count > 0 {
+                out.push_str(&format!("{},{},{}\n", csv_field(file), category, count));
+            }
+        }
+        out
+    }
+
+    pub fn render_json(&self, version: SchemaVersion) -> Result<String> {
+        match version {
+            SchemaVersion::V1 => serde_json::to_string_pretty(&v1::Stats::from(self)),
+            SchemaVersion::V2 => serde_json::to_string_pretty(&v2::Stats::from(self)),
+        }
+        .context("failed to serialize stats")
+    }
+
+    pub fn to_json_value(&self, version: SchemaVersion) -> Result<serde_json::Value> {
+        match version {
+            SchemaVersion::V1 => serde_json::to_value(v1::Stats::from(self)),
+            SchemaVersion::V2 => serde_json::to_value(v2::Stats::from(self)),
+        }
+        .context("failed to serialize stats")
+    }
+}
+
+/// One JSON line for `span` in the given schema.
+pub fn render_span_json(span: &RedactionSpan, version: SchemaVersion) -> Result<String> {
+    match version {
+        SchemaVersion::V1 => serde_json::to_string(&v1::Span::from(span)),
+        SchemaVersion::V2 => serde_json::to_string(&v2::Span::from(span)),
+    }
+    .context("failed to serialize span")
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+fn owned_versions(versions: &BTreeMap<&'static str, String>) -> BTreeMap<String, String> {
+    versions
+        .iter()
+        .map(|(name, version)| (name.to_string(), version.clone()))
+        .collect()
+}
+
+fn label(category: &CategoryKey) -> String {
+    match category {
+        CategoryKey::Builtin(category) => builtin_label(*category).to_string(),
+        CategoryKey::Custom(_) => category.to_string(),
+    }
+}
+
+fn builtin_label(category: Category) -> &'static str {
+    match category {
+        Category::Email => "emails",
+        Category::Phone => "phones",
+        Category::Fax => "faxes",
+        Category::Pager => "pagers",
+        Category::Date => "dates",
+        Category::RelativeDate => "relative dates",
+        Category::Age => "ages over 89",
+        Category::Ssn => "ssn",
+        Category::Mrn => "mrn",
+        Category::Account => "accounts",
+        Category::Zip => "zip codes",
+        Category::Person => "persons",
+        Category::Facility => "facilities",
+        Category::Employer => "employers",
+        Category::Address => "addresses",
+        Category::Geo => "geographic",
+        Category::Coordinate => "coordinates",
+        Category::Url => "urls",
+        Category::Handle => "handles",
+        Category::Insurance => "insurance",
+        Category::Mbi => "mbi",
+        Category::License => "licenses",
+        Category::GovtId => "government ids",
+        Category::Certificate => "certificates",
+        Category::Vehicle => "vehicles",
+        Category::Device => "devices",
+        Category::Ip => "ip addresses",
+        Category::Segment => "segments",
+    }
+}
+
+/// Quotes a field that holds a comma, quote, or line break, doubling inner quotes.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn names(categories: &[CategoryKey]) -> String {
+    categories
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scrubber::LineCount;
+
+    #[test]
+    fn renders_every_category() {
+        let mut stats = ScrubStats::default();
+        for (index, category) in Category::ALL.iter().enumerate() {
+            stats.record(*category, index + 1);
+        }
+        let options = ReportOptions {
+            active: Category::ALL.map(CategoryKey::from).to_vec(),
+            ..Default::default()
+        };
+        let expected = "\
+Redactions applied: 406
+  emails         : 1
+  phones         : 2
+  faxes          : 3
+  pagers         : 4
+  dates          : 5
+  relative dates : 6
+  ages over 89   : 7
+  ssn            : 8
+  mrn            : 9
+  accounts       : 10
+  zip codes      : 11
+  persons        : 12
+  facilities     : 13
+  employers      : 14
+  addresses      : 15
+  geographic     : 16
+  coordinates    : 17
+  urls           : 18
+  handles        : 19
+  insurance      : 20
+  mbi            : 21
+  licenses       : 22
+  government ids : 23
+  certificates   : 24
+  vehicles       : 25
+  devices        : 26
+  ip addresses   : 27
+  segments       : 28
+";
+        assert_eq!(stats.render_human(&options), expected);
+    }
+
+    #[test]
+    fn lists_quiet_skipped_and_disabled_categories() {
+        let mut stats = ScrubStats::default();
+        stats.record(Category::Email, 2);
+        let options = ReportOptions {
+            active: [Category::Email, Category::Phone, Category::Date]
+                .map(CategoryKey::from)
+                .to_vec(),
+            skipped: vec![Category::Person.into()],
+            not_enabled: vec![Category::Url.into(), Category::Ip.into()],
+            ..Default::default()
+        };
+        let expected = "\
+Redactions applied: 2
+  emails         : 2
+No matches: phone, date
+Skipped: person
+Not enabled: url, ip
+";
+        assert_eq!(stats.render_human(&options), expected);
+    }
+
+    #[test]
+    fn lists_per_line_counts_after_the_summary() {
+        let mut stats = ScrubStats::default();
+        stats.record(Category::Person, 2);
+        stats.record(Category::Phone, 1);
+        stats.lines = [(42, Category::Phone, 1), (42, Category::Person, 2)]
+            .map(|(line, category, count)| LineCount {
+                line,
+                category: category.into(),
+                count,
+            })
+            .to_vec();
+        let expected = "\
+Redactions applied: 3
+  phones         : 1
+  persons        : 2
+  line 42: phone 1, person 2
+";
+        assert_eq!(stats.render_human(&ReportOptions::default()), expected);
+    }
+
+    #[test]
+    fn csv_lists_fired_categories_or_all_and_quotes_file_names() {
+        let mut stats = ScrubStats::default();
+        stats.record(Category::Phone, 2);
+        assert_eq!(
+            stats.render_csv("notes/a,b.txt", false),
+            "file,category,count\n\"notes/a,b.txt\",phone,2\n"
+        );
+        let all = stats.render_csv("a.txt", true);
+        assert_eq!(all.lines().count(), Category::ALL.len() + 1);
+        assert!(all.contains("\na.txt,email,0\n"));
+    }
+
+    #[test]
+    fn json_includes_every_counter() {
+        let json: serde_json::Value = serde_json::from_str(
+            &ScrubStats::default()
+                .render_json(SchemaVersion::V1)
+                .expect("json"),
+        )
+        .expect("parse");
+        let object = json.as_object().expect("object");
+        assert_eq!(object.len(), Category::ALL.len() + 1);
+        assert_eq!(object["schema_version"], 1);
+
+        let json = ScrubStats::default()
+            .to_json_value(SchemaVersion::V2)
+            .expect("json");
+        assert_eq!(
+            json["counts"].as_object().expect("counts").len(),
+            Category::ALL.len()
+        );
+        assert_eq!(json["schema_version"], 2);
+    }
+
+    #[test]
+    fn deserializes_stats_fixtures() {
+        let stats: v1::Stats =
+            serde_json::from_str(include_str!("../tests/fixtures/stats_v1.json")).expect("v1");
+        assert_eq!(stats.schema_version, 1);
+        assert_eq!((stats.emails, stats.phones, stats.mrn), (1, 2, 3));
+        assert!(stats.spans_truncated);
+        assert_eq!(stats.spans_dropped, 4);
+
+        let stats: v2::Stats =
+            serde_json::from_str(include_str!("../tests/fixtures/stats_v2.json")).expect("v2");
+        assert_eq!(stats.schema_version, 2);
+        assert_eq!(stats.total, 6);
+        assert_eq!(stats.counts["phone"], 2);
+        assert_eq!(stats.dictionaries["names"], "e0145e430b8535ff");
+    }
+
+    #[test]
+    fn deserializes_span_fixtures() {
+        let spans: Vec<v1::Span> = include_str!("../tests/fixtures/spans_v1.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("v1 span"))
+            .collect();
+        assert_eq!(spans[1].category, Category::Phone.into());
+        assert_eq!(spans[1].original, 31..43);
+
+        let spans: Vec<v2::Span> = include_str!("../tests/fixtures/spans_v2.ndjson")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("v2 span"))
+            .collect();
+        assert_eq!(spans[1].category, Category::Phone.into());
+        assert_eq!((spans[1].start, spans[1].output_end), (31, 24));
+    }
+
+    #[test]
+    fn rendered_stats_round_trip() {
+        let mut stats = ScrubStats::default();
+        stats.record(Category::Fax, 3);
+        stats.lines = vec![LineCount {
+            line: 7,
+            category: Category::Fax.into(),
+            count: 3,
+        }];
+        for version in [SchemaVersion::V1, SchemaVersion::V2] {
+            let json = stats.render_json(version).expect("json");
+            match version {
+                SchemaVersion::V1 => {
+                    let parsed: v1::Stats = serde_json::from_str(&json).expect("v1");
+                    assert_eq!(parsed, v1::Stats::from(&stats));
+                }
+                SchemaVersion::V2 => {
+                    let parsed: v2::Stats = serde_json::from_str(&json).expect("v2");
+                    assert_eq!(parsed, v2::Stats::from(&stats));
+                }
+            }
+        }
+    }
+}
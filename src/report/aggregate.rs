@@ -0,0 +1,183 @@
+//! `--stats-report`: one JSON report with the stats of every file scrubbed into it and their
+//! total. Each run adds or replaces its own file's entry and recomputes the total, so a shell
+//! loop over a folder of notes builds a single report.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{v1, v2, SchemaVersion};
+use crate::dates::CivilDate;
+use crate::scrubber::ScrubStats;
+
+#[derive(Serialize, Deserialize)]
+struct ReportFile {
+    tool_version: String,
+    /// When the report was last written, in UTC (RFC 3339).
+    generated_at: String,
+    /// Stats per file, keyed by the path as given on the command line (`-` for stdin).
+    files: BTreeMap<String, serde_json::Value>,
+    total: serde_json::Value,
+}
+
+#[derive(Debug, Default)]
+pub struct StatsReport {
+    files: BTreeMap<String, ScrubStats>,
+}
+
+impl StatsReport {
+    /// Loads a report. A missing file gives an empty report. An unreadable or corrupt file
+    /// gives an empty report and a warning; entries in either schema version are read.
+    pub fn load(path: &Path) -> (Self, Option<String>) {
+        let mut report = Self::default();
+        let raw = match fs::read(path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return (report, None),
+            Err(err) => {
+                let warning = format!("starting a new stats report {}: {}", path.display(), err);
+                return (report, Some(warning));
+            }
+        };
+        let parsed = serde_json::from_slice::<ReportFile>(&raw)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| {
+                file.files
+                    .into_iter()
+                    .map(|(name, stats)| Ok((name, parse_stats(stats)?)))
+                    .collect::<Result<BTreeMap<_, _>>>()
+            });
+        let warning = match parsed {
+            Ok(files) => {
+                report.files = files;
+                None
+            }
+            Err(err) => Some(format!(
+                "starting a new stats report {}: {}",
+                path.display(),
+                err
+            )),
+        };
+        (report, warning)
+    }
+
+    /// Records the stats of `file`, replacing those of an earlier run on the same file.
+    pub fn insert(&mut self, file: impl Into<String>, stats: &ScrubStats) {
+        self.files.insert(file.into(), stats.clone());
+    }
+
+    /// The sum of every file's stats.
+    pub fn total(&self) -> ScrubStats {
+        let mut total = ScrubStats::default();
+        for stats in self.files.values() {
+            total += stats;
+        }
+        total
+    }
+
+    /// Writes the report beside `path` and renames it into place.
+    pub fn save(&self, path: &Path, version: SchemaVersion) -> Result<()> {
+        let file = ReportFile {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: utc_timestamp(SystemTime::now()),
+            files: self
+                .files
+                .iter()
+                .map(|(name, stats)| Ok((name.clone(), stats.to_json_value(version)?)))
+                .collect::<Result<_>>()?,
+            total: self.total().to_json_value(version)?,
+        };
+        let json = serde_json::to_vec_pretty(&file).context("failed to serialize stats report")?;
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        fs::write(&partial, json)
+            .and_then(|()| fs::rename(&partial, path))
+            .with_context(|| format!("failed to write stats report: {}", path.display()))
+    }
+}
+
+fn parse_stats(value: serde_json::Value) -> Result<ScrubStats> {
+    Ok(if value["schema_version"] == v2::SCHEMA_VERSION {
+        let stats: v2::Stats = serde_json::from_value(value)?;
+        ScrubStats::from(&stats)
+    } else {
+        let stats: v1::Stats = serde_json::from_value(value)?;
+        ScrubStats::from(&stats)
+    })
+}
+
+/// `2026-10-16T09:30:00Z`.
+pub(crate) fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let date = CivilDate::from_days((seconds / 86_400) as i64);
+    let time = seconds % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        date.year,
+        date.month,
+        date.day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::Category;
+
+    #[test]
+    fn keeps_one_entry_per_file_across_runs_and_schema_versions() {
+        let dir = std::env::temp_dir().join(format!("stats-report-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("dir");
+        let path = dir.join("report.json");
+        let _ = fs::remove_file(&path);
+
+        let mut first = ScrubStats::default();
+        first.record(Category::Phone, 2);
+        let (mut report, warning) = StatsReport::load(&path);
+        assert!(warning.is_none());
+        report.insert("a.txt", &first);
+        report.save(&path, SchemaVersion::V1).expect("save");
+
+        let mut second = ScrubStats::default();
+        second.record(Category::Person, 3);
+        let (mut report, warning) = StatsReport::load(&path);
+        assert!(warning.is_none());
+        report.insert("b.txt", &second);
+        report.insert("a.txt", &first);
+        report.save(&path, SchemaVersion::V2).expect("save");
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&fs::read(&path).expect("read")).expect("json");
+        assert_eq!(json["tool_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["files"].as_object().expect("files").len(), 2);
+        assert_eq!(json["files"]["a.txt"]["counts"]["phone"], 2);
+        assert_eq!(json["total"]["total"], 5);
+        assert_eq!(json["total"]["counts"]["person"], 3);
+
+        fs::write(&path, "not json").expect("corrupt");
+        let (report, warning) = StatsReport::load(&path);
+        assert!(warning
+            .expect("warning")
+            .contains("starting a new stats report"));
+        assert_eq!(report.total().total(), 0);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn formats_utc_timestamps() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_792_143_000);
+        assert_eq!(utc_timestamp(time), "2026-10-16T09:30:00Z");
+    }
+}
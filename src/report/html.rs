@@ -0,0 +1,98 @@
+//! `--format html`: the scrubbed note as a self-contained page for reviewers, with each
+//! redaction marked by category and the stats table (which doubles as the legend) on top.
+
+use crate::scrubber::ScrubResult;
+use crate::CategoryKey;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { border: 1px solid #ccc; padding: 2px 8px; text-align: left; }
+pre { white-space: pre-wrap; font-size: 14px; }
+mark.phi { background: #fde68a; border-radius: 3px; padding: 0 2px; }
+";
+
+/// Renders `result` as an HTML page. Redactions past the `max_spans` cap have no span record,
+/// so their tokens appear unmarked.
+pub fn render_html(result: &ScrubResult) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+    out.push_str("<meta charset=\"utf-8\">\n<title>Scrubbed note</title>\n");
+    out.push_str(&format!("<style>\n{}</style>\n</head>\n<body>\n", STYLE));
+
+    out.push_str("<table class=\"stats\">\n<tr><th>Category</th><th>Redactions</th></tr>\n");
+    for (category, count) in result.stats.by_key() {
+        if count > 0 {
+            out.push_str(&format!(
+                "<tr><td><mark class=\"{}\">{}</mark></td><td>{}</td></tr>\n",
+                class(&category),
+                escape(&super::label(&category)),
+                count
+            ));
+        }
+    }
+    out.push_str(&format!(
+        "<tr><th>Total</th><th>{}</th></tr>\n</table>\n",
+        result.stats.total()
+    ));
+
+    out.push_str("<pre class=\"note\">");
+    let mut cursor = 0;
+    for span in &result.spans {
+        out.push_str(&escape(&result.text[cursor..span.output.start]));
+        out.push_str(&format!(
+            "<mark class=\"{}\">{}</mark>",
+            class(&span.category),
+            escape(&result.text[span.output.clone()])
+        ));
+        cursor = span.output.end;
+    }
+    out.push_str(&escape(&result.text[cursor..]));
+    out.push_str("</pre>\n</body>\n</html>\n");
+    out
+}
+
+/// "phi phi-person", or "phi phi-custom-encounter" for a custom category.
+fn class(category: &CategoryKey) -> String {
+    format!("phi phi-{}", category.to_string().replace(':', "-"))
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::config::ScrubberConfig;
+    use crate::{ScrubMode, Scrubber};
+
+    #[test]
+    fn matches_the_golden_page_for_the_sample_note() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let note = include_str!("../../tests/fixtures/sample_note.txt");
+        let result = scrubber.scrub_detailed(note, &HashSet::new());
+        assert_eq!(
+            render_html(&result),
+            include_str!("../../tests/fixtures/sample_note.html")
+        );
+
+        let result = scrubber.scrub_detailed("a<b & \"c\" for Jane Doe", &HashSet::new());
+        assert!(render_html(&result).contains(
+            "a&lt;b &amp; &quot;c&quot; for <mark class=\"phi phi-person\">[PERSON]</mark>"
+        ));
+    }
+}
@@ -0,0 +1,213 @@
+//! `--manifest`: one JSON record of a batch run for audits. It lists each input and output
+//! with its SHA-256, each file's stats, the inputs skipped as binary, the hash of the resolved
+//! config, the tool and schema versions, and when the run started and finished.
+//!
+//! The manifest is written once, at the end of the run, beside its path and then renamed into
+//! place, so a run that dies leaves either no manifest or a complete one. A run stopped by
+//! Ctrl-C still ends, and its manifest says it was interrupted.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use super::aggregate::utc_timestamp;
+use super::SchemaVersion;
+use crate::dedupe::DedupeCounts;
+
+/// What became of one file of the run.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileStatus {
+    Scrubbed,
+    /// A byte-identical input was scrubbed earlier in the run; its output was copied.
+    Reused,
+    Failed,
+    /// The input looked binary and was not read.
+    Skipped,
+}
+
+/// One file of the run. Hashes are lowercase hex SHA-256 of the bytes read and written; a
+/// failed or skipped file has neither, nor an output.
+#[derive(Clone, Debug, Serialize)]
+pub struct ManifestFile {
+    pub input: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_sha256: Option<String>,
+    pub status: FileStatus,
+    /// For a reused file, the input it repeats byte for byte (`--dedupe`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<String>,
+    /// The file's stats, in the run's schema version; absent for reused, failed and skipped
+    /// files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Manifest {
+    /// The `--schema-version` the stats in `files` and `total` are written in.
+    pub schema_version: u32,
+    pub tool_version: String,
+    /// UTC (RFC 3339).
+    pub started_at: String,
+    pub finished_at: String,
+    pub elapsed_ms: u64,
+    /// SHA-256 of the merged config (as `--print-config` shows it) and the settings resolved
+    /// from it (as the `config` subcommand shows them), so overrides from the environment, the
+    /// mode and the command line's skips are covered.
+    pub config_sha256: String,
+    pub files: Vec<ManifestFile>,
+    pub total: serde_json::Value,
+    /// Unique and duplicate documents, with `--dedupe` or `--dedupe-cache`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedupe: Option<DedupeCounts>,
+    /// True when Ctrl-C stopped the run: `files` and `total` cover only the files finished
+    /// before it, and the rest were never started.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+}
+
+impl Manifest {
+    /// A manifest for a run that started at `started` and finishes now.
+    pub fn new(
+        version: SchemaVersion,
+        started: SystemTime,
+        config_sha256: String,
+        files: Vec<ManifestFile>,
+        total: serde_json::Value,
+    ) -> Self {
+        let finished = SystemTime::now();
+        let elapsed = finished.duration_since(started).unwrap_or_default();
+        Self {
+            schema_version: version.number(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: utc_timestamp(started),
+            finished_at: utc_timestamp(finished),
+            elapsed_ms: elapsed.as_millis() as u64,
+            config_sha256,
+            files,
+            total,
+            dedupe: None,
+            interrupted: false,
+        }
+    }
+
+    /// Writes and syncs the manifest beside `path`, then renames it into place.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(self).context("failed to serialize manifest")?;
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let written = fs::File::create(&partial).and_then(|mut file| {
+            file.write_all(&json)?;
+            file.write_all(b"\n")?;
+            file.sync_all()
+        });
+        if let Err(err) = written.and_then(|()| fs::rename(&partial, path)) {
+            fs::remove_file(&partial).ok();
+            return Err(err)
+                .with_context(|| format!("failed to write manifest: {}", path.display()));
+        }
+        Ok(())
+    }
+}
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 (FIPS 180-4) as 64 lowercase hex digits. The crate has no crypto dependency, and
+/// the manifest's hashes must match what `sha256sum` prints.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((bytes.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut words = [0u32; 64];
+        for (word, chunk) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        for i in 16..64 {
+            let s0 = words[i - 15].rotate_right(7)
+                ^ words[i - 15].rotate_right(18)
+                ^ (words[i - 15] >> 3);
+            let s1 = words[i - 2].rotate_right(17)
+                ^ words[i - 2].rotate_right(19)
+                ^ (words[i - 2] >> 10);
+            words[i] = words[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(words[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (constant, word) in ROUND_CONSTANTS.iter().zip(words) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(*constant)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+    state.iter().map(|word| format!("{:08x}", word)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_matches_the_standard_test_vectors() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+}
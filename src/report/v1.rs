@@ -0,0 +1,247 @@
+//! Version 1 JSON shapes: stats as flat per-category counters named after the `ScrubStats`
+//! fields, and spans with `original`/`output` ranges.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::describe::Confidence;
+use crate::profiles::Regulation;
+use crate::profiling::DetectorTiming;
+use crate::scrubber::{LineCount, ScrubStats};
+use crate::spans::RedactionSpan;
+use crate::CategoryKey;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub schema_version: u32,
+    pub emails: usize,
+    pub phones: usize,
+    pub faxes: usize,
+    pub pagers: usize,
+    pub dates: usize,
+    /// Dates `date_shift` could not parse and redacted instead; part of the date count.
+    #[serde(default, skip_serializing_if = "super::is_zero")]
+    pub date_shift_fallbacks: usize,
+    pub relative_dates: usize,
+    #[serde(default)]
+    pub ages: usize,
+    pub ssn: usize,
+    pub mrn: usize,
+    /// Absent from output written before accounts were split from MRNs.
+    #[serde(default)]
+    pub accounts: usize,
+    pub zip_codes: usize,
+    pub persons: usize,
+    pub facilities: usize,
+    #[serde(default)]
+    pub employers: usize,
+    pub addresses: usize,
+    #[serde(default)]
+    pub geo: usize,
+    pub coordinates: usize,
+    /// Coordinates rounded per `coordinate_mode` instead of redacted; part of the coordinate
+    /// count.
+    #[serde(default, skip_serializing_if = "super::is_zero")]
+    pub coordinates_rounded: usize,
+    pub urls: usize,
+    #[serde(default)]
+    pub handles: usize,
+    pub insurance_ids: usize,
+    #[serde(default)]
+    pub mbi: usize,
+    pub licenses: usize,
+    #[serde(default)]
+    pub govt_ids: usize,
+    #[serde(default)]
+    pub certificates: usize,
+    pub vehicles: usize,
+    pub devices: usize,
+    pub ip_addresses: usize,
+    #[serde(default)]
+    pub segments: usize,
+    /// Redactions folded into `segments`, per category; they are not in the counts above.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub segment_subsumed: BTreeMap<String, usize>,
+    /// Counts for `custom_patterns` categories, keyed by name without the `custom:` prefix.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, usize>,
+    /// The regulation profile that ran, when one was selected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Regulation>,
+    /// The seed the scrubber ran with, so the run can be repeated with `--seed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dictionaries: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub spans_truncated: bool,
+    #[serde(default, skip_serializing_if = "super::is_zero")]
+    pub spans_dropped: usize,
+    /// JSON Lines records that did not parse and were passed through unscrubbed.
+    #[serde(default, skip_serializing_if = "super::is_zero")]
+    pub parse_errors: usize,
+    /// Distinct redacted values per category that fired; the values themselves are never
+    /// reported.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub unique: BTreeMap<String, usize>,
+    /// The config token override used for each category that fired.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tokens: BTreeMap<String, String>,
+    /// Redactions per `<category>.<rule>`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, usize>,
+    /// Redactions made by low-confidence rules, per category; part of the counts.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub low_confidence: BTreeMap<String, usize>,
+    /// Redactions per input line, present only with `--verbose`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<LineCount>,
+    /// Per-detector timings, present only with `--profile-detectors`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detector_timings: Vec<DetectorTiming>,
+}
+
+impl From<&ScrubStats> for Stats {
+    fn from(stats: &ScrubStats) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            emails: stats.emails,
+            phones: stats.phones,
+            faxes: stats.faxes,
+            pagers: stats.pagers,
+            dates: stats.dates,
+            date_shift_fallbacks: stats.date_shift_fallbacks,
+            relative_dates: stats.relative_dates,
+            ages: stats.ages,
+            ssn: stats.ssn,
+            mrn: stats.mrn,
+            accounts: stats.accounts,
+            zip_codes: stats.zip_codes,
+            persons: stats.persons,
+            facilities: stats.facilities,
+            employers: stats.employers,
+            addresses: stats.addresses,
+            geo: stats.geo,
+            coordinates: stats.coordinates,
+            coordinates_rounded: stats.coordinates_rounded,
+            urls: stats.urls,
+            handles: stats.handles,
+            insurance_ids: stats.insurance_ids,
+            mbi: stats.mbi,
+            licenses: stats.licenses,
+            govt_ids: stats.govt_ids,
+            certificates: stats.certificates,
+            vehicles: stats.vehicles,
+            devices: stats.devices,
+            ip_addresses: stats.ip_addresses,
+            segments: stats.segments,
+            segment_subsumed: stats.segment_subsumed.clone(),
+            custom: stats.custom.clone(),
+            profile: stats.profile,
+            seed: stats.seed,
+            dictionaries: super::owned_versions(&stats.dictionaries),
+            spans_truncated: stats.spans_truncated,
+            spans_dropped: stats.spans_dropped,
+            parse_errors: stats.parse_errors,
+            unique: stats.unique.clone(),
+            tokens: stats.tokens.clone(),
+            rules: stats.rules.clone(),
+            low_confidence: stats.low_confidence.clone(),
+            lines: stats.lines.clone(),
+            detector_timings: stats.detector_timings.clone(),
+        }
+    }
+}
+
+/// Reads stats back from their JSON form. Dictionary versions are not restored.
+impl From<&Stats> for ScrubStats {
+    fn from(stats: &Stats) -> Self {
+        Self {
+            emails: stats.emails,
+            phones: stats.phones,
+            faxes: stats.faxes,
+            pagers: stats.pagers,
+            dates: stats.dates,
+            date_shift_fallbacks: stats.date_shift_fallbacks,
+            relative_dates: stats.relative_dates,
+            ages: stats.ages,
+            ssn: stats.ssn,
+            mrn: stats.mrn,
+            accounts: stats.accounts,
+            zip_codes: stats.zip_codes,
+            persons: stats.persons,
+            facilities: stats.facilities,
+            employers: stats.employers,
+            addresses: stats.addresses,
+            geo: stats.geo,
+            coordinates: stats.coordinates,
+            coordinates_rounded: stats.coordinates_rounded,
+            urls: stats.urls,
+            handles: stats.handles,
+            insurance_ids: stats.insurance_ids,
+            mbi: stats.mbi,
+            licenses: stats.licenses,
+            govt_ids: stats.govt_ids,
+            certificates: stats.certificates,
+            vehicles: stats.vehicles,
+            devices: stats.devices,
+            ip_addresses: stats.ip_addresses,
+            segments: stats.segments,
+            segment_subsumed: stats.segment_subsumed.clone(),
+            custom: stats.custom.clone(),
+            profile: stats.profile,
+            seed: stats.seed,
+            spans_truncated: stats.spans_truncated,
+            spans_dropped: stats.spans_dropped,
+            parse_errors: stats.parse_errors,
+            unique: stats.unique.clone(),
+            tokens: stats.tokens.clone(),
+            rules: stats.rules.clone(),
+            low_confidence: stats.low_confidence.clone(),
+            lines: stats.lines.clone(),
+            detector_timings: stats.detector_timings.clone(),
+            ..ScrubStats::default()
+        }
+    }
+}
+
+/// One line of `--spans-out`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub schema_version: u32,
+    pub category: CategoryKey,
+    /// The detector that made the redaction. Absent from spans written before rules were
+    /// recorded.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub rule: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,
+    pub original: Range<usize>,
+    pub output: Range<usize>,
+    /// The text written in place of the original. Absent from spans written before tokens
+    /// were recorded.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub token: String,
+    /// Entity number of a `[PERSON_2]`-style token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+impl From<&RedactionSpan> for Span {
+    fn from(span: &RedactionSpan) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            category: span.category.clone(),
+            rule: span.rule.to_string(),
+            confidence: Some(span.confidence),
+            original: span.original.clone(),
+            output: span.output.clone(),
+            token: span.token.clone(),
+            index: span.index,
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Version 2 JSON shapes: stats keyed by category name under `counts`, so new categories do
+//! not add top-level fields, and flat span offsets.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::describe::Confidence;
+use crate::profiles::Regulation;
+use crate::profiling::DetectorTiming;
+use crate::scrubber::{LineCount, ScrubStats};
+use crate::spans::RedactionSpan;
+use crate::CategoryKey;
+
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub schema_version: u32,
+    pub total: usize,
+    /// Redactions per category, keyed by the names `--skip` accepts (`custom:<name>` for custom
+    /// patterns); every category is listed.
+    pub counts: BTreeMap<String, usize>,
+    /// The regulation profile that ran, when one was selected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Regulation>,
+    /// The seed the scrubber ran with, so the run can be repeated with `--seed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub dictionaries: BTreeMap<String, String>,
+    /// Redactions that were applied and counted but have no span record.
+    pub spans_dropped: usize,
+    /// JSON Lines records that did not parse and were passed through unscrubbed.
+    #[serde(default, skip_serializing_if = "super::is_zero")]
+    pub parse_errors: usize,
+    /// Redactions folded into the `segment` count, per category; they are not in `counts`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub segment_subsumed: BTreeMap<String, usize>,
+    /// Dates `date_shift` could not parse and redacted instead; part of the date count.
+    #[serde(default, skip_serializing_if = "super::is_zero")]
+    pub date_shift_fallbacks: usize,
+    /// Coordinates rounded per `coordinate_mode` instead of redacted; part of the coordinate
+    /// count.
+    #[serde(default, skip_serializing_if = "super::is_zero")]
+    pub coordinates_rounded: usize,
+    /// Distinct redacted values per category that fired; the values themselves are never
+    /// reported.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub unique: BTreeMap<String, usize>,
+    /// The config token override used for each category that fired.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tokens: BTreeMap<String, String>,
+    /// Redactions per `<category>.<rule>`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, usize>,
+    /// Redactions made by low-confidence rules, per category; part of the counts.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub low_confidence: BTreeMap<String, usize>,
+    /// Redactions per input line, present only with `--verbose`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<LineCount>,
+    /// Per-detector timings, present only with `--profile-detectors`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detector_timings: Vec<DetectorTiming>,
+}
+
+impl From<&ScrubStats> for Stats {
+    fn from(stats: &ScrubStats) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            total: stats.total(),
+            counts: stats
+                .by_key()
+                .into_iter()
+                .map(|(category, count)| (category.to_string(), count))
+                .collect(),
+            profile: stats.profile,
+            seed: stats.seed,
+            dictionaries: super::owned_versions(&stats.dictionaries),
+            spans_dropped: stats.spans_dropped,
+            parse_errors: stats.parse_errors,
+            date_shift_fallbacks: stats.date_shift_fallbacks,
+            coordinates_rounded: stats.coordinates_rounded,
+            segment_subsumed: stats.segment_subsumed.clone(),
+            unique: stats.unique.clone(),
+            tokens: stats.tokens.clone(),
+            rules: stats.rules.clone(),
+            low_confidence: stats.low_confidence.clone(),
+            lines: stats.lines.clone(),
+            detector_timings: stats.detector_timings.clone(),
+        }
+    }
+}
+
+/// Reads stats back from their JSON form. Dictionary versions are not restored, and counts
+/// under names this build does not know are dropped.
+impl From<&Stats> for ScrubStats {
+    fn from(stats: &Stats) -> Self {
+        let mut out = ScrubStats {
+            profile: stats.profile,
+            seed: stats.seed,
+            spans_truncated: stats.spans_dropped > 0,
+            spans_dropped: stats.spans_dropped,
+            parse_errors: stats.parse_errors,
+            date_shift_fallbacks: stats.date_shift_fallbacks,
+            coordinates_rounded: stats.coordinates_rounded,
+            segment_subsumed: stats.segment_subsumed.clone(),
+            unique: stats.unique.clone(),
+            tokens: stats.tokens.clone(),
+            rules: stats.rules.clone(),
+            low_confidence: stats.low_confidence.clone(),
+            lines: stats.lines.clone(),
+            detector_timings: stats.detector_timings.clone(),
+            ..ScrubStats::default()
+        };
+        for (name, &count) in &stats.counts {
+            if let Ok(category) = name.parse::<CategoryKey>() {
+                out.record_key(&category, count);
+            }
+        }
+        out
+    }
+}
+
+/// One line of `--spans-out`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub schema_version: u32,
+    pub category: CategoryKey,
+    /// The detector that made the redaction. Absent from spans written before rules were
+    /// recorded.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub rule: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,
+    pub start: usize,
+    pub end: usize,
+    pub output_start: usize,
+    pub output_end: usize,
+    /// The text written in place of the original. Absent from spans written before tokens
+    /// were recorded.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub token: String,
+    /// Entity number of a `[PERSON_2]`-style token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+impl From<&RedactionSpan> for Span {
+    fn from(span: &RedactionSpan) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            category: span.category.clone(),
+            rule: span.rule.to_string(),
+            confidence: Some(span.confidence),
+            start: span.original.start,
+            end: span.original.end,
+            output_start: span.output.start,
+            output_end: span.output.end,
+            token: span.token.clone(),
+            index: span.index,
+        }
+    }
+}
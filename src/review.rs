@@ -0,0 +1,281 @@
+//! `--interactive`: asks about each borderline redaction before the output is written.
+//!
+//! Only redactions made by the chosen rules, and below high confidence, are asked about; the
+//! rest stay. An "always" or "never" answer covers the same text for the rest of the session,
+//! and the session's answers can be saved as a config snippet.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+
+use crate::describe::Confidence;
+use crate::spans::RedactionSpan;
+use crate::{Category, CategoryKey, ScrubResult};
+
+/// Rules asked about unless `--review-rule` says otherwise.
+pub const DEFAULT_RULES: [&str; 1] = ["person.capital-sequence"];
+
+#[derive(Clone, Debug)]
+pub struct ReviewOptions {
+    /// `<category>.<rule>` keys as listed by `--describe-rules`; `_` is read as `-`.
+    pub rules: Vec<String>,
+    /// Words of input shown on each side of the match.
+    pub context_words: usize,
+}
+
+impl Default for ReviewOptions {
+    fn default() -> Self {
+        Self {
+            rules: DEFAULT_RULES.map(String::from).to_vec(),
+            context_words: 5,
+        }
+    }
+}
+
+impl ReviewOptions {
+    fn covers(&self, span: &RedactionSpan) -> bool {
+        span.confidence != Confidence::High
+            && self
+                .rules
+                .iter()
+                .any(|rule| rule.replace('_', "-") == format!("{}.{}", span.category, span.rule))
+    }
+}
+
+/// The session's "always" and "never" answers, keyed by text as `unique` counts it.
+#[derive(Clone, Debug, Default)]
+pub struct Decisions {
+    /// Text redacted without asking, with the category it was found under.
+    always: BTreeMap<String, (CategoryKey, String)>,
+    /// Text kept without asking.
+    never: BTreeMap<String, String>,
+}
+
+impl Decisions {
+    pub fn is_empty(&self) -> bool {
+        self.always.is_empty() && self.never.is_empty()
+    }
+
+    fn decided(&self, key: &str) -> Option<bool> {
+        if self.always.contains_key(key) {
+            Some(true)
+        } else if self.never.contains_key(key) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// A config to pass with `--config` on later runs: "never" answers go to `allowlist`;
+    /// "always" answers go to `names` for person hits and to `keywords` for the rest.
+    pub fn to_config_snippet(&self) -> Value {
+        let mut names = Vec::new();
+        let mut keywords = Vec::new();
+        for (category, text) in self.always.values() {
+            if *category == CategoryKey::Builtin(Category::Person) {
+                names.push(text.clone());
+            } else {
+                keywords.push(text.clone());
+            }
+        }
+        let allowlist: Vec<String> = self.never.values().cloned().collect();
+        let mut snippet = Map::new();
+        for (field, terms) in [
+            ("names", names),
+            ("keywords", keywords),
+            ("allowlist", allowlist),
+        ] {
+            if !terms.is_empty() {
+                snippet.insert(field.to_string(), json!(terms));
+            }
+        }
+        Value::Object(snippet)
+    }
+}
+
+/// Asks on `writer` about each of `result`'s spans that `options` covers, reading answers from
+/// `reader`, and puts back the text of every redaction turned down. `input` is the text that
+/// was scrubbed.
+pub fn review(
+    input: &str,
+    result: &mut ScrubResult,
+    options: &ReviewOptions,
+    decisions: &mut Decisions,
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+) -> Result<()> {
+    let keys = result.entity_keys().clone();
+    result.restore(input, |span| {
+        if !options.covers(span) {
+            return Ok(true);
+        }
+        let text = &input[span.original.clone()];
+        let key = keys.key(&span.category, text);
+        if let Some(redact) = decisions.decided(&key) {
+            return Ok(redact);
+        }
+        writeln!(
+            writer,
+            "{} ({}): {}",
+            span.category,
+            span.rule,
+            context(input, span, options.context_words)
+        )?;
+        loop {
+            write!(
+                writer,
+                "Redact? [y]es, [n]o, [a]lways, ne[v]er (default yes): "
+            )?;
+            writer.flush()?;
+            let mut answer = String::new();
+            if reader.read_line(&mut answer)? == 0 {
+                return Err(anyhow!("input ended before every detection was reviewed"));
+            }
+            match answer.trim().to_ascii_lowercase().as_str() {
+                "" | "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                "a" | "always" => {
+                    decisions
+                        .always
+                        .insert(key, (span.category.clone(), text.to_string()));
+                    return Ok(true);
+                }
+                "v" | "never" => {
+                    decisions.never.insert(key, text.to_string());
+                    return Ok(false);
+                }
+                _ => writeln!(writer, "answer y, n, a or v")?,
+            }
+        }
+    })
+}
+
+/// The match in `«»` with up to `words` words of the input on each side, on one line.
+fn context(input: &str, span: &RedactionSpan, words: usize) -> String {
+    let before: Vec<&str> = input[..span.original.start]
+        .split_whitespace()
+        .rev()
+        .take(words)
+        .collect();
+    let after: Vec<&str> = input[span.original.end..]
+        .split_whitespace()
+        .take(words)
+        .collect();
+    let mut line: Vec<String> = before.into_iter().rev().map(String::from).collect();
+    line.push(format!(
+        "«{}»",
+        input[span.original.clone()]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    ));
+    line.extend(after.into_iter().map(String::from));
+    line.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScrubberConfig;
+    use crate::{ScrubMode, Scrubber};
+    use std::collections::HashSet;
+
+    const INPUT: &str = "Seen with Zorbin Quaxley in clinic. Zorbin Quaxley and Mira Telvane \
+                         agreed. Called Dr. Alan Brooks.\n";
+
+    fn scrub(input: &str) -> ScrubResult {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        scrubber.scrub_detailed(input, &HashSet::new())
+    }
+
+    #[test]
+    fn answers_decide_borderline_hits_and_high_confidence_ones_are_not_asked() {
+        let mut result = scrub(INPUT);
+        assert_eq!(result.stats.persons, 4);
+        let mut decisions = Decisions::default();
+        let mut prompts = Vec::new();
+        review(
+            INPUT,
+            &mut result,
+            &ReviewOptions::default(),
+            &mut decisions,
+            "maybe\nv\n\n".as_bytes(),
+            &mut prompts,
+        )
+        .expect("review");
+
+        let prompts = String::from_utf8(prompts).expect("utf-8");
+        assert_eq!(prompts.matches("person (capital-sequence): ").count(), 2);
+        assert!(
+            prompts.contains("person (capital-sequence): Seen with «Zorbin Quaxley» in clinic.")
+        );
+        assert!(prompts.contains("answer y, n, a or v\n"));
+        assert!(!prompts.contains("titled-name"));
+
+        assert_eq!(
+            result.text,
+            "Seen with Zorbin Quaxley in clinic. Zorbin Quaxley and [PERSON] agreed. Called [PERSON].\n"
+        );
+        assert_eq!(result.stats.persons, 2);
+        assert_eq!(result.stats.unique["person"], 2);
+        assert_eq!(result.stats.low_confidence["person"], 1);
+        assert_eq!(result.stats.rules["person.capital-sequence"], 1);
+        let mira = INPUT.find("Mira").expect("offset");
+        let span = &result.spans[0];
+        assert_eq!(span.original.start, mira);
+        assert_eq!(&result.text[span.output.clone()], "[PERSON]");
+        let zorbin = INPUT.find("Quaxley").expect("offset");
+        let restored = result.project_span(zorbin..zorbin + 7).expect("restored");
+        assert_eq!(&result.text[restored], "Quaxley");
+        assert_eq!(result.project_span(span.original.clone()), None);
+
+        assert_eq!(
+            decisions.to_config_snippet(),
+            json!({"allowlist": ["Zorbin Quaxley"]})
+        );
+    }
+
+    #[test]
+    fn always_answers_carry_over_and_input_must_not_run_out() {
+        let options = ReviewOptions {
+            rules: vec!["person.capital_sequence".to_string()],
+            context_words: 1,
+        };
+        let mut decisions = Decisions::default();
+        let mut result = scrub(INPUT);
+        review(
+            INPUT,
+            &mut result,
+            &options,
+            &mut decisions,
+            "a\n".as_bytes(),
+            Vec::new(),
+        )
+        .expect_err("ran out of answers");
+
+        let mut result = scrub(INPUT);
+        let mut prompts = Vec::new();
+        review(
+            INPUT,
+            &mut result,
+            &options,
+            &mut decisions,
+            "no\n".as_bytes(),
+            &mut prompts,
+        )
+        .expect("review");
+        let prompts = String::from_utf8(prompts).expect("utf-8");
+        assert!(prompts.starts_with("person (capital-sequence): and «Mira Telvane» agreed.\n"));
+        assert_eq!(
+            result.text,
+            "Seen with [PERSON] in clinic. [PERSON] and Mira Telvane agreed. Called [PERSON].\n"
+        );
+        assert_eq!(
+            decisions.to_config_snippet(),
+            json!({"names": ["Zorbin Quaxley"]})
+        );
+    }
+}
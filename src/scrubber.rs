@@ -1,16 +1,39 @@
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{AddAssign, Range, RangeInclusive};
+use std::time::Instant;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use once_cell::sync::Lazy;
-use regex::{Captures, Regex, RegexBuilder};
-use serde::Serialize;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::is_combining_mark;
 use unicode_normalization::UnicodeNormalization;
 
-use crate::config::ScrubberConfig;
-use crate::Category;
+use crate::alignment::{rewrite_all, Alignment, Rewriter, SegmentKind};
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::config::{
+    AgeMode, AreaMode, BirthYearMode, CompositeIdMode, CoordinateMode, DateMode, DateOrder,
+    DateShift, FirstNameMode, PersonMode, ScrubberConfig, SegmentFallback, SegmentMode, TokenStyle,
+    ZipMode, DEFAULT_MRN_MAX_LENGTH, DEFAULT_MRN_MIN_LENGTH,
+};
+use crate::dates::{CivilDate, WrittenDate};
+use crate::describe::{
+    CategoryStates, CharacterClasses, Confidence, DisabledBy, MrnLength, ResolvedConfig,
+    RuleDescription, RuleSetDescription, RuleSource,
+};
+use crate::dictionaries::{read_terms, Dictionaries, Dictionary};
+use crate::profiles::{Regulation, RegulationProfile};
+use crate::profiling::DetectorTiming;
+use crate::report::ReportOptions;
+use crate::segmentation;
+use crate::spans::{RedactionSpan, SpanLog, DEFAULT_MAX_SPANS};
+use crate::{Category, CategoryKey};
 
 const EMAIL_TOKEN: &str = "[EMAIL]";
 const PHONE_TOKEN: &str = "[PHONE]";
+const FAX_TOKEN: &str = "[FAX]";
+const PAGER_TOKEN: &str = "[PAGER]";
 const DATE_TOKEN: &str = "[DATE]";
 const REL_DATE_TOKEN: &str = "[REL_DATE]";
 const SSN_TOKEN: &str = "[SSN]";
@@ -21,379 +44,1058 @@ const FACILITY_TOKEN: &str = "[FACILITY]";
 const ZIP_TOKEN: &str = "[ZIP]";
 const COORD_TOKEN: &str = "[COORD]";
 const URL_TOKEN: &str = "[URL]";
+const EMPLOYER_TOKEN: &str = "[EMPLOYER]";
+const HANDLE_TOKEN: &str = "[HANDLE]";
 const INSURANCE_TOKEN: &str = "[INSURANCE]";
 const LICENSE_TOKEN: &str = "[LICENSE]";
 const VEHICLE_TOKEN: &str = "[VEHICLE]";
 const DEVICE_TOKEN: &str = "[DEVICE]";
 const IP_TOKEN: &str = "[IP]";
+const ACCOUNT_TOKEN: &str = "[ACCOUNT]";
+const MBI_TOKEN: &str = "[MBI]";
+const CERTIFICATE_TOKEN: &str = "[CERT]";
+const GOVT_ID_TOKEN: &str = "[GOVT_ID]";
+const SEGMENT_TOKEN: &str = "[REDACTED_SEGMENT]";
+const GEO_TOKEN: &str = "[GEO]";
 
-const DEFAULT_NAMES: &[&str] = &[
-    "Smith",
-    "Johnson",
-    "Williams",
-    "Brown",
-    "Jones",
-    "Garcia",
-    "Miller",
-    "Davis",
-    "Rodriguez",
-    "Martinez",
-    "Hernandez",
-    "Lopez",
-    "Gonzalez",
-    "Wilson",
-    "Anderson",
-    "Thomas",
-    "Taylor",
-    "Moore",
-    "Jackson",
-    "Martin",
-    "Lee",
-    "Perez",
-    "Thompson",
-    "White",
-    "Harris",
-    "Sanchez",
-    "Clark",
-    "Ramirez",
-    "Lewis",
-    "Robinson",
-    "Walker",
-    "Young",
-    "Allen",
-    "King",
-    "Wright",
-    "Scott",
-    "Torres",
-    "Nguyen",
-    "Hill",
-    "Flores",
-    "Green",
-    "Adams",
-    "Nelson",
-    "Baker",
-    "Hall",
-    "Rivera",
-    "Campbell",
-    "Mitchell",
-    "Carter",
-    "Roberts",
-    "Gomez",
-    "Phillips",
-    "Turner",
-    "Parker",
-    "Evans",
-    "Edwards",
-    "Collins",
-    "Stewart",
-    "Sanchez",
-    "Morris",
-    "Murphy",
-    "Cook",
-    "Rogers",
-    "Morgan",
-    "Patel",
-    "Singh",
-    "Khan",
-    "Ali",
-    "Mohammed",
-    "Mohammad",
-    "Abdullah",
-    "Hussain",
-    "Kim",
-    "Park",
-    "Chen",
-    "Wang",
-    "Zhang",
-    "Lin",
-    "Tran",
-    "Ng",
-    "Chaudhry",
-    "Ahmad",
-    "Iqbal",
-    "Rahman",
+/// Labels that introduce a medical record number; config `mrn_labels` adds more.
+const MRN_LABELS: &[&str] = &["MRN", "Patient ID", "Chart"];
+
+/// The identifier after an MRN label unless config `mrn_pattern` replaces it.
+const DEFAULT_MRN_PATTERN: &str = r"[A-Za-z0-9-]{4,}\b";
+
+/// Labels that introduce a health plan identifier; config `insurance_labels` adds more.
+const INSURANCE_LABELS: &[&str] = &[
+    "Member",
+    "Policy",
+    "Plan",
+    "Group",
+    "Subscriber",
+    "Payer",
+    "BCBS",
+    "Insurance",
+];
+
+/// Labels that introduce a passport or immigration number; config `govt_id_labels` adds more.
+const GOVT_ID_LABELS: &[&str] = &["Passport", "Visa", "Alien Registration", "A-Number"];
+
+/// Labels that introduce a certificate or court document number; config `certificate_labels`
+/// adds more. "Case" alone is too common ("in case the patient falls"), so it needs "No"/"#".
+const CERTIFICATE_LABELS: &[&str] = &[
+    "Case No",
+    "Case Number",
+    "Case #",
+    "Cert",
+    "Certificate",
+    "Docket",
+];
+
+/// Spoken digits, including "oh" for zero.
+const DIGIT_WORDS: &[(&str, char)] = &[
+    ("zero", '0'),
+    ("oh", '0'),
+    ("o", '0'),
+    ("one", '1'),
+    ("two", '2'),
+    ("three", '3'),
+    ("four", '4'),
+    ("five", '5'),
+    ("six", '6'),
+    ("seven", '7'),
+    ("eight", '8'),
+    ("nine", '9'),
+];
+
+/// Safe Harbor allows ages up to 89; anything older must be generalized.
+const MAX_UNREDACTED_AGE: i32 = 89;
+const AGE_TOKEN: &str = "[AGE_OVER_89]";
+/// What an age over `MAX_UNREDACTED_AGE` becomes in `AgeMode::Generalize`.
+const AGE_GENERALIZED: &str = "90+";
+/// Number words for ages over 89, with their values ("ninety-two", "one hundred and one").
+const AGE_WORDS: &str = r"(?:ninety|(?:one[-\s])?hundred(?:[-\s](?:and[-\s])?)?)(?:[-\s]?(?:one|two|three|four|five|six|seven|eight|nine))?";
+
+/// Three-digit ZIP prefixes covering 20,000 people or fewer, which Safe Harbor requires to be
+/// reported as 000.
+const RESTRICTED_ZIP_PREFIXES: &[&str] = &[
+    "036", "059", "063", "102", "203", "556", "692", "790", "821", "823", "830", "831", "878",
+    "879", "884", "890", "893",
+];
+
+/// A capitalized place name of up to three words, optionally after St./Ft./Mt. ("Oak Park",
+/// "St. Louis", "Winston-Salem").
+const GEO_PLACE: &str = r"(?:(?:St|Ft|Mt)\.?[\ \t]+)?[A-Z][a-z]+(?:[\ \t-][A-Z][a-z]+){0,2}";
+
+/// US states and territories with their postal codes. Safe Harbor allows keeping them.
+const US_STATES: &[(&str, &str)] = &[
+    ("Alabama", "AL"),
+    ("Alaska", "AK"),
+    ("Arizona", "AZ"),
+    ("Arkansas", "AR"),
+    ("California", "CA"),
+    ("Colorado", "CO"),
+    ("Connecticut", "CT"),
+    ("Delaware", "DE"),
+    ("Florida", "FL"),
+    ("Georgia", "GA"),
+    ("Hawaii", "HI"),
+    ("Idaho", "ID"),
+    ("Illinois", "IL"),
+    ("Indiana", "IN"),
+    ("Iowa", "IA"),
+    ("Kansas", "KS"),
+    ("Kentucky", "KY"),
+    ("Louisiana", "LA"),
+    ("Maine", "ME"),
+    ("Maryland", "MD"),
+    ("Massachusetts", "MA"),
+    ("Michigan", "MI"),
+    ("Minnesota", "MN"),
+    ("Mississippi", "MS"),
+    ("Missouri", "MO"),
+    ("Montana", "MT"),
+    ("Nebraska", "NE"),
+    ("Nevada", "NV"),
+    ("New Hampshire", "NH"),
+    ("New Jersey", "NJ"),
+    ("New Mexico", "NM"),
+    ("New York", "NY"),
+    ("North Carolina", "NC"),
+    ("North Dakota", "ND"),
+    ("Ohio", "OH"),
+    ("Oklahoma", "OK"),
+    ("Oregon", "OR"),
+    ("Pennsylvania", "PA"),
+    ("Rhode Island", "RI"),
+    ("South Carolina", "SC"),
+    ("South Dakota", "SD"),
+    ("Tennessee", "TN"),
+    ("Texas", "TX"),
+    ("Utah", "UT"),
+    ("Vermont", "VT"),
+    ("Virginia", "VA"),
+    ("Washington", "WA"),
+    ("West Virginia", "WV"),
+    ("Wisconsin", "WI"),
+    ("Wyoming", "WY"),
+    ("District of Columbia", "DC"),
+    ("Puerto Rico", "PR"),
+    ("Guam", "GU"),
+    ("U.S. Virgin Islands", "VI"),
+    ("American Samoa", "AS"),
+    ("Northern Mariana Islands", "MP"),
 ];
+/// Postal codes that are also common credentials ("Jane Smith, MD"); after a city they count
+/// only when a ZIP code follows.
+const AMBIGUOUS_STATE_CODES: &[&str] = &["MD", "PA"];
 
-const COMMON_FIRST_NAMES: &[&str] = &[
-    "James",
-    "Mary",
-    "Robert",
-    "Patricia",
-    "John",
-    "Jennifer",
-    "Michael",
-    "Linda",
-    "William",
-    "Elizabeth",
-    "David",
-    "Barbara",
-    "Richard",
-    "Susan",
-    "Joseph",
-    "Jessica",
-    "Thomas",
-    "Sarah",
-    "Charles",
-    "Karen",
-    "Christopher",
-    "Nancy",
-    "Daniel",
-    "Lisa",
-    "Matthew",
-    "Betty",
-    "Anthony",
-    "Margaret",
-    "Mark",
-    "Sandra",
-    "Donald",
-    "Ashley",
-    "Steven",
-    "Kimberly",
-    "Paul",
-    "Emily",
-    "Andrew",
-    "Donna",
-    "Joshua",
-    "Michelle",
-    "Kenneth",
-    "Dorothy",
-    "Kevin",
-    "Carol",
-    "Brian",
-    "Amanda",
-    "George",
-    "Melissa",
-    "Timothy",
-    "Deborah",
-    "Ronald",
-    "Stephanie",
-    "Edward",
-    "Rebecca",
-    "Jason",
-    "Sharon",
-    "Jeffrey",
-    "Laura",
-    "Ryan",
-    "Cynthia",
-    "Jacob",
-    "Kathleen",
-    "Gary",
-    "Amy",
-    "Nicholas",
-    "Shirley",
-    "Eric",
-    "Angela",
-    "Jonathan",
-    "Helen",
-    "Stephen",
-    "Anna",
-    "Larry",
-    "Brenda",
-    "Justin",
-    "Pamela",
-    "Scott",
-    "Nicole",
-    "Brandon",
-    "Samantha",
-    "Frank",
-    "Katherine",
-    "Benjamin",
-    "Emma",
-    "Gregory",
-    "Ruth",
-    "Samuel",
-    "Christine",
-    "Patrick",
-    "Catherine",
-    "Alexander",
-    "Debra",
-    "Jack",
-    "Rachel",
-    "Dennis",
-    "Carolyn",
-    "Jerry",
-    "Janet",
-    "Tyler",
-    "Maria",
-    "Mohammed",
-    "Muhammad",
-    "Ahmed",
-    "Ahmad",
-    "Omar",
-    "Hassan",
-    "Hussein",
-    "Abdullah",
-    "Fatima",
-    "Aisha",
-    "Amelia",
-    "Priya",
-    "Anjali",
-    "Sofia",
-    "Noor",
-    "Amina",
-    "Li",
-    "Wei",
-    "Min",
-    "Hao",
-    "Jin",
-    "Sang",
-    "Hye",
-    "Yuki",
-    "Mei",
-    "Ravi",
-    "Imran",
-    "Farah",
-    "Leila",
-    "Zara",
+/// Words that are capitalized only because they open a sentence in clinical prose.
+const SENTENCE_OPENERS: &[&str] = &[
+    "The",
+    "This",
+    "Patient",
+    "Pt",
+    "Seen",
+    "Saw",
+    "Spoke",
+    "Met",
+    "Called",
+    "Discussed",
+    "Reviewed",
+    "Per",
+    "Plan",
+    "Will",
+    "Continue",
+    "Follow",
+    "Return",
+    "Please",
+    "Denies",
+    "Reports",
+    "Today",
+    "Yesterday",
+    "No",
+    "Mother",
+    "Father",
+    "Wife",
+    "Husband",
+    "Daughter",
+    "Son",
+    "Sister",
+    "Brother",
 ];
-const DEFAULT_FACILITY_TERMS: &[&str] = &[
-    "General Hospital",
-    "Medical Center",
-    "Children's Hospital",
-    "Urgent Care",
-    "Cardiology Clinic",
-    "Dialysis Center",
-    "Health System",
-    "Cancer Institute",
-    "Family Practice",
-    "Primary Care",
-    "Internal Medicine",
+
+const HONORIFICS: &str = r"Drs?\.?|Prof\.?|Mr\.?|Mrs\.?|Ms\.?|Mx\.?|Capt\.?|Captain|Lt\.?|Lieutenant|Sgt\.?|Sergeant|Officer|Chief|Judge|Sir|Dame|Madam|Rev\.?|Reverend|Father|Fr\.?|Sister|Brother|Pastor|Chaplain|Rabbi|Imam";
+/// Phrases that introduce a person, so a bare first name after them is a name ("spoke with
+/// Kevin", "daughter Linda").
+const FIRST_NAME_CUES: &str = r"spoke\s+(?:with|to)|talked\s+(?:with|to)|met\s+with|discussed\s+with|called|per|accompanied\s+by|visited\s+by|wife|husband|partner|son|daughter|mother|father|sister|brother|friend|caregiver|patient";
+
+/// Relationship words whose next capitalized word is a name ("his wife Carol", "lives with
+/// brother Tom Nguyen").
+const RELATIONSHIP_CUES: &str =
+    r"wife|husband|son|daughter|mother|father|brother|sister|partner|neighbou?r|caregiver";
+
+/// Capitalized words after a relationship cue that are not names.
+const RELATIONSHIP_EXCLUDED: &[&str] = &[
+    "He", "She", "They", "It", "I", "We", "You", "His", "Her", "Him", "Them", "Their", "Who",
+    "This", "That", "Is", "Was",
 ];
 
-const NAME_STOPLIST: &[&str] = &[
-    "CKD",
-    "ESBL",
-    "ICU",
-    "BKA",
-    "IDDM",
-    "MRSA",
-    "ASTHMA",
-    "DIALYSIS",
-    "MEROPENEM",
-    "SEPSIS",
-    "HYPERTENSION",
-    "DIABETES",
-    "E COLI",
-    "E. COLI",
-    "HGB",
-    "HCT",
-    "POC",
-    "IV",
+/// Lowercase words that show a capitalized first name is really a verb or noun ("Mark the
+/// site", "Bill for the visit", "Jack up the bed").
+const FIRST_NAME_EXCLUDED_NEXT: &[&str] = &[
+    "the", "a", "an", "for", "out", "up", "down", "off", "over", "as", "it", "this", "that",
+    "these", "those", "all", "each",
 ];
 
+const STREET_SUFFIXES: &str = r"(?:Street|St|Avenue|Ave|Road|Rd|Drive|Dr|Boulevard|Blvd|Lane|Ln|Court|Ct|Place|Pl|Terrace|Ter|Way)\b\.?";
+const FACILITY_SUFFIXES: &str =
+    r"(?:Hospital|Med(?:ical)?\s*Center|Clinic|Health(?:care)?|Infirmary)\b";
+
+static SAINT_PREFIX_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:St\.?|Saint)\s").expect("valid saint prefix regex"));
+static FACILITY_SUFFIX_END_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"(?i){}$", FACILITY_SUFFIXES)).expect("valid facility suffix regex")
+});
+
 static MULTISPACE_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"[^\S\r\n]+").expect("multispace regex"));
 static SPACE_AROUND_PUNCT_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"\s+([.,;:!?])").expect("punct regex"));
+/// `SPACE_AROUND_PUNCT_RE` without line breaks, for output that keeps the input's lines.
+static SPACE_BEFORE_PUNCT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"[^\S\r\n]+([.,;:!?])").expect("punct regex"));
 static DUP_PUNCT_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"([.,;:!?]){2,}").expect("dup punct regex"));
+static OCR_SSN_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d{3}-?\d{2}-?\d{4}$").expect("ocr ssn regex"));
+static OCR_DATE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d{1,2}[/-]\d{1,2}[/-](?:\d{2}|\d{4})$").expect("ocr date regex"));
+static OCR_PHONE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[\d()\-. ]+$").expect("ocr phone regex"));
+static STREET_SUFFIX_END_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(&format!(r"(?i)\s{}$", STREET_SUFFIXES)).expect("street suffix regex"));
 
-#[derive(Debug, Default, Serialize)]
+// Letter, combining-mark, and number classes for the name and facility patterns. `ascii-only`
+// swaps in ASCII classes so the regex crate's Unicode category tables can be left out.
+#[cfg(not(feature = "ascii-only"))]
+const LETTER: &str = r"\p{L}";
+#[cfg(not(feature = "ascii-only"))]
+const MARK: &str = r"\p{M}";
+#[cfg(not(feature = "ascii-only"))]
+const NUMBER: &str = r"\p{N}";
+#[cfg(feature = "ascii-only")]
+const LETTER: &str = "A-Za-z";
+#[cfg(feature = "ascii-only")]
+const MARK: &str = "";
+#[cfg(feature = "ascii-only")]
+const NUMBER: &str = "0-9";
+
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct ScrubStats {
     pub emails: usize,
     pub phones: usize,
+    pub faxes: usize,
+    pub pagers: usize,
     pub dates: usize,
+    /// Dates that `date_shift` could not parse and redacted instead; included in `dates`.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub date_shift_fallbacks: usize,
     pub relative_dates: usize,
+    pub ages: usize,
     pub ssn: usize,
     pub mrn: usize,
+    pub accounts: usize,
     pub zip_codes: usize,
     pub persons: usize,
     pub facilities: usize,
+    pub employers: usize,
     pub addresses: usize,
+    pub geo: usize,
     pub coordinates: usize,
+    /// Coordinates generalized by `CoordinateMode::Round` rather than redacted; included in
+    /// `coordinates`.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub coordinates_rounded: usize,
     pub urls: usize,
+    pub handles: usize,
     pub insurance_ids: usize,
+    pub mbi: usize,
     pub licenses: usize,
+    pub govt_ids: usize,
+    pub certificates: usize,
     pub vehicles: usize,
     pub devices: usize,
     pub ip_addresses: usize,
+    pub segments: usize,
+    /// Redactions folded into a `segments` replacement, keyed by category name. They are no
+    /// longer counted under their own category.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub segment_subsumed: BTreeMap<String, usize>,
+    /// Redactions per `custom_patterns` category, keyed by name without the `custom:` prefix.
+    /// Every configured custom category is listed, even with no matches.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, usize>,
+    /// The regulation profile the scrubber ran with, so audits can confirm the rule set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Regulation>,
+    /// The scrubber's seed (`Scrubber::seed`), so a run without a configured seed can be
+    /// repeated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Content hash of each word list used, so output can be traced to a dictionary version.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub dictionaries: BTreeMap<&'static str, String>,
+    /// True when more redactions were made than `max_spans` allows detail records for.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub spans_truncated: bool,
+    /// Redactions that were applied and counted but have no span record.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub spans_dropped: usize,
+    /// `--format jsonl` records that did not parse and were passed through unscrubbed.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub parse_errors: usize,
+    /// Distinct redacted values per category that fired, compared case-insensitively with
+    /// whitespace collapsed. Merged stats add these up per document.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub unique: BTreeMap<String, usize>,
+    /// The config `tokens` override used for each category that fired; other categories used
+    /// their built-in token.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tokens: BTreeMap<String, String>,
+    /// Redactions per `<category>.<rule>`, using the rule names from `--list-rules`, e.g.
+    /// `person.titled-name`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, usize>,
+    /// Redactions made by low-confidence rules, per category; they are part of the counts.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub low_confidence: BTreeMap<String, usize>,
+    /// Redactions per input line and category, in line order; empty unless requested.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<LineCount>,
+    /// One entry per detector that ran, in run order; empty unless timings were requested.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub detector_timings: Vec<DetectorTiming>,
+}
+
+/// Redactions of one category that start on one line of the input, counted from 1. Lines end
+/// at `\n`, so CRLF input and blank lines number the same as in an editor.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LineCount {
+    pub line: usize,
+    pub category: CategoryKey,
+    pub count: usize,
 }
 
 impl ScrubStats {
+    /// Counts per category in report order. Rendering goes through this so no category can be
+    /// left out of a report.
+    pub fn by_category(&self) -> Vec<(Category, usize)> {
+        Category::ALL
+            .iter()
+            .map(|&category| (category, self.count(category)))
+            .collect()
+    }
+
+    /// Like `by_category`, followed by each custom category in name order.
+    pub fn by_key(&self) -> Vec<(CategoryKey, usize)> {
+        let mut counts: Vec<(CategoryKey, usize)> = self
+            .by_category()
+            .into_iter()
+            .map(|(category, count)| (category.into(), count))
+            .collect();
+        counts.extend(
+            self.custom
+                .iter()
+                .map(|(name, &count)| (CategoryKey::Custom(name.clone()), count)),
+        );
+        counts
+    }
+
+    pub fn count_key(&self, category: &CategoryKey) -> usize {
+        match category {
+            CategoryKey::Builtin(category) => self.count(*category),
+            CategoryKey::Custom(name) => self.custom.get(name).copied().unwrap_or(0),
+        }
+    }
+
+    pub fn count(&self, category: Category) -> usize {
+        match category {
+            Category::Email => self.emails,
+            Category::Phone => self.phones,
+            Category::Fax => self.faxes,
+            Category::Pager => self.pagers,
+            Category::Date => self.dates,
+            Category::RelativeDate => self.relative_dates,
+            Category::Age => self.ages,
+            Category::Ssn => self.ssn,
+            Category::Mrn => self.mrn,
+            Category::Account => self.accounts,
+            Category::Zip => self.zip_codes,
+            Category::Person => self.persons,
+            Category::Facility => self.facilities,
+            Category::Employer => self.employers,
+            Category::Address => self.addresses,
+            Category::Geo => self.geo,
+            Category::Coordinate => self.coordinates,
+            Category::Url => self.urls,
+            Category::Handle => self.handles,
+            Category::Insurance => self.insurance_ids,
+            Category::Mbi => self.mbi,
+            Category::License => self.licenses,
+            Category::GovtId => self.govt_ids,
+            Category::Certificate => self.certificates,
+            Category::Vehicle => self.vehicles,
+            Category::Device => self.devices,
+            Category::Ip => self.ip_addresses,
+            Category::Segment => self.segments,
+        }
+    }
+
+    pub(crate) fn record(&mut self, category: Category, count: usize) {
+        *self.slot(category) += count;
+    }
+
+    fn slot(&mut self, category: Category) -> &mut usize {
+        match category {
+            Category::Email => &mut self.emails,
+            Category::Phone => &mut self.phones,
+            Category::Fax => &mut self.faxes,
+            Category::Pager => &mut self.pagers,
+            Category::Date => &mut self.dates,
+            Category::RelativeDate => &mut self.relative_dates,
+            Category::Age => &mut self.ages,
+            Category::Ssn => &mut self.ssn,
+            Category::Mrn => &mut self.mrn,
+            Category::Account => &mut self.accounts,
+            Category::Zip => &mut self.zip_codes,
+            Category::Person => &mut self.persons,
+            Category::Facility => &mut self.facilities,
+            Category::Employer => &mut self.employers,
+            Category::Address => &mut self.addresses,
+            Category::Geo => &mut self.geo,
+            Category::Coordinate => &mut self.coordinates,
+            Category::Url => &mut self.urls,
+            Category::Handle => &mut self.handles,
+            Category::Insurance => &mut self.insurance_ids,
+            Category::Mbi => &mut self.mbi,
+            Category::License => &mut self.licenses,
+            Category::GovtId => &mut self.govt_ids,
+            Category::Certificate => &mut self.certificates,
+            Category::Vehicle => &mut self.vehicles,
+            Category::Device => &mut self.devices,
+            Category::Ip => &mut self.ip_addresses,
+            Category::Segment => &mut self.segments,
+        }
+    }
+
+    /// Takes back one redaction of `category` that a later claim replaced.
+    fn retract(&mut self, category: &CategoryKey) {
+        let slot = match category {
+            CategoryKey::Builtin(category) => self.slot(*category),
+            CategoryKey::Custom(name) => self.custom.entry(name.clone()).or_default(),
+        };
+        *slot = slot.saturating_sub(1);
+    }
+
+    /// Moves one redaction of `category` into `segment_subsumed`.
+    fn subsume(&mut self, category: &CategoryKey) {
+        self.retract(category);
+        *self
+            .segment_subsumed
+            .entry(category.to_string())
+            .or_default() += 1;
+    }
+
+    /// Takes back one redaction made by `span`, which started on `line`.
+    fn unrecord(&mut self, span: &RedactionSpan, line: usize) {
+        let slot = match &span.category {
+            CategoryKey::Builtin(category) => self.slot(*category),
+            CategoryKey::Custom(name) => self.custom.entry(name.clone()).or_default(),
+        };
+        *slot = slot.saturating_sub(1);
+        let category = span.category.to_string();
+        let take = |counts: &mut BTreeMap<String, usize>, key: String| {
+            if let Some(count) = counts.get_mut(&key) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    counts.remove(&key);
+                }
+            }
+        };
+        take(&mut self.rules, format!("{}.{}", category, span.rule));
+        if span.confidence == Confidence::Low {
+            take(&mut self.low_confidence, category.clone());
+        }
+        if self.count_key(&span.category) == 0 {
+            self.tokens.remove(&category);
+        }
+        if let Some(index) = self
+            .lines
+            .iter()
+            .position(|count| count.line == line && count.category == span.category)
+        {
+            self.lines[index].count -= 1;
+            if self.lines[index].count == 0 {
+                self.lines.remove(index);
+            }
+        }
+    }
+
+    pub(crate) fn record_key(&mut self, category: &CategoryKey, count: usize) {
+        match category {
+            CategoryKey::Builtin(category) => self.record(*category, count),
+            CategoryKey::Custom(name) => *self.custom.entry(name.clone()).or_default() += count,
+        }
+    }
+
+    /// Sum over `Category::ALL` and the custom categories. `count` and `record` match on every
+    /// category, so a new category cannot be left out of the total.
     pub fn total(&self) -> usize {
-        self.emails
-            + self.phones
-            + self.dates
-            + self.relative_dates
-            + self.ssn
-            + self.mrn
-            + self.zip_codes
-            + self.persons
-            + self.facilities
-            + self.addresses
-            + self.coordinates
-            + self.urls
-            + self.insurance_ids
-            + self.licenses
-            + self.vehicles
-            + self.devices
-            + self.ip_addresses
+        self.by_key().iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// Adds another document's stats, so the stats of several documents read as those of their
+/// concatenation. Counts and counters add up (so a value repeated across documents counts
+/// as unique once per document), timings add up per detector, and the first
+/// profile, seed and dictionary versions seen are kept. `lines` numbers the lines of a single
+/// document, so it is left as is.
+impl AddAssign<&ScrubStats> for ScrubStats {
+    fn add_assign(&mut self, other: &ScrubStats) {
+        for (category, count) in other.by_key() {
+            if count > 0 {
+                self.record_key(&category, count);
+            }
+        }
+        self.date_shift_fallbacks += other.date_shift_fallbacks;
+        self.coordinates_rounded += other.coordinates_rounded;
+        for (category, count) in &other.segment_subsumed {
+            *self.segment_subsumed.entry(category.clone()).or_default() += count;
+        }
+        for (category, count) in &other.unique {
+            *self.unique.entry(category.clone()).or_default() += count;
+        }
+        for (category, token) in &other.tokens {
+            self.tokens
+                .entry(category.clone())
+                .or_insert_with(|| token.clone());
+        }
+        for (rule, count) in &other.rules {
+            *self.rules.entry(rule.clone()).or_default() += count;
+        }
+        for (category, count) in &other.low_confidence {
+            *self.low_confidence.entry(category.clone()).or_default() += count;
+        }
+        self.profile = self.profile.or(other.profile);
+        self.seed = self.seed.or(other.seed);
+        for (name, version) in &other.dictionaries {
+            self.dictionaries
+                .entry(name)
+                .or_insert_with(|| version.clone());
+        }
+        self.spans_truncated |= other.spans_truncated;
+        self.spans_dropped += other.spans_dropped;
+        self.parse_errors += other.parse_errors;
+        for timing in &other.detector_timings {
+            match self
+                .detector_timings
+                .iter_mut()
+                .find(|existing| existing.detector == timing.detector)
+            {
+                Some(existing) => {
+                    existing.elapsed_us += timing.elapsed_us;
+                    existing.matches += timing.matches;
+                }
+                None => self.detector_timings.push(timing.clone()),
+            }
+        }
+    }
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+/// Post-match checks applied before a detector claims a candidate.
+#[derive(Clone, Copy)]
+enum Filter {
+    None,
+    NameStopwords,
+    /// Neither a street nor a St./Saint surname that `saint-context` left unresolved.
+    Facility,
+    /// Labeled IDs must contain a digit, so "insurance coverage" and "MRN pending" are not IDs.
+    HasDigit,
+}
+
+impl Filter {
+    fn accepts(self, candidate: &str, stoplist: &HashSet<String>) -> bool {
+        match self {
+            Filter::None => true,
+            Filter::NameStopwords => !is_name_stopword(candidate, stoplist),
+            Filter::Facility => !ends_with_street_suffix(candidate) && !is_saint_surname(candidate),
+            Filter::HasDigit => candidate.chars().any(|c| c.is_ascii_digit()),
+        }
+    }
+}
+
+enum Rule {
+    Pattern {
+        regex: Regex,
+        filter: Filter,
+    },
+    /// See `Scrubber::resolve_saint_names`.
+    SaintContext(Regex),
+    /// See `claim_capital_sequences`.
+    CapitalSequence(Regex),
+    /// See `apply_segment_fallback`. Runs last, over everything the other detectors claimed.
+    SegmentFallback(SegmentFallback),
+    /// A `custom_patterns` entry; see `claim_custom`.
+    Custom(Regex),
+    /// See `Scrubber::claim_birth_years`.
+    BirthYear(Regex),
+    /// See `Scrubber::claim_ages`.
+    Age(Regex),
+    /// See `Scrubber::claim_dates`.
+    Date(Regex),
+    /// See `Scrubber::claim_composite_ids`.
+    CompositeId(Regex),
+    /// Lat/long pairs, rendered per `CoordinateMode`.
+    Coordinate(Regex),
+    /// ZIP codes, rendered per `ZipMode`.
+    Zip(Regex),
+    /// See `Scrubber::claim_spelled_zips`.
+    SpelledZip(Regex),
+    /// See `Scrubber::claim_area_prefixes`.
+    AreaPrefix(Regex),
+    /// See `Scrubber::claim_narrative_first_names`.
+    NarrativeFirstName(Regex),
+    /// See `Scrubber::claim_relative_names`.
+    RelativeName(Regex),
+    /// See `Scrubber::claim_ocr_values`.
+    OcrLabeled(Regex),
+    /// See `claim_urls`.
+    Url(Regex),
+    /// See `claim_employers`.
+    Employer(Regex),
+    /// See `Scrubber::claim_geo`.
+    Geo {
+        context: Regex,
+        city_state: Regex,
+        county: Regex,
+    },
+    /// See `claim_handles`.
+    Handle {
+        handle: Regex,
+        profile: Regex,
+    },
+    /// See `claim_licenses`.
+    License {
+        labeled: Regex,
+        dea: Regex,
+        npi: Regex,
+    },
+    /// See `claim_devices`.
+    Device {
+        labeled: Regex,
+        gs1: Regex,
+        hibcc: Regex,
+    },
+    /// See `claim_vehicles`.
+    Vehicle {
+        vin: Regex,
+        plate: Regex,
+    },
+    /// See `claim_ips`.
+    Ip {
+        v6: Regex,
+        v4: Regex,
+    },
+    /// See `claim_labeled_numbers`.
+    LabeledNumber {
+        labels: Regex,
+        number: Regex,
+    },
+}
+
+/// Mode a detector requires before it runs.
+#[derive(Clone, Copy)]
+enum Gate {
+    Always,
+    SafeHarbor,
+    FieldMode,
+    /// Open unless `first_name_alone` is off or `person_mode` is strict.
+    FirstNameAlone,
+    OcrTolerant,
+    /// Open when `person_mode` is at least this level.
+    PersonMode(PersonMode),
+}
+
+impl Gate {
+    /// The lowest `person_mode` that runs detectors behind this gate.
+    fn person_mode(self) -> PersonMode {
+        match self {
+            Gate::PersonMode(level) => level,
+            Gate::FirstNameAlone => PersonMode::Balanced,
+            _ => PersonMode::Strict,
+        }
+    }
+}
+
+struct Detector {
+    name: &'static str,
+    categories: Vec<CategoryKey>,
+    source: RuleSource,
+    summary: String,
+    gate: Gate,
+    confidence: Confidence,
+    rule: Rule,
+}
+
+impl Detector {
+    fn pattern(name: &'static str, category: Category, summary: &str, regex: Regex) -> Self {
+        Self {
+            name,
+            categories: vec![category.into()],
+            source: RuleSource::Builtin,
+            summary: summary.to_string(),
+            gate: Gate::Always,
+            confidence: Confidence::High,
+            rule: Rule::Pattern {
+                regex,
+                filter: Filter::None,
+            },
+        }
+    }
+
+    fn filtered(mut self, filter: Filter) -> Self {
+        if let Rule::Pattern { filter: slot, .. } = &mut self.rule {
+            *slot = filter;
+        }
+        self
+    }
+
+    fn configured(mut self, from_config: bool) -> Self {
+        if from_config {
+            self.source = RuleSource::Config;
+        }
+        self
+    }
+
+    fn person_mode(mut self, level: PersonMode) -> Self {
+        self.gate = Gate::PersonMode(level);
+        self
+    }
+
+    fn confidence(mut self, confidence: Confidence) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    fn safe_harbor_only(mut self) -> Self {
+        self.gate = Gate::SafeHarbor;
+        self
+    }
+
+    fn field_mode_only(mut self) -> Self {
+        self.gate = Gate::FieldMode;
+        self
+    }
+
+    /// The name timings are reported under. Custom patterns share one rule name, so they are
+    /// told apart by category.
+    fn label(&self) -> String {
+        match self.categories.as_slice() {
+            [category @ CategoryKey::Custom(_)] => category.to_string(),
+            _ => self.name.to_string(),
+        }
+    }
+
+    fn disabled_by(
+        &self,
+        gate_open: bool,
+        profile_disabled: &HashSet<CategoryKey>,
+        skip: &HashSet<CategoryKey>,
+    ) -> Option<DisabledBy> {
+        if !gate_open {
+            Some(DisabledBy::Profile)
+        } else if self
+            .categories
+            .iter()
+            .all(|category| skip.contains(category))
+        {
+            Some(DisabledBy::Skip)
+        } else if self
+            .categories
+            .iter()
+            .all(|category| skip.contains(category) || profile_disabled.contains(category))
+        {
+            Some(DisabledBy::Profile)
+        } else {
+            None
+        }
+    }
+}
+
+/// Which identifier set a scrubber redacts.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ScrubMode {
+    /// The clinical-note defaults.
+    #[default]
+    Standard,
+    /// HIPAA Safe Harbor: also insurance, certificate, license, vehicle, device, and IP
+    /// identifiers, ages over 89, and ZIP codes truncated to three digits unless the config or
+    /// regulation picks a `zip_mode`.
+    SafeHarbor,
+}
+
+/// Optional work a scrub can do besides redacting.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScrubOptions {
+    /// Time each detector pass into `ScrubStats::detector_timings`. Off by default; when off no
+    /// clock is read.
+    pub collect_timings: bool,
+    /// Count redactions per input line into `ScrubStats::lines`. Unlike spans these counts are
+    /// never capped.
+    pub collect_lines: bool,
+    /// Keep a span record for every redaction, ignoring `max_spans`, for outputs such as the
+    /// audit log that must not lose any.
+    pub all_spans: bool,
+}
+
+/// Output of `Scrubber::scrub_detailed`.
+#[derive(Debug)]
+pub struct ScrubResult {
+    pub text: String,
+    pub stats: ScrubStats,
+    /// Maps byte ranges of the input onto `text`.
+    pub alignment: Alignment,
+    /// One record per redaction in input order, up to the configured `max_spans`; see
+    /// `stats.spans_dropped` for the rest.
+    pub spans: Vec<RedactionSpan>,
+    /// The scrubber's entity keys, for recounting `unique` after `restore`.
+    entity_keys: EntityKeys,
+}
+
+impl ScrubResult {
+    /// Projects a byte range of the input onto the scrubbed text, or `None` if it overlaps a
+    /// redaction.
+    pub fn project_span(&self, original: Range<usize>) -> Option<Range<usize>> {
+        self.alignment.project_span(original)
+    }
+
+    pub(crate) fn entity_keys(&self) -> &EntityKeys {
+        &self.entity_keys
+    }
+
+    /// Puts back the original text of each redaction `keep` turns down, as if it had not been
+    /// made: the text, spans, alignment and counts are updated. `input` is the text that was
+    /// scrubbed. `keep` sees the spans in input order; redactions past `max_spans` have no
+    /// span and always stay.
+    pub fn restore(
+        &mut self,
+        input: &str,
+        mut keep: impl FnMut(&RedactionSpan) -> Result<bool>,
+    ) -> Result<()> {
+        let decisions = self
+            .spans
+            .iter()
+            .map(&mut keep)
+            .collect::<Result<Vec<bool>>>()?;
+        if decisions.iter().all(|&kept| kept) {
+            return Ok(());
+        }
+        let mut rewriter = Rewriter::new(&self.text);
+        let mut spans = Vec::with_capacity(self.spans.len());
+        let mut restored = Vec::new();
+        for (mut span, kept) in std::mem::take(&mut self.spans).into_iter().zip(decisions) {
+            if kept {
+                rewriter.copy_to(span.output.start);
+                let start = rewriter.output_len();
+                rewriter.copy_to(span.output.end);
+                span.output = start..rewriter.output_len();
+                spans.push(span);
+                continue;
+            }
+            rewriter.replace(
+                span.output.start,
+                span.output.end,
+                &input[span.original.clone()],
+                SegmentKind::Rewritten,
+            );
+            let end = rewriter.output_len();
+            restored.push((span.original.clone(), end - span.original.len()..end));
+            let line = input[..span.original.start].matches('\n').count() + 1;
+            self.stats.unrecord(&span, line);
+        }
+        let (text, restoring) = rewriter.finish();
+        // With every span at hand, distinct values can be counted again; otherwise the
+        // counts made while scrubbing stay.
+        if !self.stats.spans_truncated {
+            let mut unique: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+            for span in &spans {
+                unique.entry(span.category.to_string()).or_default().insert(
+                    self.entity_keys
+                        .key(&span.category, &input[span.original.clone()]),
+                );
+            }
+            self.stats.unique = unique
+                .into_iter()
+                .map(|(category, values)| (category, values.len()))
+                .collect();
+        }
+        self.text = text;
+        self.alignment = self.alignment.then(&restoring);
+        for (original, output) in restored {
+            self.alignment.unredact(original, output);
+        }
+        self.spans = spans;
+        Ok(())
     }
 }
 
 pub struct Scrubber {
-    email_regex: Regex,
-    phone_regex: Regex,
-    ssn_regex: Regex,
-    mrn_regex: Regex,
-    mrn_label_regex: Regex,
-    zip_regex: Regex,
-    facility_regex: Regex,
-    custom_facility_regex: Option<Regex>,
-    address_regex: Regex,
-    location_regex: Regex,
-    coordinate_regex: Regex,
-    url_regex: Regex,
-    obfuscated_email_regex: Regex,
+    /// Detectors in priority order; earlier detectors claim text first.
+    detectors: Vec<Detector>,
+    /// Config `allowlist` terms, reserved before any detector runs.
+    allowlist_regex: Option<Regex>,
     name_dictionary_regex: Option<Regex>,
-    titled_name_regex: Regex,
-    first_last_regex: Regex,
-    capital_sequence_regex: Regex,
-    date_regex: Regex,
-    relative_date_regex: Regex,
-    insurance_regex: Regex,
-    license_regex: Regex,
-    vehicle_regex: Regex,
-    device_regex: Regex,
-    ip_regex: Regex,
     safe_harbor: bool,
+    field_mode: bool,
+    /// Config `normalize_newlines`: drop the final newline and join line breaks before
+    /// punctuation instead of keeping the input's lines.
+    normalize_newlines: bool,
+    reference_date: CivilDate,
+    redact_states: bool,
+    date_mode: DateMode,
+    date_order: DateOrder,
+    /// Days added to every date, from `ScrubberConfig::date_shift`.
+    date_offset: Option<i64>,
+    /// Config `seed`, or one drawn from OS entropy; reported in the stats.
+    seed: u64,
+    birth_year_mode: BirthYearMode,
+    age_mode: AgeMode,
+    mrn_lengths: RangeInclusive<usize>,
+    composite_ids: CompositeIdMode,
+    regulation: Option<Regulation>,
+    /// Categories the regulation profile leaves unredacted; treated like `--skip`.
+    profile_disabled: HashSet<CategoryKey>,
+    /// Config `skip`; added to the categories skipped on every call.
+    skip: HashSet<CategoryKey>,
+    /// Categories declared by `custom_patterns`, in config order.
+    custom_categories: Vec<CategoryKey>,
+    /// Replacement text from config `tokens`.
+    tokens: BTreeMap<CategoryKey, String>,
+    token_style: TokenStyle,
+    zip_mode: ZipMode,
+    coordinate_mode: CoordinateMode,
+    first_name_alone: FirstNameMode,
+    person_mode: PersonMode,
+    area_mode: AreaMode,
+    ocr_tolerant: bool,
+    /// Detectors below this tier do not run.
+    min_confidence: Confidence,
+    max_spans: usize,
+    dictionaries: Dictionaries,
+    /// `dictionaries.name_stoplist`, uppercased for comparison.
+    name_stoplist: HashSet<String>,
+    entity_keys: EntityKeys,
+    /// The settings above in serializable form, for `resolved_config`.
+    resolved: ResolvedConfig,
 }
 
 impl Scrubber {
-    pub fn new(config: ScrubberConfig, safe_harbor: bool) -> Result<Self> {
-        let mrn_min = config.mrn_min_length.unwrap_or(6);
-        let mrn_max = config.mrn_max_length.unwrap_or(10);
-        if mrn_min == 0 || mrn_max == 0 || mrn_min > mrn_max {
-            return Err(anyhow!("invalid MRN length range: {}-{}", mrn_min, mrn_max));
+    pub fn new(mut config: ScrubberConfig, mode: ScrubMode) -> Result<Self> {
+        config.validate()?;
+        let mut dictionaries = Dictionaries::load(&config.dictionaries)?;
+        if let Some(stoplist) = &config.stoplist_replace {
+            dictionaries.name_stoplist = Dictionary::configured(stoplist.clone());
         }
+        let first_names = &dictionaries.first_names.entries;
+        let profile = config.regulation.map(RegulationProfile::for_regulation);
+        let safe_harbor =
+            mode == ScrubMode::SafeHarbor || profile.as_ref().is_some_and(|p| p.safe_harbor);
+        let profile_disabled: HashSet<CategoryKey> = profile
+            .as_ref()
+            .map(|p| p.disabled.iter().map(|&category| category.into()).collect())
+            .unwrap_or_default();
+        let zip_mode = config
+            .zip_mode
+            .or(profile.as_ref().map(|p| p.zip_mode))
+            .unwrap_or(match mode {
+                ScrubMode::Standard => ZipMode::Redact,
+                ScrubMode::SafeHarbor => ZipMode::Truncate,
+            });
+        let date_mode = config
+            .date_mode
+            .or(profile.as_ref().map(|p| p.date_mode))
+            .unwrap_or_default();
+
+        let reference_date = match &config.reference_date {
+            Some(value) => CivilDate::parse(value)?,
+            None => CivilDate::today(),
+        };
+        let seed = config.seed.unwrap_or_else(entropy_seed);
+
+        let mrn_min = config.mrn_min_length.unwrap_or(DEFAULT_MRN_MIN_LENGTH);
+        let mrn_max = config.mrn_max_length.unwrap_or(DEFAULT_MRN_MAX_LENGTH);
 
         let email_regex = RegexBuilder::new(r"(?xi)\b[\w.+%-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b")
             .case_insensitive(true)
             .build()?;
 
-        let phone_regex = Regex::new(
-            r"(?xi)
-            \b(?:\+?1[-.\s•·]?)?
-            (?:\(?\d{3}\)?|\d{3})[-.\s•·]?
+        let phone_pattern = r"
+            (?:\(\d{3}\)|\b(?:\+?1[-.\s•·]?)?(?:\(?\d{3}\)?|\d{3}))[-.\s•·]?
             \d{3}[-.\s•·]?\d{4}
             (?:\s*(?:x|ext\.?|extension)\s*\d{1,6})?
-            \b",
+            \b";
+        let phone_regex = Regex::new(&format!("(?xi){}", phone_pattern))?;
+        let phone_label_regex = Regex::new(
+            r"(?xi)
+            \b(?:(?P<fax>fax|fx|facsimile)|(?P<pager>page|pager|pgr|beeper)|(?P<callback>call[-\s]?back)
+                |ph(?:one)?|tel(?:ephone)?|cell|mobile)
+            \b\.?",
         )?;
+        // Pagers and callbacks are often short extensions ("Pager 0482", "page 5-1234"); other
+        // labels need a full number.
+        let labeled_number_regex = Regex::new(&format!(
+            r"(?xi)(?P<phone>{})|(?P<short>\b(?:\d{{4,7}}|\d{{1,3}}-\d{{3,4}})\b)",
+            phone_pattern
+        ))?;
 
         let ssn_regex = Regex::new(r"\b(?:\d{3}-\d{2}-\d{4}|xxx-xx-\d{4})\b")?;
         let mrn_regex = Regex::new(&format!(r"\b\d{{{},{}}}\b", mrn_min, mrn_max))?;
-        let mrn_label_regex = Regex::new(
-            r"(?i)\b(?:MRN|Acct|Account|Patient\s*ID|Chart)\s*[:#]?\s*-?\s*[A-Za-z0-9-]{4,}\b",
+        let mrn_label_regex =
+            build_mrn_label_regex(&config.mrn_labels, config.mrn_pattern.as_deref())?;
+        // Billing identifiers are not linkable to the medical record, so they get their own
+        // category. The number must contain a digit, so "Account balance" is left alone.
+        let account_label_regex = Regex::new(
+            r"(?xi)
+            \b(?:Acct\.?|Account|(?:Billing|Guarantor)\s*(?:\#|No\.?|Number))
+            \s*(?:\#|No\.?|Number)?\s*:?\s*-?\s*[A-Z0-9-]*\d[A-Z0-9-]*\b
+        ",
         )?;
+        let composite_id_regex = Regex::new(r"[A-Za-z0-9]+(?:[\^_-][A-Za-z0-9]+)+")?;
         let zip_regex = Regex::new(r"\b\d{5}(?:-\d{4})?\b")?;
+        let digit_word = DIGIT_WORDS
+            .iter()
+            .map(|(word, _)| *word)
+            .collect::<Vec<_>>()
+            .join("|");
+        let spelled_zip_regex = Regex::new(&format!(
+            r"(?i)\bzip(?:\s*code)?\s*:?\s+(?P<digits>(?:{d})(?:[\s-]+(?:{d})){{4,8}})\b",
+            d = digit_word
+        ))?;
+        let area_prefix_regex = Regex::new(
+            r"(?i)\b(?:area\s+code\s+(?P<after>\d{3})|the\s+(?P<before>\d{3})\s+area)\b",
+        )?;
 
-        let facility_regex = Regex::new(
+        let facility_regex = Regex::new(&format!(
             r"(?xi)
             \b(?:St\.|Saint|Mt\.|Mount|Univ\.|University|Memorial|Children'?s|General|County)\s+
-            (?:[A-Z][\p{L}\p{M}\p{N}’'\.-]+(?:\s+[A-Z][\p{L}\p{M}\p{N}’'\.-]+){0,4})
+            (?-i:[A-Z]{word}+(?:\s+[A-Z]{word}+){{0,4}})
             (?:\s+(?:Hospital|Med(?:ical)?\s*Center|Clinic|Health(?:care)?|Infirmary))?
             \b",
-        )?;
+            word = format!(r"[{}{}{}’'\.-]", LETTER, MARK, NUMBER),
+        ))?;
 
         let address_regex = Regex::new(
             r"(?xi)
@@ -409,16 +1111,40 @@ impl Scrubber {
         ",
         )?;
 
+        // Signed pairs without hemisphere letters need four decimals on both sides and are
+        // range-checked in `claim_coordinates`, so "2.5, 3.0" is left alone.
         let coordinate_regex = Regex::new(
             r"(?xi)
             \b-?\d{1,3}\.\d+\s*(?:°|º)?\s*[NS]\b[,;\s]*-?\d{1,3}\.\d+\s*(?:°|º)?\s*[EW]\b
+            |
+            (?:-|\b)\d{1,2}\.\d{4,}\s*,\s*-?\d{1,3}\.\d{4,}\b
         ",
         )?;
 
+        // A URL never ends in sentence punctuation or a closing bracket.
         let url_regex = Regex::new(
+            r#"(?xi)
+            \b(?:
+                (?:https?://|www\.)[^\s<>"]*[^\s<>".,;:!?)'\]]
+              | [a-z0-9](?:[a-z0-9-]*[a-z0-9])?(?:\.[a-z0-9](?:[a-z0-9-]*[a-z0-9])?)*
+                \.(?:com|org|net|edu|gov|mil|io|us|info|health)\b
+                (?:/[^\s<>"]*[^\s<>".,;:!?)'\]])?
+            )"#,
+        )?;
+
+        // A bare "@word" is too common in templates ("@ bedside", "@home"), so a handle needs a
+        // platform named right before or after it.
+        let platform = r"(?:Twitter|(?-i:X)|Instagram|IG|Tik\s?Tok|Facebook|FB|Reddit)";
+        let handle_regex = Regex::new(&format!(
             r"(?xi)
-            \b(?:https?://|www\.)\S+
-        ",
+            (?P<before>@[A-Za-z0-9_](?:[A-Za-z0-9_.]*[A-Za-z0-9_])?)\s+(?:on|via)\s+{platform}\b
+          | \b{platform}\s*(?:handle|account|username|user|profile|page)?\s*(?:is\s+)?[:-]?\s*
+            (?P<after>@[A-Za-z0-9_](?:[A-Za-z0-9_.]*[A-Za-z0-9_])?|u/[A-Za-z0-9_-]{{3,20}})",
+        ))?;
+        let profile_regex = Regex::new(
+            r#"(?xi)
+            \b(?:https?://)?(?:www\.|m\.)?(?:twitter|x|instagram|tiktok|facebook|fb|reddit)\.com
+            /[^\s<>"]*[^\s<>".,;:!?)'\]]"#,
         )?;
 
         let obfuscated_email_regex = Regex::new(
@@ -427,522 +1153,5823 @@ impl Scrubber {
         ",
         )?;
 
-        let insurance_regex = Regex::new(
-            r"(?xi)
-            \b(?:Member|Policy|Plan|Subscriber|Insurance)\s*(?:ID|No\.?|Number|\#)?\s*[:\#-]?\s*[A-Z0-9]{3,}(?:[-\s][A-Z0-9]{2,}){0,3}\b
-        ",
-        )?;
+        // Medicare Beneficiary Identifiers: 11 characters in fixed letter/digit positions,
+        // optionally dashed after the 4th and 7th. Letters never include S, L, O, I, B, or Z.
+        let mbi_regex = Regex::new(&format!(
+            r"\b[1-9]{a}{an}\d(?:-{a}{an}\d-|{a}{an}\d){a}{a}\d\d\b",
+            a = "[AC-HJKMNP-RT-Y]",
+            an = "[AC-HJKMNP-RT-Y0-9]",
+        ))?;
+
+        let insurance_labels: Vec<String> = INSURANCE_LABELS
+            .iter()
+            .map(|label| label.to_string())
+            .chain(config.insurance_labels.iter().cloned())
+            .collect();
+        let insurance_regex = build_insurance_regex(&insurance_labels)?;
+        let certificate_labels: Vec<String> = CERTIFICATE_LABELS
+            .iter()
+            .map(|label| label.to_string())
+            .chain(config.certificate_labels.iter().cloned())
+            .collect();
+        let certificate_regex = build_certificate_regex(&certificate_labels)?;
+        let govt_id_labels: Vec<String> = GOVT_ID_LABELS
+            .iter()
+            .map(|label| label.to_string())
+            .chain(config.govt_id_labels.iter().cloned())
+            .collect();
+        let govt_id_regex = build_govt_id_regex(&govt_id_labels)?;
 
+        // The ID must contain a digit, so "License expired" is not a license number.
         let license_regex = Regex::new(
             r"(?xi)
-            \b(?:Driver'?s\s+License|DL|License|Lic|Passport|State\s+ID|ID\s+Number)\s*(?:No\.?|Number|ID|\#)?\s*[:\#-]?\s*[A-Z0-9]{3,}(?:[-\s][A-Z0-9]{2,}){0,3}\b
+            \b(?:Driver'?s\s+License|DL|(?:State\s+)?License|Lic|State\s+ID|ID\s+Number)
+            \s*(?:No\.?|Number|ID|\#)?\s*[:\#-]?\s*[A-Z0-9][A-Z0-9-]*\d[A-Z0-9-]*\b
         ",
         )?;
+        let dea_regex = Regex::new(r"\b[A-Z][A-Z9]\d{7}\b")?;
+        let npi_regex =
+            Regex::new(r"(?i)\bNPI\b\s*(?:No\.?|Number|\#)?\s*[:\#-]?\s*(?P<npi>\d{10})\b")?;
 
-        let vehicle_regex = Regex::new(
+        // VINs never use I, O, or Q; `claim_vehicles` checks the ISO 3779 check digit.
+        let vin_regex = Regex::new(r"\b[A-HJ-NPR-Z0-9]{17}\b")?;
+        // Plate-shaped strings alone look like order codes, so a plate needs its label.
+        let plate_regex = Regex::new(
             r"(?xi)
-            \b(?:VIN|Vehicle\s+Identification\s+Number|Plate|Tag)\s*(?:No\.?|Number|ID|\#)?\s*[:\#-]?\s*[A-HJ-NPR-Z0-9]{5,17}\b
+            \b(?:License\s+Plate|Plate|Tag)\s*(?:No\.?|Number|\#)?\s*[:\#-]?\s*
+            (?P<plate>[A-Z0-9]{1,4}[-\s]?[A-Z0-9]{1,5})\b
         ",
         )?;
 
+        // The serial must contain a digit, so "pump settings" is not a serial.
         let device_regex = Regex::new(
             r"(?xi)
-            \b(?:Device|Implant|Pump|Pacemaker|Generator|Catheter|Serial|S/N)\s*(?:ID|No\.?|Number|\#)?\s*[:\#-]?\s*[A-Z0-9]{3,}(?:[-/][A-Z0-9]{2,}){0,3}\b
+            \b(?:
+                (?:Device|Implant|Pump|Pacemaker|Generator|Catheter)\s+(?:ID|Serial|SN|S/N)
+                |Device\s+ID|Serial|SN|S/N|UDI|Lot
+            )
+            \s*(?:No\.?|Number|\#)?\s*[:\#-]?\s*
+            (?P<serial>[A-Z0-9]{3,}(?:[-/][A-Z0-9]{2,}){0,3})\b
         ",
         )?;
+        // Where a GS1 UDI may start; `gs1_udi_end` finds where it stops.
+        let gs1_regex = Regex::new(r"\(01\)|\b01\d{14}")?;
+        let hibcc_regex = Regex::new(r"\+[A-Z][A-Z0-9]{3}[A-Z0-9]{2,19}(?:/[A-Z0-9$+%./-]+)?")?;
+
+        // Candidates only; `claim_ips` parses them and checks the surrounding text.
+        let ipv6_regex = Regex::new(r"(?i)[0-9a-f.]*(?::[0-9a-f.]*){2,}(?:%[0-9a-z_.-]+)?")?;
+        let ipv4_regex = Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b")?;
+
+        let employers = build_dictionary(&[], &config.employers);
+        let employer_dictionary_regex =
+            build_dictionary_regex("employer", &employers, config.regex_size_limit_mb)?;
+        // Lowercase words end the phrase, so "works at maximal capacity" is not an employer.
+        let employer_context_regex = Regex::new(
+            r"(?x)
+            \b(?i:works?\s+(?:at|for)|working\s+(?:at|for)|employed\s+(?:at|by)|student\s+at
+                |teach(?:er|es)\s+at|attends|enrolled\s+at)
+            \s+(?:the\s+)?
+            (?P<employer>[A-Z][\w&'-]*(?:[\ \t]+(?:(?:of|and|&)[\ \t]+)?[A-Z][\w&'-]*)*)",
+        )?;
 
-        let ip_regex = Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b")?;
+        let geo_places = build_dictionary(&[], &config.geo_places);
+        let geo_dictionary_regex =
+            build_dictionary_regex("geo place", &geo_places, config.regex_size_limit_mb)?;
+        let state_names: Vec<String> = US_STATES
+            .iter()
+            .map(|(name, _)| regex::escape(name).replace(' ', r"\s+"))
+            .collect();
+        let state_names = state_names.join("|");
+        let state_codes: Vec<&str> = US_STATES.iter().map(|(_, code)| *code).collect();
+        let state_codes = state_codes.join("|");
+        let geo_context_regex = Regex::new(&format!(
+            r"(?x)
+            \b(?i:lives\s+in|living\s+in|resides\s+in|residing\s+in|resident\s+of|native\s+of
+                |moved\s+(?:here\s+)?(?:from|to)|relocated\s+(?:from|to)|hometown\s+(?:of|is)
+                |grew\s+up\s+in|born\s+in)
+            \s+(?:the\s+)?
+            (?P<place>{place})",
+            place = GEO_PLACE
+        ))?;
+        let geo_city_state_regex = Regex::new(&format!(
+            r"(?x)
+            (?P<city>{place}),[\ \t]*
+            (?P<state>{names}|{codes})\b
+            (?P<zip>[\ \t]+\d{{5}})?",
+            place = GEO_PLACE,
+            names = state_names,
+            codes = state_codes
+        ))?;
+        let geo_county_regex =
+            Regex::new(r"\b[A-Z][a-z]+(?:[ \t][A-Z][a-z]+)?[ \t](?:County|Parish|Borough)\b")?;
+        let state_name_regex = Regex::new(&format!(r"\b(?:{})\b", state_names))?;
 
-        let facility_terms = build_dictionary(DEFAULT_FACILITY_TERMS, &config.keywords);
-        let custom_facility_regex = build_dictionary_regex(&facility_terms)?;
+        let mut keywords = config.keywords.clone();
+        keywords.extend(read_terms("keywords", &config.keywords_files)?);
+        let facility_terms = build_dictionary(&dictionaries.facility_terms.entries, &keywords);
+        let custom_facility_regex =
+            build_dictionary_regex("facility", &facility_terms, config.regex_size_limit_mb)?;
 
-        let names = build_dictionary(DEFAULT_NAMES, &config.names);
-        let name_dictionary_regex = build_dictionary_regex(&names)?;
-        let titled_name_regex = build_titled_name_regex()?;
-        let first_last_regex = build_first_last_regex()?;
+        let mut extra_names = config.names.clone();
+        extra_names.extend(read_terms("names", &config.names_files)?);
+        let names = build_dictionary(&dictionaries.names.entries, &extra_names);
+        let name_dictionary_regex =
+            build_dictionary_regex("name", &names, config.regex_size_limit_mb)?;
+        let titles = build_title_alternation(&config.titles, config.titles_replace.as_deref());
+        let titled_name_regex = build_titled_name_regex(&titles)?;
+        let entity_keys = EntityKeys::new(&titles)?;
+        let first_last_regex = build_first_last_regex(first_names)?;
         let capital_sequence_regex = build_capital_sequence_regex()?;
+        let saint_regex = build_saint_regex(first_names, &titles)?;
+        let first_name_regex = build_first_name_regex(first_names)?;
+        let narrative_first_name_regex = build_narrative_first_name_regex(first_names)?;
+        let relative_name_regex = build_relative_name_regex()?;
 
         let date_regex = Regex::new(
             r"(?xi)
             \b(
                 \d{1,2}[/-]\d{1,2}(?:[/-]\d{2,4})|
                 \d{4}-\d{2}-\d{2}|
-                (?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Sept|Oct|Nov|Dec)[a-z]*\s+\d{1,2},?\s+\d{2,4}
+                (?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Sept|Oct|Nov|Dec)[a-z]*\s+\d{1,2},?\s+\d{2,4}|
+                \d{1,2}\s+(?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Sept|Oct|Nov|Dec)[a-z]*,?\s+\d{2,4}
             )\b",
         )?;
 
-        let relative_date_regex = Regex::new(
+        // The compact form ("DOB 01021987") is only a date after a label; bare, it is an MRN.
+        let dob_label_regex = Regex::new(
             r"(?xi)
-            \b(
-                yesterday|today|tomorrow|
-                last\s+(?:night|week|month|year|Monday|Tuesday|Wednesday|Thursday|Friday|Saturday|Sunday)|
-                this\s+(?:morning|afternoon|evening|week|month)|
-                \d+\s+(?:day|days|week|weeks|month|months|year|years)\s+ago
+            \b(?:DOB|D\.O\.B|Date\s+of\s+Birth|Birth\s*date)\b\.?\s*[:\#-]?\s*
+            (?P<date>
+                \d{1,2}[/-]\d{1,2}[/-]\d{2,4}
+              | \d{4}-\d{2}-\d{2}
+              | (?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Sept|Oct|Nov|Dec)[a-z]*\s+\d{1,2},?\s+\d{2,4}
+              | \d{1,2}\s+(?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Sept|Oct|Nov|Dec)[a-z]*,?\s+\d{2,4}
+              | \d{8}
+            )\b",
+        )?;
+
+        let birth_year_regex = Regex::new(
+            r"(?xi)
+            \b(?:
+                (?:born\s+(?:in\s+)?|b\.\s*|birth\s*year\s*(?:of\s+|:\s*)?|DOB\s+year\s*:?\s*|YOB(?:\s+year)?\s*[:\#-]?\s*)
+                (?P<year>(?:18|19|20)\d{2})
+              | (?P<year_before>(?:18|19|20)\d{2})\s+birth\s*year
             )\b",
         )?;
 
+        // A bare "96 years" is too often a duration, so it counts only in parentheses, as in
+        // "DOB 1/2/1928 (96 years)".
+        let age_regex = Regex::new(&format!(
+            r"(?xi)
+            \b(?:
+                (?P<age>\d{{2,3}}|{words})[\s-]*(?:years?|yrs?|y)\.?[\s-]*(?:old|/o|o)
+              | age[ds]?\s*:?\s*(?P<age_after>\d{{2,3}}|{words})
+            )\b
+          | \(\s*(?P<age_paren>\d{{2,3}})[\s-]*(?:years?|yrs?|y/?o)\b",
+            words = AGE_WORDS,
+        ))?;
+
+        let yearless_date_regex = Regex::new(r"\b\d{1,2}[/-]\d{1,2}\b")?;
+
+        let relative_date_regex = build_relative_date_regex(&config.relative_date_phrases)?;
+
+        let ocr_value_regex = Regex::new(
+            r"(?x)
+            \b(?P<label>(?i:SSN|MRN|DOB|Phone|Ph))\b\.?\s*[:\#]?\s*
+            (?P<value>[0-9OoIlS(][0-9OoIlS().\-/]*(?:\x20[0-9OoIlS().\-/]+){0,2})",
+        )?;
+
+        let mut custom_categories = Vec::with_capacity(config.custom_patterns.len());
+        let mut custom_detectors = Vec::with_capacity(config.custom_patterns.len());
+        for custom in &config.custom_patterns {
+            let category = CategoryKey::custom(&custom.name).map_err(|err| anyhow!(err))?;
+            if custom_categories.contains(&category) {
+                return Err(anyhow!("custom pattern '{}' is defined twice", custom.name));
+            }
+            let regex = RegexBuilder::new(&custom.pattern)
+                .case_insensitive(custom.case_insensitive)
+                .build()
+                .with_context(|| format!("invalid pattern for custom:{}", custom.name))?;
+            if let Some(token) = &custom.token {
+                if config.tokens.contains_key(&category) {
+                    return Err(anyhow!(
+                        "custom pattern '{}' has a token and a tokens entry",
+                        custom.name
+                    ));
+                }
+                config.tokens.insert(category.clone(), token.clone());
+            }
+            custom_detectors.push(Detector {
+                name: "custom-pattern",
+                categories: vec![category.clone()],
+                source: RuleSource::Config,
+                summary: format!("{}: {}", category, custom.pattern),
+                gate: Gate::Always,
+                confidence: Confidence::High,
+                rule: Rule::Custom(regex),
+            });
+            custom_categories.push(category);
+        }
+        if let Some(category) = config.skip.iter().find(|category| {
+            matches!(category, CategoryKey::Custom(_)) && !custom_categories.contains(category)
+        }) {
+            return Err(anyhow!(
+                "skip lists {}, but no custom_patterns entry defines it",
+                category
+            ));
+        }
+        if let Some(category) = config.tokens.keys().find(|category| {
+            matches!(category, CategoryKey::Custom(_)) && !custom_categories.contains(category)
+        }) {
+            return Err(anyhow!(
+                "token override for {} has no matching custom_patterns entry",
+                category
+            ));
+        }
+
+        let mrn_from_config = config.mrn_min_length.is_some() || config.mrn_max_length.is_some();
+        let mut detectors = vec![
+            Detector::pattern("email", Category::Email, "user@domain.tld", email_regex),
+            Detector::pattern(
+                "obfuscated-email",
+                Category::Email,
+                "user at domain dot tld",
+                obfuscated_email_regex,
+            )
+            .confidence(Confidence::Medium),
+            Detector {
+                name: "handle",
+                categories: vec![Category::Handle.into()],
+                source: RuleSource::Builtin,
+                summary: "@handles next to a platform name, and social profile links".to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::Medium,
+                rule: Rule::Handle {
+                    handle: handle_regex,
+                    profile: profile_regex,
+                },
+            },
+            Detector {
+                name: "url",
+                categories: vec![Category::Url.into()],
+                source: RuleSource::Builtin,
+                summary: "http(s)://, www., and host.tld/path links".to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::High,
+                rule: Rule::Url(url_regex),
+            },
+            Detector::pattern(
+                "mbi",
+                Category::Mbi,
+                "Medicare Beneficiary Identifiers (CMS character rules)",
+                mbi_regex,
+            ),
+            Detector::pattern(
+                "insurance",
+                Category::Insurance,
+                &format!(
+                    "{} member/policy/group/payer labels + an ID with a digit",
+                    insurance_labels.len()
+                ),
+                insurance_regex,
+            )
+            .filtered(Filter::HasDigit)
+            .configured(!config.insurance_labels.is_empty())
+            .safe_harbor_only(),
+            Detector::pattern(
+                "certificate",
+                Category::Certificate,
+                &format!(
+                    "{} case/certificate/docket labels + an ID with a digit",
+                    certificate_labels.len()
+                ),
+                certificate_regex,
+            )
+            .filtered(Filter::HasDigit)
+            .configured(!config.certificate_labels.is_empty())
+            .safe_harbor_only(),
+            Detector::pattern(
+                "govt-id",
+                Category::GovtId,
+                &format!(
+                    "{} passport/visa/alien registration labels + an 8-9 digit number",
+                    govt_id_labels.len()
+                ),
+                govt_id_regex,
+            )
+            .configured(!config.govt_id_labels.is_empty())
+            .safe_harbor_only(),
+            Detector {
+                name: "vehicle",
+                categories: vec![Category::Vehicle.into()],
+                source: RuleSource::Builtin,
+                summary: "VINs with a valid check digit; labeled plate/tag numbers".to_string(),
+                gate: Gate::SafeHarbor,
+                confidence: Confidence::High,
+                rule: Rule::Vehicle {
+                    vin: vin_regex,
+                    plate: plate_regex,
+                },
+            },
+            Detector {
+                name: "device",
+                categories: vec![Category::Device.into()],
+                source: RuleSource::Builtin,
+                summary: "GS1/HIBCC UDIs; labeled serial/lot/device IDs with a digit".to_string(),
+                gate: Gate::SafeHarbor,
+                confidence: Confidence::High,
+                rule: Rule::Device {
+                    labeled: device_regex,
+                    gs1: gs1_regex,
+                    hibcc: hibcc_regex,
+                },
+            },
+            Detector {
+                name: "license",
+                categories: vec![Category::License.into()],
+                source: RuleSource::Builtin,
+                summary: "labeled license/state IDs, DEA and NPI numbers with valid check digits"
+                    .to_string(),
+                gate: Gate::SafeHarbor,
+                confidence: Confidence::High,
+                rule: Rule::License {
+                    labeled: license_regex,
+                    dea: dea_regex,
+                    npi: npi_regex,
+                },
+            },
+            Detector {
+                name: "ocr-labeled",
+                categories: vec![
+                    Category::Ssn.into(),
+                    Category::Mrn.into(),
+                    Category::Date.into(),
+                    Category::Phone.into(),
+                ],
+                source: RuleSource::Builtin,
+                summary: "SSN/MRN/DOB/phone values read with O/l/I/S as digits".to_string(),
+                gate: Gate::OcrTolerant,
+                confidence: Confidence::High,
+                rule: Rule::OcrLabeled(ocr_value_regex),
+            }
+            .configured(config.ocr_tolerant),
+            Detector {
+                name: "dob-label",
+                categories: vec![Category::Date.into()],
+                source: RuleSource::Builtin,
+                summary: "DOB/Date of Birth/Birthdate labels, including compact MMDDYYYY"
+                    .to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::High,
+                rule: Rule::Date(dob_label_regex),
+            },
+            Detector {
+                name: "phone-label",
+                categories: vec![
+                    Category::Phone.into(),
+                    Category::Fax.into(),
+                    Category::Pager.into(),
+                ],
+                source: RuleSource::Builtin,
+                summary: "Ph/Fax/Pager labels attribute the next number on the line".to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::High,
+                rule: Rule::LabeledNumber {
+                    labels: phone_label_regex,
+                    number: labeled_number_regex,
+                },
+            },
+            Detector::pattern(
+                "phone",
+                Category::Phone,
+                "10-digit numbers with optional extension",
+                phone_regex,
+            ),
+            Detector::pattern("ssn", Category::Ssn, "###-##-####", ssn_regex),
+            Detector::pattern(
+                "mrn-label",
+                Category::Mrn,
+                &format!(
+                    "{} labels followed by an ID with a digit",
+                    MRN_LABELS
+                        .iter()
+                        .copied()
+                        .chain(config.mrn_labels.iter().map(String::as_str))
+                        .collect::<Vec<_>>()
+                        .join("/")
+                ),
+                mrn_label_regex,
+            )
+            .filtered(Filter::HasDigit)
+            .configured(!config.mrn_labels.is_empty() || config.mrn_pattern.is_some()),
+            Detector::pattern(
+                "account-label",
+                Category::Account,
+                "Acct/Account/Billing #/Guarantor # labels followed by a number",
+                account_label_regex,
+            ),
+            Detector {
+                name: "mrn-composite",
+                categories: vec![Category::Mrn.into()],
+                source: RuleSource::Builtin,
+                summary: format!(
+                    "{}-{} digit segments inside ^/-/_ joined identifiers ({})",
+                    mrn_min,
+                    mrn_max,
+                    match config.composite_ids {
+                        CompositeIdMode::Segment => "segment",
+                        CompositeIdMode::Whole => "whole",
+                    }
+                ),
+                gate: Gate::Always,
+                confidence: Confidence::Medium,
+                rule: Rule::CompositeId(composite_id_regex),
+            }
+            .configured(config.composite_ids != CompositeIdMode::default()),
+            Detector::pattern(
+                "mrn-digits",
+                Category::Mrn,
+                &format!("bare {}-{} digit runs", mrn_min, mrn_max),
+                mrn_regex,
+            )
+            .confidence(Confidence::Medium)
+            .configured(mrn_from_config),
+            Detector {
+                name: "geo-context",
+                categories: vec![Category::Geo.into()],
+                source: RuleSource::Builtin,
+                summary: format!(
+                    "places after lives in/resident of/moved from, \"City, ST\" pairs, counties; {}",
+                    if config.redact_states {
+                        "states too"
+                    } else {
+                        "states kept"
+                    }
+                ),
+                gate: Gate::Always,
+                confidence: Confidence::Medium,
+                rule: Rule::Geo {
+                    context: geo_context_regex,
+                    city_state: geo_city_state_regex,
+                    county: geo_county_regex,
+                },
+            }
+            .configured(config.redact_states),
+            Detector {
+                name: "zip",
+                categories: vec![Category::Zip.into()],
+                source: RuleSource::Builtin,
+                summary: match zip_mode {
+                    ZipMode::Redact => "#####(-####)",
+                    ZipMode::Truncate => "#####(-####), truncated to 3 digits",
+                }
+                .to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::High,
+                rule: Rule::Zip(zip_regex),
+            }
+            .configured(config.zip_mode.is_some()),
+            Detector {
+                name: "zip-spelled",
+                categories: vec![Category::Zip.into()],
+                source: RuleSource::Builtin,
+                summary: "ZIP codes spelled out after a \"zip\" label".to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::Medium,
+                rule: Rule::SpelledZip(spelled_zip_regex),
+            },
+            Detector {
+                name: "zip-area",
+                categories: vec![Category::Zip.into()],
+                source: RuleSource::Builtin,
+                summary: match config.area_mode {
+                    AreaMode::Zip => "\"the 606 area\", \"area code 606\" (as ZIP)",
+                    AreaMode::Geo => "\"the 606 area\", \"area code 606\" (as [GEO])",
+                }
+                .to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::Medium,
+                rule: Rule::AreaPrefix(area_prefix_regex),
+            }
+            .configured(config.area_mode != AreaMode::default()),
+        ];
+        if let Some(regex) = geo_dictionary_regex {
+            detectors.push(
+                Detector::pattern(
+                    "geo-dictionary",
+                    Category::Geo,
+                    &format!("{} city and county names", geo_places.len()),
+                    regex,
+                )
+                .configured(true),
+            );
+        }
+        if config.redact_states {
+            detectors.push(
+                Detector::pattern(
+                    "geo-state",
+                    Category::Geo,
+                    "US state and territory names",
+                    state_name_regex,
+                )
+                .confidence(Confidence::Medium)
+                .configured(true),
+            );
+        }
+        if let Some(regex) = employer_dictionary_regex {
+            detectors.push(
+                Detector::pattern(
+                    "employer-dictionary",
+                    Category::Employer,
+                    &format!("{} employer and school names", employers.len()),
+                    regex,
+                )
+                .configured(true),
+            );
+        }
+        detectors.extend([
+            Detector {
+                name: "employer-context",
+                categories: vec![Category::Employer.into()],
+                source: RuleSource::Builtin,
+                summary: "capitalized names after works at/for, employed by, student at, attends"
+                    .to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::Medium,
+                rule: Rule::Employer(employer_context_regex),
+            },
+            Detector {
+                name: "saint-context",
+                categories: vec![
+                    Category::Facility.into(),
+                    Category::Address.into(),
+                    Category::Person.into(),
+                ],
+                source: RuleSource::Builtin,
+                summary: "St./Saint resolved by facility, street, or person context".to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::Medium,
+                rule: Rule::SaintContext(saint_regex),
+            },
+            Detector::pattern(
+                "facility-pattern",
+                Category::Facility,
+                "Saint/Memorial/University... names",
+                facility_regex,
+            )
+            .confidence(Confidence::Medium)
+            .filtered(Filter::Facility),
+        ]);
+        if let Some(regex) = custom_facility_regex {
+            detectors.push(
+                Detector::pattern(
+                    "facility-dictionary",
+                    Category::Facility,
+                    &format!("{} facility terms", facility_terms.len()),
+                    regex,
+                )
+                .configured(!keywords.is_empty() || dictionaries.facility_terms.overridden),
+            );
+        }
+        detectors.extend([
+            Detector::pattern(
+                "street-address",
+                Category::Address,
+                "house number + street suffix",
+                address_regex,
+            ),
+            Detector::pattern(
+                "location-unit",
+                Category::Address,
+                "suite/room/unit/bed numbers",
+                location_regex,
+            )
+            .confidence(Confidence::Medium),
+            Detector {
+                name: "coordinate",
+                categories: vec![Category::Coordinate.into()],
+                source: RuleSource::Builtin,
+                summary: match config.coordinate_mode {
+                    CoordinateMode::Redact => "decimal lat/long pairs".to_string(),
+                    CoordinateMode::Round { places } => {
+                        format!("decimal lat/long pairs, rounded to {} places", places)
+                    }
+                },
+                gate: Gate::Always,
+                confidence: Confidence::High,
+                rule: Rule::Coordinate(coordinate_regex),
+            }
+            .configured(config.coordinate_mode != CoordinateMode::Redact),
+            Detector {
+                name: "ip",
+                categories: vec![Category::Ip.into()],
+                source: RuleSource::Builtin,
+                summary: "IPv4 (octets 0-255) and IPv6 with zone IDs; not version numbers"
+                    .to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::High,
+                rule: Rule::Ip {
+                    v6: ipv6_regex,
+                    v4: ipv4_regex,
+                },
+            }
+            .safe_harbor_only(),
+        ]);
+        // Before titled-name, which would take "brother"/"sister" as an honorific.
+        detectors.push(Detector {
+            name: "relative-name",
+            categories: vec![Category::Person.into()],
+            source: RuleSource::Builtin,
+            summary: "one or two capitalized words after wife/son/sister/neighbor/caregiver..."
+                .to_string(),
+            gate: Gate::PersonMode(PersonMode::Balanced),
+            confidence: Confidence::Medium,
+            rule: Rule::RelativeName(relative_name_regex),
+        });
+        if let Some(regex) = &name_dictionary_regex {
+            detectors.push(
+                Detector::pattern(
+                    "name-dictionary",
+                    Category::Person,
+                    &format!("{} names", names.len()),
+                    regex.clone(),
+                )
+                .filtered(Filter::NameStopwords)
+                .configured(!extra_names.is_empty() || dictionaries.names.overridden),
+            );
+        }
+        detectors.extend([
+            Detector::pattern(
+                "titled-name",
+                Category::Person,
+                "honorific + capitalized name",
+                titled_name_regex,
+            )
+            .configured(!config.titles.is_empty() || config.titles_replace.is_some())
+            .filtered(Filter::NameStopwords),
+            Detector::pattern(
+                "first-last",
+                Category::Person,
+                &format!("{} common first names + surname", first_names.len()),
+                first_last_regex,
+            )
+            .confidence(Confidence::Medium)
+            .filtered(Filter::NameStopwords)
+            .configured(dictionaries.first_names.overridden)
+            .person_mode(PersonMode::Balanced),
+            Detector {
+                name: "capital-sequence",
+                categories: vec![Category::Person.into()],
+                source: RuleSource::Builtin,
+                summary: "2-3 consecutive capitalized words".to_string(),
+                gate: Gate::PersonMode(PersonMode::Aggressive),
+                confidence: Confidence::Low,
+                rule: Rule::CapitalSequence(capital_sequence_regex),
+            },
+            Detector::pattern(
+                "first-name-alone",
+                Category::Person,
+                &format!("{} common first names without a surname", first_names.len()),
+                first_name_regex,
+            )
+            .confidence(Confidence::Low)
+            .filtered(Filter::NameStopwords)
+            .configured(dictionaries.first_names.overridden)
+            .field_mode_only(),
+            Detector {
+                name: "first-name-narrative",
+                categories: vec![Category::Person.into()],
+                source: RuleSource::Builtin,
+                summary: match config.first_name_alone {
+                    FirstNameMode::Off => "bare first names (off)".to_string(),
+                    FirstNameMode::CueRequired => {
+                        "bare first names after a cue (\"spoke with\", \"daughter\")".to_string()
+                    }
+                    FirstNameMode::Aggressive => "bare first names in narrative text".to_string(),
+                },
+                gate: Gate::FirstNameAlone,
+                confidence: Confidence::Low,
+                rule: Rule::NarrativeFirstName(narrative_first_name_regex),
+            }
+            .configured(dictionaries.first_names.overridden),
+            Detector {
+                name: "age",
+                categories: vec![Category::Age.into()],
+                source: RuleSource::Builtin,
+                summary: format!(
+                    "ages over {} (\"92-year-old\", \"age 93\", \"(96 years)\"){}",
+                    MAX_UNREDACTED_AGE,
+                    match config.age_mode {
+                        Some(AgeMode::Generalize) => ", rendered as 90+",
+                        _ => "",
+                    }
+                ),
+                gate: match config.age_mode {
+                    Some(_) => Gate::Always,
+                    None => Gate::SafeHarbor,
+                },
+                confidence: Confidence::High,
+                rule: Rule::Age(age_regex),
+            }
+            .configured(config.age_mode.is_some()),
+            Detector {
+                name: "birth-year",
+                categories: vec![Category::Date.into()],
+                source: RuleSource::Builtin,
+                summary: format!(
+                    "cued birth years of patients over {} (as of {}-{:02}-{:02})",
+                    MAX_UNREDACTED_AGE,
+                    reference_date.year,
+                    reference_date.month,
+                    reference_date.day
+                ),
+                gate: Gate::Always,
+                confidence: Confidence::High,
+                rule: Rule::BirthYear(birth_year_regex),
+            },
+            Detector {
+                name: "date",
+                categories: vec![Category::Date.into()],
+                source: RuleSource::Builtin,
+                summary: match (config.date_shift, date_mode) {
+                    (Some(_), _) => {
+                        "numeric, ISO, and month-name dates, shifted by a seeded offset"
+                    }
+                    (None, DateMode::Redact) => "numeric, ISO, and month-name dates",
+                    (None, DateMode::Year) => {
+                        "numeric, ISO, and month-name dates, kept as the year"
+                    }
+                    (None, DateMode::Token) => "numeric, ISO, and month-name dates, as [DATE:year]",
+                }
+                .to_string(),
+                gate: Gate::Always,
+                confidence: Confidence::High,
+                rule: Rule::Date(date_regex),
+            }
+            .configured(config.date_shift.is_some() || config.date_mode.is_some()),
+            Detector::pattern(
+                "date-without-year",
+                Category::Date,
+                "month/day with no year",
+                yearless_date_regex,
+            )
+            .confidence(Confidence::Medium)
+            .field_mode_only(),
+            Detector::pattern(
+                "relative-date",
+                Category::RelativeDate,
+                "yesterday, last week, 3 days ago, POD 3...",
+                relative_date_regex,
+            )
+            .confidence(Confidence::Medium)
+            .configured(!config.relative_date_phrases.is_empty()),
+        ]);
+        // After the built-ins, so a broad site pattern cannot take a phone number or date the
+        // built-ins would label more precisely.
+        detectors.extend(custom_detectors);
+        if let Some(fallback) = config.segment_fallback {
+            detectors.push(Detector {
+                name: "segment-fallback",
+                categories: vec![Category::Segment.into()],
+                source: RuleSource::Config,
+                summary: format!(
+                    "whole {} when more than {} categories{} are redacted",
+                    match fallback.mode {
+                        SegmentMode::Line => "line",
+                        SegmentMode::Sentence => "sentence",
+                    },
+                    fallback.threshold,
+                    fallback
+                        .density
+                        .map(|density| format!(" or {:.0}% of characters", density * 100.0))
+                        .unwrap_or_default(),
+                ),
+                gate: Gate::Always,
+                confidence: Confidence::Low,
+                rule: Rule::SegmentFallback(fallback),
+            });
+        }
+
+        let allowlist: Vec<String> = config
+            .allowlist
+            .iter()
+            .map(|term| term.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+
+        let name_stoplist: HashSet<String> = dictionaries
+            .name_stoplist
+            .entries
+            .iter()
+            .chain(&config.stoplist)
+            .map(|entry| stopword_key(entry))
+            .filter(|key| !key.is_empty())
+            .collect();
+        let age_mode = config.age_mode.unwrap_or_default();
+        let max_spans = config.max_spans.unwrap_or(DEFAULT_MAX_SPANS);
+        let resolved = ResolvedConfig {
+            regulation: config.regulation,
+            safe_harbor,
+            field_mode: config.field_mode,
+            min_confidence: config.min_confidence,
+            person_mode: config.person_mode,
+            categories: CategoryStates::default(),
+            custom_patterns: config
+                .custom_patterns
+                .iter()
+                .map(|custom| custom.name.clone())
+                .collect(),
+            tokens: custom_categories
+                .iter()
+                .filter_map(|category| Some((category.clone(), category.default_token()?)))
+                .chain(config.tokens.clone())
+                .collect(),
+            token_style: config.token_style,
+            mrn_length: MrnLength {
+                min: mrn_min,
+                max: mrn_max,
+            },
+            reference_date: format!(
+                "{:04}-{:02}-{:02}",
+                reference_date.year, reference_date.month, reference_date.day
+            ),
+            date_mode,
+            date_order: config.date_order,
+            date_shift: config.date_shift.is_some(),
+            birth_year_mode: config.birth_year_mode,
+            age_mode,
+            zip_mode,
+            area_mode: config.area_mode,
+            coordinate_mode: config.coordinate_mode,
+            composite_ids: config.composite_ids,
+            first_name_alone: config.first_name_alone,
+            redact_states: config.redact_states,
+            ocr_tolerant: config.ocr_tolerant,
+            max_spans,
+            dictionaries: BTreeMap::from([
+                ("allowlist", allowlist.len()),
+                ("certificate_labels", certificate_labels.len()),
+                ("employers", employers.len()),
+                ("facility_terms", facility_terms.len()),
+                ("first_names", first_names.len()),
+                ("geo_places", geo_places.len()),
+                ("govt_id_labels", govt_id_labels.len()),
+                ("insurance_labels", insurance_labels.len()),
+                ("mrn_labels", MRN_LABELS.len() + config.mrn_labels.len()),
+                ("name_stoplist", name_stoplist.len()),
+                ("names", names.len()),
+                ("relative_date_phrases", config.relative_date_phrases.len()),
+                (
+                    "titles",
+                    config.titles.len()
+                        + config
+                            .titles_replace
+                            .as_ref()
+                            .map_or(HONORIFICS.split('|').count(), Vec::len),
+                ),
+            ]),
+        };
+
         Ok(Self {
-            email_regex,
-            phone_regex,
-            ssn_regex,
-            mrn_regex,
-            mrn_label_regex,
-            zip_regex,
-            facility_regex,
-            custom_facility_regex,
-            address_regex,
-            location_regex,
-            coordinate_regex,
-            url_regex,
-            obfuscated_email_regex,
+            detectors,
+            allowlist_regex: build_dictionary_regex(
+                "allowlist",
+                &allowlist,
+                config.regex_size_limit_mb,
+            )?,
             name_dictionary_regex,
-            titled_name_regex,
-            first_last_regex,
-            capital_sequence_regex,
-            date_regex,
-            relative_date_regex,
-            insurance_regex,
-            license_regex,
-            vehicle_regex,
-            device_regex,
-            ip_regex,
             safe_harbor,
+            field_mode: config.field_mode,
+            normalize_newlines: config.normalize_newlines,
+            reference_date,
+            redact_states: config.redact_states,
+            date_mode,
+            date_order: config.date_order,
+            date_offset: config.date_shift.map(|shift| date_offset(shift, seed)),
+            seed,
+            birth_year_mode: config.birth_year_mode,
+            age_mode,
+            mrn_lengths: mrn_min..=mrn_max,
+            composite_ids: config.composite_ids,
+            regulation: config.regulation,
+            profile_disabled,
+            skip: config.skip.into_iter().collect(),
+            custom_categories,
+            tokens: config.tokens,
+            token_style: config.token_style,
+            zip_mode,
+            coordinate_mode: config.coordinate_mode,
+            first_name_alone: config.first_name_alone,
+            person_mode: config.person_mode,
+            area_mode: config.area_mode,
+            ocr_tolerant: config.ocr_tolerant,
+            min_confidence: config.min_confidence,
+            max_spans,
+            name_stoplist,
+            dictionaries,
+            entity_keys,
+            resolved,
         })
     }
 
-    pub fn scrub(&self, input: &str, skip: &HashSet<Category>) -> (String, ScrubStats) {
-        let normalized = normalize_input(input);
-        let mut output = normalized.clone();
-        let mut stats = ScrubStats::default();
+    /// Config `tokens` that an active detector would redact if it saw them, e.g. a person token
+    /// of "May Johnson". Tokens are only rendered after every detector has run, so they cannot
+    /// change a scrub's output or counts; the warning is for downstream tools that re-scan.
+    pub fn token_warnings(&self) -> Vec<String> {
+        self.tokens
+            .iter()
+            .filter_map(|(category, token)| {
+                let (_, stats) = self.scrub(token, &HashSet::new());
+                let matched: Vec<String> = stats
+                    .by_key()
+                    .into_iter()
+                    .filter(|(_, count)| *count > 0)
+                    .map(|(matched, _)| matched.to_string())
+                    .collect();
+                (!matched.is_empty()).then(|| {
+                    format!(
+                        "token {:?} for {} looks like {}",
+                        token,
+                        category,
+                        matched.join(", ")
+                    )
+                })
+            })
+            .collect()
+    }
 
-        if !skip.contains(&Category::Email) {
-            let (next, count_a) = replace_all(&self.email_regex, &output, EMAIL_TOKEN);
-            output = next;
-            let (next, count_b) = replace_all(&self.obfuscated_email_regex, &output, EMAIL_TOKEN);
-            output = next;
-            stats.emails = count_a + count_b;
-        }
+    /// Content hash of each word list in effect, keyed by list name.
+    pub fn dictionary_versions(&self) -> BTreeMap<&'static str, String> {
+        self.dictionaries.versions()
+    }
 
-        if self.safe_harbor && !skip.contains(&Category::Url) {
-            let (next, count) = replace_all(&self.url_regex, &output, URL_TOKEN);
-            output = next;
-            stats.urls = count;
+    /// Describes every detector in run order, as `scrub` would apply them with `skip`.
+    pub fn describe(&self, skip: &HashSet<CategoryKey>) -> RuleSetDescription {
+        let skip = &*self.with_config_skip(skip);
+        let rules = self
+            .detectors
+            .iter()
+            .enumerate()
+            .map(|(index, detector)| RuleDescription {
+                priority: index + 1,
+                name: detector.name,
+                categories: detector.categories.clone(),
+                source: detector.source,
+                confidence: detector.confidence,
+                summary: detector.summary.clone(),
+                disabled_by: self.disabled_by(detector, skip),
+            })
+            .collect();
+        RuleSetDescription {
+            regulation: self.regulation,
+            safe_harbor: self.safe_harbor,
+            field_mode: self.field_mode,
+            character_classes: CharacterClasses::ACTIVE,
+            dictionaries: self.dictionary_versions(),
+            rules,
         }
+    }
 
-        if !skip.contains(&Category::Phone) {
-            let (next, count) = replace_all(&self.phone_regex, &output, PHONE_TOKEN);
-            output = next;
-            stats.phones = count;
+    /// The seed behind every randomized choice: config `seed`, or the one drawn for this
+    /// scrubber when the config sets none.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether output keeps the input's line breaks and final newline (config
+    /// `normalize_newlines` off).
+    pub fn keeps_line_breaks(&self) -> bool {
+        !self.normalize_newlines
+    }
+
+    /// Every built-in category in report order, then the custom categories from config.
+    pub fn categories(&self) -> Vec<CategoryKey> {
+        Category::ALL
+            .iter()
+            .map(|&category| category.into())
+            .chain(self.custom_categories.iter().cloned())
+            .collect()
+    }
+
+    /// Which categories will run, were skipped, or are disabled by the current mode, for
+    /// rendering alongside stats.
+    pub fn report_options(&self, skip: &HashSet<CategoryKey>) -> ReportOptions {
+        let skip = &*self.with_config_skip(skip);
+        let mut options = ReportOptions {
+            person_mode: self.person_mode,
+            person_rules_off: self
+                .detectors
+                .iter()
+                .filter(|detector| self.person_mode < detector.gate.person_mode())
+                .map(|detector| detector.name)
+                .collect(),
+            ..ReportOptions::default()
+        };
+        for category in self.categories() {
+            let detectors: Vec<&Detector> = self
+                .detectors
+                .iter()
+                .filter(|detector| detector.categories.contains(&category))
+                .collect();
+            if skip.contains(&category) {
+                options.skipped.push(category);
+            } else if !self.profile_disabled.contains(&category)
+                && detectors.iter().any(|detector| {
+                    self.gate_open(detector.gate) && detector.confidence >= self.min_confidence
+                })
+            {
+                options.active.push(category);
+            } else {
+                options.not_enabled.push(category);
+            }
         }
+        options
+    }
 
-        if !skip.contains(&Category::Ssn) {
-            let (next, count) = replace_all(&self.ssn_regex, &output, SSN_TOKEN);
-            output = next;
-            stats.ssn = count;
+    /// Every resolved setting, with categories sorted by how `scrub` would treat them with
+    /// `skip`.
+    pub fn resolved_config(&self, skip: &HashSet<CategoryKey>) -> ResolvedConfig {
+        let options = self.report_options(skip);
+        ResolvedConfig {
+            categories: CategoryStates {
+                enabled: options.active,
+                skipped: options.skipped,
+                not_enabled: options.not_enabled,
+            },
+            ..self.resolved.clone()
         }
+    }
 
-        if !skip.contains(&Category::Mrn) {
-            let (next, count_a) = replace_all(&self.mrn_label_regex, &output, MRN_TOKEN);
-            output = next;
-            let (next, count_b) = replace_all(&self.mrn_regex, &output, MRN_TOKEN);
-            output = next;
-            stats.mrn = count_a + count_b;
+    /// `skip` plus the config's `skip`.
+    fn with_config_skip<'a>(
+        &self,
+        skip: &'a HashSet<CategoryKey>,
+    ) -> Cow<'a, HashSet<CategoryKey>> {
+        if self.skip.is_subset(skip) {
+            Cow::Borrowed(skip)
+        } else {
+            Cow::Owned(skip.union(&self.skip).cloned().collect())
         }
+    }
 
-        if !skip.contains(&Category::Zip) {
-            let (next, count) = replace_all(&self.zip_regex, &output, ZIP_TOKEN);
-            output = next;
-            stats.zip_codes = count;
+    fn disabled_by(&self, detector: &Detector, skip: &HashSet<CategoryKey>) -> Option<DisabledBy> {
+        if self.person_mode < detector.gate.person_mode() {
+            return Some(DisabledBy::PersonMode);
         }
+        detector
+            .disabled_by(self.gate_open(detector.gate), &self.profile_disabled, skip)
+            .or_else(|| {
+                (detector.confidence < self.min_confidence).then_some(DisabledBy::Confidence)
+            })
+    }
 
-        if !skip.contains(&Category::Facility) {
-            let (next, count_a) = replace_all(&self.facility_regex, &output, FACILITY_TOKEN);
-            output = next;
-            let mut facility_total = count_a;
-            if let Some(regex) = &self.custom_facility_regex {
-                let (next, count_b) = replace_all(regex, &output, FACILITY_TOKEN);
-                output = next;
-                facility_total += count_b;
+    fn gate_open(&self, gate: Gate) -> bool {
+        match gate {
+            Gate::Always => true,
+            Gate::SafeHarbor => self.safe_harbor,
+            Gate::FieldMode => self.field_mode,
+            Gate::FirstNameAlone => {
+                self.first_name_alone != FirstNameMode::Off
+                    && self.person_mode >= gate.person_mode()
             }
-            stats.facilities = facility_total;
+            Gate::OcrTolerant => self.ocr_tolerant,
+            Gate::PersonMode(level) => self.person_mode >= level,
         }
+    }
+
+    pub fn scrub(&self, input: &str, skip: &HashSet<CategoryKey>) -> (String, ScrubStats) {
+        let result = self.scrub_detailed(input, skip);
+        (result.text, result.stats)
+    }
 
-        if !skip.contains(&Category::Address) {
-            let (next, count_a) = replace_all(&self.address_regex, &output, ADDRESS_TOKEN);
-            output = next;
-            let (next, count_b) = replace_all(&self.location_regex, &output, ADDRESS_TOKEN);
-            output = next;
-            stats.addresses = count_a + count_b;
+    /// Like `scrub`, but also returns the alignment between `input` and the output text.
+    pub fn scrub_detailed(&self, input: &str, skip: &HashSet<CategoryKey>) -> ScrubResult {
+        self.scrub_cancellable(input, skip, &CancellationToken::new())
+            .expect("scrub without a cancelled token")
+    }
+
+    /// Like `scrub_detailed`, but checks `cancel` before each detector pass and stops with
+    /// `Cancelled` once it is set.
+    pub fn scrub_cancellable(
+        &self,
+        input: &str,
+        skip: &HashSet<CategoryKey>,
+        cancel: &CancellationToken,
+    ) -> Result<ScrubResult, Cancelled> {
+        self.scrub_with_options(input, skip, &ScrubOptions::default(), cancel)
+    }
+
+    /// Like `scrub_cancellable`, with the extra work `options` asks for.
+    pub fn scrub_with_options(
+        &self,
+        input: &str,
+        skip: &HashSet<CategoryKey>,
+        options: &ScrubOptions,
+        cancel: &CancellationToken,
+    ) -> Result<ScrubResult, Cancelled> {
+        self.scrub_record(input, skip, options, cancel, &mut BTreeMap::new())
+    }
+
+    /// One scrub, with each redacted value's entity key added to `unique` under its category.
+    /// `stats.unique` holds the sizes of those sets afterwards, so a caller scrubbing a stream
+    /// record by record can count distinct values over the whole stream.
+    pub(crate) fn scrub_record(
+        &self,
+        input: &str,
+        skip: &HashSet<CategoryKey>,
+        options: &ScrubOptions,
+        cancel: &CancellationToken,
+        unique: &mut BTreeMap<String, HashSet<String>>,
+    ) -> Result<ScrubResult, Cancelled> {
+        let (text, normalization) = normalize_input(input);
+        let mut claims = Claims::default();
+        let mut stats = ScrubStats {
+            profile: self.regulation,
+            seed: Some(self.seed),
+            dictionaries: self.dictionary_versions(),
+            ..ScrubStats::default()
+        };
+        for category in &self.custom_categories {
+            stats.record_key(category, 0);
         }
+        let sentence_starts = segmentation::sentence_starts(&text);
+
+        let skip: HashSet<CategoryKey> = skip
+            .iter()
+            .chain(&self.profile_disabled)
+            .chain(&self.skip)
+            .cloned()
+            .collect();
+        let skip = &skip;
+        // Whether a detector found a patient over `MAX_UNREDACTED_AGE`; see `claim_dates`.
+        let mut over_max_age = false;
 
-        if !skip.contains(&Category::Coordinate) {
-            let (next, count) = replace_all(&self.coordinate_regex, &output, COORD_TOKEN);
-            output = next;
-            stats.coordinates = count;
+        if let Some(regex) = &self.allowlist_regex {
+            for mat in regex.find_iter(&text) {
+                claims.reserve(mat.start(), mat.end());
+            }
+        }
+        // A skipped custom identifier stays verbatim instead of being picked apart by the
+        // built-in detectors, which run before the custom ones.
+        for detector in &self.detectors {
+            if let (Rule::Custom(regex), Some(DisabledBy::Skip)) =
+                (&detector.rule, self.disabled_by(detector, skip))
+            {
+                reserve_all(regex, &text, &mut claims);
+            }
+        }
+        for detector in &self.detectors {
+            if cancel.is_cancelled() {
+                return Err(Cancelled);
+            }
+            if let Some(disabled) = self.disabled_by(detector, skip) {
+                // A skipped account number stays verbatim instead of being picked apart by the
+                // later detectors (or redacted as a bare MRN).
+                if let (
+                    DisabledBy::Skip,
+                    Rule::Pattern { regex, .. },
+                    [CategoryKey::Builtin(Category::Account)],
+                ) = (disabled, &detector.rule, detector.categories.as_slice())
+                {
+                    reserve_all(regex, &text, &mut claims);
+                }
+                continue;
+            }
+            claims.rule = ClaimRule {
+                name: detector.name,
+                confidence: detector.confidence,
+            };
+            let started = options
+                .collect_timings
+                .then(|| (Instant::now(), stats.total()));
+            match &detector.rule {
+                Rule::Geo {
+                    context,
+                    city_state,
+                    county,
+                } => {
+                    let count = self.claim_geo(context, city_state, county, &text, &mut claims);
+                    stats.record(Category::Geo, count);
+                }
+                Rule::Employer(regex) => {
+                    let count = claim_employers(regex, &text, &mut claims);
+                    stats.record(Category::Employer, count);
+                }
+                Rule::Handle { handle, profile } => {
+                    let count = claim_handles(handle, profile, &text, &mut claims);
+                    stats.record(Category::Handle, count);
+                }
+                Rule::Pattern { regex, filter } => {
+                    let category = &detector.categories[0];
+                    let count =
+                        claim_all_filtered(regex, &text, &mut claims, category, |candidate| {
+                            filter.accepts(candidate, &self.name_stoplist)
+                        });
+                    stats.record_key(category, count);
+                }
+                Rule::SaintContext(regex) => {
+                    self.resolve_saint_names(regex, &text, &mut claims, &mut stats, skip);
+                }
+                Rule::Custom(regex) => {
+                    let category = &detector.categories[0];
+                    let count = claim_custom(regex, &text, &mut claims, &mut stats, category);
+                    stats.record_key(category, count);
+                }
+                Rule::Coordinate(regex) => {
+                    let (count, rounded) = self.claim_coordinates(regex, &text, &mut claims);
+                    stats.record(Category::Coordinate, count);
+                    stats.coordinates_rounded += rounded;
+                }
+                Rule::Zip(regex) => {
+                    let count = self.claim_zips(regex, &text, &mut claims);
+                    stats.record(Category::Zip, count);
+                }
+                Rule::SpelledZip(regex) => {
+                    let count = self.claim_spelled_zips(regex, &text, &mut claims);
+                    stats.record(Category::Zip, count);
+                }
+                Rule::AreaPrefix(regex) => {
+                    let count = self.claim_area_prefixes(regex, &text, &mut claims);
+                    stats.record(Category::Zip, count);
+                }
+                Rule::CompositeId(regex) => {
+                    let count = self.claim_composite_ids(regex, &text, &mut claims);
+                    stats.record(Category::Mrn, count);
+                }
+                Rule::BirthYear(regex) => {
+                    let count = self.claim_birth_years(regex, &text, &mut claims);
+                    stats.record(Category::Date, count);
+                    over_max_age |= count > 0;
+                }
+                Rule::Age(regex) => {
+                    let count = self.claim_ages(regex, &text, &mut claims);
+                    stats.record(Category::Age, count);
+                    over_max_age |= count > 0;
+                }
+                Rule::Date(regex) => {
+                    let (count, fallbacks) =
+                        self.claim_dates(regex, &text, &mut claims, over_max_age);
+                    stats.record(Category::Date, count);
+                    stats.date_shift_fallbacks += fallbacks;
+                }
+                Rule::RelativeName(regex) => {
+                    let count = self.claim_relative_names(regex, &text, &mut claims);
+                    stats.record(Category::Person, count);
+                }
+                Rule::NarrativeFirstName(regex) => {
+                    let count = self.claim_narrative_first_names(regex, &text, &mut claims);
+                    stats.record(Category::Person, count);
+                }
+                Rule::OcrLabeled(regex) => {
+                    self.claim_ocr_values(regex, &text, &mut claims, &mut stats, skip);
+                }
+                Rule::Url(regex) => {
+                    let count = claim_urls(regex, &text, &mut claims);
+                    stats.record(Category::Url, count);
+                }
+                Rule::License { labeled, dea, npi } => {
+                    let count = claim_licenses(labeled, dea, npi, &text, &mut claims);
+                    stats.record(Category::License, count);
+                }
+                Rule::Device {
+                    labeled,
+                    gs1,
+                    hibcc,
+                } => {
+                    let count = claim_devices(labeled, gs1, hibcc, &text, &mut claims);
+                    stats.record(Category::Device, count);
+                }
+                Rule::Vehicle { vin, plate } => {
+                    let count = claim_vehicles(vin, plate, &text, &mut claims);
+                    stats.record(Category::Vehicle, count);
+                }
+                Rule::Ip { v6, v4 } => {
+                    let count = claim_ips(v6, v4, &text, &mut claims);
+                    stats.record(Category::Ip, count);
+                }
+                Rule::LabeledNumber { labels, number } => {
+                    claim_labeled_numbers(labels, number, &text, &mut claims, &mut stats, skip);
+                }
+                Rule::CapitalSequence(regex) => {
+                    let count = claim_capital_sequences(
+                        regex,
+                        &text,
+                        &sentence_starts,
+                        &self.name_stoplist,
+                        &mut claims,
+                    );
+                    stats.record(Category::Person, count);
+                }
+                Rule::SegmentFallback(fallback) => {
+                    apply_segment_fallback(
+                        fallback,
+                        &text,
+                        &sentence_starts,
+                        &mut claims,
+                        &mut stats,
+                    );
+                }
+            }
+            if let Some((started, before)) = started {
+                stats.detector_timings.push(DetectorTiming {
+                    detector: detector.label(),
+                    elapsed_us: started.elapsed().as_micros() as u64,
+                    matches: stats.total().saturating_sub(before),
+                });
+            }
         }
 
-        if self.safe_harbor && !skip.contains(&Category::Insurance) {
-            let (next, count) = replace_all(&self.insurance_regex, &output, INSURANCE_TOKEN);
-            output = next;
-            stats.insurance_ids = count;
+        let mut spans = SpanLog::new(if options.all_spans {
+            usize::MAX
+        } else {
+            self.max_spans
+        });
+        let mut starts = Vec::new();
+        let mut rules = BTreeMap::new();
+        let mut low_confidence = BTreeMap::new();
+        let (redacted, redaction) = claims.render(
+            &text,
+            &self.tokens,
+            (self.token_style == TokenStyle::Numbered).then_some(&self.entity_keys),
+            |category, rule, index, normalized, redacted| {
+                if options.collect_lines {
+                    let start = normalization.original_range(normalized.clone()).start;
+                    starts.push((start, category.clone()));
+                }
+                unique
+                    .entry(category.to_string())
+                    .or_default()
+                    .insert(self.entity_keys.key(category, &text[normalized.clone()]));
+                *rules
+                    .entry(format!("{}.{}", category, rule.name))
+                    .or_default() += 1;
+                if rule.confidence == Confidence::Low {
+                    *low_confidence.entry(category.to_string()).or_default() += 1;
+                }
+                spans.push(RedactionSpan {
+                    category: category.clone(),
+                    rule: rule.name,
+                    confidence: rule.confidence,
+                    original: normalized,
+                    output: redacted,
+                    token: String::new(),
+                    index,
+                });
+            },
+        );
+        let line_ending = if self.normalize_newlines {
+            None
+        } else {
+            Some(final_line_ending(input))
+        };
+        let (output, tidying) = tidy_punctuation(&redacted, line_ending);
+        let (mut spans, dropped) = spans.finish();
+        for span in &mut spans {
+            span.token = redacted[span.output.clone()].to_string();
+            span.original = normalization.original_range(span.original.clone());
+            span.output = tidying
+                .project_span(span.output.clone())
+                .unwrap_or(span.output.clone());
         }
+        stats.spans_truncated = dropped > 0;
+        stats.spans_dropped = dropped;
+        stats.lines = count_lines(input, starts);
+        stats.rules = rules;
+        stats.tokens = self
+            .tokens
+            .iter()
+            .filter(|(category, _)| stats.count_key(category) > 0)
+            .map(|(category, token)| (category.to_string(), token.clone()))
+            .collect();
+        stats.low_confidence = low_confidence;
+        // Only the sizes are reported; the matched values never leave the crate.
+        stats.unique = unique
+            .iter()
+            .map(|(category, values)| (category.clone(), values.len()))
+            .collect();
+        Ok(ScrubResult {
+            text: output,
+            stats,
+            alignment: normalization.then(&redaction).then(&tidying),
+            spans,
+            entity_keys: self.entity_keys.clone(),
+        })
+    }
 
-        if self.safe_harbor && !skip.contains(&Category::License) {
-            let (next, count) = replace_all(&self.license_regex, &output, LICENSE_TOKEN);
-            output = next;
-            stats.licenses = count;
+    /// "St."/"Saint" is shared by facilities, streets, and surnames, so those candidates are
+    /// classified by their surroundings before the generic facility/address/person passes run.
+    /// A resolved candidate whose category is skipped is reserved so no other pass claims it.
+    fn resolve_saint_names(
+        &self,
+        regex: &Regex,
+        text: &str,
+        claims: &mut Claims,
+        stats: &mut ScrubStats,
+        skip: &HashSet<CategoryKey>,
+    ) {
+        let mut resolved = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for caps in regex.captures_iter(&text[gap_start..gap_end]) {
+                let whole = caps.get(0).expect("whole match");
+                let saint = caps.name("saint").expect("saint group");
+                let (category, start) = if caps.name("facility").is_some() {
+                    (Category::Facility, saint.start())
+                } else if caps.name("street").is_some() {
+                    (Category::Address, saint.start())
+                } else if caps.name("lead").is_some() || self.is_dictionary_name(saint.as_str()) {
+                    (Category::Person, whole.start())
+                } else {
+                    continue;
+                };
+                resolved.push((category, gap_start + start, gap_start + whole.end()));
+            }
         }
 
-        if self.safe_harbor && !skip.contains(&Category::Vehicle) {
-            let (next, count) = replace_all(&self.vehicle_regex, &output, VEHICLE_TOKEN);
-            output = next;
-            stats.vehicles = count;
+        for (category, start, end) in resolved {
+            if skip.contains(&CategoryKey::from(category)) {
+                claims.reserve(start, end);
+                continue;
+            }
+
+            if claims.claim(start, end, category, token_for(category)) {
+                stats.record(category, 1);
+            }
         }
+    }
 
-        if self.safe_harbor && !skip.contains(&Category::Device) {
-            let (next, count) = replace_all(&self.device_regex, &output, DEVICE_TOKEN);
-            output = next;
-            stats.devices = count;
+    /// Claims birth years that follow (or precede) a birth cue when the patient would be over
+    /// 89 at the reference date. Only the year is compared, so a year exactly 90 before the
+    /// reference year counts as over 89. Younger patients' birth years are left alone.
+    fn claim_birth_years(&self, regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for caps in regex.captures_iter(&text[gap_start..gap_end]) {
+                let Some(year) = caps.name("year").or_else(|| caps.name("year_before")) else {
+                    continue;
+                };
+                let value: i32 = year.as_str().parse().unwrap_or(self.reference_date.year);
+                if self.reference_date.year - value <= MAX_UNREDACTED_AGE {
+                    continue;
+                }
+                let token: Cow<'static, str> = match self.birth_year_mode {
+                    BirthYearMode::Redact => DATE_TOKEN.into(),
+                    BirthYearMode::Decade => format!("{}s", value - value % 10).into(),
+                };
+                found.push((gap_start + year.start(), gap_start + year.end(), token));
+            }
         }
 
-        if self.safe_harbor && !skip.contains(&Category::Ip) {
-            let (next, count) = replace_all(&self.ip_regex, &output, IP_TOKEN);
-            output = next;
-            stats.ip_addresses = count;
+        found
+            .into_iter()
+            .filter(|(start, end, token)| claims.claim(*start, *end, Category::Date, token.clone()))
+            .count()
+    }
+
+    /// Claims places smaller than a state: the city in a "City, ST" or "City, State" pair, the
+    /// place after a residence cue ("lives in Skokie", "moved from Peoria"), and named counties
+    /// ("Cook County"). A state code that doubles as a credential ("Smith, MD") counts only
+    /// before a ZIP code, and the ZIP itself is left to the ZIP detector. States are claimed
+    /// only with `redact_states`.
+    fn claim_geo(
+        &self,
+        context: &Regex,
+        city_state: &Regex,
+        county: &Regex,
+        text: &str,
+        claims: &mut Claims,
+    ) -> usize {
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            let gap = &text[gap_start..gap_end];
+            for caps in city_state.captures_iter(gap) {
+                let state = caps.name("state").expect("state group");
+                if AMBIGUOUS_STATE_CODES.contains(&state.as_str()) && caps.name("zip").is_none() {
+                    continue;
+                }
+                let city = caps.name("city").expect("city group");
+                let start = city.start() + leading_opener_len(city.as_str());
+                found.push((gap_start + start, gap_start + city.end()));
+                if self.redact_states {
+                    found.push((gap_start + state.start(), gap_start + state.end()));
+                }
+            }
+            for caps in context.captures_iter(gap) {
+                let place = caps.name("place").expect("place group");
+                if self.redact_states || !is_state_name(place.as_str()) {
+                    found.push((gap_start + place.start(), gap_start + place.end()));
+                }
+            }
+            for mat in county.find_iter(gap) {
+                let start = mat.start() + leading_opener_len(mat.as_str());
+                found.push((gap_start + start, gap_start + mat.end()));
+            }
         }
+        found.sort_unstable();
+
+        found
+            .into_iter()
+            .filter(|&(start, end)| claims.claim(start, end, Category::Geo, GEO_TOKEN))
+            .count()
+    }
 
-        if !skip.contains(&Category::Person) {
-            let mut person_total = 0;
-            if let Some(regex) = &self.name_dictionary_regex {
-                let (next, count) = replace_names(regex, &output, PERSON_TOKEN);
-                output = next;
-                person_total += count;
+    /// Claims full dates (or a regex's `date` group), shifted by `date_offset` when set and
+    /// otherwise rendered per `DateMode`. A date is still redacted as `[DATE]` when it would keep an age over
+    /// `MAX_UNREDACTED_AGE` recoverable: when the note names such a patient (`over_max_age`,
+    /// set by the age and birth-year detectors) or when the year itself is that long ago. A
+    /// date that does not parse is redacted too. Returns the number of dates claimed and how
+    /// many of those a date shift had to redact because they did not parse.
+    fn claim_dates(
+        &self,
+        regex: &Regex,
+        text: &str,
+        claims: &mut Claims,
+        over_max_age: bool,
+    ) -> (usize, usize) {
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for caps in regex.captures_iter(&text[gap_start..gap_end]) {
+                let mat = caps.name("date").or_else(|| caps.get(0)).expect("match");
+                let written = match (self.date_offset, self.date_mode) {
+                    (None, DateMode::Redact) => None,
+                    _ => {
+                        WrittenDate::parse(mat.as_str(), self.reference_date.year, self.date_order)
+                    }
+                };
+                let unparsed = written.is_none();
+                let written = written.filter(|written| {
+                    !over_max_age
+                        && self.reference_date.year - written.date.year <= MAX_UNREDACTED_AGE
+                });
+                let token: Cow<'static, str> = match (written, self.date_offset, self.date_mode) {
+                    (Some(written), Some(offset), _) => {
+                        written.render(written.date.add_days(offset)).into()
+                    }
+                    (Some(written), None, DateMode::Year) => written.date.year.to_string().into(),
+                    (Some(written), None, DateMode::Token) => {
+                        format!("[DATE:{}]", written.date.year).into()
+                    }
+                    _ => DATE_TOKEN.into(),
+                };
+                let fallback = unparsed && self.date_offset.is_some();
+                found.push((
+                    gap_start + mat.start(),
+                    gap_start + mat.end(),
+                    token,
+                    fallback,
+                ));
             }
+        }
 
-            let (next, count) = replace_names(&self.titled_name_regex, &output, PERSON_TOKEN);
-            output = next;
-            person_total += count;
+        let mut count = 0;
+        let mut fallbacks = 0;
+        for (start, end, token, fallback) in found {
+            if claims.claim(start, end, Category::Date, token) {
+                count += 1;
+                fallbacks += usize::from(fallback);
+            }
+        }
+        (count, fallbacks)
+    }
 
-            let (next, count) = replace_names(&self.first_last_regex, &output, PERSON_TOKEN);
-            output = next;
-            person_total += count;
+    /// Claims the number in an age over `MAX_UNREDACTED_AGE` ("92-year-old", "age 93",
+    /// "ninety-two-year-old", "(96 years)"), rendered per `AgeMode`. Ages of 89 and under are
+    /// left alone.
+    fn claim_ages(&self, regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+        let token = match self.age_mode {
+            AgeMode::Redact => AGE_TOKEN,
+            AgeMode::Generalize => AGE_GENERALIZED,
+        };
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for caps in regex.captures_iter(&text[gap_start..gap_end]) {
+                let Some(age) = caps
+                    .name("age")
+                    .or_else(|| caps.name("age_after"))
+                    .or_else(|| caps.name("age_paren"))
+                else {
+                    continue;
+                };
+                if parse_age(age.as_str()).is_some_and(|value| value > MAX_UNREDACTED_AGE) {
+                    found.push((gap_start + age.start(), gap_start + age.end()));
+                }
+            }
+        }
 
-            let (next, count) = replace_names(&self.capital_sequence_regex, &output, PERSON_TOKEN);
-            output = next;
-            person_total += count;
+        found
+            .into_iter()
+            .filter(|&(start, end)| claims.claim(start, end, Category::Age, token))
+            .count()
+    }
 
-            stats.persons = person_total;
+    /// Returns the number of pairs claimed and how many of those were rounded. A pair that
+    /// cannot be rounded is redacted instead.
+    fn claim_coordinates(&self, regex: &Regex, text: &str, claims: &mut Claims) -> (usize, usize) {
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for mat in regex.find_iter(&text[gap_start..gap_end]) {
+                let pair = mat.as_str();
+                let signed = !pair.contains(|c: char| "NSEWnsew".contains(c));
+                if signed && !is_signed_lat_long(pair) {
+                    continue;
+                }
+                let rounded = match self.coordinate_mode {
+                    CoordinateMode::Redact => None,
+                    CoordinateMode::Round { places } => round_coordinates(pair, places),
+                };
+                let token: Cow<'static, str> = match &rounded {
+                    Some(rounded) => rounded.clone().into(),
+                    None => COORD_TOKEN.into(),
+                };
+                found.push((
+                    gap_start + mat.start(),
+                    gap_start + mat.end(),
+                    token,
+                    rounded.is_some(),
+                ));
+            }
         }
 
-        if !skip.contains(&Category::Date) {
-            let (next, count) = replace_all(&self.date_regex, &output, DATE_TOKEN);
-            output = next;
-            stats.dates = count;
+        let mut counts = (0, 0);
+        for (start, end, token, rounded) in found {
+            if claims.claim(start, end, Category::Coordinate, token) {
+                counts.0 += 1;
+                counts.1 += usize::from(rounded);
+            }
         }
+        counts
+    }
 
-        if !skip.contains(&Category::RelativeDate) {
-            let (next, count) = replace_all(&self.relative_date_regex, &output, REL_DATE_TOKEN);
-            output = next;
-            stats.relative_dates = count;
+    fn claim_zips(&self, regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for mat in regex.find_iter(&text[gap_start..gap_end]) {
+                let token: Cow<'static, str> = match self.zip_mode {
+                    ZipMode::Redact => ZIP_TOKEN.into(),
+                    ZipMode::Truncate => truncate_zip(mat.as_str()).into(),
+                };
+                found.push((gap_start + mat.start(), gap_start + mat.end(), token));
+            }
         }
 
-        output = tidy_punctuation(&output);
-        (output, stats)
+        found
+            .into_iter()
+            .filter(|(start, end, token)| claims.claim(*start, *end, Category::Zip, token.clone()))
+            .count()
     }
-}
 
-fn replace_all(regex: &Regex, input: &str, replacement: &str) -> (String, usize) {
+    /// Claims ZIP codes spoken digit by digit after a "zip"/"zip code" label ("zip code six oh
+    /// six one four"). Only the digits are claimed, and only when they decode to 5 or 9 digits;
+    /// the decoded value is what `ZipMode::Truncate` keeps.
+    fn claim_spelled_zips(&self, regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for caps in regex.captures_iter(&text[gap_start..gap_end]) {
+                let digits = caps.name("digits").expect("digits group");
+                let decoded: String = digits
+                    .as_str()
+                    .split(|c: char| c.is_whitespace() || c == '-')
+                    .filter(|word| !word.is_empty())
+                    .filter_map(|word| {
+                        DIGIT_WORDS
+                            .iter()
+                            .find(|(name, _)| name.eq_ignore_ascii_case(word))
+                            .map(|&(_, digit)| digit)
+                    })
+                    .collect();
+                if decoded.len() != 5 && decoded.len() != 9 {
+                    continue;
+                }
+                let token: Cow<'static, str> = match self.zip_mode {
+                    ZipMode::Redact => ZIP_TOKEN.into(),
+                    ZipMode::Truncate => truncate_zip(&decoded).into(),
+                };
+                found.push((gap_start + digits.start(), gap_start + digits.end(), token));
+            }
+        }
+
+        found
+            .into_iter()
+            .filter(|(start, end, token)| claims.claim(*start, *end, Category::Zip, token.clone()))
+            .count()
+    }
+
+    /// Claims three-digit ZIP prefixes named as an area ("the 606 area", "area code 606").
+    /// In `AreaMode::Zip` they follow `ZipMode`, so a truncating mode leaves them as they are.
+    fn claim_area_prefixes(&self, regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+        let token = match (self.area_mode, self.zip_mode) {
+            (AreaMode::Geo, _) => GEO_TOKEN,
+            (AreaMode::Zip, ZipMode::Redact) => ZIP_TOKEN,
+            (AreaMode::Zip, ZipMode::Truncate) => return 0,
+        };
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for caps in regex.captures_iter(&text[gap_start..gap_end]) {
+                let prefix = caps
+                    .name("after")
+                    .or_else(|| caps.name("before"))
+                    .expect("prefix group");
+                found.push((gap_start + prefix.start(), gap_start + prefix.end()));
+            }
+        }
+
+        found
+            .into_iter()
+            .filter(|&(start, end)| claims.claim(start, end, Category::Zip, token))
+            .count()
+    }
+
+    /// Claims values after an SSN/MRN/DOB/phone label that only fit the label's format once OCR
+    /// look-alikes are read as digits ("478-29-1O36", "48291l0"). The longest run of up to three
+    /// space-separated parts that fits wins; the original characters are what get redacted.
+    fn claim_ocr_values(
+        &self,
+        regex: &Regex,
+        text: &str,
+        claims: &mut Claims,
+        stats: &mut ScrubStats,
+        skip: &HashSet<CategoryKey>,
+    ) {
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            let gap = &text[gap_start..gap_end];
+            for caps in regex.captures_iter(gap) {
+                let label = caps.name("label").expect("label group").as_str();
+                let value = caps.name("value").expect("value group");
+                let category = match label.to_ascii_uppercase().as_str() {
+                    "SSN" => Category::Ssn,
+                    "MRN" => Category::Mrn,
+                    "DOB" => Category::Date,
+                    _ => Category::Phone,
+                };
+                let fitting = value
+                    .as_str()
+                    .match_indices(' ')
+                    .map(|(index, _)| index)
+                    .chain([value.len()])
+                    .rev()
+                    .map(|len| value.as_str()[..len].trim_end_matches(['.', '-', '/']))
+                    .find(|candidate| {
+                        let next = gap[value.start() + candidate.len()..].chars().next();
+                        !next.is_some_and(char::is_alphanumeric)
+                            && self.fits_after_ocr(category, candidate)
+                    });
+                if let Some(candidate) = fitting {
+                    let start = gap_start + value.start();
+                    found.push((category, start, start + candidate.len()));
+                }
+            }
+        }
+
+        for (category, start, end) in found {
+            if !skip.contains(&CategoryKey::from(category))
+                && claims.claim(start, end, category, token_for(category))
+            {
+                stats.record(category, 1);
+            }
+        }
+    }
+
+    /// True when `value`, with O/o read as 0, l/I as 1, and S as 5, fits the format for
+    /// `category` and is mostly real digits to begin with.
+    fn fits_after_ocr(&self, category: Category, value: &str) -> bool {
+        let digits = value.bytes().filter(u8::is_ascii_digit).count();
+        let lookalikes = value.bytes().filter(|b| b"OoIlS".contains(b)).count();
+        if digits < lookalikes * 2 {
+            return false;
+        }
+        let read: String = value
+            .chars()
+            .map(|c| match c {
+                'O' | 'o' => '0',
+                'l' | 'I' => '1',
+                'S' => '5',
+                other => other,
+            })
+            .collect();
+        let only_digits: String = read.chars().filter(char::is_ascii_digit).collect();
+        match category {
+            Category::Ssn => OCR_SSN_RE.is_match(&read),
+            Category::Mrn => {
+                only_digits.len() == read.len() && self.mrn_lengths.contains(&read.len())
+            }
+            Category::Date => OCR_DATE_RE.is_match(&read),
+            _ => {
+                OCR_PHONE_RE.is_match(&read)
+                    && (only_digits.len() == 10
+                        || (only_digits.len() == 11 && only_digits.starts_with('1')))
+            }
+        }
+    }
+
+    /// Claims MRNs embedded in accession/barcode identifiers such as "SPEC-2024-4829130-01" or
+    /// "RAD^4829130^CT": any `^`, `-`, or `_` delimited segment that is an MRN-length digit run.
+    /// Depending on the configured mode only those segments or the whole identifier are claimed.
+    fn claim_composite_ids(&self, regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for mat in regex.find_iter(&text[gap_start..gap_end]) {
+                let base = gap_start + mat.start();
+                let mut segments = Vec::new();
+                let mut offset = 0;
+                for segment in mat.as_str().split(['^', '-', '_']) {
+                    if self.mrn_lengths.contains(&segment.len())
+                        && segment.bytes().all(|b| b.is_ascii_digit())
+                    {
+                        segments.push((base + offset, base + offset + segment.len()));
+                    }
+                    offset += segment.len() + 1;
+                }
+                if segments.is_empty() {
+                    continue;
+                }
+                match self.composite_ids {
+                    CompositeIdMode::Segment => found.extend(segments),
+                    CompositeIdMode::Whole => found.push((base, gap_start + mat.end())),
+                }
+            }
+        }
+
+        found
+            .into_iter()
+            .filter(|&(start, end)| claims.claim(start, end, Category::Mrn, MRN_TOKEN))
+            .count()
+    }
+
+    /// Claims capitalized common first names that stand alone in narrative text. A name
+    /// followed by an excluded lowercase word ("Mark the site") or sitting in a heading or
+    /// label ("Linda:") is never claimed; in cue-required mode the name must also follow a cue.
+    fn claim_narrative_first_names(&self, regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for caps in regex.captures_iter(&text[gap_start..gap_end]) {
+                let name = caps.name("name").expect("name group");
+                if self.first_name_alone == FirstNameMode::CueRequired && caps.name("cue").is_none()
+                {
+                    continue;
+                }
+                let start = gap_start + name.start();
+                let end = gap_start + name.end();
+                let next = text[end..]
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or("")
+                    .trim_end_matches(|c: char| !c.is_alphanumeric());
+                if FIRST_NAME_EXCLUDED_NEXT.contains(&next)
+                    || is_name_stopword(name.as_str(), &self.name_stoplist)
+                    || is_heading_position(text, start, end)
+                {
+                    continue;
+                }
+                found.push((start, end));
+            }
+        }
+
+        found
+            .into_iter()
+            .filter(|&(start, end)| claims.claim(start, end, Category::Person, PERSON_TOKEN))
+            .count()
+    }
+
+    /// Claims the name after a relationship cue ("daughter Megan drove him", "lives with
+    /// brother Tom Nguyen"), keeping the cue. A pronoun or stoplisted word is not a name, and
+    /// a second word is only kept when it is a name too.
+    fn claim_relative_names(&self, regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+        let is_name = |word: &str| {
+            !RELATIONSHIP_EXCLUDED.contains(&word) && !is_name_stopword(word, &self.name_stoplist)
+        };
+        let mut found = Vec::new();
+        for (gap_start, gap_end) in claims.gaps(text.len()) {
+            for caps in regex.captures_iter(&text[gap_start..gap_end]) {
+                let first = caps.name("first").expect("first group");
+                if !is_name(first.as_str()) {
+                    continue;
+                }
+                let end = match caps.name("last") {
+                    Some(last) if is_name(last.as_str()) => last.end(),
+                    _ => first.end(),
+                };
+                found.push((gap_start + first.start(), gap_start + end));
+            }
+        }
+
+        found
+            .into_iter()
+            .filter(|&(start, end)| claims.claim(start, end, Category::Person, PERSON_TOKEN))
+            .count()
+    }
+
+    fn is_dictionary_name(&self, candidate: &str) -> bool {
+        self.name_dictionary_regex
+            .as_ref()
+            .and_then(|regex| regex.find(candidate))
+            .is_some_and(|m| m.start() == 0 && m.end() == candidate.len())
+    }
+}
+
+/// A byte range of the normalized text that a detector has claimed. `token` is `None` for
+/// reserved ranges, which block later detectors but are rendered unchanged.
+struct Claim {
+    end: usize,
+    token: Option<(CategoryKey, Cow<'static, str>)>,
+    rule: ClaimRule,
+}
+
+/// The detector a claim came from, reported with its span.
+#[derive(Clone, Copy, Debug, Default)]
+struct ClaimRule {
+    name: &'static str,
+    confidence: Confidence,
+}
+
+/// Non-overlapping claims keyed by start offset. Detectors run in priority order against the
+/// same normalized text; the first detector to claim a range wins, and replacements are only
+/// rendered once every detector has run.
+#[derive(Default)]
+struct Claims {
+    ranges: BTreeMap<usize, Claim>,
+    /// The detector now running; its claims are tagged with it.
+    rule: ClaimRule,
+}
+
+impl Claims {
+    fn is_free(&self, start: usize, end: usize) -> bool {
+        match self.ranges.range(..end).next_back() {
+            Some((_, claim)) => claim.end <= start,
+            None => true,
+        }
+    }
+
+    fn claim(
+        &mut self,
+        start: usize,
+        end: usize,
+        category: impl Into<CategoryKey>,
+        token: impl Into<Cow<'static, str>>,
+    ) -> bool {
+        if start >= end || !self.is_free(start, end) {
+            return false;
+        }
+        self.ranges.insert(
+            start,
+            Claim {
+                end,
+                token: Some((category.into(), token.into())),
+                rule: self.rule,
+            },
+        );
+        true
+    }
+
+    fn reserve(&mut self, start: usize, end: usize) {
+        if start < end && self.is_free(start, end) {
+            let rule = self.rule;
+            self.ranges.insert(
+                start,
+                Claim {
+                    end,
+                    token: None,
+                    rule,
+                },
+            );
+        }
+    }
+
+    /// Removes every claim starting in `start..end` and returns the categories of the
+    /// redactions among them.
+    fn take_range(&mut self, start: usize, end: usize) -> Vec<CategoryKey> {
+        let starts: Vec<usize> = self.ranges.range(start..end).map(|(&at, _)| at).collect();
+        starts
+            .into_iter()
+            .filter_map(|at| self.ranges.remove(&at)?.token)
+            .map(|(category, _)| category)
+            .collect()
+    }
+
+    /// Unclaimed ranges between existing claims. Detectors search each gap on its own, which
+    /// matches what they would see if earlier claims had already been replaced by tokens.
+    fn gaps(&self, len: usize) -> Vec<(usize, usize)> {
+        let mut gaps = Vec::with_capacity(self.ranges.len() + 1);
+        let mut cursor = 0;
+        for (&start, claim) in &self.ranges {
+            if start > cursor {
+                gaps.push((cursor, start));
+            }
+            cursor = claim.end;
+        }
+        if cursor < len {
+            gaps.push((cursor, len));
+        }
+        gaps
+    }
+
+    /// Renders every token, substituting any override in `tokens`, and reports each
+    /// redaction's category, the rule that claimed it, its entity number, its range in `text`,
+    /// and its range in the rendered output to `on_redaction`. With `numbered`, bracket tokens
+    /// become `[PERSON_1]`, `[PERSON_2]`, ... numbered per category by first appearance of the
+    /// entity as it keys it; other replacement text (shifted dates, kept years) is left as is.
+    fn render<F>(
+        &self,
+        text: &str,
+        tokens: &BTreeMap<CategoryKey, String>,
+        numbered: Option<&EntityKeys>,
+        mut on_redaction: F,
+    ) -> (String, Alignment)
+    where
+        F: FnMut(&CategoryKey, ClaimRule, Option<usize>, Range<usize>, Range<usize>),
+    {
+        let mut rewriter = Rewriter::new(text);
+        let mut entities: HashMap<&CategoryKey, HashMap<String, usize>> = HashMap::new();
+        for (&start, claim) in &self.ranges {
+            match &claim.token {
+                Some((category, token)) => {
+                    let mut token =
+                        Cow::Borrowed(tokens.get(category).map_or(token.as_ref(), String::as_str));
+                    let mut index = None;
+                    if let Some(keys) =
+                        numbered.filter(|_| token.starts_with('[') && token.ends_with(']'))
+                    {
+                        let seen = entities.entry(category).or_default();
+                        let next = seen.len() + 1;
+                        let number = *seen
+                            .entry(keys.key(category, &text[start..claim.end]))
+                            .or_insert(next);
+                        token = Cow::Owned(format!("{}_{}]", &token[..token.len() - 1], number));
+                        index = Some(number);
+                    }
+                    rewriter.copy_to(start);
+                    let out_start = rewriter.output_len();
+                    rewriter.replace(start, claim.end, &token, SegmentKind::Redacted);
+                    on_redaction(
+                        category,
+                        claim.rule,
+                        index,
+                        start..claim.end,
+                        out_start..rewriter.output_len(),
+                    );
+                }
+                None => rewriter.copy_to(claim.end),
+            }
+        }
+        rewriter.finish()
+    }
+}
+
+/// Replaces a whole line or sentence with the segment token when its redactions span more
+/// than `threshold` categories or cover more than `density` of its non-space characters.
+/// Segments widen to take in any claim that straddles their edges.
+fn apply_segment_fallback(
+    fallback: &SegmentFallback,
+    text: &str,
+    sentence_starts: &[usize],
+    claims: &mut Claims,
+    stats: &mut ScrubStats,
+) {
+    let mut bounds: Vec<usize> = match fallback.mode {
+        SegmentMode::Line => text.match_indices('\n').map(|(at, _)| at + 1).collect(),
+        SegmentMode::Sentence => sentence_starts.to_vec(),
+    };
+    bounds.retain(|&at| at > 0 && at < text.len());
+    bounds.insert(0, 0);
+    bounds.push(text.len());
+    bounds.dedup();
+
+    let mut cursor = 0;
+    for window in bounds.windows(2) {
+        if window[1] <= cursor {
+            continue;
+        }
+        // The previous segment already took in any claim straddling `cursor`.
+        let start = cursor;
+        let end = match claims.ranges.range(..window[1]).next_back() {
+            Some((_, claim)) => window[1].max(claim.end),
+            None => window[1],
+        };
+        cursor = end;
+
+        let segment = &text[start..end];
+        let start = start + (segment.len() - segment.trim_start().len());
+        let end = end - (segment.len() - segment.trim_end().len());
+        if start >= end {
+            continue;
+        }
+        let visible =
+            |range: Range<usize>| text[range].chars().filter(|c| !c.is_whitespace()).count();
+        let mut categories = HashSet::new();
+        let mut redacted = 0;
+        for (&at, claim) in claims.ranges.range(start..end) {
+            if let Some((category, _)) = &claim.token {
+                categories.insert(category);
+                redacted += visible(at..claim.end);
+            }
+        }
+        let dense = fallback
+            .density
+            .is_some_and(|density| redacted as f64 > density * visible(start..end) as f64);
+        if categories.len() <= fallback.threshold && !dense {
+            continue;
+        }
+        for category in claims.take_range(start, end) {
+            stats.subsume(&category);
+        }
+        claims.claim(start, end, Category::Segment, SEGMENT_TOKEN);
+        stats.record(Category::Segment, 1);
+    }
+}
+
+/// Claims capitalized word runs as person names. Capitalization at the start of a sentence is
+/// not evidence of a name, so a leading sentence-opener word there ("Seen Jane Doe") is dropped
+/// and the rest must still be at least two words.
+fn claim_capital_sequences(
+    regex: &Regex,
+    text: &str,
+    sentence_starts: &[usize],
+    stoplist: &HashSet<String>,
+    claims: &mut Claims,
+) -> usize {
+    let mut candidates = Vec::new();
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        for mat in regex.find_iter(&text[gap_start..gap_end]) {
+            if is_name_stopword(mat.as_str(), stoplist) {
+                continue;
+            }
+            let start = gap_start + mat.start();
+            let mut end = gap_start + mat.end();
+            // A last word that prefixes a number ("Encounter ENC-2024-00123") starts an ID.
+            if text[end..].starts_with('-')
+                && text[end + 1..].starts_with(|c: char| c.is_ascii_digit())
+            {
+                end = start
+                    + mat
+                        .as_str()
+                        .trim_end_matches(|c: char| !c.is_whitespace())
+                        .trim_end()
+                        .len();
+                if text[start..end].split_whitespace().count() < 2 {
+                    continue;
+                }
+            }
+            let candidate = &text[start..end];
+            let mut words = candidate.split_whitespace();
+            let first = words.next().unwrap_or("");
+            if segmentation::is_sentence_start(sentence_starts, start)
+                && SENTENCE_OPENERS.contains(&first)
+            {
+                if words.count() < 2 {
+                    continue;
+                }
+                let rest = candidate[first.len()..].trim_start();
+                candidates.push((end - rest.len(), end));
+            } else {
+                candidates.push((start, end));
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|&(start, end)| claims.claim(start, end, Category::Person, PERSON_TOKEN))
+        .count()
+}
+
+/// Claims web links, including bare hosts such as "mychart.hospital.org/visit/12345". A host
+/// right after "@" is the domain of an email address (left alone when emails are skipped), and
+/// a link never takes the punctuation that ends its sentence.
+fn claim_urls(regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+    let mut found = Vec::new();
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        for mat in regex.find_iter(&text[gap_start..gap_end]) {
+            let start = gap_start + mat.start();
+            if text[..start].ends_with(['@', '.', '/', '\\']) {
+                continue;
+            }
+            found.push((start, gap_start + mat.end()));
+        }
+    }
+
+    found
+        .into_iter()
+        .filter(|&(start, end)| claims.claim(start, end, Category::Url, URL_TOKEN))
+        .count()
+}
+
+/// Claims the capitalized phrase after an employment or school cue ("works at Caterpillar in
+/// Peoria", "teacher at Lincoln Elementary School."). A lone acronym of up to three letters is
+/// more often a program ("attends AA", "attends PT") and is left alone.
+fn claim_employers(regex: &Regex, text: &str, claims: &mut Claims) -> usize {
+    let mut found = Vec::new();
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        for caps in regex.captures_iter(&text[gap_start..gap_end]) {
+            let employer = caps.name("employer").expect("employer group");
+            let phrase = employer.as_str();
+            if phrase.len() <= 3 && !phrase.chars().any(char::is_lowercase) {
+                continue;
+            }
+            found.push((gap_start + employer.start(), gap_start + employer.end()));
+        }
+    }
+
+    found
+        .into_iter()
+        .filter(|&(start, end)| claims.claim(start, end, Category::Employer, EMPLOYER_TOKEN))
+        .count()
+}
+
+/// Claims social profile links, then @handles named with their platform ("@jsmith_chicago on
+/// Instagram", "Reddit u/jsmith"). Only the handle is claimed, and an "@" inside a word
+/// belongs to an email address instead.
+fn claim_handles(handle: &Regex, profile: &Regex, text: &str, claims: &mut Claims) -> usize {
+    let mut count = claim_all_filtered(profile, text, claims, &Category::Handle.into(), |_| true);
+    let mut found = Vec::new();
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        for caps in handle.captures_iter(&text[gap_start..gap_end]) {
+            let Some(name) = caps.name("before").or_else(|| caps.name("after")) else {
+                continue;
+            };
+            let start = gap_start + name.start();
+            if text[..start].ends_with(|c: char| c.is_alphanumeric()) {
+                continue;
+            }
+            found.push((start, gap_start + name.end()));
+        }
+    }
+    count += found
+        .into_iter()
+        .filter(|&(start, end)| claims.claim(start, end, Category::Handle, HANDLE_TOKEN))
+        .count();
+    count
+}
+
+/// Claims NPI numbers after an "NPI" label and DEA registration numbers anywhere, each only
+/// when its check digit is valid, then labeled license and state ID numbers. Requiring the
+/// check digit keeps accession numbers and order codes of the same shape out of this category.
+fn claim_licenses(
+    labeled: &Regex,
+    dea: &Regex,
+    npi: &Regex,
+    text: &str,
+    claims: &mut Claims,
+) -> usize {
+    let mut found = Vec::new();
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        let gap = &text[gap_start..gap_end];
+        for caps in npi.captures_iter(gap) {
+            let whole = caps.get(0).expect("match");
+            if is_valid_npi(&caps["npi"]) {
+                found.push((gap_start + whole.start(), gap_start + whole.end()));
+            }
+        }
+        for mat in dea.find_iter(gap) {
+            if is_valid_dea(mat.as_str()) {
+                found.push((gap_start + mat.start(), gap_start + mat.end()));
+            }
+        }
+    }
+    let mut count = found
+        .into_iter()
+        .filter(|&(start, end)| claims.claim(start, end, Category::License, LICENSE_TOKEN))
+        .count();
+    count += claim_all_filtered(labeled, text, claims, &Category::License.into(), |_| true);
+    count
+}
+
+static COORDINATE_COMPONENT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"-?\d+\.\d+").expect("coordinate component regex"));
+
+/// True when a pair without hemisphere letters is a plausible latitude then longitude.
+fn is_signed_lat_long(pair: &str) -> bool {
+    let values: Vec<f64> = COORDINATE_COMPONENT_RE
+        .find_iter(pair)
+        .filter_map(|mat| mat.as_str().parse().ok())
+        .collect();
+    matches!(values.as_slice(), [lat, long] if lat.abs() <= 90.0 && long.abs() <= 180.0)
+}
+
+/// Rounds each component of a pair to `places` decimals (never adding precision), leaving signs,
+/// degree symbols, hemisphere letters, and separators as written. `None` unless exactly two
+/// components parse.
+fn round_coordinates(pair: &str, places: u8) -> Option<String> {
+    let components: Vec<_> = COORDINATE_COMPONENT_RE.find_iter(pair).collect();
+    if components.len() != 2 {
+        return None;
+    }
+    let mut out = String::with_capacity(pair.len());
+    let mut last = 0;
+    for component in components {
+        let value: f64 = component.as_str().parse().ok()?;
+        let decimals = component.as_str().len() - component.as_str().find('.')? - 1;
+        let places = decimals.min(usize::from(places));
+        out.push_str(&pair[last..component.start()]);
+        out.push_str(&format!("{:.*}", places, value));
+        last = component.end();
+    }
+    out.push_str(&pair[last..]);
+    Some(out)
+}
+
+/// Claims whole UDIs first, so a serial inside one is never left half redacted, then labeled
+/// serials. A labeled claim covers its label, like the other labeled IDs.
+fn claim_devices(
+    labeled: &Regex,
+    gs1: &Regex,
+    hibcc: &Regex,
+    text: &str,
+    claims: &mut Claims,
+) -> usize {
+    let mut found = Vec::new();
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        let gap = &text[gap_start..gap_end];
+        let mut resume = 0;
+        for mat in gs1.find_iter(gap) {
+            if mat.start() < resume {
+                continue;
+            }
+            if let Some(end) = gs1_udi_end(gap, mat.start()) {
+                found.push((gap_start + mat.start(), gap_start + end));
+                resume = end;
+            }
+        }
+        for mat in hibcc.find_iter(gap) {
+            // The check character may itself be a period, so a sentence-ending one is only
+            // dropped when the code does not check out with it.
+            let candidate = mat.as_str();
+            let trimmed = candidate.strip_suffix('.').unwrap_or(candidate);
+            if let Some(code) = [candidate, trimmed].into_iter().find(|c| is_valid_hibcc(c)) {
+                found.push((
+                    gap_start + mat.start(),
+                    gap_start + mat.start() + code.len(),
+                ));
+            }
+        }
+    }
+    let mut count = found
+        .into_iter()
+        .filter(|&(start, end)| claims.claim(start, end, Category::Device, DEVICE_TOKEN))
+        .count();
+
+    let mut found = Vec::new();
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        for caps in labeled.captures_iter(&text[gap_start..gap_end]) {
+            if caps["serial"].contains(|c: char| c.is_ascii_digit()) {
+                let whole = caps.get(0).expect("match");
+                found.push((gap_start + whole.start(), gap_start + whole.end()));
+            }
+        }
+    }
+    count += found
+        .into_iter()
+        .filter(|&(start, end)| claims.claim(start, end, Category::Device, DEVICE_TOKEN))
+        .count();
+    count
+}
+
+/// Parses a GS1 UDI starting at `start` and returns where it ends. The device identifier (AI 01)
+/// must come first with a valid GTIN-14 check digit.
+///
+/// In the parenthesized form every `(AI)value` element that follows is included, whatever the
+/// AI. In the flat form the elements have no delimiters and variable-length values (lot,
+/// serial) run to the end of the token, so the whole token is included.
+fn gs1_udi_end(text: &str, start: usize) -> Option<usize> {
+    let rest = &text[start..];
+    if let Some(after) = rest.strip_prefix("(01)") {
+        let after = after.strip_prefix(' ').unwrap_or(after);
+        let gtin = after.get(..14)?;
+        if !is_valid_gtin(gtin) {
+            return None;
+        }
+        let mut end = rest.len() - after.len() + 14;
+        loop {
+            let tail = &rest[end..];
+            let spaced = tail.strip_prefix(' ').unwrap_or(tail);
+            let Some(ai_len) = spaced
+                .strip_prefix('(')
+                .and_then(|inner| inner.find(')'))
+                .filter(|&len| (2..=4).contains(&len))
+            else {
+                break;
+            };
+            if !spaced[1..1 + ai_len].bytes().all(|b| b.is_ascii_digit()) {
+                break;
+            }
+            let value = &spaced[ai_len + 2..];
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            let value_len = udi_token_len(value, true);
+            if value_len == 0 {
+                break;
+            }
+            end = rest.len() - value.len() + value_len;
+        }
+        Some(start + end)
+    } else {
+        let gtin = rest.get(2..16)?;
+        if !is_valid_gtin(gtin) {
+            return None;
+        }
+        Some(start + udi_token_len(rest, false).max(16))
+    }
+}
+
+/// Length of the UDI element at the front of `text`: up to whitespace (or the next `(` in the
+/// parenthesized form), without trailing sentence punctuation.
+fn udi_token_len(text: &str, parenthesized: bool) -> usize {
+    let len = text
+        .find(|c: char| c.is_whitespace() || (parenthesized && c == '('))
+        .unwrap_or(text.len());
+    text[..len]
+        .trim_end_matches(['.', ',', ';', ':', ')'])
+        .len()
+}
+
+/// GTIN-14: digits weighted 3, 1, 3, ... from the right of the body, with the last digit
+/// bringing the sum to a multiple of 10.
+fn is_valid_gtin(candidate: &str) -> bool {
+    if candidate.len() != 14 || !candidate.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    let digits: Vec<u32> = candidate.bytes().map(|b| u32::from(b - b'0')).collect();
+    let sum: u32 = digits[..13]
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { digit * 3 } else { *digit })
+        .sum();
+    (sum + digits[13]).is_multiple_of(10)
+}
+
+/// HIBCC UDIs: `+`, a four-character labeler code, product and unit of measure, an optional
+/// `/` secondary (lot, expiry, serial), and a final mod-43 check character over the rest.
+fn is_valid_hibcc(candidate: &str) -> bool {
+    const CHARSET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-. $/+%";
+    let Some((&check, body)) = candidate.as_bytes().split_last() else {
+        return false;
+    };
+    let mut sum = 0;
+    for byte in body {
+        match CHARSET.iter().position(|c| c == byte) {
+            Some(value) => sum += value,
+            None => return false,
+        }
+    }
+    CHARSET[sum % 43] == check
+}
+
+/// Claims VINs that pass `is_valid_vin` and labeled plates of 2-8 characters with a digit; a
+/// plate claim covers its label, like the other labeled IDs.
+fn claim_vehicles(vin: &Regex, plate: &Regex, text: &str, claims: &mut Claims) -> usize {
+    let mut found = Vec::new();
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        let gap = &text[gap_start..gap_end];
+        for mat in vin.find_iter(gap) {
+            if is_valid_vin(mat.as_str()) {
+                found.push((gap_start + mat.start(), gap_start + mat.end()));
+            }
+        }
+        for caps in plate.captures_iter(gap) {
+            let whole = caps.get(0).expect("match");
+            let characters = caps["plate"]
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .count();
+            if (2..=8).contains(&characters) && caps["plate"].contains(|c: char| c.is_ascii_digit())
+            {
+                found.push((gap_start + whole.start(), gap_start + whole.end()));
+            }
+        }
+    }
+    found
+        .into_iter()
+        .filter(|&(start, end)| claims.claim(start, end, Category::Vehicle, VEHICLE_TOKEN))
+        .count()
+}
+
+/// ISO 3779 VINs: letters transliterate to digits, each position is weighted, and the ninth
+/// character is the weighted sum mod 11 (`X` for 10).
+fn is_valid_vin(candidate: &str) -> bool {
+    const WEIGHTS: [u32; 17] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'0'..=b'9' => Some(u32::from(c - b'0')),
+            b'A'..=b'H' => Some(u32::from(c - b'A') + 1),
+            b'J'..=b'N' => Some(u32::from(c - b'J') + 1),
+            b'P' => Some(7),
+            b'R' => Some(9),
+            b'S'..=b'Z' => Some(u32::from(c - b'S') + 2),
+            _ => None,
+        }
+    }
+    let bytes = candidate.as_bytes();
+    if bytes.len() != 17 {
+        return false;
+    }
+    let mut sum = 0;
+    for (&byte, weight) in bytes.iter().zip(WEIGHTS) {
+        match value(byte) {
+            Some(value) => sum += value * weight,
+            None => return false,
+        }
+    }
+    let expected = match sum % 11 {
+        10 => b'X',
+        digit => b'0' + digit as u8,
+    };
+    bytes[8] == expected
+}
+
+/// DEA numbers: a registrant-type letter, the registrant's initial (or 9), and seven digits
+/// whose last is `(d1 + d3 + d5 + 2 * (d2 + d4 + d6)) % 10`.
+fn is_valid_dea(candidate: &str) -> bool {
+    let bytes = candidate.as_bytes();
+    if bytes.len() != 9 || !b"ABCDEFGHJKLMPRSTUX".contains(&bytes[0]) {
+        return false;
+    }
+    let digits: Vec<u32> = bytes[2..].iter().map(|b| u32::from(b - b'0')).collect();
+    let sum = digits[0] + digits[2] + digits[4] + 2 * (digits[1] + digits[3] + digits[5]);
+    sum % 10 == digits[6]
+}
+
+/// NPI numbers: ten digits starting with 1 or 2 that pass the Luhn check with the "80840"
+/// health-industry prefix.
+fn is_valid_npi(candidate: &str) -> bool {
+    if candidate.len() != 10 || !candidate.starts_with(['1', '2']) {
+        return false;
+    }
+    let digits = "80840".chars().chain(candidate.chars());
+    let sum: u32 = digits
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(index, digit)| {
+            if index % 2 == 1 {
+                let doubled = digit * 2;
+                doubled / 10 + doubled % 10
+            } else {
+                digit
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Claims IPv6 addresses (full, compressed, IPv4-mapped, with an optional "%zone") and then
+/// dotted-quad IPv4 addresses. Candidates must parse as addresses, so times such as "10:22:01"
+/// and quads like "999.999.1.1" are left alone. A quad that is part of a longer dotted run or
+/// reads as a version ("v2.4.1.1", "Epic 2.4.1.1", "build 10.0.19045.1") is not an address.
+fn claim_ips(v6: &Regex, v4: &Regex, text: &str, claims: &mut Claims) -> usize {
     let mut count = 0;
-    let result = regex.replace_all(input, |_: &Captures| {
-        count += 1;
-        replacement
-    });
-    (result.into_owned(), count)
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        let gap = &text[gap_start..gap_end];
+        for mat in v6.find_iter(gap) {
+            let attached = gap[..mat.start()]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == ':')
+                || gap[mat.end()..]
+                    .chars()
+                    .next()
+                    .is_some_and(char::is_alphanumeric);
+            if attached {
+                continue;
+            }
+            if let Some(len) = ipv6_len(mat.as_str()) {
+                let start = gap_start + mat.start();
+                if claims.claim(start, start + len, Category::Ip, IP_TOKEN) {
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        let gap = &text[gap_start..gap_end];
+        for mat in v4.find_iter(gap) {
+            let before = &text[..gap_start + mat.start()];
+            let after = &gap[mat.end()..];
+            let dotted_run = before.ends_with('.')
+                || (after.starts_with('.') && after[1..].starts_with(|c: char| c.is_ascii_digit()));
+            if dotted_run
+                || is_version_context(before)
+                || mat.as_str().parse::<std::net::Ipv4Addr>().is_err()
+            {
+                continue;
+            }
+            let start = gap_start + mat.start();
+            if claims.claim(start, start + mat.len(), Category::Ip, IP_TOKEN) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Length of the IPv6 address at the start of `candidate`, without sentence punctuation after
+/// it, or `None` if it is not one.
+fn ipv6_len(candidate: &str) -> Option<usize> {
+    let trimmed = candidate.trim_end_matches('.');
+    let (address, zone) = match trimmed.split_once('%') {
+        Some((address, zone)) => (address, Some(zone)),
+        None => (trimmed, None),
+    };
+    if zone.is_some_and(str::is_empty)
+        || !address.contains(|c: char| c.is_ascii_hexdigit())
+        || address.parse::<std::net::Ipv6Addr>().is_err()
+    {
+        return None;
+    }
+    Some(trimmed.len())
 }
 
-fn replace_names(regex: &Regex, input: &str, replacement: &str) -> (String, usize) {
-    replace_all_filtered(regex, input, replacement, |candidate| {
-        !is_name_stopword(candidate)
+/// Words that make a following dotted quad a version number rather than an address.
+const VERSION_CUES: &[&str] = &[
+    "version",
+    "ver",
+    "v",
+    "build",
+    "release",
+    "rev",
+    "revision",
+    "update",
+    "patch",
+    "firmware",
+    "epic",
+    "cerner",
+    "meditech",
+    "allscripts",
+    "citrix",
+    "windows",
+    "ios",
+    "android",
+    "macos",
+    "chrome",
+    "firefox",
+    "java",
+    "python",
+];
+
+fn is_version_context(before: &str) -> bool {
+    let word = before
+        .trim_end_matches([' ', '\t', ':', '.'])
+        .rsplit(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or("");
+    VERSION_CUES
+        .iter()
+        .any(|cue| cue.eq_ignore_ascii_case(word))
+}
+
+/// Attributes each phone-type label ("Ph", "Fax", "Pager") to the nearest number after it on
+/// the same line and before the next label, so multi-number lines get distinct tokens. Numbers
+/// with no label are left for the plain phone detector. A number whose category is skipped is
+/// reserved so it is not redacted as a phone instead.
+fn claim_labeled_numbers(
+    labels: &Regex,
+    number: &Regex,
+    text: &str,
+    claims: &mut Claims,
+    stats: &mut ScrubStats,
+    skip: &HashSet<CategoryKey>,
+) {
+    let mut found = Vec::new();
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        let gap = &text[gap_start..gap_end];
+        let found_labels: Vec<_> = labels.captures_iter(gap).collect();
+        for (index, caps) in found_labels.iter().enumerate() {
+            let label = caps.get(0).expect("whole match");
+            let category = if caps.name("fax").is_some() {
+                Category::Fax
+            } else if caps.name("pager").is_some() {
+                Category::Pager
+            } else {
+                Category::Phone
+            };
+            let next_label = found_labels
+                .get(index + 1)
+                .map_or(gap.len(), |next| next.get(0).expect("whole match").start());
+            let line_end = gap[label.end()..]
+                .find('\n')
+                .map_or(gap.len(), |i| label.end() + i);
+            let window = &gap[label.end()..next_label.min(line_end)];
+            let short_allowed = category == Category::Pager || caps.name("callback").is_some();
+            let Some(mat) = number.captures_iter(window).find_map(|caps| {
+                caps.name("phone").or_else(|| {
+                    caps.name("short")
+                        .filter(|_| short_allowed)
+                        .filter(|short| is_short_callback(window, short.range()))
+                })
+            }) else {
+                continue;
+            };
+            let start = gap_start + label.end() + mat.start();
+            found.push((category, start, start + mat.len()));
+        }
+    }
+
+    for (category, start, end) in found {
+        if skip.contains(&CategoryKey::from(category)) {
+            claims.reserve(start, end);
+        } else if claims.claim(start, end, category, token_for(category)) {
+            stats.record(category, 1);
+        }
+    }
+}
+
+/// Whether `place` is a US state or territory name ("New York", "new  york").
+fn is_state_name(place: &str) -> bool {
+    let place: Vec<&str> = place.split_whitespace().collect();
+    US_STATES.iter().any(|(name, _)| {
+        name.split_whitespace()
+            .map(str::to_ascii_lowercase)
+            .eq(place.iter().map(|word| word.to_ascii_lowercase()))
     })
 }
 
-fn replace_all_filtered<F>(
+/// The length of the sentence openers ("Seen in") that start a capitalized `phrase`, so a
+/// claim can skip past them.
+fn leading_opener_len(phrase: &str) -> usize {
+    let mut offset = 0;
+    for word in phrase.split_inclusive([' ', '\t']) {
+        if !SENTENCE_OPENERS.contains(&word.trim_end()) {
+            break;
+        }
+        offset += word.len();
+    }
+    offset
+}
+
+/// A seed for a run that sets none: std's hasher keys are drawn from OS entropy.
+fn entropy_seed() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+}
+
+/// The number of days `shift` moves every date: never zero, at most `max_days` (default 365)
+/// either way, and the same for every run with the same seed (FNV-1a of its bytes). `seed` is
+/// used when `shift` has none of its own.
+fn date_offset(shift: DateShift, seed: u64) -> i64 {
+    let max_days = i64::from(shift.max_days.unwrap_or(365).max(1));
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in shift.seed.unwrap_or(seed).to_le_bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    let offset = (hash % (2 * max_days as u64)) as i64 - max_days;
+    if offset >= 0 {
+        offset + 1
+    } else {
+        offset
+    }
+}
+
+/// The first three digits of a ZIP code followed by `**`, or `000**` for a sparsely populated
+/// prefix.
+fn truncate_zip(digits: &str) -> String {
+    let prefix = &digits[..3];
+    if RESTRICTED_ZIP_PREFIXES.contains(&prefix) {
+        "000**".to_string()
+    } else {
+        format!("{}**", prefix)
+    }
+}
+
+/// The value of an age written in digits or as `AGE_WORDS` ("ninety-two", "one hundred one").
+fn parse_age(value: &str) -> Option<i32> {
+    if let Ok(age) = value.parse() {
+        return Some(age);
+    }
+    let value = value.to_ascii_lowercase();
+    let base = if value.starts_with("ninety") { 90 } else { 100 };
+    let unit = [
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    ]
+    .iter()
+    .position(|unit| value.ends_with(unit) && !value.ends_with("ninety"))
+    .map_or(0, |index| index as i32 + 1);
+    Some(base + unit)
+}
+
+/// A short number counts only right after its label ("page 31042", "pager #5-1234"), so a
+/// later count on the line is not taken for it, and never as pagination ("page 1204 of 1310").
+fn is_short_callback(window: &str, short: Range<usize>) -> bool {
+    let before = &window[..short.start];
+    let after = window[short.end..].trim_start();
+    before
+        .chars()
+        .all(|c| c.is_whitespace() || matches!(c, '#' | ':'))
+        && !after
+            .get(..2)
+            .is_some_and(|word| word.eq_ignore_ascii_case("of"))
+}
+
+fn claim_all_filtered<F>(
     regex: &Regex,
-    input: &str,
-    replacement: &str,
-    mut should_replace: F,
-) -> (String, usize)
+    text: &str,
+    claims: &mut Claims,
+    category: &CategoryKey,
+    mut should_claim: F,
+) -> usize
 where
     F: FnMut(&str) -> bool,
 {
+    let token = key_token(category);
+    let mut count = 0;
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        for mat in regex.find_iter(&text[gap_start..gap_end]) {
+            let (start, end) = (gap_start + mat.start(), gap_start + mat.end());
+            if should_claim(mat.as_str())
+                && claims.claim(start, end, category.clone(), token.clone())
+            {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Claims each match of a custom pattern, after the built-in detectors. A match takes over the
+/// redactions inside it, so a site ID is not left in pieces ("ENC-2024-[ZIP]"), but not one a
+/// built-in made of the whole match, such as a bare phone number or date, nor one running past
+/// its edges, nor allowlisted text.
+fn claim_custom(
+    regex: &Regex,
+    text: &str,
+    claims: &mut Claims,
+    stats: &mut ScrubStats,
+    category: &CategoryKey,
+) -> usize {
+    let token = key_token(category);
     let mut count = 0;
-    let result = regex.replace_all(input, |caps: &Captures| {
-        let mat = caps.get(0).map(|m| m.as_str()).unwrap_or("");
-        if should_replace(mat) {
+    for mat in regex.find_iter(text) {
+        let (start, end) = (mat.start(), mat.end());
+        let straddled = claims
+            .ranges
+            .range(..start)
+            .next_back()
+            .is_some_and(|(_, claim)| claim.end > start);
+        let blocked = claims.ranges.range(start..end).any(|(&at, claim)| {
+            claim.end > end || claim.token.is_none() || (at == start && claim.end == end)
+        });
+        if start == end || straddled || blocked {
+            continue;
+        }
+        for taken in claims.take_range(start, end) {
+            stats.retract(&taken);
+        }
+        if claims.claim(start, end, category.clone(), token.clone()) {
             count += 1;
-            replacement.to_string()
-        } else {
-            mat.to_string()
         }
-    });
-    (result.into_owned(), count)
+    }
+    count
+}
+
+fn reserve_all(regex: &Regex, text: &str, claims: &mut Claims) {
+    for (gap_start, gap_end) in claims.gaps(text.len()) {
+        for mat in regex.find_iter(&text[gap_start..gap_end]) {
+            claims.reserve(gap_start + mat.start(), gap_start + mat.end());
+        }
+    }
 }
 
-fn build_dictionary(defaults: &[&str], overrides: &[String]) -> Vec<String> {
-    let mut set: HashSet<String> = defaults.iter().map(|s| s.to_string()).collect();
+fn key_token(category: &CategoryKey) -> Cow<'static, str> {
+    match category {
+        CategoryKey::Builtin(category) => Cow::Borrowed(token_for(*category)),
+        CategoryKey::Custom(_) => Cow::Owned(category.default_token().unwrap_or_default()),
+    }
+}
+
+fn token_for(category: Category) -> &'static str {
+    match category {
+        Category::Email => EMAIL_TOKEN,
+        Category::Phone => PHONE_TOKEN,
+        Category::Fax => FAX_TOKEN,
+        Category::Pager => PAGER_TOKEN,
+        Category::Date => DATE_TOKEN,
+        Category::RelativeDate => REL_DATE_TOKEN,
+        Category::Age => AGE_TOKEN,
+        Category::Ssn => SSN_TOKEN,
+        Category::Mrn => MRN_TOKEN,
+        Category::Account => ACCOUNT_TOKEN,
+        Category::Zip => ZIP_TOKEN,
+        Category::Person => PERSON_TOKEN,
+        Category::Facility => FACILITY_TOKEN,
+        Category::Employer => EMPLOYER_TOKEN,
+        Category::Address => ADDRESS_TOKEN,
+        Category::Geo => GEO_TOKEN,
+        Category::Coordinate => COORD_TOKEN,
+        Category::Url => URL_TOKEN,
+        Category::Handle => HANDLE_TOKEN,
+        Category::Insurance => INSURANCE_TOKEN,
+        Category::Mbi => MBI_TOKEN,
+        Category::Segment => SEGMENT_TOKEN,
+        Category::License => LICENSE_TOKEN,
+        Category::GovtId => GOVT_ID_TOKEN,
+        Category::Certificate => CERTIFICATE_TOKEN,
+        Category::Vehicle => VEHICLE_TOKEN,
+        Category::Device => DEVICE_TOKEN,
+        Category::Ip => IP_TOKEN,
+    }
+}
+
+fn ends_with_street_suffix(candidate: &str) -> bool {
+    STREET_SUFFIX_END_RE.is_match(candidate)
+}
+
+fn build_dictionary(defaults: &[String], overrides: &[String]) -> Vec<String> {
+    let mut set: HashSet<String> = defaults.iter().cloned().collect();
     for entry in overrides {
         if entry.trim().is_empty() {
             continue;
         }
         set.insert(entry.trim().to_string());
     }
-    let mut list: Vec<String> = set.into_iter().collect();
-    list.sort();
-    list
-}
+    let mut list: Vec<String> = set.into_iter().collect();
+    list.sort();
+    list
+}
+
+/// One case-insensitive alternation of `entries`. `size_limit` (MiB) overrides the regex
+/// crate's compiled size limit; a dictionary that exceeds it fails with the entry count and
+/// pattern size rather than a bare regex error.
+fn build_dictionary_regex(
+    kind: &str,
+    entries: &[String],
+    size_limit: Option<usize>,
+) -> Result<Option<Regex>> {
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    let patterns: Vec<String> = entries
+        .iter()
+        .map(|value| {
+            let mut escaped = regex::escape(value);
+            escaped = escaped.replace('\u{2019}', "[\u{2019}']");
+            escaped = escaped.replace("'", "[\u{2019}']");
+            escaped = escaped.replace(r"\ ", r"\s+");
+            escaped
+        })
+        .collect();
+
+    let joined = patterns.join("|");
+    let pattern = format!("(?i)\\b(?:{})\\b", joined);
+    let mut builder = RegexBuilder::new(&pattern);
+    if let Some(megabytes) = size_limit {
+        builder.size_limit(megabytes << 20);
+    }
+    match builder.build() {
+        Ok(regex) => Ok(Some(regex)),
+        Err(regex::Error::CompiledTooBig(limit)) => Err(anyhow!(
+            "the {} dictionary ({} entries, {} KiB of pattern) compiles past the {} MiB regex \
+             size limit; raise `regex_size_limit_mb` in the config",
+            kind,
+            entries.len(),
+            pattern.len().div_ceil(1024),
+            limit >> 20
+        )),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// An MRN label, an optional ":" or "#", then an identifier matching `pattern` (or the
+/// default). Labels match in any case and ignore inner spacing ("PatientID"). Built-in labels
+/// also catch an ID glued to them ("MRN4829130"); config `extra` labels ending in a letter or
+/// digit must end a word, so "FIN" does not match "Financial".
+fn build_mrn_label_regex(extra: &[String], pattern: Option<&str>) -> Result<Regex> {
+    let escape = |label: &str| regex::escape(label.trim()).replace(' ', r"\s*");
+    let labels: Vec<String> = MRN_LABELS
+        .iter()
+        .map(|label| escape(label))
+        .chain(extra.iter().map(|label| {
+            if label.trim_end().ends_with(|c: char| c.is_alphanumeric()) {
+                format!(r"{}\b", escape(label))
+            } else {
+                escape(label)
+            }
+        }))
+        .collect();
+    let pattern = pattern.unwrap_or(DEFAULT_MRN_PATTERN);
+    Regex::new(pattern).context("invalid mrn_pattern")?;
+    Ok(Regex::new(&format!(
+        r"\b(?i:{})\s*[:\#]?\s*-?\s*(?:{})",
+        labels.join("|"),
+        pattern
+    ))?)
+}
+
+/// A label, an optional "ID"/"No."/"#" and separator, then one ID token. Extra space-separated
+/// parts must be all digits ("8392 77 551") so the ID does not run into the next word.
+fn build_insurance_regex(labels: &[String]) -> Result<Regex> {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|label| {
+            regex::escape(label)
+                .replace(r"\ ", r"\s+")
+                .replace(' ', r"\s+")
+        })
+        .collect();
+    let pattern = format!(
+        r"(?i)\b(?:{})\s*(?:ID|No\.?|Number|\#)?\s*[:\#-]?\s*[A-Z0-9][A-Z0-9-]{{2,}}(?:\s\d{{2,}}){{0,3}}\b",
+        labels.join("|")
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+/// A label, an optional "No."/"#" and separator, then one dashed ID token ("Case No.
+/// 2023-GD-00412", "Cert #: 123-45-678901"). Labels ending in a letter must end a word.
+fn build_certificate_regex(labels: &[String]) -> Result<Regex> {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|label| {
+            let escaped = regex::escape(label).replace(' ', r"\s*");
+            if label.ends_with(|c: char| c.is_alphanumeric()) {
+                format!(r"{}\b\.?", escaped)
+            } else {
+                escaped
+            }
+        })
+        .collect();
+    let pattern = format!(
+        r"(?i)\b(?:{})\s*(?:No\.?|Number|\#)?\s*[:\#-]?\s*[A-Z0-9][A-Z0-9-]{{2,}}\b",
+        labels.join("|")
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+/// A label, an optional "No."/"#" and separator, then 8-9 digits with an optional letter prefix
+/// ("US passport 512345678", "A-Number A012345678"). The digits are required, so "passport to
+/// wellness program" is not an ID.
+fn build_govt_id_regex(labels: &[String]) -> Result<Regex> {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|label| regex::escape(label).replace(' ', r"\s*"))
+        .collect();
+    let pattern = format!(
+        r"(?i)\b(?:{})\s*(?:No\.?|Number|\#)?\s*[:\#-]?\s*[A-Z]{{0,2}}\d{{8,9}}\b",
+        labels.join("|")
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+fn build_first_last_regex(first_names: &[String]) -> Result<Regex> {
+    let firsts: Vec<String> = first_names.iter().map(|name| regex::escape(name)).collect();
+    let pattern = format!(
+        r"(?x)\b(?i:{firsts})\s+[A-Z][{letter}\u{{2019}}'-]+(?:\s+[A-Z][{letter}\u{{2019}}'-]+)?",
+        firsts = firsts.join("|"),
+        letter = LETTER,
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+fn build_first_name_regex(first_names: &[String]) -> Result<Regex> {
+    let firsts: Vec<String> = first_names.iter().map(|name| regex::escape(name)).collect();
+    let pattern = format!(r"(?i)\b(?:{})\b", firsts.join("|"));
+    Ok(Regex::new(&pattern)?)
+}
+
+/// Capitalized common first names (case-sensitive, so "mark" is not a candidate), with the
+/// optional preceding cue captured for gating.
+fn build_narrative_first_name_regex(first_names: &[String]) -> Result<Regex> {
+    let firsts: Vec<String> = first_names.iter().map(|name| regex::escape(name)).collect();
+    let pattern = format!(
+        r"(?P<cue>\b(?i:{cues})\s+)?(?P<name>\b(?:{firsts})\b)",
+        cues = FIRST_NAME_CUES,
+        firsts = firsts.join("|"),
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+/// Built-in relative dates plus config `relative_date_phrases`, tried longest first. Counted
+/// hospital and post-op days ("POD 3", "hospital day 5") need their number, so a bare "day" is
+/// left alone.
+fn build_relative_date_regex(extra: &[String]) -> Result<Regex> {
+    let mut phrases: Vec<&String> = extra.iter().collect();
+    phrases.sort_by_key(|phrase| std::cmp::Reverse(phrase.trim().len()));
+    let configured: String = phrases
+        .into_iter()
+        .map(|phrase| {
+            let words: Vec<String> = phrase.split_whitespace().map(regex::escape).collect();
+            words.join(r"\s+") + "|"
+        })
+        .collect();
+    let pattern = format!(
+        r"(?xi)
+        \b(
+            {configured}
+            yesterday|today|tomorrow|
+            last\s+(?:night|week|month|year|Monday|Tuesday|Wednesday|Thursday|Friday|Saturday|Sunday)|
+            this\s+(?:morning|afternoon|evening|week|month)|
+            \d+\s+(?:day|days|week|weeks|month|months|year|years)\s+ago|
+            (?:POD|post[-\s]?op(?:erative)?\s+day|hospital\s+day)\s*\#?\s*\d{{1,3}}
+        )\b",
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+fn build_relative_name_regex() -> Result<Regex> {
+    let pattern = format!(
+        r"\b(?i:{cues})[ \t]+(?P<first>[A-Z][{letter}\u{{2019}}'-]*)(?:[ \t]+(?P<last>[A-Z][{letter}\u{{2019}}'-]+))?\b",
+        cues = RELATIONSHIP_CUES,
+        letter = LETTER,
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+/// True when `start..end` is a label ("Linda:") or sits on a heading line ending in a colon.
+fn is_heading_position(text: &str, start: usize, end: usize) -> bool {
+    if text[end..].trim_start_matches([' ', '\t']).starts_with(':') {
+        return true;
+    }
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+    text[line_start..line_end].trim_end().ends_with(':')
+}
+
+/// The titles that introduce a name, as one regex alternation: config `titles` ahead of the
+/// built-in `HONORIFICS`, or ahead of `titles_replace` when set. Config titles go longest first
+/// so "Charge Nurse" is tried before "Charge"; inner spaces match any whitespace, and a trailing
+/// "." is optional.
+fn build_title_alternation(extra: &[String], replace: Option<&[String]>) -> String {
+    let mut configured: Vec<&String> = extra.iter().chain(replace.into_iter().flatten()).collect();
+    configured.sort_by_key(|title| std::cmp::Reverse(title.trim().len()));
+    let mut titles: Vec<String> = configured
+        .into_iter()
+        .map(|title| {
+            let words: Vec<String> = title.split_whitespace().map(regex::escape).collect();
+            let title = words.join(r"\s+");
+            match title.strip_suffix(r"\.") {
+                Some(stem) => format!(r"{}\.?", stem),
+                None => title,
+            }
+        })
+        .collect();
+    if replace.is_none() {
+        titles.push(HONORIFICS.to_string());
+    }
+    titles.join("|")
+}
+
+fn build_titled_name_regex(titles: &str) -> Result<Regex> {
+    let pattern = format!(
+        r"(?x)\b(?i:{honorifics})\s+[A-Z][{letter}\u{{2019}}'-]+(?:\s+[A-Z][{letter}\u{{2019}}'-]+)?",
+        honorifics = titles,
+        letter = LETTER,
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+fn build_capital_sequence_regex() -> Result<Regex> {
+    let pattern = format!(
+        r"(?x)
+        \b
+        [A-Z][{letter}\u{{2019}}']+\s+[A-Z][{letter}\u{{2019}}']+
+        (?:\s+[A-Z][{letter}\u{{2019}}']+)?
+        \b",
+        letter = LETTER,
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+/// Matches "St."/"Saint" followed by capitalized words, capturing the surrounding context used
+/// to classify the candidate: a facility suffix, a street suffix, or a leading honorific or
+/// common first name.
+fn build_saint_regex(first_names: &[String], titles: &str) -> Result<Regex> {
+    let firsts: Vec<String> = first_names.iter().map(|name| regex::escape(name)).collect();
+    let word = format!(r"[A-Z][{}{}\u{{2019}}'-]+", LETTER, MARK);
+    let pattern = format!(
+        r"(?x)
+        (?P<lead>\b(?i:{honorifics}|{firsts})\s+)?
+        (?P<saint>\b(?:St\.?|Saint)\s+{word}
+            (?:
+                (?:\s+{word}){{0,3}}\s+(?P<facility>(?i:{facility}))
+              | (?:\s+{word}){{0,3}}\s+(?P<street>(?i:{street}))
+              | (?:\s+{word})?
+            )
+        )",
+        honorifics = titles,
+        firsts = firsts.join("|"),
+        word = &word,
+        facility = FACILITY_SUFFIXES,
+        street = STREET_SUFFIXES,
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+/// "St. Pierre": St./Saint and capitalized words with neither a facility suffix nor a
+/// possessive, which `saint-context` could not place. These are surnames as often as
+/// facilities, so the facility pattern leaves them alone.
+fn is_saint_surname(candidate: &str) -> bool {
+    SAINT_PREFIX_RE.is_match(candidate)
+        && !FACILITY_SUFFIX_END_RE.is_match(candidate)
+        && !candidate.contains("'s")
+        && !candidate.contains("\u{2019}s")
+}
+
+/// True when the candidate, or any run of its words, is a stoplisted term or phrase, so
+/// "COPD Exacerbation" is rejected by a stoplist with either the phrase or "COPD".
+fn is_name_stopword(candidate: &str, stoplist: &HashSet<String>) -> bool {
+    let key = stopword_key(candidate);
+    let words: Vec<&str> = key.split(' ').collect();
+    (0..words.len()).any(|start| {
+        (start + 1..=words.len()).any(|end| stoplist.contains(&words[start..end].join(" ")))
+    })
+}
+
+/// Uppercase, letters, digits and single spaces only: "E. coli" and "E  COLI" both give
+/// "E COLI".
+fn stopword_key(text: &str) -> String {
+    let upper: String = text
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .flat_map(|c| c.to_uppercase())
+        .collect();
+    upper.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// What makes two redacted strings the same entity: case and runs of whitespace are ignored.
+fn entity_key(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Keys redactions by entity for numbered tokens and `unique` counts. A person's leading title
+/// is dropped, so "Dr. Harmon", "Mr. Harmon" and "Harmon" are one entity.
+#[derive(Clone, Debug)]
+pub(crate) struct EntityKeys {
+    title: Regex,
+}
+
+impl EntityKeys {
+    fn new(titles: &str) -> Result<Self> {
+        Ok(Self {
+            title: Regex::new(&format!(r"^(?i:{})\s+", titles))?,
+        })
+    }
+
+    pub(crate) fn key(&self, category: &CategoryKey, text: &str) -> String {
+        let text = match category {
+            CategoryKey::Builtin(Category::Person) => self
+                .title
+                .find(text)
+                .map_or(text, |title| &text[title.end()..]),
+            _ => text,
+        };
+        entity_key(text)
+    }
+}
+
+/// Groups redactions by the input line their start offset falls on.
+fn count_lines(input: &str, starts: Vec<(usize, CategoryKey)>) -> Vec<LineCount> {
+    let newlines: Vec<usize> = input.match_indices('\n').map(|(at, _)| at).collect();
+    let mut counts: BTreeMap<(usize, CategoryKey), usize> = BTreeMap::new();
+    for (start, category) in starts {
+        let line = newlines.partition_point(|&at| at < start) + 1;
+        *counts.entry((line, category)).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|((line, category), count)| LineCount {
+            line,
+            category,
+            count,
+        })
+        .collect()
+}
+
+/// NFKC-normalizes the input, folds typographic quotes, dashes, and bullets to ASCII, and
+/// collapses runs of horizontal whitespace. Each base character is normalized together with
+/// its combining marks so the alignment back to the input stays exact.
+fn normalize_input(input: &str) -> (String, Alignment) {
+    let mut rewriter = Rewriter::new(input);
+    let mut chars = input.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        let mut end = start + ch.len_utf8();
+        while let Some(&(next, mark)) = chars.peek() {
+            if !is_combining_mark(mark) {
+                break;
+            }
+            end = next + mark.len_utf8();
+            chars.next();
+        }
+        let cluster = &input[start..end];
+        if cluster.is_ascii() {
+            continue;
+        }
+        let normalized: String = cluster.nfkc().map(fold_punctuation).collect();
+        rewriter.replace(start, end, &normalized, SegmentKind::Rewritten);
+    }
+    let (folded, folding) = rewriter.finish();
+    let (collapsed, collapsing) = rewrite_all(&folded, &MULTISPACE_RE, |_| " ".to_string());
+    (collapsed, folding.then(&collapsing))
+}
+
+fn fold_punctuation(ch: char) -> char {
+    match ch {
+        '\u{2018}' | '\u{2019}' | '\u{201B}' | '\u{2032}' => '\'',
+        '\u{201C}' | '\u{201D}' | '\u{2033}' => '"',
+        '\u{2013}' | '\u{2014}' | '\u{2212}' => '-',
+        '\u{2022}' | '\u{00B7}' | '\u{2027}' | '\u{2043}' | '\u{30FB}' => ' ',
+        other => other,
+    }
+}
+
+/// The line break the text ends with: `\r\n`, `\n`, or nothing.
+pub(crate) fn final_line_ending(text: &str) -> &'static str {
+    if text.ends_with("\r\n") {
+        "\r\n"
+    } else if text.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+/// Removes space before punctuation and repeated punctuation, and trims the ends. With
+/// `line_ending` (the input's, from `final_line_ending`), line breaks are kept and the text
+/// ends with that line ending.
+fn tidy_punctuation(input: &str, line_ending: Option<&str>) -> (String, Alignment) {
+    let space = match line_ending {
+        Some(_) => &SPACE_BEFORE_PUNCT_RE,
+        None => &SPACE_AROUND_PUNCT_RE,
+    };
+    let (spaced, spacing) = rewrite_all(input, space, |caps| {
+        caps.get(1).map(|m| m.as_str()).unwrap_or("").to_string()
+    });
+    let (deduped, deduping) = rewrite_all(&spaced, &DUP_PUNCT_RE, |caps| {
+        caps.get(1).map(|m| m.as_str()).unwrap_or("").to_string()
+    });
+
+    let trimmed = deduped.trim();
+    let start = if trimmed.is_empty() {
+        deduped.len()
+    } else {
+        deduped.len() - deduped.trim_start().len()
+    };
+    let end = start + trimmed.len();
+    let mut rewriter = Rewriter::new(&deduped);
+    rewriter.replace(0, start, "", SegmentKind::Rewritten);
+    rewriter.copy_to(end);
+    rewriter.replace(
+        end,
+        deduped.len(),
+        line_ending.unwrap_or(""),
+        SegmentKind::Rewritten,
+    );
+    let (text, trimming) = rewriter.finish();
+    (text, spacing.then(&deduping).then(&trimming))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Category;
+    use std::collections::HashSet;
+
+    // Batch mode shares one scrubber across its worker threads.
+    const _: fn() = || {
+        fn send_sync<T: Send + Sync>() {}
+        send_sync::<Scrubber>();
+    };
+
+    #[test]
+    fn redacts_email_and_phone() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Reach me at jane.doe@example.com or (555) 867-5309.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(EMAIL_TOKEN));
+        assert!(output.contains(PHONE_TOKEN));
+        assert_eq!(stats.emails, 1);
+        assert_eq!(stats.phones, 1);
+    }
+
+    #[test]
+    fn redacts_fax_numbers_separately_from_phones() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub("Fax: (312) 555-0100", &HashSet::new());
+        assert_eq!(output, "Fax: [FAX]");
+        assert_eq!((stats.phones, stats.faxes), (0, 1));
+
+        let (output, stats) = scrubber.scrub(
+            "Clinic phone (312) 555-0182, FX: 312.555.0190; facsimile 312-555-0191",
+            &HashSet::new(),
+        );
+        assert_eq!(output, "Clinic phone [PHONE], FX: [FAX]; facsimile [FAX]");
+        assert_eq!((stats.phones, stats.faxes, stats.total()), (1, 2, 3));
+    }
+
+    #[test]
+    fn short_pager_and_callback_numbers_but_not_pagination() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "Please page 31042 overnight, pager #5-1234 after 7, beeper 0482; callback 77120.",
+            &HashSet::new(),
+        );
+        assert_eq!(
+            output,
+            "Please page [PAGER] overnight, pager #[PAGER] after 7, beeper [PAGER]; callback [PHONE]."
+        );
+        assert_eq!((stats.pagers, stats.phones), (3, 1));
+
+        for pagination in [
+            "Continued on page 3 of 12.",
+            "See page 1204 of 1310.",
+            "Page 12: vitals stable, weight 1450 g.",
+        ] {
+            let (output, stats) = scrubber.scrub(pagination, &HashSet::new());
+            assert_eq!(output, pagination);
+            assert_eq!(stats.total(), 0, "{}", pagination);
+        }
+    }
+
+    #[test]
+    fn attributes_numbers_to_phone_fax_and_pager_labels() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "Ph 312-555-0182 / Fax 312-555-0190 / Pager 0482",
+            &HashSet::new(),
+        );
+        assert_eq!(output, "Ph [PHONE] / Fax [FAX] / Pager [PAGER]");
+        assert_eq!((stats.phones, stats.faxes, stats.pagers), (1, 1, 1));
+
+        let (output, stats) =
+            scrubber.scrub("Fax 312-555-0190 or 312-555-0191 | Office", &HashSet::new());
+        assert_eq!(output, "Fax [FAX] or [PHONE] | Office");
+        assert_eq!((stats.phones, stats.faxes), (1, 1));
+    }
+
+    #[test]
+    fn honors_skip_categories() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Call 555-111-2222 and email foo@bar.com.";
+        let mut skip = HashSet::new();
+        skip.insert(Category::Phone.into());
+        let (output, stats) = scrubber.scrub(input, &skip);
+        assert!(output.contains("555-111-2222"));
+        assert!(output.contains(EMAIL_TOKEN));
+        assert_eq!(stats.phones, 0);
+        assert_eq!(stats.emails, 1);
+    }
+
+    #[test]
+    fn redacts_directional_address_and_unit() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Lives at 1200 W. 31st St., Apt #4B in Chicago.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(ADDRESS_TOKEN));
+        assert!(stats.addresses >= 1);
+    }
+
+    #[test]
+    fn redacts_obfuscated_email() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Contact jrsmith at midstate dot edu for questions.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(EMAIL_TOKEN));
+        assert_eq!(stats.emails, 1);
+    }
+
+    #[test]
+    fn redacts_url() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let input = "See http://midstate.example/pt/12345 for records.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(URL_TOKEN));
+        assert_eq!(stats.urls, 1);
+    }
+
+    #[test]
+    fn redacts_ip_addresses_under_safe_harbor() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let skip = HashSet::new();
+        let cases = [
+            (
+                "sshd[211]: Accepted publickey from 10.32.1.117 port 22",
+                "sshd[211]: Accepted publickey from [IP] port 22",
+            ),
+            (
+                "GET /labs 200 src=192.168.0.4:8443",
+                "GET /labs 200 src=[IP]:8443",
+            ),
+            (
+                "Portal at fe80::1ff:fe23:4567:890a%eth0.",
+                "Portal at [IP].",
+            ),
+            (
+                "Host 2001:0db8:85a3:0000:0000:8a2e:0370:7334 and ::ffff:10.0.0.1 seen",
+                "Host [IP] and [IP] seen",
+            ),
+            (
+                "Bad quad 999.999.1.1 ignored",
+                "Bad quad 999.999.1.1 ignored",
+            ),
+            (
+                "Upgraded to v2.4.1.1 today",
+                "Upgraded to v2.4.1.1 [REL_DATE]",
+            ),
+            (
+                "Client is on epic 2.4.1.1 now",
+                "Client is on epic 2.4.1.1 now",
+            ),
+            ("Code 1.2.3.4.5 applies", "Code 1.2.3.4.5 applies"),
+            ("Seen at 10:22:01 in clinic", "Seen at 10:22:01 in clinic"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(scrubber.scrub(input, &skip).0, expected, "{}", input);
+        }
+
+        let (_, stats) = scrubber.scrub("from 10.0.0.1 and 2001:db8::7", &skip);
+        assert_eq!(stats.ip_addresses, 2);
+
+        let standard =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        assert_eq!(
+            standard.scrub("from 10.32.1.117", &skip).0,
+            "from 10.32.1.117"
+        );
+    }
+
+    #[test]
+    fn redacts_employers_from_context_and_dictionary() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let skip = HashSet::new();
+        let cases = [
+            (
+                "He works at Caterpillar in Peoria.",
+                "He works at [EMPLOYER] in Peoria.",
+            ),
+            (
+                "Mother is a teacher at Lincoln Elementary School. Stable.",
+                "Mother is a teacher at [EMPLOYER]. Stable.",
+            ),
+            (
+                "Employed by the University of Chicago since 2019",
+                "Employed by the [EMPLOYER] since 2019",
+            ),
+            (
+                "Heart works at maximal capacity.",
+                "Heart works at maximal capacity.",
+            ),
+            ("She attends AA weekly.", "She attends AA weekly."),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(scrubber.scrub(input, &skip).0, expected, "{}", input);
+        }
+
+        let config = ScrubberConfig {
+            employers: vec!["Caterpillar".to_string()],
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub("Retired from caterpillar last spring.", &skip);
+        assert_eq!(output, "Retired from [EMPLOYER] last spring.");
+        assert_eq!(stats.employers, 1);
+        assert_eq!(
+            scrubber
+                .describe(&skip)
+                .rule("employer-dictionary")
+                .expect("rule")
+                .source,
+            RuleSource::Config
+        );
+    }
+
+    #[test]
+    fn redacts_social_handles_only_with_a_platform() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let skip = HashSet::new();
+        let cases = [
+            (
+                "Patient posts under @jsmith_chicago on Instagram.",
+                "Patient posts under [HANDLE] on Instagram.",
+            ),
+            (
+                "Twitter handle: @jay.smith; TikTok @jsmith99",
+                "Twitter handle: [HANDLE]; TikTok [HANDLE]",
+            ),
+            (
+                "Active on Reddit as Reddit u/quiet_owl",
+                "Active on Reddit as Reddit [HANDLE]",
+            ),
+            (
+                "Old page facebook.com/john.smith.7741.",
+                "Old page [HANDLE].",
+            ),
+            (
+                "See https://www.instagram.com/jsmith_chicago/ for posts",
+                "See [HANDLE] for posts",
+            ),
+            (
+                "Vitals @ bedside, ambulating @home",
+                "Vitals @ bedside, ambulating @home",
+            ),
+            ("Meds given @0800 per MAR", "Meds given @0800 per MAR"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(scrubber.scrub(input, &skip).0, expected, "{}", input);
+        }
+        let (_, stats) = scrubber.scrub(cases[1].0, &skip);
+        assert_eq!((stats.handles, stats.urls), (2, 0));
+    }
+
+    #[test]
+    fn redacts_urls_without_safe_harbor() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let skip = HashSet::new();
+        let cases = [
+            (
+                "Results: https://portal.example.org/labs?id=48&view=full.",
+                "Results: [URL].",
+            ),
+            (
+                "Log in at www.mychart.org/login, then call.",
+                "Log in at [URL], then call.",
+            ),
+            (
+                "Visit summary at mychart.hospital.org/visit/12345 was shared today",
+                "Visit summary at [URL] was shared [REL_DATE]",
+            ),
+            ("(see http://example.com/a/b?q=1)!", "(see [URL])!"),
+            (
+                "Saved to C:\\notes\\visit.txt",
+                "Saved to C:\\notes\\visit.txt",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(scrubber.scrub(input, &skip).0, expected, "{}", input);
+        }
+
+        let (output, stats) = scrubber.scrub("Email jane.doe@example.com please", &skip);
+        assert_eq!(output, "Email [EMAIL] please");
+        assert_eq!(stats.urls, 0);
+
+        let mut skip_email = HashSet::new();
+        skip_email.insert(Category::Email.into());
+        assert_eq!(
+            scrubber
+                .scrub("Email jane.doe@example.com please", &skip_email)
+                .0,
+            "Email jane.doe@example.com please"
+        );
+    }
+
+    #[test]
+    fn redacts_custom_names() {
+        let config = ScrubberConfig {
+            names: vec!["Zelda Fitzgerald".to_string()],
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let input = "Discussed plan with Zelda Fitzgerald today.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(PERSON_TOKEN));
+        assert_eq!(stats.persons, 1);
+    }
+
+    fn encounter_config() -> ScrubberConfig {
+        ScrubberConfig {
+            custom_patterns: vec![crate::config::CustomPattern {
+                name: "encounter-number".to_string(),
+                pattern: r"\bENC-\d{4}-\d{5}\b".to_string(),
+                token: None,
+                case_insensitive: false,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn custom_patterns_from_json_take_their_own_token_and_case() {
+        let config: ScrubberConfig = serde_json::from_str(
+            r#"{"custom_patterns": [
+                {"name": "study-id", "pattern": "\\bstu-\\d{4}-\\d{4}\\b",
+                 "token": "[STUDY]", "case_insensitive": true},
+                {"name": "csn", "pattern": "\\bCSN\\s*\\d{9}\\b"}
+            ]}"#,
+        )
+        .expect("config");
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (text, stats) = scrubber.scrub(
+            "Enrolled in STU-2021-0441 (csn 123456789), see stu-2021-0441.",
+            &HashSet::new(),
+        );
+        assert_eq!(text, "Enrolled in [STUDY] (csn [MRN]), see [STUDY].");
+        let json = stats
+            .to_json_value(crate::report::SchemaVersion::V2)
+            .expect("json");
+        assert_eq!(json["counts"]["custom:study-id"], 2);
+        assert_eq!(json["counts"]["custom:csn"], 0);
+        assert_eq!(json["tokens"]["custom:study-id"], "[STUDY]");
+
+        let invalid: ScrubberConfig =
+            serde_json::from_str(r#"{"custom_patterns": [{"name": "study-id", "pattern": "("}]}"#)
+                .expect("config");
+        let err = Scrubber::new(invalid, ScrubMode::Standard)
+            .err()
+            .expect("rejected");
+        assert!(err.to_string().contains("custom:study-id"));
+    }
+
+    #[test]
+    fn custom_patterns_are_addressed_by_category_key() {
+        let encounter: CategoryKey = "custom:encounter-number".parse().expect("key");
+        let input = "Encounter ENC-2024-00123 on 03/04/2024.";
+
+        let scrubber = Scrubber::new(encounter_config(), ScrubMode::Standard).expect("scrubber");
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        assert_eq!(result.text, "Encounter [ENCOUNTER_NUMBER] on [DATE].");
+        assert_eq!(result.stats.count_key(&encounter), 1);
+        assert_eq!(result.stats.total(), 2);
+        assert_eq!(result.spans[0].category, encounter);
+        assert_eq!(&input[result.spans[0].original.clone()], "ENC-2024-00123");
+
+        let skip = HashSet::from([encounter.clone()]);
+        let result = scrubber.scrub_detailed(input, &skip);
+        assert_eq!(result.text, "Encounter ENC-2024-00123 on [DATE].");
+        assert_eq!(result.stats.custom["encounter-number"], 0);
+        let description = scrubber.describe(&skip);
+        let rule = description.rule("custom-pattern").expect("rule");
+        assert_eq!(rule.disabled_by, Some(DisabledBy::Skip));
+        assert_eq!(
+            scrubber.report_options(&skip).skipped,
+            vec![encounter.clone()]
+        );
+
+        let mut config = encounter_config();
+        config.tokens.insert(encounter.clone(), "[ENC]".to_string());
+        config
+            .tokens
+            .insert(Category::Date.into(), "[WHEN]".to_string());
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        assert_eq!(result.text, "Encounter [ENC] on [WHEN].");
+        assert_eq!(&result.text[result.spans[0].output.clone()], "[ENC]");
+    }
+
+    #[test]
+    fn custom_patterns_leave_what_the_built_ins_label_to_them() {
+        let config: ScrubberConfig = serde_json::from_str(
+            r#"{"custom_patterns": [{"name": "site-number", "pattern": "\\b\\d[\\d/-]{7,}\\b"}]}"#,
+        )
+        .expect("config");
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (text, stats) = scrubber.scrub(
+            "Call 312-555-0182 on 03/14/2024, ref 44-1187-73.",
+            &HashSet::new(),
+        );
+        assert_eq!(text, "Call [PHONE] on [DATE], ref [SITE_NUMBER].");
+        assert_eq!((stats.phones, stats.dates), (1, 1));
+        assert_eq!(stats.custom["site-number"], 1);
+    }
+
+    #[test]
+    fn reads_extra_names_from_files_beside_the_config() {
+        let dir = std::env::temp_dir().join(format!("names-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        std::fs::write(
+            dir.join("staff.txt"),
+            "# HR export\n\n  Quaxley\nZorbin\nQuaxley\n",
+        )
+        .expect("names");
+        let path = dir.join("site.json");
+        std::fs::write(&path, r#"{"names_files": ["staff.txt"]}"#).expect("config");
+
+        let config = ScrubberConfig::from_file(&path).expect("config");
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (text, stats) = scrubber.scrub("paged quaxley and zorbin at noon", &HashSet::new());
+        assert_eq!(text, "paged [PERSON] and [PERSON] at noon");
+        assert_eq!(stats.persons, 2);
+
+        std::fs::write(&path, r#"{"keywords_files": ["missing.txt"]}"#).expect("config");
+        let config = ScrubberConfig::from_file(&path).expect("config");
+        let err = Scrubber::new(config, ScrubMode::Standard)
+            .err()
+            .expect("missing");
+        assert!(format!("{:#}", err).contains("missing.txt"));
+
+        let config = ScrubberConfig {
+            regex_size_limit_mb: Some(0),
+            ..ScrubberConfig::default()
+        };
+        let err = Scrubber::new(config, ScrubMode::Standard)
+            .err()
+            .expect("too big");
+        assert!(err.to_string().contains("raise `regex_size_limit_mb`"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn allowlisted_terms_survive_every_detector() {
+        let config = ScrubberConfig {
+            allowlist: vec![
+                "ruth  hale".to_string(),
+                "Mercy General Hospital".to_string(),
+            ],
+            ..ScrubberConfig::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (text, stats) = scrubber.scrub(
+            "Note signed by Dr. Ruth\tHale and Dr. Alan Brooks, Mercy General Hospital.",
+            &HashSet::new(),
+        );
+        assert_eq!(
+            text,
+            "Note signed by Dr. Ruth Hale and [PERSON], Mercy General Hospital."
+        );
+        assert_eq!(stats.persons, 1);
+        assert_eq!(stats.facilities, 0);
+
+        let config = ScrubberConfig {
+            allowlist: vec![" ".to_string()],
+            ..ScrubberConfig::default()
+        };
+        assert!(Scrubber::new(config, ScrubMode::Standard).is_err());
+    }
+
+    #[test]
+    fn numbered_tokens_give_each_entity_its_own_number() {
+        let config = ScrubberConfig {
+            token_style: crate::config::TokenStyle::Numbered,
+            ..ScrubberConfig::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let result = scrubber.scrub_detailed(
+            "Dr. Harmon saw Nurse Lopez. Dr. Harmon left.",
+            &HashSet::new(),
+        );
+        assert_eq!(
+            result.text,
+            "[PERSON_1] saw Nurse [PERSON_2]. [PERSON_1] left."
+        );
+        assert_eq!(result.stats.unique["person"], 2);
+
+        let input = "Seen by Dr. Harmon on 03/14/2021. Nurse Lopez paged Mr.  HARMON \
+                     on 03/15/2021, follow-up 03/14/2021.";
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        assert_eq!(
+            result.text,
+            "Seen by [PERSON_1] on [DATE_1]. Nurse [PERSON_2] paged [PERSON_1] on [DATE_2], \
+             follow-up [DATE_1]."
+        );
+        let indexes: Vec<(String, Option<usize>)> = result
+            .spans
+            .iter()
+            .map(|span| (span.category.to_string(), span.index))
+            .collect();
+        assert_eq!(
+            indexes,
+            [
+                ("person".to_string(), Some(1)),
+                ("date".to_string(), Some(1)),
+                ("person".to_string(), Some(2)),
+                ("person".to_string(), Some(1)),
+                ("date".to_string(), Some(2)),
+                ("date".to_string(), Some(1)),
+            ]
+        );
+        assert_eq!(result.stats.unique["person"], 2);
+        assert_eq!(result.stats.unique["date"], 2);
+
+        let config = ScrubberConfig {
+            token_style: crate::config::TokenStyle::Numbered,
+            date_mode: Some(crate::config::DateMode::Year),
+            ..ScrubberConfig::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let result = scrubber.scrub_detailed("Seen on 03/14/2021.", &HashSet::new());
+        assert_eq!(result.text, "Seen on 2021.");
+        assert_eq!(result.spans[0].index, None);
+    }
+
+    #[test]
+    fn token_overrides_replace_only_their_categories() {
+        let mut config = ScrubberConfig::default();
+        config
+            .tokens
+            .insert(Category::Person.into(), "<PHI type=\"NAME\"/>".to_string());
+        config
+            .tokens
+            .insert(Category::Date.into(), "<PHI type=\"DATE\"/>".to_string());
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+
+        let input = "Dr. Alan Brooks saw her on 03/14/2021; call 312-555-0182.";
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        assert_eq!(
+            result.text,
+            "<PHI type=\"NAME\"/> saw her on <PHI type=\"DATE\"/>; call [PHONE]."
+        );
+        let tokens: Vec<&str> = result
+            .spans
+            .iter()
+            .map(|span| span.token.as_str())
+            .collect();
+        assert_eq!(
+            tokens,
+            ["<PHI type=\"NAME\"/>", "<PHI type=\"DATE\"/>", "[PHONE]"]
+        );
+        assert_eq!(result.stats.tokens.len(), 2);
+        assert_eq!(result.stats.tokens["person"], "<PHI type=\"NAME\"/>");
+        let json = result
+            .stats
+            .render_json(crate::report::SchemaVersion::V2)
+            .expect("json");
+        assert!(json.contains(r#""date": "<PHI type=\"DATE\"/>""#));
+
+        for bad in ["", "  ", "[A]\n[B]"] {
+            let mut config = ScrubberConfig::default();
+            config
+                .tokens
+                .insert(Category::Phone.into(), bad.to_string());
+            let err = Scrubber::new(config, ScrubMode::Standard)
+                .err()
+                .expect("rejected");
+            assert!(err.to_string().contains("token override for phone"));
+        }
+    }
+
+    #[test]
+    fn tokens_are_never_rescanned() {
+        let mut config = ScrubberConfig::default();
+        config
+            .tokens
+            .insert(Category::Ssn.into(), "123-45-6789".to_string());
+        config
+            .tokens
+            .insert(Category::Person.into(), "May Johnson".to_string());
+        config
+            .tokens
+            .insert(Category::Email.into(), "[E]".to_string());
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+
+        let input = "SSN 111-22-3333 for Mrs. Darrow, SSN 222-33-4444 for Mr. Ruiz.";
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        assert_eq!(
+            result.text,
+            "SSN 123-45-6789 for May Johnson, SSN 123-45-6789 for May Johnson."
+        );
+        assert_eq!(result.stats.ssn, 2);
+        assert_eq!(result.stats.persons, 2);
+        assert_eq!(result.stats.dates, 0);
+        assert_eq!(result.spans.len(), 4);
+
+        let warnings = scrubber.token_warnings();
+        assert_eq!(
+            warnings,
+            vec![
+                "token \"123-45-6789\" for ssn looks like ssn".to_string(),
+                "token \"May Johnson\" for person looks like person".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn collects_detector_timings_only_on_request() {
+        let scrubber = Scrubber::new(encounter_config(), ScrubMode::SafeHarbor).expect("scrubber");
+        let input = "Encounter ENC-2024-00123 for Jane Doe, jane.doe@example.com, 03/14/2024.";
+        let cancel = CancellationToken::new();
+
+        let plain = scrubber.scrub_detailed(input, &HashSet::new());
+        assert!(plain.stats.detector_timings.is_empty());
+
+        let options = ScrubOptions {
+            collect_timings: true,
+            ..ScrubOptions::default()
+        };
+        let started = Instant::now();
+        let result = scrubber
+            .scrub_with_options(input, &HashSet::new(), &options, &cancel)
+            .expect("not cancelled");
+        let wall_us = started.elapsed().as_micros() as u64;
+
+        assert_eq!(result.text, plain.text);
+        let timings = &result.stats.detector_timings;
+        assert!(timings.iter().any(|timing| timing.detector == "email"));
+        assert!(timings
+            .iter()
+            .any(|timing| timing.detector == "custom:encounter-number"));
+        let matched: usize = timings.iter().map(|timing| timing.matches).sum();
+        assert_eq!(matched, result.stats.total());
+        let timed: u64 = timings.iter().map(|timing| timing.elapsed_us).sum();
+        assert!(timed <= wall_us, "{} > {}", timed, wall_us);
+    }
+
+    #[test]
+    fn counts_redactions_per_line_across_crlf_and_blank_lines() {
+        let scrubber = Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("ok");
+        let input = "Seen by Dr. Alan Brooks.\r\n\r\n\r\nCall 312-555-0182 or 312-555-0199,\r\n\
+                     \u{201C}Jane Doe\u{201D}  at  jane.doe@example.com\r\n";
+        let cancel = CancellationToken::new();
+        let plain = scrubber.scrub_detailed(input, &HashSet::new());
+        assert!(plain.stats.lines.is_empty());
+
+        let options = ScrubOptions {
+            collect_lines: true,
+            ..ScrubOptions::default()
+        };
+        let result = scrubber
+            .scrub_with_options(input, &HashSet::new(), &options, &cancel)
+            .expect("not cancelled");
+        let lines: Vec<(usize, String, usize)> = result
+            .stats
+            .lines
+            .iter()
+            .map(|entry| (entry.line, entry.category.to_string(), entry.count))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (1, "person".to_string(), 1),
+                (4, "phone".to_string(), 2),
+                (5, "email".to_string(), 1),
+                (5, "person".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            lines.iter().map(|line| line.2).sum::<usize>(),
+            result.stats.total()
+        );
+    }
+
+    #[test]
+    fn merged_stats_match_the_stats_of_the_concatenated_notes() {
+        let scrubber = Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("ok");
+        let notes = [
+            "Seen by Dr. Alan Brooks on 03/14/2024. Call 312-555-0182.",
+            "Email jane.doe@example.com, MRN 4829130, ZIP 60614.",
+            "Patient Maria Lopez returns tomorrow; fax 312-555-0199.",
+        ];
+        let mut merged = ScrubStats::default();
+        for note in notes {
+            merged += &scrubber.scrub(note, &HashSet::new()).1;
+        }
+        let whole = scrubber.scrub(&notes.join("\n\n"), &HashSet::new()).1;
+        assert_eq!(merged.by_key(), whole.by_key());
+        assert_eq!(merged.total(), whole.total());
+    }
+
+    #[test]
+    fn rejects_bad_custom_pattern_config() {
+        let mut config = encounter_config();
+        config.custom_patterns[0].name = "Encounter".to_string();
+        assert!(Scrubber::new(config, ScrubMode::Standard).is_err());
+
+        let mut config = encounter_config();
+        config
+            .tokens
+            .insert("custom:visit".parse().expect("key"), "[V]".to_string());
+        assert!(Scrubber::new(config, ScrubMode::Standard).is_err());
+    }
+
+    #[test]
+    fn redacts_common_first_last_pair() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "David Harmon discussed the plan.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(PERSON_TOKEN));
+        assert_eq!(stats.persons, 1);
+    }
+
+    #[test]
+    fn redacts_extended_honorifics() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Rev. O'Connor provided counseling.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(PERSON_TOKEN));
+        assert_eq!(stats.persons, 1);
+    }
+
+    #[test]
+    fn redacts_coordinates() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Coordinates 41.8781° N, 87.6298° W were logged.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(COORD_TOKEN));
+        assert_eq!(stats.coordinates, 1);
+    }
+
+    #[test]
+    fn rounds_coordinates_when_configured() {
+        let input = "Home 41.8781° N, 87.6298° W; clinic 41.8781, -87.6298; ratio 2.5, 3.0.";
+        let round = |places| {
+            let config = ScrubberConfig {
+                coordinate_mode: CoordinateMode::Round { places },
+                ..Default::default()
+            };
+            let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+            scrubber.scrub(input, &HashSet::new())
+        };
+
+        let (output, stats) = round(1);
+        assert_eq!(
+            output,
+            "Home 41.9° N, 87.6° W; clinic 41.9, -87.6; ratio 2.5, 3.0."
+        );
+        assert_eq!((stats.coordinates, stats.coordinates_rounded), (2, 2));
+
+        let (output, _) = round(2);
+        assert_eq!(
+            output,
+            "Home 41.88° N, 87.63° W; clinic 41.88, -87.63; ratio 2.5, 3.0."
+        );
+
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(output, "Home [COORD]; clinic [COORD]; ratio 2.5, 3.0.");
+        assert_eq!((stats.coordinates, stats.coordinates_rounded), (2, 0));
+    }
+
+    #[test]
+    fn rounding_falls_back_to_redaction() {
+        assert_eq!(round_coordinates("41.8781° N", 1), None);
+        assert_eq!(
+            round_coordinates("-41.8781, 87.6", 2).as_deref(),
+            Some("-41.88, 87.6")
+        );
+        assert!(!is_signed_lat_long("95.1234, 87.6298"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "ascii-only"))]
+    fn redacts_accented_names_whole() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, _) = scrubber.scrub("Seen by Dr. José Peña.", &HashSet::new());
+        assert_eq!(output, "Seen by [PERSON].");
+    }
+
+    #[test]
+    fn breaks_counts_down_by_rule() {
+        let input =
+            "Reviewed with Zorbin Quaxley, Dr. Alan Brooks and Dr. Ruth Hale. MRN: 4829130.";
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (_, stats) = scrubber.scrub(input, &HashSet::new());
+        let rules: Vec<(&str, usize)> = stats
+            .rules
+            .iter()
+            .map(|(rule, count)| (rule.as_str(), *count))
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                ("mrn.mrn-label", 1),
+                ("person.capital-sequence", 1),
+                ("person.titled-name", 2),
+            ]
+        );
+        assert_eq!(stats.rules.values().sum::<usize>(), stats.total());
+
+        let options = ReportOptions {
+            rules: true,
+            ..scrubber.report_options(&HashSet::new())
+        };
+        assert!(stats
+            .render_human(&options)
+            .contains("By rule:\n  mrn.mrn-label: 1\n  person.capital-sequence: 1\n"));
+        assert!(!stats
+            .render_human(&ReportOptions::default())
+            .contains("By rule"));
+    }
+
+    #[test]
+    fn counts_unique_values_apart_from_mentions() {
+        let input = "Dr. Alan Brooks saw her. DR. ALAN  BROOKS called 312-555-0182 and \
+                     Dr. Alan Brooks left.";
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (_, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(stats.persons, 3);
+        assert_eq!(stats.unique["person"], 1);
+        assert_eq!(stats.unique["phone"], 1);
+        let rendered = stats.render_human(&ReportOptions::default());
+        assert!(rendered.contains("persons        : 3 mentions, 1 unique\n"));
+        assert!(rendered.contains("phones         : 1 mention, 1 unique\n"));
+        assert!(!rendered.to_lowercase().contains("brooks"));
+
+        let mut total = stats.clone();
+        total += &stats;
+        assert_eq!(total.unique["person"], 2);
+    }
+
+    #[test]
+    fn spans_name_their_rule_and_min_confidence_drops_guesses() {
+        let input = "SSN 123-45-6789. Reviewed with Zorbin Quaxley and Dr. Alan Brooks.";
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        let rules: Vec<(&str, Confidence)> = result
+            .spans
+            .iter()
+            .map(|span| (span.rule, span.confidence))
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                ("ssn", Confidence::High),
+                ("capital-sequence", Confidence::Low),
+                ("titled-name", Confidence::High),
+            ]
+        );
+        assert_eq!(result.stats.persons, 2);
+        assert_eq!(result.stats.low_confidence["person"], 1);
+        assert!(result
+            .stats
+            .render_human(&ReportOptions::default())
+            .contains("persons        : 2 mentions, 2 unique (1 low-confidence)\n"));
+
+        let config = ScrubberConfig {
+            min_confidence: Confidence::Medium,
+            ..ScrubberConfig::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (text, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(
+            text,
+            "SSN [SSN]. Reviewed with Zorbin Quaxley and [PERSON]."
+        );
+        assert!(stats.low_confidence.is_empty());
+        let description = scrubber.describe(&HashSet::new());
+        let rule = description.rule("capital-sequence").expect("rule");
+        assert_eq!(rule.disabled_by, Some(DisabledBy::Confidence));
+        assert_eq!(
+            description.rule("first-last").expect("rule").disabled_by,
+            None
+        );
+    }
+
+    #[test]
+    fn describe_reports_character_classes() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let description = scrubber.describe(&HashSet::new());
+        let (expected, name) = if cfg!(feature = "ascii-only") {
+            (CharacterClasses::Ascii, "ascii")
+        } else {
+            (CharacterClasses::Unicode, "unicode")
+        };
+        assert_eq!(description.character_classes, expected);
+        assert!(description.render_table().starts_with(&format!(
+            "Regulation: none, Safe Harbor: off, field mode: off, character classes: {}\n",
+            name
+        )));
+    }
+
+    #[test]
+    fn account_numbers_are_separate_from_mrns() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "MRN: 4432211. Account #: 99871234, Guarantor # G-55120; account balance due.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(output, "[MRN]. [ACCOUNT], [ACCOUNT]; account balance due.");
+        assert_eq!((stats.mrn, stats.accounts), (1, 2));
+
+        let skip = HashSet::from([Category::Mrn.into()]);
+        let (output, stats) = scrubber.scrub(input, &skip);
+        assert_eq!(
+            output,
+            "MRN: 4432211. [ACCOUNT], [ACCOUNT]; account balance due."
+        );
+        assert_eq!((stats.mrn, stats.accounts), (0, 2));
+
+        let skip = HashSet::from([Category::Account.into()]);
+        let (output, stats) = scrubber.scrub(input, &skip);
+        assert_eq!(
+            output,
+            "[MRN]. Account #: 99871234, Guarantor # G-55120; account balance due."
+        );
+        assert_eq!((stats.mrn, stats.accounts), (1, 0));
+    }
+
+    #[test]
+    fn redacts_titles_and_addresses() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Dr. Harmon visited 128 Elmwood Drive.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(PERSON_TOKEN));
+        assert!(output.contains(ADDRESS_TOKEN));
+        assert_eq!(stats.persons, 1);
+        assert_eq!(stats.addresses, 1);
+    }
+
+    #[test]
+    fn safe_harbor_redacts_insurance_and_license() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let input = "Policy Number 8392-77-551 and Driver's License #A123-4567.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(INSURANCE_TOKEN));
+        assert!(output.contains(LICENSE_TOKEN));
+        assert!(stats.insurance_ids >= 1);
+        assert!(stats.licenses >= 1);
+    }
+
+    #[test]
+    fn redacts_medicare_beneficiary_identifiers() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        for valid in [
+            "1EG4-TE5-MK73",
+            "1EG4TE5MK73",
+            "9AA0-AA0-AA00",
+            "2C94-W71-XY45",
+        ] {
+            let (output, stats) =
+                scrubber.scrub(&format!("MBI {} on file", valid), &HashSet::new());
+            assert_eq!(output, "MBI [MBI] on file", "{}", valid);
+            assert_eq!(stats.mbi, 1);
+        }
+        for invalid in [
+            "0EG4-TE5-MK73", // position 1 is 1-9
+            "1SG4-TE5-MK73", // S is never used
+            "1EB4-TE5-MK73", // nor B
+            "12G4-TE5-MK73", // position 2 is a letter
+            "1EGA-TE5-MK73", // position 4 is a digit
+            "1EG4-TE5-MK7A", // position 11 is a digit
+            "1EG4-TE5MK73",  // dashes come in pairs
+            "1EG4TE5MK7",    // too short
+            "1eg4-te5-mk73", // upper case only
+        ] {
+            let (_, stats) = scrubber.scrub(&format!("MBI {} on file", invalid), &HashSet::new());
+            assert_eq!(stats.mbi, 0, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn segment_fallback_replaces_contact_lists_only() {
+        let config = ScrubberConfig {
+            segment_fallback: Some(SegmentFallback {
+                threshold: 3,
+                density: None,
+                mode: SegmentMode::Line,
+            }),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let input = "Dr. Harmon saw her at St. Mary Hospital on 03/14/2024 for chest pain.\n\
+                     Contacts: Jane Doe 312-555-0182 jane.doe@example.com, 128 Elmwood Drive\n";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(
+            output,
+            "[PERSON] saw her at [FACILITY] on [DATE] for chest pain.\n[REDACTED_SEGMENT]\n"
+        );
+        assert_eq!(stats.segments, 1);
+        assert_eq!((stats.emails, stats.phones, stats.addresses), (0, 0, 0));
+        assert_eq!((stats.dates, stats.persons, stats.facilities), (1, 1, 1));
+        assert_eq!(stats.segment_subsumed["person"], 1);
+        assert_eq!(stats.segment_subsumed["email"], 1);
+        assert_eq!(stats.segment_subsumed["phone"], 1);
+        assert_eq!(stats.segment_subsumed["address"], 1);
+
+        let skip = HashSet::from([Category::Segment.into()]);
+        let (output, stats) = scrubber.scrub(input, &skip);
+        assert!(output.ends_with("\nContacts: [PERSON] [PHONE] [EMAIL], [ADDRESS]\n"));
+        assert!(stats.segment_subsumed.is_empty());
+    }
+
+    #[test]
+    fn redacts_labeled_insurance_ids() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let skip = HashSet::new();
+        let cases = [
+            ("Aetna Member ID: W123456789", "Aetna [INSURANCE]"),
+            ("Group #: 48122-A on file", "[INSURANCE] on file"),
+            (
+                "Payer ID 60054 and BCBS ID XYZ8812",
+                "[INSURANCE] and [INSURANCE]",
+            ),
+            (
+                "Policy Number 8392 77 551 confirmed",
+                "[INSURANCE] confirmed",
+            ),
+            (
+                "insurance coverage discussed",
+                "insurance coverage discussed",
+            ),
+            ("Plan 2: continue lisinopril", "Plan 2: continue lisinopril"),
+            ("Group therapy weekly", "Group therapy weekly"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(scrubber.scrub(input, &skip).0, expected, "{}", input);
+        }
+
+        let config = ScrubberConfig {
+            insurance_labels: vec!["Medicaid".to_string()],
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::SafeHarbor).expect("scrubber");
+        let (output, stats) = scrubber.scrub("Medicaid # IL55012345 active", &skip);
+        assert_eq!(output, "[INSURANCE] active");
+        assert_eq!(stats.insurance_ids, 1);
+        let rule = scrubber.describe(&skip);
+        assert_eq!(
+            rule.rule("insurance").expect("rule").source,
+            RuleSource::Config
+        );
+    }
+
+    #[test]
+    fn person_mode_levels_run_progressively_more_matchers() {
+        let input = "Seen by Dr. Okafor; Lopez called. Linda Ferreira and daughter Quinn \
+                     visited with Harlow Pemberton.";
+        let run = |person_mode| {
+            let config = ScrubberConfig {
+                person_mode,
+                ..Default::default()
+            };
+            let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+            let (output, stats) = scrubber.scrub(input, &HashSet::new());
+            let rules: Vec<String> = stats.rules.keys().cloned().collect();
+            (output, stats.persons, rules)
+        };
+
+        let (output, persons, rules) = run(PersonMode::Strict);
+        assert_eq!(
+            output,
+            "Seen by [PERSON]; [PERSON] called. Linda Ferreira and daughter Quinn visited with \
+             Harlow Pemberton."
+        );
+        assert_eq!(persons, 2);
+        assert_eq!(rules, ["person.name-dictionary", "person.titled-name"]);
+
+        let (output, persons, rules) = run(PersonMode::Balanced);
+        assert_eq!(
+            output,
+            "Seen by [PERSON]; [PERSON] called. [PERSON] and daughter [PERSON] visited with \
+             Harlow Pemberton."
+        );
+        assert_eq!(persons, 4);
+        assert!(!rules.contains(&"person.capital-sequence".to_string()));
+
+        let (output, persons, rules) = run(PersonMode::Aggressive);
+        assert_eq!(
+            output,
+            "Seen by [PERSON]; [PERSON] called. [PERSON] and daughter [PERSON] visited with \
+             [PERSON]."
+        );
+        assert_eq!(persons, 5);
+        assert!(rules.contains(&"person.capital-sequence".to_string()));
+
+        let scrubber = Scrubber::new(
+            ScrubberConfig {
+                person_mode: PersonMode::Strict,
+                ..Default::default()
+            },
+            ScrubMode::Standard,
+        )
+        .expect("scrubber");
+        let description = scrubber.describe(&HashSet::new());
+        assert_eq!(
+            description.rule("first-last").expect("rule").disabled_by,
+            Some(DisabledBy::PersonMode)
+        );
+        let (_, stats) = scrubber.scrub(input, &HashSet::new());
+        let report = stats.render_human(&ReportOptions {
+            rules: true,
+            ..scrubber.report_options(&HashSet::new())
+        });
+        assert!(
+            report.contains(
+                "off by person_mode strict: relative-name, first-last, capital-sequence, \
+                 first-name-narrative"
+            ),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn config_titles_add_to_or_replace_the_honorifics() {
+        let skip = HashSet::new();
+        let default =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Seen by charge nurse Quill; sheikh Hamdan; cpl. Osei.";
+        assert_eq!(default.scrub(input, &skip).0, input);
+
+        let config = ScrubberConfig {
+            titles: vec![
+                "Charge".to_string(),
+                "Charge Nurse".to_string(),
+                "Sheikh".to_string(),
+                "Cpl.".to_string(),
+            ],
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        assert_eq!(
+            scrubber.scrub(input, &skip).0,
+            "Seen by [PERSON]; [PERSON]; [PERSON]."
+        );
+        assert_eq!(
+            scrubber.scrub("Charge\nNurse Quill; cpl Osei", &skip).0,
+            "[PERSON]; [PERSON]"
+        );
+        let defaults = "seen by Dr. Okafor; Rev. Imbert.";
+        assert_eq!(
+            scrubber.scrub(defaults, &skip).0,
+            "seen by [PERSON]; [PERSON]."
+        );
+
+        let config = ScrubberConfig {
+            titles_replace: Some(vec!["Elder".to_string()]),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        assert_eq!(
+            scrubber.scrub("elder Tanaka; seen by Dr. Okafor.", &skip).0,
+            "[PERSON]; seen by Dr. Okafor."
+        );
+    }
+
+    #[test]
+    fn config_mrn_labels_and_pattern_extend_the_label_rule() {
+        let skip = HashSet::new();
+        let config = ScrubberConfig {
+            mrn_labels: vec!["CSN".to_string(), "FIN".to_string(), "Enc #".to_string()],
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let cases = [
+            ("CSN: 884422011 opened", "[MRN] opened"),
+            ("Enc # E-20931 closed", "[MRN] closed"),
+            ("MRN: 4432211 on file", "[MRN] on file"),
+            ("Patient ID 77-1234", "[MRN]"),
+            (
+                "Financial counseling offered",
+                "Financial counseling offered",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(scrubber.scrub(input, &skip).0, expected, "{}", input);
+        }
+        assert_eq!(
+            scrubber
+                .describe(&skip)
+                .rule("mrn-label")
+                .expect("rule")
+                .source,
+            RuleSource::Config
+        );
+
+        let config = ScrubberConfig {
+            mrn_labels: vec!["FIN".to_string()],
+            mrn_pattern: Some(r"[A-Z]\d{6,}\b".to_string()),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        assert_eq!(
+            scrubber.scrub("FIN: K2204417; Chart reviewed", &skip).0,
+            "[MRN]; Chart reviewed"
+        );
+
+        let config = ScrubberConfig {
+            mrn_pattern: Some("[A-Z".to_string()),
+            ..Default::default()
+        };
+        let err = Scrubber::new(config, ScrubMode::Standard)
+            .err()
+            .expect("bad pattern");
+        assert!(err.to_string().contains("mrn_pattern"), "{:#}", err);
+    }
+
+    #[test]
+    fn redacts_passport_and_immigration_numbers_under_safe_harbor() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let skip = HashSet::new();
+        let cases = [
+            ("US passport 512345678 on file", "US [GOVT_ID] on file"),
+            ("UK Passport No. 103456789.", "UK [GOVT_ID]."),
+            ("Visa #: C12345678 expires", "[GOVT_ID] expires"),
+            ("A-Number A012345678 per intake", "[GOVT_ID] per intake"),
+            ("Alien Registration # 087654321 noted", "[GOVT_ID] noted"),
+            (
+                "Enrolled in the Passport to Wellness program",
+                "Enrolled in the Passport to Wellness program",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(scrubber.scrub(input, &skip).0, expected, "{}", input);
+        }
+        let (_, stats) = scrubber.scrub(cases[0].0, &skip);
+        assert_eq!((stats.govt_ids, stats.licenses), (1, 0));
+
+        let standard =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        assert_eq!(standard.scrub(cases[0].0, &skip).1.govt_ids, 0);
+
+        let config = ScrubberConfig {
+            govt_id_labels: vec!["Green Card".to_string()],
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::SafeHarbor).expect("scrubber");
+        let (output, _) = scrubber.scrub("Green Card 123456789 seen", &skip);
+        assert_eq!(output, "[GOVT_ID] seen");
+    }
+
+    #[test]
+    fn redacts_labeled_certificate_and_case_numbers() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let skip = HashSet::new();
+        let cases = [
+            (
+                "Guardianship Case No. 2023-GD-00412 filed",
+                "Guardianship [CERT] filed",
+            ),
+            (
+                "Death Cert #: 123-45-678901 received",
+                "Death [CERT] received",
+            ),
+            ("Docket 23-CV-1182 continued", "[CERT] continued"),
+            (
+                "Birth Certificate B2019-0044 on file",
+                "Birth [CERT] on file",
+            ),
+            (
+                "Call in case the patient falls again",
+                "Call in case the patient falls again",
+            ),
+            ("Certified nurse present", "Certified nurse present"),
+            ("Case reviewed with team", "Case reviewed with team"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(scrubber.scrub(input, &skip).0, expected, "{}", input);
+        }
+        let standard =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        assert_eq!(standard.scrub(cases[1].0, &skip).1.certificates, 0);
+
+        let config = ScrubberConfig {
+            certificate_labels: vec!["Guardianship No".to_string()],
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::SafeHarbor).expect("scrubber");
+        let (output, stats) = scrubber.scrub("Guardianship No GD-55012 active", &skip);
+        assert_eq!(output, "[CERT] active");
+        assert_eq!(stats.certificates, 1);
+        assert_eq!(
+            scrubber
+                .describe(&skip)
+                .rule("certificate")
+                .expect("rule")
+                .source,
+            RuleSource::Config
+        );
+    }
+
+    #[test]
+    fn validates_dea_and_npi_check_digits() {
+        assert!(is_valid_dea("AB1234563"));
+        assert!(is_valid_dea("F91234563"));
+        assert!(!is_valid_dea("AB1234567"));
+        assert!(!is_valid_dea("IB1234563"));
+        assert!(is_valid_npi("1234567893"));
+        assert!(!is_valid_npi("1234567890"));
+        assert!(!is_valid_npi("3234567893"));
+    }
+
+    #[test]
+    fn redacts_license_dea_and_npi_numbers() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let skip = HashSet::new();
+        let cases = [
+            ("License #: A-123456 on file", "[LICENSE] on file"),
+            (
+                "Signed by prescriber, DEA AB1234563",
+                "Signed by prescriber, DEA [LICENSE]",
+            ),
+            (
+                "Signed by prescriber, DEA AB1234567",
+                "Signed by prescriber, DEA AB1234567",
+            ),
+            ("Attending NPI: 1234567893.", "Attending [LICENSE]."),
+            ("License expired last year", "License expired [REL_DATE]"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(scrubber.scrub(input, &skip).0, expected, "{}", input);
+        }
+
+        // Without the check digit or the label these are redacted as other number types.
+        for input in ["attending npi: 1234567890", "Accession 1234567893"] {
+            let (_, stats) = scrubber.scrub(input, &skip);
+            assert_eq!((stats.licenses, stats.total()), (0, 1), "{}", input);
+        }
+    }
+
+    #[test]
+    fn safe_harbor_redacts_vehicle_and_device() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let input = "VIN 1HGCM82633A004352 and Device Serial S/N-4455-XYZ.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(VEHICLE_TOKEN));
+        assert!(output.contains(DEVICE_TOKEN));
+        assert!(stats.vehicles >= 1);
+        assert!(stats.devices >= 1);
+    }
+
+    #[test]
+    fn parses_gs1_udis_to_their_end() {
+        let parenthesized = "(01)00643169007222(17)250101(10)ABC123(21)987654";
+        assert_eq!(gs1_udi_end(parenthesized, 0), Some(parenthesized.len()));
+        let spaced = "(01) 00643169007222 (17) 250101 (21)987654.";
+        assert_eq!(gs1_udi_end(spaced, 0), Some(spaced.len() - 1));
+        let flat = "01006431690072221725010110ABC123 noted";
+        assert_eq!(gs1_udi_end(flat, 0), Some(32));
+        assert_eq!(gs1_udi_end("(01)00643169007223(21)987654", 0), None);
+        assert!(is_valid_hibcc("+A99912345/$$52001510X33"));
+        assert!(!is_valid_hibcc("+A99912345/$$52001510X34"));
+    }
+
+    #[test]
+    fn redacts_udis_and_labeled_serials() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let input =
+            "Medtronic pump SN: NGP123456H. UDI (01)00643169007222(17)250101(10)ABC123(21)987654 \
+                     and 01006431690072221725010110ABC123; HIBCC +A99912345/$$52001510X33. \
+                     Lot 4471A, Serial 88213. Pump settings unchanged.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(
+            output,
+            "Medtronic [DEVICE]. UDI [DEVICE] \
+             and [DEVICE]; HIBCC [DEVICE]. \
+             [DEVICE], [DEVICE]. Pump settings unchanged."
+        );
+        assert_eq!(stats.devices, 6);
+    }
+
+    #[test]
+    fn validates_vin_check_digits() {
+        assert!(is_valid_vin("1HGCM82633A004352"));
+        assert!(is_valid_vin("1M8GDM9AXKP042788"));
+        assert!(!is_valid_vin("1HGCM82643A004352"));
+        assert!(!is_valid_vin("1HGCM82633A00435"));
+        assert!(!is_valid_vin("1HGCM8263OA004352"));
+    }
+
+    #[test]
+    fn redacts_vins_and_labeled_plates() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let input = "VIN 1HGCM82633A004352, plate ABC-1234 (IL), license plate 7XYZ219. \
+                     Mistyped as 1HGCM82643A004352. Order code ABC-1234; tag along.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(
+            output,
+            "VIN [VEHICLE], [VEHICLE] (IL), [VEHICLE]. \
+             Mistyped as 1HGCM82643A004352. Order code ABC-1234; tag along."
+        );
+        assert_eq!(stats.vehicles, 3);
+
+        let (output, _) = Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard)
+            .expect("scrubber")
+            .scrub("VIN 1HGCM82633A004352", &HashSet::new());
+        assert_eq!(output, "VIN 1HGCM82633A004352");
+    }
+
+    #[test]
+    fn safe_harbor_redacts_ip_addresses() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let input = "Client connected from 192.168.10.24 and 10.0.0.5.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(IP_TOKEN));
+        assert!(stats.ip_addresses >= 2);
+    }
+
+    #[test]
+    fn redacts_saint_facilities_with_curly_apostrophe() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Transferred from St. John\u{2019}s Medical Center.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(FACILITY_TOKEN));
+        assert_eq!(stats.facilities, 1);
+    }
+
+    #[test]
+    fn relative_dates_detected() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Symptoms started 3 days ago and worsened yesterday.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert!(output.contains(REL_DATE_TOKEN));
+        assert_eq!(stats.relative_dates, 2);
+    }
+
+    #[test]
+    fn clinical_day_counts_and_config_phrases_are_relative_dates() {
+        let skip = HashSet::new();
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "POD 3, afebrile. Post-op day #4 ambulating. Hospital day 5; day of discharge TBD.",
+            &skip,
+        );
+        assert_eq!(
+            output,
+            "[REL_DATE], afebrile. [REL_DATE] ambulating. [REL_DATE]; day of discharge TBD."
+        );
+        assert_eq!(stats.relative_dates, 3);
+        let input = "Seen the other day; worse a few weeks back.";
+        assert_eq!(scrubber.scrub(input, &skip).0, input);
+
+        let config = ScrubberConfig {
+            relative_date_phrases: vec![
+                "the other day".to_string(),
+                "a few weeks back".to_string(),
+                "earlier this week".to_string(),
+            ],
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(input, &skip);
+        assert_eq!(output, "Seen [REL_DATE]; worse [REL_DATE].");
+        assert_eq!(stats.relative_dates, 2);
+        assert_eq!(
+            scrubber.scrub("Earlier this week she fell.", &skip).0,
+            "[REL_DATE] she fell."
+        );
+    }
+
+    #[test]
+    fn resolves_saint_by_context() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Seen at St. John's Medical Center, lives on St. Charles Avenue, referred by Dr. St. Pierre.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(
+            output,
+            "Seen at [FACILITY], lives on [ADDRESS], referred by [PERSON]."
+        );
+        assert_eq!(stats.facilities, 1);
+        assert_eq!(stats.addresses, 1);
+        assert_eq!(stats.persons, 1);
+    }
+
+    #[test]
+    fn unresolved_saint_surname_is_not_a_facility() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub("Patient St. Pierre was seen.", &HashSet::new());
+        assert_eq!(output, "Patient St. Pierre was seen.");
+        assert_eq!(stats.facilities, 0);
+    }
+
+    #[test]
+    fn skipped_saint_context_is_not_claimed_by_other_categories() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let mut skip = HashSet::new();
+        skip.insert(Category::Address.into());
+        let input = "Lives on St. Charles Avenue.";
+        let (output, stats) = scrubber.scrub(input, &skip);
+        assert!(output.contains("St. Charles Avenue"));
+        assert_eq!(stats.facilities, 0);
+    }
+
+    #[test]
+    fn describe_reports_config_rules_and_skipped_categories() {
+        let config = ScrubberConfig {
+            keywords: vec!["Seattle Grace".to_string()],
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let mut skip = HashSet::new();
+        skip.insert(Category::Zip.into());
+        let description = scrubber.describe(&skip);
+
+        let facility = description
+            .rule("facility-dictionary")
+            .expect("facility rule");
+        assert_eq!(facility.source, RuleSource::Config);
+        assert_eq!(
+            facility.summary,
+            format!(
+                "{} facility terms",
+                Dictionaries::builtin()
+                    .expect("dictionaries")
+                    .facility_terms
+                    .entries
+                    .len()
+                    + 1
+            )
+        );
+        assert_eq!(facility.disabled_by, None);
+
+        let zip = description.rule("zip").expect("zip rule");
+        assert_eq!(zip.disabled_by, Some(DisabledBy::Skip));
+        let ip = description.rule("ip").expect("ip rule");
+        assert_eq!(ip.disabled_by, Some(DisabledBy::Profile));
+        assert_eq!(description.rules.len(), scrubber.detectors.len());
+    }
+
+    #[test]
+    fn field_values_outside_field_mode() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let skip = HashSet::new();
+        assert_eq!(scrubber.scrub("Jane", &skip).0, "Jane");
+        assert_eq!(scrubber.scrub("60614", &skip).0, ZIP_TOKEN);
+        assert_eq!(scrubber.scrub("3/14/24", &skip).0, DATE_TOKEN);
+        assert_eq!(scrubber.scrub("3/14", &skip).0, "3/14");
+    }
+
+    #[test]
+    fn field_mode_redacts_short_values() {
+        let config = ScrubberConfig {
+            field_mode: true,
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let skip = HashSet::new();
+        assert_eq!(scrubber.scrub("Jane", &skip).0, PERSON_TOKEN);
+        assert_eq!(scrubber.scrub("Smith", &skip).0, PERSON_TOKEN);
+        assert_eq!(scrubber.scrub("60614", &skip).0, ZIP_TOKEN);
+        assert_eq!(scrubber.scrub("3/14/24", &skip).0, DATE_TOKEN);
+        assert_eq!(scrubber.scrub("3/14", &skip).0, DATE_TOKEN);
+    }
+
+    #[test]
+    fn whitespace_only_input_is_empty_in_both_modes() {
+        for field_mode in [false, true] {
+            let config = ScrubberConfig {
+                field_mode,
+                ..Default::default()
+            };
+            let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+            let (output, stats) = scrubber.scrub("  \t ", &HashSet::new());
+            assert_eq!(output, "");
+            assert_eq!(stats.total(), 0);
+        }
+    }
+
+    #[test]
+    fn output_keeps_line_endings_unless_normalized() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let skip = HashSet::new();
+        let crlf = "Call 312-555-0182\r\n.\r\nEmail jane.doe@example.com\r\n";
+        assert_eq!(
+            scrubber.scrub(crlf, &skip).0,
+            "Call [PHONE]\r\n.\r\nEmail [EMAIL]\r\n"
+        );
+        assert_eq!(
+            scrubber
+                .scrub("Call 312-555-0182.\nEmail jane.doe@example.com", &skip)
+                .0,
+            "Call [PHONE].\nEmail [EMAIL]"
+        );
+        assert_eq!(scrubber.scrub("", &skip).0, "");
+
+        let config = ScrubberConfig {
+            normalize_newlines: true,
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        assert_eq!(
+            scrubber.scrub(crlf, &skip).0,
+            "Call [PHONE].\r\nEmail [EMAIL]"
+        );
+        assert_eq!(scrubber.scrub("", &skip).0, "");
+    }
+
+    #[test]
+    fn capital_sequence_ignores_sentence_opener() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let skip = HashSet::new();
+        let (output, stats) =
+            scrubber.scrub("Seen Meredith Grey in clinic. Follow Up in clinic.", &skip);
+        assert_eq!(output, "Seen [PERSON] in clinic. Follow Up in clinic.");
+        assert_eq!(stats.persons, 1);
+    }
+
+    fn birth_year_scrubber(mode: BirthYearMode) -> Scrubber {
+        let config = ScrubberConfig {
+            reference_date: Some("2024-06-01".to_string()),
+            birth_year_mode: mode,
+            ..Default::default()
+        };
+        Scrubber::new(config, ScrubMode::Standard).expect("scrubber")
+    }
+
+    #[test]
+    fn redacts_birth_years_over_89() {
+        let scrubber = birth_year_scrubber(BirthYearMode::Redact);
+        let skip = HashSet::new();
+        let (output, stats) =
+            scrubber.scrub("She was born in 1931 and has a 1929 birth year.", &skip);
+        assert_eq!(
+            output,
+            "She was born in [DATE] and has a [DATE] birth year."
+        );
+        assert_eq!(stats.dates, 2);
+
+        let scrubber = birth_year_scrubber(BirthYearMode::Decade);
+        let (output, _) = scrubber.scrub("Born in 1931.", &skip);
+        assert_eq!(output, "Born in 1930s.");
+    }
+
+    #[test]
+    fn keeps_recent_and_uncued_years() {
+        let scrubber = birth_year_scrubber(BirthYearMode::Redact);
+        let skip = HashSet::new();
+        let (output, stats) = scrubber.scrub(
+            "Born in 1985. Studies from 1929 were cited. Recalls the 1931 flood. YOB 1950.",
+            &skip,
+        );
+        assert_eq!(
+            output,
+            "Born in 1985. Studies from 1929 were cited. Recalls the 1931 flood. YOB 1950."
+        );
+        assert_eq!(stats.dates, 0);
+    }
+
+    #[test]
+    fn yob_labels_and_date_modes_never_reveal_an_old_birth_year() {
+        let skip = HashSet::new();
+        for date_mode in [DateMode::Redact, DateMode::Year, DateMode::Token] {
+            let config = ScrubberConfig {
+                reference_date: Some("2024-06-01".to_string()),
+                date_mode: Some(date_mode),
+                ..Default::default()
+            };
+            let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+            let (output, stats) = scrubber.scrub("YOB: 1930. b. 1940. YOB 1935.", &skip);
+            assert_eq!(output, "YOB: [DATE]. b. 1940. YOB 1935.", "{:?}", date_mode);
+            assert_eq!(stats.dates, 1);
+        }
+        let (output, _) = birth_year_scrubber(BirthYearMode::Decade).scrub("YOB 1931", &skip);
+        assert_eq!(output, "YOB 1930s");
+    }
+
+    #[test]
+    fn redacts_mrn_segment_of_composite_ids() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Specimen SPEC_2024_4829130_01 and study RAD^4829130^CT.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(
+            output,
+            "Specimen SPEC_2024_[MRN]_01 and study RAD^[MRN]^CT."
+        );
+        assert_eq!(stats.mrn, 2);
+    }
+
+    #[test]
+    fn redacts_whole_composite_ids() {
+        let config = ScrubberConfig {
+            composite_ids: CompositeIdMode::Whole,
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let input = "Specimen SPEC-2024-4829130-01 and study RAD^4829130^CT.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(output, "Specimen [MRN] and study [MRN].");
+        assert_eq!(stats.mrn, 2);
+    }
+
+    #[test]
+    fn limited_dataset_keeps_dates_and_truncates_zip() {
+        let config = ScrubberConfig {
+            regulation: Some(Regulation::LimitedDataset),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let input = "Seen 03/14/2021 at 60614-2201, member ID AB12345.";
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(output, "Seen 03/14/2021 at 606**, [INSURANCE].");
+        assert_eq!(stats.zip_codes, 1);
+        assert_eq!(stats.dates, 0);
+
+        let description = scrubber.describe(&HashSet::new());
+        assert_eq!(description.regulation, Some(Regulation::LimitedDataset));
+        let date = description.rule("date").expect("date rule");
+        assert_eq!(date.disabled_by, Some(DisabledBy::Profile));
+    }
 
-fn build_dictionary_regex(entries: &[String]) -> Result<Option<Regex>> {
-    if entries.is_empty() {
-        return Ok(None);
+    #[test]
+    fn profile_config_key_selects_a_regulation_bundle() {
+        let config: ScrubberConfig =
+            serde_json::from_str(r#"{"profile": "safe-harbor", "reference_date": "2024-06-01"}"#)
+                .expect("config");
+        assert_eq!(config.regulation, Some(Regulation::SafeHarbor));
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub("Seen 03/14/2021 from 10.42.7.19.", &HashSet::new());
+        assert_eq!(output, "Seen 2021 from [IP].");
+        assert_eq!(stats.profile, Some(Regulation::SafeHarbor));
+
+        let config = ScrubberConfig {
+            regulation: Some(Regulation::Standard),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, _) = scrubber.scrub("Seen 03/14/2021 from 10.42.7.19.", &HashSet::new());
+        assert_eq!(output, "Seen [DATE] from 10.42.7.19.");
     }
 
-    let patterns: Vec<String> = entries
-        .iter()
-        .map(|value| {
-            let mut escaped = regex::escape(value);
-            escaped = escaped.replace('\u{2019}', "[\u{2019}']");
-            escaped = escaped.replace("'", "[\u{2019}']");
-            escaped = escaped.replace(r"\ ", r"\s+");
-            escaped
-        })
-        .collect();
+    #[test]
+    fn explicit_zip_mode_overrides_regulation() {
+        let config = ScrubberConfig {
+            regulation: Some(Regulation::LimitedDataset),
+            zip_mode: Some(ZipMode::Redact),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, _) = scrubber.scrub("ZIP 60614", &HashSet::new());
+        assert_eq!(output, "ZIP [ZIP]");
+    }
 
-    let joined = patterns.join("|");
-    let pattern = format!("(?i)\\b(?:{})\\b", joined);
-    let regex = Regex::new(&pattern)?;
-    Ok(Some(regex))
-}
+    #[test]
+    fn safe_harbor_mode_redacts_old_ages_and_truncates_zips() {
+        let skip = HashSet::new();
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let input = "A 93 y/o (age: 104) and her 89-year-old sister; ZIP 60614, ZIP 05901-1234.";
+        let (output, stats) = scrubber.scrub(input, &skip);
+        assert_eq!(
+            output,
+            "A [AGE_OVER_89] y/o (age: [AGE_OVER_89]) and her 89-year-old sister; \
+             ZIP 606**, ZIP 000**."
+        );
+        assert_eq!((stats.ages, stats.zip_codes), (2, 2));
 
-fn build_first_last_regex() -> Result<Regex> {
-    let firsts: Vec<String> = COMMON_FIRST_NAMES
-        .iter()
-        .map(|name| regex::escape(name))
-        .collect();
-    let pattern = format!(
-        r"(?xi)\b(?:{})\s+[A-Z][\p{{L}}\u{{2019}}'-]+(?:\s+[A-Z][\p{{L}}\u{{2019}}'-]+)?",
-        firsts.join("|")
-    );
-    Ok(Regex::new(&pattern)?)
-}
+        let standard =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, _) = standard.scrub(input, &skip);
+        assert_eq!(
+            output,
+            "A 93 y/o (age: 104) and her 89-year-old sister; ZIP [ZIP], ZIP [ZIP]."
+        );
+    }
 
-fn build_titled_name_regex() -> Result<Regex> {
-    let pattern = r"(?xi)\b(?:Drs?\.?|Prof\.?|Mr\.?|Mrs\.?|Ms\.?|Mx\.?|Capt\.?|Captain|Lt\.?|Lieutenant|Sgt\.?|Sergeant|Officer|Chief|Judge|Sir|Dame|Madam|Rev\.?|Reverend|Father|Fr\.?|Sister|Brother|Pastor|Chaplain|Rabbi|Imam)\s+[A-Z][\p{L}\u{2019}'-]+(?:\s+[A-Z][\p{L}\u{2019}'-]+)?";
-    Ok(Regex::new(pattern)?)
-}
+    #[test]
+    fn date_modes_render_each_format() {
+        let input = "Seen 03/14/2021, 3-14-21, 2021-03-14, Jan 5, 2020 and January 5 2020.";
+        let expected = [
+            (
+                DateMode::Redact,
+                "Seen [DATE], [DATE], [DATE], [DATE] and [DATE].",
+            ),
+            (DateMode::Year, "Seen 2021, 2021, 2021, 2020 and 2020."),
+            (
+                DateMode::Token,
+                "Seen [DATE:2021], [DATE:2021], [DATE:2021], [DATE:2020] and [DATE:2020].",
+            ),
+        ];
+        for (date_mode, expected) in expected {
+            let config = ScrubberConfig {
+                date_mode: Some(date_mode),
+                reference_date: Some("2024-06-01".to_string()),
+                ..Default::default()
+            };
+            let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+            let (output, stats) = scrubber.scrub(input, &HashSet::new());
+            assert_eq!(output, expected, "{:?}", date_mode);
+            assert_eq!(stats.dates, 5);
+        }
+    }
 
-fn build_capital_sequence_regex() -> Result<Regex> {
-    let pattern = r"(?x)
-        \b
-        [A-Z][\p{L}\u{2019}']+\s+[A-Z][\p{L}\u{2019}']+
-        (?:\s+[A-Z][\p{L}\u{2019}']+)?
-        \b";
-    Ok(Regex::new(pattern)?)
-}
+    #[test]
+    fn labeled_dobs_in_any_format_count_as_dates_not_mrns() {
+        let input = "DOB: 1/2/87\nDate of Birth - Jan 2 1987\nDOB 01021987\nBirthdate: 19870102";
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(
+            output,
+            "DOB: [DATE]\nDate of Birth - [DATE]\nDOB [DATE]\nBirthdate: [DATE]"
+        );
+        assert_eq!((stats.dates, stats.mrn), (4, 0));
 
-fn is_name_stopword(candidate: &str) -> bool {
-    let trimmed = candidate.trim();
-    let lower = trimmed.to_ascii_lowercase();
-    if lower.starts_with("st. ") || lower.starts_with("st ") {
-        return true;
+        let config = ScrubberConfig {
+            date_mode: Some(DateMode::Year),
+            reference_date: Some("2024-06-01".to_string()),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, _) = scrubber.scrub(input, &HashSet::new());
+        assert_eq!(
+            output,
+            "DOB: 1987\nDate of Birth - 1987\nDOB 1987\nBirthdate: 1987"
+        );
+        let (output, stats) = scrubber.scrub("MRN 01021987", &HashSet::new());
+        assert_eq!((output.as_str(), stats.mrn), ("[MRN]", 1));
     }
 
-    let upper: String = trimmed
-        .chars()
-        .filter(|c| c.is_alphanumeric() || *c == ' ')
-        .flat_map(|c| c.to_uppercase())
-        .collect();
-    NAME_STOPLIST.contains(&upper.trim())
-}
+    #[test]
+    fn date_shift_keeps_intervals_and_leaves_relative_dates() {
+        let config = ScrubberConfig {
+            date_shift: Some(DateShift {
+                seed: Some(42),
+                max_days: None,
+            }),
+            reference_date: Some("2024-06-01".to_string()),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "Admitted 03/01/2024, discharged Mar 5, 2024; seen yesterday. Recheck 02/30/2024.",
+            &HashSet::new(),
+        );
 
-fn normalize_input(input: &str) -> String {
-    let nfkc: String = input.nfkc().collect();
-    let mut normalized = nfkc
-        .replace(['\u{2018}', '\u{2019}', '\u{201B}', '\u{2032}'], "'")
-        .replace(['\u{201C}', '\u{201D}', '\u{2033}'], "\"")
-        .replace(['\u{2013}', '\u{2014}', '\u{2212}'], "-")
-        .replace(
-            ['\u{2022}', '\u{00B7}', '\u{2027}', '\u{2043}', '\u{30FB}'],
-            " ",
+        let shifted: Vec<WrittenDate> =
+            Regex::new(r"\d{2}/\d{2}/\d{4}|[A-Z][a-z]{2} \d{1,2}, \d{4}")
+                .unwrap()
+                .find_iter(&output)
+                .map(|mat| {
+                    WrittenDate::parse(mat.as_str(), 2024, DateOrder::Auto).expect("shifted date")
+                })
+                .collect();
+        assert_eq!(shifted.len(), 2, "{}", output);
+        let admitted = CivilDate::parse("2024-03-01").unwrap();
+        let delta = shifted[0].date.days() - admitted.days();
+        assert_ne!(delta, 0);
+        assert!(delta.abs() <= 365);
+        assert_eq!(shifted[1].date.days() - shifted[0].date.days(), 4);
+        assert!(
+            output.contains("seen [REL_DATE]. Recheck [DATE]."),
+            "{}",
+            output
         );
+        assert_eq!((stats.dates, stats.date_shift_fallbacks), (3, 1));
+        assert_eq!(stats.relative_dates, 1);
 
-    normalized = MULTISPACE_RE.replace_all(&normalized, " ").into_owned();
-    normalized
-}
+        let (again, _) = scrubber.scrub("Admitted 03/01/2024", &HashSet::new());
+        assert_eq!(
+            again,
+            format!("Admitted {}", shifted[0].render(shifted[0].date))
+        );
+    }
 
-fn tidy_punctuation(input: &str) -> String {
-    let mut text = SPACE_AROUND_PUNCT_RE.replace_all(input, "$1").into_owned();
-    text = DUP_PUNCT_RE
-        .replace_all(&text, |caps: &Captures| {
-            caps.get(1).map(|m| m.as_str()).unwrap_or("").to_string()
-        })
-        .into_owned();
-    text.trim().to_string()
-}
+    #[test]
+    fn date_shift_without_a_seed_uses_the_config_seed_or_a_drawn_one() {
+        let scrubber = |date_seed: Option<u64>, seed: Option<u64>| {
+            let config = ScrubberConfig {
+                date_shift: Some(DateShift {
+                    seed: date_seed,
+                    max_days: None,
+                }),
+                seed,
+                ..Default::default()
+            };
+            Scrubber::new(config, ScrubMode::Standard).expect("scrubber")
+        };
+        let skip = HashSet::new();
+        let note = "Admitted 03/01/2024.";
+        let shifted = scrubber(Some(42), None).scrub(note, &skip).0;
+        let (output, stats) = scrubber(None, Some(42)).scrub(note, &skip);
+        assert_eq!((output, stats.seed), (shifted, Some(42)));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Category;
-    use std::collections::HashSet;
+        let drawn = scrubber(None, None);
+        let (output, stats) = drawn.scrub(note, &skip);
+        assert_eq!(stats.seed, Some(drawn.seed()));
+        assert_eq!(scrubber(None, stats.seed).scrub(note, &skip).0, output);
+    }
 
     #[test]
-    fn redacts_email_and_phone() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "Reach me at jane.doe@example.com or (555) 867-5309.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(EMAIL_TOKEN));
-        assert!(output.contains(PHONE_TOKEN));
-        assert_eq!(stats.emails, 1);
-        assert_eq!(stats.phones, 1);
+    fn date_order_decides_how_shifted_and_year_mode_dates_are_read() {
+        let skip = HashSet::new();
+        let numeric = Regex::new(r"\d{2}/\d{2}/\d{4}").unwrap();
+        for order in [DateOrder::Auto, DateOrder::Mdy, DateOrder::Dmy] {
+            let config = ScrubberConfig {
+                date_shift: Some(DateShift {
+                    seed: Some(7),
+                    max_days: Some(30),
+                }),
+                date_order: order,
+                reference_date: Some("2024-06-01".to_string()),
+                ..Default::default()
+            };
+            let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+            let (output, _) = scrubber.scrub("Admitted 03/04/2021, discharged 05/04/2021.", &skip);
+            let shifted: Vec<WrittenDate> = numeric
+                .find_iter(&output)
+                .map(|mat| WrittenDate::parse(mat.as_str(), 2024, order).expect("shifted date"))
+                .collect();
+            let stay = shifted[1].date.days() - shifted[0].date.days();
+            let expected = if order == DateOrder::Dmy { 2 } else { 61 };
+            assert_eq!(stay, expected, "{:?}: {}", order, output);
+        }
+
+        let year_mode = |order| {
+            let config = ScrubberConfig {
+                date_mode: Some(DateMode::Year),
+                date_order: order,
+                reference_date: Some("2024-06-01".to_string()),
+                ..Default::default()
+            };
+            let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+            scrubber
+                .scrub("Seen 31/12/2023, 12/31/2023, 31 December 2023.", &skip)
+                .0
+        };
+        assert_eq!(year_mode(DateOrder::Auto), "Seen 2023, 2023, 2023.");
+        assert_eq!(year_mode(DateOrder::Mdy), "Seen [DATE], 2023, 2023.");
+        assert_eq!(year_mode(DateOrder::Dmy), "Seen 2023, [DATE], 2023.");
     }
 
     #[test]
-    fn honors_skip_categories() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "Call 555-111-2222 and email foo@bar.com.";
-        let mut skip = HashSet::new();
-        skip.insert(Category::Phone);
-        let (output, stats) = scrubber.scrub(input, &skip);
-        assert!(output.contains("555-111-2222"));
-        assert!(output.contains(EMAIL_TOKEN));
-        assert_eq!(stats.phones, 0);
-        assert_eq!(stats.emails, 1);
+    fn date_year_mode_still_redacts_dates_of_patients_over_89() {
+        let config = ScrubberConfig {
+            date_mode: Some(DateMode::Year),
+            reference_date: Some("2024-06-01".to_string()),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::SafeHarbor).expect("scrubber");
+        let skip = HashSet::new();
+        let (output, _) = scrubber.scrub("Admitted 03/14/21; DOB 02/03/1930.", &skip);
+        assert_eq!(output, "Admitted 2021; DOB [DATE].");
+        let (output, stats) = scrubber.scrub("A 93 y/o admitted 03/14/21.", &skip);
+        assert_eq!(output, "A [AGE_OVER_89] y/o admitted [DATE].");
+        assert_eq!((stats.ages, stats.dates), (1, 1));
+        let (output, _) = scrubber.scrub("Born in 1931, admitted 03/14/21.", &skip);
+        assert_eq!(output, "Born in [DATE], admitted [DATE].");
     }
 
     #[test]
-    fn redacts_directional_address_and_unit() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "Lives at 1200 W. 31st St., Apt #4B in Chicago.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(ADDRESS_TOKEN));
-        assert!(stats.addresses >= 1);
+    fn redacts_places_below_the_state_level() {
+        let skip = HashSet::new();
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "She lives in Skokie, Cook County, and moved from Ohio. Mail to Springfield, IL 62704. \
+             Seen with Dr. Alan Smith, MD.",
+            &skip,
+        );
+        assert_eq!(
+            output,
+            "She lives in [GEO], [GEO], and moved from Ohio. Mail to [GEO], IL [ZIP]. \
+             Seen with [PERSON] [PERSON], MD."
+        );
+        assert_eq!((stats.geo, stats.zip_codes), (3, 1));
+
+        let config = ScrubberConfig {
+            zip_mode: Some(ZipMode::Truncate),
+            geo_places: vec!["Oak Park".to_string()],
+            redact_states: true,
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "Springfield, IL 62704; grew up in oak park, moved to Ohio, then Annapolis, MD 21401.",
+            &skip,
+        );
+        assert_eq!(
+            output,
+            "[GEO], [GEO] 627**; grew up in [GEO], moved to [GEO], then [GEO], [GEO] 214**."
+        );
+        assert_eq!((stats.geo, stats.zip_codes), (6, 2));
     }
 
     #[test]
-    fn redacts_obfuscated_email() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "Contact jrsmith at midstate dot edu for questions.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(EMAIL_TOKEN));
-        assert_eq!(stats.emails, 1);
+    fn names_after_relationship_words_but_not_pronouns_or_lowercase() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "His wife Zorana called; daughter Ysolde drove him. Lives with brother Quill Nguyen.",
+            &HashSet::new(),
+        );
+        assert_eq!(
+            output,
+            "His wife [PERSON] called; daughter [PERSON] drove him. Lives with brother [PERSON]."
+        );
+        assert_eq!(stats.persons, 3);
+
+        let (output, stats) = scrubber.scrub(
+            "His wife reports he fell. Her son He is unsure; per neighbor, ICU stay.",
+            &HashSet::new(),
+        );
+        assert_eq!(
+            output,
+            "His wife reports he fell. Her son He is unsure; per neighbor, ICU stay."
+        );
+        assert_eq!(stats.persons, 0);
     }
 
     #[test]
-    fn redacts_url() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), true).expect("scrubber");
-        let input = "See http://midstate.example/pt/12345 for records.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(URL_TOKEN));
-        assert_eq!(stats.urls, 1);
+    fn zip_truncate_mode_keeps_three_digits_except_restricted_prefixes() {
+        let config = ScrubberConfig {
+            zip_mode: Some(ZipMode::Truncate),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "ZIP 60614; ZIP 60614-2201; ZIP 03601; ZIP 03601-0042.",
+            &HashSet::new(),
+        );
+        assert_eq!(output, "ZIP 606**; ZIP 606**; ZIP 000**; ZIP 000**.");
+        assert_eq!(stats.zip_codes, 4);
     }
 
     #[test]
-    fn redacts_custom_names() {
+    fn ages_over_89_in_digits_words_and_parentheses() {
+        let skip = HashSet::new();
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::SafeHarbor).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "A ninety-two-year-old, a 94 yo, age 90, an 89 y/o and a one hundred and one year old. \
+             Born 1928 (96 years); follow up in 96 years.",
+            &skip,
+        );
+        assert_eq!(
+            output,
+            "A [AGE_OVER_89]-year-old, a [AGE_OVER_89] yo, age [AGE_OVER_89], an 89 y/o and a \
+             [AGE_OVER_89] year old. Born [DATE] ([AGE_OVER_89] years); follow up in 96 years."
+        );
+        assert_eq!(stats.ages, 5);
+
         let config = ScrubberConfig {
-            names: vec!["Zelda Fitzgerald".to_string()],
+            age_mode: Some(AgeMode::Generalize),
             ..Default::default()
         };
-        let scrubber = Scrubber::new(config, false).expect("scrubber");
-        let input = "Discussed plan with Zelda Fitzgerald today.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(PERSON_TOKEN));
-        assert_eq!(stats.persons, 1);
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let (output, _) = scrubber.scrub("A 92-year-old and a 45-year-old.", &skip);
+        assert_eq!(output, "A 90+-year-old and a 45-year-old.");
+        let skip_age = HashSet::from([Category::Age.into()]);
+        let (output, _) = scrubber.scrub("A 92-year-old.", &skip_age);
+        assert_eq!(output, "A 92-year-old.");
     }
 
     #[test]
-    fn redacts_common_first_last_pair() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "David Harmon discussed the plan.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(PERSON_TOKEN));
-        assert_eq!(stats.persons, 1);
+    fn stats_json_has_a_counter_for_every_category() {
+        use crate::report::SchemaVersion;
+
+        let counters = |json: serde_json::Value| -> Vec<u64> {
+            let mut values: Vec<u64> = json
+                .as_object()
+                .expect("object")
+                .values()
+                .filter_map(serde_json::Value::as_u64)
+                .filter(|&value| value >= 100)
+                .collect();
+            values.sort_unstable();
+            values
+        };
+        let mut stats = ScrubStats::default();
+        for (index, &category) in Category::ALL.iter().enumerate() {
+            stats.record(category, 100 + index);
+        }
+        let expected: Vec<u64> = (100..100 + Category::ALL.len() as u64).collect();
+        assert_eq!(
+            counters(serde_json::to_value(&stats).expect("json")),
+            expected
+        );
+        assert_eq!(
+            counters(stats.to_json_value(SchemaVersion::V1).expect("json")),
+            expected
+        );
+
+        // Zero counts are still written, so the schema does not depend on the input.
+        let empty = serde_json::to_value(ScrubStats::default()).expect("json");
+        let stats = serde_json::to_value(&stats).expect("json");
+        for key in stats.as_object().expect("object").keys() {
+            if stats[key].as_u64().is_some_and(|value| value >= 100) {
+                assert_eq!(empty[key], 0, "{}", key);
+            }
+        }
     }
 
     #[test]
-    fn redacts_extended_honorifics() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "Rev. O'Connor provided counseling.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(PERSON_TOKEN));
-        assert_eq!(stats.persons, 1);
+    fn projects_original_spans_onto_scrubbed_output() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Knee pain \u{2014}  call 555-111-2222 before  clinic .";
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        assert_eq!(result.text, "Knee pain - call [PHONE] before clinic.");
+
+        let before = result.project_span(0..4).expect("before");
+        assert_eq!(&result.text[before], "Knee");
+        let phone = input.find("555").expect("phone");
+        assert_eq!(result.project_span(phone + 4..phone + 7), None);
+        let clinic = input.find("clinic").expect("clinic");
+        let after = result.project_span(clinic..clinic + 6).expect("after");
+        assert_eq!(&result.text[after], "clinic");
     }
 
     #[test]
-    fn redacts_coordinates() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "Coordinates 41.8781° N, 87.6298° W were logged.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(COORD_TOKEN));
-        assert_eq!(stats.coordinates, 1);
+    fn bare_first_names_follow_the_configured_gate() {
+        let scrubber_with = |first_name_alone| {
+            let config = ScrubberConfig {
+                first_name_alone,
+                ..Default::default()
+            };
+            Scrubber::new(config, ScrubMode::Standard).expect("scrubber")
+        };
+        let skip = HashSet::new();
+        let reports = "Linda reports the pain started Monday.";
+        let spoke = "Spoke with Kevin at bedside.";
+        let mark = "Mark the site before the procedure.";
+
+        let off = scrubber_with(FirstNameMode::Off);
+        assert_eq!(off.scrub(spoke, &skip).0, spoke);
+
+        let cue = scrubber_with(FirstNameMode::CueRequired);
+        assert_eq!(cue.scrub(reports, &skip).0, reports);
+        assert_eq!(cue.scrub(spoke, &skip).0, "Spoke with [PERSON] at bedside.");
+        assert_eq!(cue.scrub(mark, &skip).0, mark);
+
+        let aggressive = scrubber_with(FirstNameMode::Aggressive);
+        assert_eq!(
+            aggressive.scrub(reports, &skip).0,
+            "[PERSON] reports the pain started Monday."
+        );
+        assert_eq!(aggressive.scrub(mark, &skip).0, mark);
+        assert_eq!(
+            aggressive.scrub("Linda:\nstable", &skip).0,
+            "Linda:\nstable"
+        );
     }
 
     #[test]
-    fn redacts_titles_and_addresses() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "Dr. Harmon visited 128 Elmwood Drive.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(PERSON_TOKEN));
-        assert!(output.contains(ADDRESS_TOKEN));
-        assert_eq!(stats.persons, 1);
-        assert_eq!(stats.addresses, 1);
+    fn config_skip_applies_to_every_call() {
+        let config: ScrubberConfig = serde_json::from_str(r#"{"skip": ["zip"]}"#).expect("config");
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let none = HashSet::new();
+        assert_eq!(
+            scrubber.scrub("Seen yesterday, ZIP 60614.", &none).0,
+            "Seen [REL_DATE], ZIP 60614."
+        );
+        assert_eq!(
+            scrubber.report_options(&none).skipped,
+            [CategoryKey::from(Category::Zip)]
+        );
+        let description = scrubber.describe(&none);
+        assert_eq!(
+            description.rule("zip").expect("rule").disabled_by,
+            Some(DisabledBy::Skip)
+        );
+
+        let err = serde_json::from_str::<ScrubberConfig>(r#"{"skip": ["zipcode"]}"#)
+            .expect_err("unknown");
+        assert!(err.to_string().contains("expected one of email, phone"));
+        let config: ScrubberConfig =
+            serde_json::from_str(r#"{"skip": ["custom:visit"]}"#).expect("config");
+        assert!(Scrubber::new(config, ScrubMode::Standard).is_err());
     }
 
     #[test]
-    fn safe_harbor_redacts_insurance_and_license() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), true).expect("scrubber");
-        let input = "Policy Number 8392-77-551 and Driver's License #A123-4567.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(INSURANCE_TOKEN));
-        assert!(output.contains(LICENSE_TOKEN));
-        assert!(stats.insurance_ids >= 1);
-        assert!(stats.licenses >= 1);
+    fn config_stoplist_adds_phrases_or_replaces_the_defaults() {
+        let input = "Admitted for COPD Exacerbation, then CT Abdomen was done. \
+                     Zorbin Quaxley reviewed. Cultures showed Mrsa Colonization.";
+        let config = ScrubberConfig {
+            stoplist: vec!["copd  exacerbation".to_string(), "CT".to_string()],
+            ..ScrubberConfig::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        assert_eq!(
+            scrubber.scrub(input, &HashSet::new()).0,
+            "Admitted for COPD Exacerbation, then CT Abdomen was done. \
+             [PERSON] reviewed. Cultures showed Mrsa Colonization."
+        );
+
+        let config = ScrubberConfig {
+            stoplist_replace: Some(vec!["COPD".to_string()]),
+            ..ScrubberConfig::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        assert_eq!(
+            scrubber.scrub(input, &HashSet::new()).0,
+            "Admitted for COPD Exacerbation, then [PERSON] was done. \
+             [PERSON] reviewed. Cultures showed [PERSON]."
+        );
+        let builtin = Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard)
+            .expect("scrubber")
+            .dictionary_versions();
+        assert_ne!(
+            scrubber.dictionary_versions()["name_stoplist"],
+            builtin["name_stoplist"]
+        );
     }
 
     #[test]
-    fn safe_harbor_redacts_vehicle_and_device() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), true).expect("scrubber");
-        let input = "VIN 1HGCM82633A004352 and Device Serial S/N-4455-XYZ.";
+    fn overridden_dictionary_replaces_builtin_list() {
+        let path =
+            std::env::temp_dir().join(format!("scrubber-stoplist-{}.txt", std::process::id()));
+        std::fs::write(&path, "# local stoplist\nRobinson\n").expect("write stoplist");
+        let config = ScrubberConfig {
+            dictionaries: crate::config::DictionaryPaths {
+                name_stoplist: Some(path.clone()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        std::fs::remove_file(&path).ok();
+        let builtin =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+
+        let versions = scrubber.dictionary_versions();
+        let builtin_versions = builtin.dictionary_versions();
+        assert_ne!(versions["name_stoplist"], builtin_versions["name_stoplist"]);
+        assert_eq!(versions["names"], builtin_versions["names"]);
+
+        let input = "Seen by Robinson for MRSA.";
+        assert_eq!(
+            builtin.scrub(input, &HashSet::new()).0,
+            "Seen by [PERSON] for MRSA."
+        );
         let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(VEHICLE_TOKEN));
-        assert!(output.contains(DEVICE_TOKEN));
-        assert!(stats.vehicles >= 1);
-        assert!(stats.devices >= 1);
+        assert_eq!(output, input);
+        assert_eq!(stats.dictionaries, versions);
     }
 
     #[test]
-    fn safe_harbor_redacts_ip_addresses() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), true).expect("scrubber");
-        let input = "Client connected from 192.168.10.24 and 10.0.0.5.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(IP_TOKEN));
-        assert!(stats.ip_addresses >= 2);
+    fn caps_span_records_but_counts_every_redaction() {
+        let config = ScrubberConfig {
+            max_spans: Some(1_000),
+            ..Default::default()
+        };
+        let scrubber = Scrubber::new(config, ScrubMode::Standard).expect("scrubber");
+        let input = "4829130 ".repeat(100_000);
+        let result = scrubber.scrub_detailed(&input, &HashSet::new());
+
+        assert_eq!(result.stats.mrn, 100_000);
+        assert_eq!(result.text.matches(MRN_TOKEN).count(), 100_000);
+        assert_eq!(result.spans.len(), 1_000);
+        assert!(result.stats.spans_truncated);
+        assert_eq!(result.stats.spans_dropped, 99_000);
+
+        let last = result.spans.last().expect("span");
+        assert_eq!(last.category, Category::Mrn.into());
+        assert_eq!(&input[last.original.clone()], "4829130");
+        assert_eq!(&result.text[last.output.clone()], MRN_TOKEN);
     }
 
     #[test]
-    fn redacts_saint_facilities_with_curly_apostrophe() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "Transferred from St. John\u{2019}s Medical Center.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(FACILITY_TOKEN));
-        assert_eq!(stats.facilities, 1);
+    fn spans_map_redactions_to_input_and_output() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Call\u{00A0}\u{00A0}jane.doe@example.com ,  or  312-555-0182.";
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        assert_eq!(result.text, "Call [EMAIL], or [PHONE].");
+        let found: Vec<(CategoryKey, &str, &str)> = result
+            .spans
+            .iter()
+            .map(|span| {
+                (
+                    span.category.clone(),
+                    &input[span.original.clone()],
+                    &result.text[span.output.clone()],
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Category::Email.into(), "jane.doe@example.com", EMAIL_TOKEN),
+                (Category::Phone.into(), "312-555-0182", PHONE_TOKEN),
+            ]
+        );
+        assert!(!result.stats.spans_truncated);
     }
 
     #[test]
-    fn relative_dates_detected() {
-        let scrubber = Scrubber::new(ScrubberConfig::default(), false).expect("scrubber");
-        let input = "Symptoms started 3 days ago and worsened yesterday.";
-        let (output, stats) = scrubber.scrub(input, &HashSet::new());
-        assert!(output.contains(REL_DATE_TOKEN));
-        assert_eq!(stats.relative_dates, 2);
+    fn applying_spans_to_the_input_reproduces_the_output() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Jane Doe (MRN 4829130) seen 03/14/2024 at 1200 Oak Street; \
+                     call 312-555-0182 or jane.doe@example.com.";
+        let result = scrubber.scrub_detailed(input, &HashSet::new());
+        assert!(result.spans.len() >= 5, "{:?}", result.spans);
+
+        let mut rebuilt = String::new();
+        let mut cursor = 0;
+        for span in &result.spans {
+            rebuilt.push_str(&input[cursor..span.original.start]);
+            rebuilt.push_str(&result.text[span.output.clone()]);
+            cursor = span.original.end;
+        }
+        rebuilt.push_str(&input[cursor..]);
+        assert_eq!(rebuilt, result.text);
+    }
+
+    #[test]
+    fn cancelled_token_stops_the_scrub() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Reach me at jane.doe@example.com.";
+        let skip = HashSet::new();
+
+        let token = CancellationToken::new();
+        let result = scrubber
+            .scrub_cancellable(input, &skip, &token)
+            .expect("not cancelled");
+        assert_eq!(result.text, scrubber.scrub(input, &skip).0);
+
+        token.clone().cancel();
+        assert_eq!(
+            scrubber.scrub_cancellable(input, &skip, &token).err(),
+            Some(Cancelled)
+        );
+    }
+
+    #[test]
+    fn redacts_spelled_zips_and_area_prefixes() {
+        let skip = HashSet::new();
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let (output, stats) = scrubber.scrub(
+            "Lives in the 60628 area. Gave zip code six oh six one four. Moved from the 606 area code.",
+            &skip,
+        );
+        assert_eq!(
+            output,
+            "Lives in the [ZIP] area. Gave zip code [ZIP]. Moved from the [ZIP] area code."
+        );
+        assert_eq!(stats.zip_codes, 3);
+        assert_eq!(
+            scrubber.scrub("zip six one", &skip).0,
+            "zip six one",
+            "too few digits to be a ZIP"
+        );
+
+        let geo = Scrubber::new(
+            ScrubberConfig {
+                area_mode: AreaMode::Geo,
+                ..Default::default()
+            },
+            ScrubMode::Standard,
+        )
+        .expect("scrubber");
+        assert_eq!(
+            geo.scrub("Call from area code 606.", &skip).0,
+            "Call from area code [GEO]."
+        );
+
+        let truncating = Scrubber::new(
+            ScrubberConfig {
+                zip_mode: Some(ZipMode::Truncate),
+                ..Default::default()
+            },
+            ScrubMode::Standard,
+        )
+        .expect("scrubber");
+        assert_eq!(
+            truncating
+                .scrub("zip code six oh six one four, the 606 area", &skip)
+                .0,
+            "zip code 606**, the 606 area"
+        );
+    }
+
+    #[test]
+    fn ocr_tolerance_reads_lookalikes_in_labeled_values() {
+        let skip = HashSet::new();
+        let tolerant = Scrubber::new(
+            ScrubberConfig {
+                ocr_tolerant: true,
+                ..Default::default()
+            },
+            ScrubMode::Standard,
+        )
+        .expect("scrubber");
+        let (output, stats) = tolerant.scrub("SSN: 478-29-1O36. MRN 48291l0.", &skip);
+        assert_eq!(output, "SSN: [SSN]. MRN [MRN].");
+        assert_eq!((stats.ssn, stats.mrn), (1, 1));
+        assert_eq!(
+            tolerant.scrub("Phone: (312) 555-Ol82 after 5pm", &skip).0,
+            "Phone: [PHONE] after 5pm"
+        );
+
+        for input in [
+            "MRN Polio",
+            "MRN: Polio",
+            "MRN pending",
+            "Patient ID band checked",
+        ] {
+            assert_eq!(tolerant.scrub(input, &skip).0, input);
+        }
+
+        let strict =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        assert_eq!(
+            strict.scrub("SSN: 478-29-1O36", &skip).0,
+            "SSN: 478-29-1O36"
+        );
+        assert_eq!(
+            tolerant.scrub("Result 478-29-1O36", &skip).0,
+            "Result 478-29-1O36",
+            "no label, no tolerance"
+        );
     }
 }
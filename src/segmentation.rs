@@ -0,0 +1,222 @@
+//! Section and sentence boundaries for clinical notes.
+//!
+//! Both functions only look at the current line (sections) or the preceding token and the next
+//! character (sentences), so boundaries found in a prefix that ends at a line break do not move
+//! when more text is appended. That keeps them usable on streamed input.
+
+use std::ops::Range;
+
+/// Abbreviations whose trailing period does not end a sentence.
+const ABBREVIATIONS: &[&str] = &[
+    "dr", "drs", "mr", "mrs", "ms", "mx", "st", "mt", "prof", "rev", "fr", "sr", "jr", "capt",
+    "lt", "sgt", "vs", "etc", "approx", "pt", "pts", "hx", "dx", "rx", "tx", "sx", "fx", "mg",
+    "mcg", "ml", "hr", "hrs", "min", "wk", "wks", "mo", "yr", "yrs", "apt", "ste", "ave", "blvd",
+    "rd", "univ",
+];
+
+/// A named section and the byte range it covers, header line included.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Section {
+    /// The matching entry from `headers`, or an empty string for text before the first header.
+    pub name: String,
+    pub range: Range<usize>,
+}
+
+/// Splits `text` into sections. A line starts a section when, after leading whitespace, it
+/// begins with one of `headers` (case-insensitive) followed by a colon or the end of the line.
+/// Each section runs until the next header line or the end of the text.
+pub fn sections(text: &str, headers: &[String]) -> Vec<Section> {
+    let mut starts: Vec<(usize, &str)> = Vec::new();
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        if let Some(header) = header_of(line, headers) {
+            starts.push((line_start, header));
+        }
+        line_start += line.len();
+    }
+
+    let mut sections = Vec::with_capacity(starts.len() + 1);
+    let first = starts
+        .first()
+        .map(|(start, _)| *start)
+        .unwrap_or(text.len());
+    if !text[..first].trim().is_empty() {
+        sections.push(Section {
+            name: String::new(),
+            range: 0..first,
+        });
+    }
+    for (index, (start, header)) in starts.iter().enumerate() {
+        let end = starts
+            .get(index + 1)
+            .map(|(next, _)| *next)
+            .unwrap_or(text.len());
+        sections.push(Section {
+            name: header.to_string(),
+            range: *start..end,
+        });
+    }
+    sections
+}
+
+fn header_of<'a>(line: &str, headers: &'a [String]) -> Option<&'a str> {
+    let trimmed = line.trim_start();
+    headers.iter().map(String::as_str).find(|header| {
+        let Some(prefix) = trimmed.get(..header.len()) else {
+            return false;
+        };
+        if !prefix.eq_ignore_ascii_case(header) {
+            return false;
+        }
+        let rest = trimmed[header.len()..].trim_start_matches([' ', '\t']);
+        rest.is_empty() || rest.starts_with(':') || rest.starts_with(['\r', '\n'])
+    })
+}
+
+/// Byte offsets where sentences begin. A sentence ends at `.`, `!`, or `?` followed by
+/// whitespace and a character that is not lowercase, at a line ending in a colon, or at a blank
+/// line. Periods in abbreviations ("Dr.", "q.h.s.", single initials) and inside decimal numbers
+/// do not end a sentence.
+pub fn sentence_starts(text: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut pending = true;
+    let mut token_start = 0;
+    let mut newlines = 0;
+    let mut previous = None;
+
+    for (index, ch) in text.char_indices() {
+        if ch.is_whitespace() {
+            if ch == '\n' {
+                newlines += 1;
+                if newlines >= 2 || previous == Some(':') {
+                    pending = true;
+                }
+            }
+            if let Some(last) = previous.take() {
+                if matches!(last, '.' | '!' | '?') && !is_abbreviation(&text[token_start..index]) {
+                    pending = true;
+                }
+            }
+            continue;
+        }
+
+        if previous.is_none() {
+            token_start = index;
+            newlines = 0;
+            if pending && (!ch.is_lowercase() || starts.is_empty()) {
+                starts.push(index);
+            }
+            pending = false;
+        }
+        previous = Some(ch);
+    }
+    starts
+}
+
+fn is_abbreviation(token: &str) -> bool {
+    let word = token
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .trim_end_matches('.');
+    if word.is_empty() {
+        return false;
+    }
+    // Single initials ("J.") and dotted abbreviations ("q.h.s.", "b.i.d.", "e.g.").
+    if word
+        .split('.')
+        .all(|part| part.chars().count() == 1 && part.chars().all(char::is_alphabetic))
+    {
+        return true;
+    }
+    let lower = word.to_lowercase();
+    ABBREVIATIONS.contains(&lower.as_str())
+}
+
+/// True when `offset` is the first character of a sentence in `starts` (as returned by
+/// `sentence_starts`).
+pub fn is_sentence_start(starts: &[usize], offset: usize) -> bool {
+    starts.binary_search(&offset).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOAP_NOTE: &str = "\
+Patient: [PERSON]  DOB: [DATE]
+SUBJECTIVE:
+Pt reports 3 days of cough. Denies fever.
+OBJECTIVE:
+T 98.6 F, BP 132/84. Lungs with scattered wheezes.
+Assessment: acute bronchitis
+PLAN:
+Albuterol 2.5 mg neb q.i.d. as needed. Return if worse.
+";
+
+    fn headers() -> Vec<String> {
+        ["Subjective", "Objective", "Assessment", "Plan"]
+            .iter()
+            .map(|header| header.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn splits_soap_note_into_sections() {
+        let sections = sections(SOAP_NOTE, &headers());
+        let names: Vec<&str> = sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["", "Subjective", "Objective", "Assessment", "Plan"]);
+
+        let subjective = &SOAP_NOTE[sections[1].range.clone()];
+        assert_eq!(
+            subjective,
+            "SUBJECTIVE:\nPt reports 3 days of cough. Denies fever.\n"
+        );
+        let assessment = &SOAP_NOTE[sections[3].range.clone()];
+        assert_eq!(assessment, "Assessment: acute bronchitis\n");
+        assert_eq!(sections[4].range.end, SOAP_NOTE.len());
+        assert_eq!(sections[0].range.start, 0);
+    }
+
+    #[test]
+    fn header_words_inside_sentences_are_not_sections() {
+        let text = "Plan to discharge tomorrow.\nPlan: home\n";
+        let sections = sections(text, &headers());
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[1].name, "Plan");
+        assert_eq!(&text[sections[1].range.clone()], "Plan: home\n");
+    }
+
+    #[test]
+    fn abbreviations_and_decimals_do_not_split_sentences() {
+        let text =
+            "Seen by Dr. Smith today. Give 2.5 mg q.h.s. Recheck BMP on 3.14. Is she eating? Yes.";
+        let starts = sentence_starts(text);
+        let words: Vec<&str> = starts
+            .iter()
+            .map(|&start| text[start..].split_whitespace().next().unwrap_or(""))
+            .collect();
+        assert_eq!(words, ["Seen", "Give", "Is", "Yes."]);
+    }
+
+    #[test]
+    fn sentence_starts_follow_headers_and_blank_lines() {
+        let starts = sentence_starts(SOAP_NOTE);
+        let words: Vec<&str> = starts
+            .iter()
+            .map(|&start| SOAP_NOTE[start..].split_whitespace().next().unwrap_or(""))
+            .collect();
+        assert_eq!(
+            words,
+            [
+                "Patient:",
+                "Pt",
+                "Denies",
+                "OBJECTIVE:",
+                "T",
+                "Lungs",
+                "Assessment:",
+                "Albuterol",
+                "Return"
+            ]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Per-redaction detail records, bounded per document.
+//!
+//! A numeric dump can produce hundreds of thousands of redactions, so only the first
+//! `max_spans` records are kept. Redaction itself and the per-category counts are never capped;
+//! the stats report how many records were dropped.
+
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::describe::Confidence;
+use crate::CategoryKey;
+
+/// Records kept per document unless the config sets `max_spans`.
+pub const DEFAULT_MAX_SPANS: usize = 10_000;
+
+/// One redaction: what it was, where it was in the input, and where its token is in the output.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct RedactionSpan {
+    pub category: CategoryKey,
+    /// The detector that made the redaction, as named by `--describe-rules`.
+    pub rule: &'static str,
+    pub confidence: Confidence,
+    pub original: Range<usize>,
+    pub output: Range<usize>,
+    /// The text written in place of the original: the category token or its config override.
+    pub token: String,
+    /// The entity number in `[PERSON_2]`-style tokens (`token_style: numbered`): the same
+    /// original text gets the same number within a document.
+    pub index: Option<usize>,
+}
+
+/// Collects spans up to a cap, counting the ones it drops.
+#[derive(Debug)]
+pub(crate) struct SpanLog {
+    spans: Vec<RedactionSpan>,
+    cap: usize,
+    dropped: usize,
+}
+
+impl SpanLog {
+    pub(crate) fn new(cap: usize) -> Self {
+        Self {
+            spans: Vec::new(),
+            cap,
+            dropped: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, span: RedactionSpan) {
+        if self.spans.len() < self.cap {
+            self.spans.push(span);
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    /// The kept spans and the number dropped.
+    pub(crate) fn finish(self) -> (Vec<RedactionSpan>, usize) {
+        (self.spans, self.dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Category;
+
+    #[test]
+    fn drops_spans_past_the_cap() {
+        let mut log = SpanLog::new(2);
+        for start in 0..5 {
+            log.push(RedactionSpan {
+                category: Category::Mrn.into(),
+                rule: "mrn-label",
+                confidence: Confidence::High,
+                original: start..start + 1,
+                output: start..start + 1,
+                token: "[MRN]".to_string(),
+                index: None,
+            });
+        }
+        let (spans, dropped) = log.finish();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1].original, 1..2);
+        assert_eq!(dropped, 3);
+    }
+}
@@ -0,0 +1,272 @@
+//! Record-by-record scrubbing for inputs too large to read whole (`--stream`).
+//!
+//! Each record, the text up to a separator (a newline by default), is scrubbed on its own and
+//! written out before the next is read, so memory follows the longest record rather than the
+//! input. Whitespace is handled as a whole-document scrub handles it: runs of spaces collapse to
+//! one and the ends of the stream are trimmed, keeping a final newline unless the config sets
+//! `normalize_newlines`, so a stream of one-line records comes out the same as scrubbing it in
+//! one piece.
+//!
+//! Detectors see one record at a time. An entity split across a separator, such as an address
+//! written over several lines, is not seen as one and may be missed, and numbered tokens count
+//! from 1 in each record. Stats are summed over the records; `unique` counts distinct values
+//! over the whole stream and `lines` numbers lines from the start of the input.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+
+use crate::cancel::CancellationToken;
+use crate::scrubber::{final_line_ending, ScrubOptions, ScrubStats, Scrubber};
+use crate::CategoryKey;
+
+/// How a stream is split into records, and how each is scrubbed.
+#[derive(Clone, Debug)]
+pub struct StreamOptions {
+    /// The bytes that end a record, such as `\n` or `\0`; must not be empty.
+    pub separator: Vec<u8>,
+    pub scrub: ScrubOptions,
+}
+
+impl StreamOptions {
+    pub fn new(separator: impl Into<Vec<u8>>) -> Self {
+        Self {
+            separator: separator.into(),
+            scrub: ScrubOptions::default(),
+        }
+    }
+}
+
+/// Scrubs `reader` record by record into `writer` and returns the stats of the whole stream.
+/// Records end at the separator, which is copied through unchanged; a `\r` before a separator
+/// starting with `\n` is kept with it. A separator that is not all whitespace, such as `\0`,
+/// also ends the output when it ends the input. `on_record` hears the number (from 1) and
+/// stats of each record with text, after it is written.
+pub fn scrub_stream(
+    scrubber: &Scrubber,
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+    stream: &StreamOptions,
+    skip: &HashSet<CategoryKey>,
+    cancel: &CancellationToken,
+    mut on_record: impl FnMut(usize, &ScrubStats) -> Result<()>,
+) -> Result<ScrubStats> {
+    let (separator, options) = (stream.separator.as_slice(), &stream.scrub);
+    let last = *separator.last().context("the record separator is empty")?;
+    let blank_separator = separator.iter().all(u8::is_ascii_whitespace);
+    let mut unique: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+    // Scrubbing nothing fills in what every report carries (profile, dictionary versions, custom
+    // categories) even when no record has text.
+    let mut total = scrubber
+        .scrub_record("", skip, options, cancel, &mut unique)?
+        .stats;
+    // Whitespace and separators after the last text written, held back until more text follows
+    // so that the stream's end is trimmed.
+    let mut pending = String::new();
+    let mut started = false;
+    let mut newlines = 0;
+    // The line break the input ends with, so far.
+    let mut ending = "";
+    // Whether the input ends with a separator that is kept at the end of the output.
+    let mut terminated = false;
+    let mut record = Vec::new();
+    for index in 1.. {
+        record.clear();
+        while reader.read_until(last, &mut record)? > 0 && !record.ends_with(separator) {}
+        if record.is_empty() {
+            break;
+        }
+        ending = final_line_ending(&String::from_utf8_lossy(
+            &record[record.len().saturating_sub(2)..],
+        ));
+        terminated = !blank_separator && record.ends_with(separator);
+        let mut body = record.len();
+        if record.ends_with(separator) {
+            body -= separator.len();
+            if separator[0] == b'\n' && record[..body].ends_with(b"\r") {
+                body -= 1;
+            }
+        }
+        let text = std::str::from_utf8(&record[..body])
+            .with_context(|| format!("record {} is not valid UTF-8", index))?;
+        let end = String::from_utf8_lossy(&record[body..]);
+        let core = text.trim();
+        if core.is_empty() {
+            pending.push_str(&collapse_spaces(text));
+            pending.push_str(&end);
+            newlines += record.iter().filter(|&&byte| byte == b'\n').count();
+            continue;
+        }
+        let core_start = text.len() - text.trim_start().len();
+        let lead = &text[..core_start];
+        if started {
+            pending.push_str(&collapse_spaces(lead));
+            writer.write_all(pending.as_bytes())?;
+        }
+        let line_offset = newlines + lead.matches('\n').count();
+        // Each record's values are gathered apart so its own stats count its distinct values.
+        let mut seen = BTreeMap::new();
+        let result = scrubber.scrub_record(core, skip, options, cancel, &mut seen)?;
+        for (category, values) in seen {
+            unique.entry(category).or_default().extend(values);
+        }
+        writer.write_all(result.text.as_bytes())?;
+        on_record(index, &result.stats)?;
+        total += &result.stats;
+        total
+            .lines
+            .extend(result.stats.lines.into_iter().map(|mut count| {
+                count.line += line_offset;
+                count
+            }));
+        pending = collapse_spaces(&text[core_start + core.len()..]);
+        pending.push_str(&end);
+        started = true;
+        newlines += record.iter().filter(|&&byte| byte == b'\n').count();
+    }
+    if terminated {
+        writer.write_all(separator)?;
+    } else if scrubber.keeps_line_breaks() {
+        writer.write_all(ending.as_bytes())?;
+    }
+    writer.flush()?;
+    total.unique = unique
+        .iter()
+        .map(|(category, values)| (category.clone(), values.len()))
+        .collect();
+    Ok(total)
+}
+
+/// Collapses each run of whitespace other than line breaks to one space, as the scrubber does.
+fn collapse_spaces(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_run = false;
+    for ch in text.chars() {
+        let space = ch.is_whitespace() && ch != '\r' && ch != '\n';
+        if !(space && in_run) {
+            out.push(if space { ' ' } else { ch });
+        }
+        in_run = space;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScrubberConfig;
+    use crate::scrubber::ScrubMode;
+
+    fn stream(input: &str, separator: &[u8], options: &ScrubOptions) -> (String, ScrubStats) {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let mut output = Vec::new();
+        let stream = StreamOptions {
+            scrub: *options,
+            ..StreamOptions::new(separator)
+        };
+        let stats = scrub_stream(
+            &scrubber,
+            input.as_bytes(),
+            &mut output,
+            &stream,
+            &HashSet::new(),
+            &CancellationToken::new(),
+            |_, _| Ok(()),
+        )
+        .expect("stream");
+        (String::from_utf8(output).expect("utf-8"), stats)
+    }
+
+    #[test]
+    fn matches_a_whole_document_scrub_for_one_line_phi() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "\n  Call 312-555-0182 today.\r\n\n\tEmail   jane.doe@example.com .\nSeen 03/14/2024 by Dr. Alan Brooks.\n\n";
+        let (whole, whole_stats) = scrubber.scrub(input, &HashSet::new());
+        let (streamed, stats) = stream(input, b"\n", &ScrubOptions::default());
+        assert_eq!(streamed, whole);
+        assert_eq!(stats.by_key(), whole_stats.by_key());
+        assert_eq!(stats.unique, whole_stats.unique);
+    }
+
+    #[test]
+    fn counts_unique_values_and_numbers_lines_across_records() {
+        let options = ScrubOptions {
+            collect_lines: true,
+            ..ScrubOptions::default()
+        };
+        let (output, stats) = stream(
+            "Call 312-555-0182.\n\nCall 312-555-0182 again.\n",
+            b"\n",
+            &options,
+        );
+        assert_eq!(output, "Call [PHONE].\n\nCall [PHONE] again.\n");
+        assert_eq!(stats.count(crate::Category::Phone), 2);
+        assert_eq!(stats.unique["phone"], 1);
+        let lines: Vec<usize> = stats.lines.iter().map(|count| count.line).collect();
+        assert_eq!(lines, [1, 3]);
+    }
+
+    #[test]
+    fn splits_records_at_a_multi_byte_separator() {
+        let (output, stats) = stream(
+            "Call\n312-555-0182.\n----\nEmail jane.doe@example.com.",
+            b"\n----\n",
+            &ScrubOptions::default(),
+        );
+        assert_eq!(output, "Call\n[PHONE].\n----\nEmail [EMAIL].");
+        assert_eq!(stats.total(), 2);
+    }
+
+    #[test]
+    fn scrubs_nul_terminated_records_apart_and_reports_each() {
+        let scrubber =
+            Scrubber::new(ScrubberConfig::default(), ScrubMode::Standard).expect("scrubber");
+        let input = "Seen by Dr. Alan Brooks.\0Call 312-555-0182 or 312-555-0182.\0\
+                     Email jane.doe@example.com\nMRN: 4829130\0";
+        let mut output = Vec::new();
+        let mut records = Vec::new();
+        let stats = scrub_stream(
+            &scrubber,
+            input.as_bytes(),
+            &mut output,
+            &StreamOptions::new(*b"\0"),
+            &HashSet::new(),
+            &CancellationToken::new(),
+            |record, stats| {
+                records.push((record, stats.by_key(), stats.unique.clone()));
+                Ok(())
+            },
+        )
+        .expect("stream");
+        assert_eq!(
+            String::from_utf8(output).expect("utf-8"),
+            "Seen by [PERSON].\0Call [PHONE] or [PHONE].\0Email [EMAIL]\n[MRN]\0"
+        );
+        let fired: Vec<(usize, Vec<(String, usize)>)> = records
+            .iter()
+            .map(|(record, counts, _)| {
+                let counts = counts
+                    .iter()
+                    .filter(|(_, count)| *count > 0)
+                    .map(|(category, count)| (category.to_string(), *count))
+                    .collect();
+                (*record, counts)
+            })
+            .collect();
+        assert_eq!(
+            fired,
+            [
+                (1, vec![("person".to_string(), 1)]),
+                (2, vec![("phone".to_string(), 2)]),
+                (3, vec![("email".to_string(), 1), ("mrn".to_string(), 1)]),
+            ]
+        );
+        assert_eq!(records[1].2["phone"], 1);
+        assert!(!records[2].2.contains_key("phone"));
+        assert_eq!(stats.total(), 5);
+        assert_eq!(stats.unique["phone"], 1);
+    }
+}
@@ -0,0 +1,434 @@
+//! Per-document notifications POSTed to an external webhook.
+//!
+//! Events are serialized on the caller's thread and delivered by a background worker through a
+//! bounded queue, so a slow endpoint never stalls scrubbing: when the queue is full the event is
+//! dropped and counted. Delivery failures are retried, logged to stderr, and counted, but are
+//! never fatal. Events carry stats and metadata only, never document text.
+//!
+//! Events go to `https://` or `http://` URLs through a blocking `reqwest` client. An auth
+//! header is only sent over TLS or to a loopback host, where cleartext never leaves the machine.
+//! Build without the `webhook` feature to leave the client out entirely.
+
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::redirect::Policy;
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::report::SchemaVersion;
+use crate::scrubber::ScrubStats;
+
+/// How events are delivered.
+#[derive(Clone, Debug)]
+pub struct WebhookOptions {
+    pub url: String,
+    /// Extra header sent with every request, e.g. `Authorization: Bearer abc123`.
+    pub auth_header: Option<String>,
+    /// Attempts after the first before an event counts as failed.
+    pub retries: u32,
+    /// Delay before the first retry; doubled for each later one.
+    pub backoff: Duration,
+    /// Events waiting for delivery before new ones are dropped.
+    pub queue_capacity: usize,
+    /// Connect timeout, and the limit on each whole attempt.
+    pub timeout: Duration,
+}
+
+impl WebhookOptions {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            auth_header: None,
+            retries: 3,
+            backoff: Duration::from_millis(250),
+            queue_capacity: 64,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// The JSON body sent for one scrubbed document.
+#[derive(Debug, Serialize)]
+pub struct DocumentEvent<'a> {
+    pub schema_version: u32,
+    /// Input path, or `-` for stdin.
+    pub document: &'a str,
+    /// Stats rendered in the event's schema version.
+    pub stats: serde_json::Value,
+    /// Conditions worth a reviewer's attention, such as `spans_truncated`.
+    pub anomalies: Vec<&'static str>,
+    /// `RuleSetDescription::fingerprint` of the rules that produced `stats`.
+    pub config_fingerprint: &'a str,
+    pub duration_ms: u128,
+}
+
+impl<'a> DocumentEvent<'a> {
+    /// Builds an event, deriving the anomaly flags from `stats`.
+    pub fn new(
+        document: &'a str,
+        stats: &ScrubStats,
+        schema: SchemaVersion,
+        config_fingerprint: &'a str,
+        duration: Duration,
+    ) -> Result<Self> {
+        let mut anomalies = Vec::new();
+        if stats.spans_truncated {
+            anomalies.push("spans_truncated");
+        }
+        Ok(Self {
+            schema_version: schema.number(),
+            document,
+            stats: stats.to_json_value(schema)?,
+            anomalies,
+            config_fingerprint,
+            duration_ms: duration.as_millis(),
+        })
+    }
+}
+
+/// Delivery outcome once the queue has drained.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct WebhookReport {
+    pub delivered: usize,
+    /// Events that still failed after every retry.
+    pub failed: usize,
+    /// Events discarded because the queue was full.
+    pub dropped: usize,
+}
+
+pub struct WebhookSender {
+    queue: SyncSender<String>,
+    worker: JoinHandle<WebhookReport>,
+    /// Events passed to `send`, which numbers them in log lines.
+    sent: usize,
+    dropped: usize,
+}
+
+impl WebhookSender {
+    /// Validates the options and starts the delivery worker.
+    pub fn start(options: WebhookOptions) -> Result<Self> {
+        let url = parse_url(&options.url)?;
+        let auth = match &options.auth_header {
+            Some(header) => {
+                let (name, value) = header
+                    .split_once(':')
+                    .and_then(|(name, value)| {
+                        Some((
+                            HeaderName::from_bytes(name.trim().as_bytes()).ok()?,
+                            HeaderValue::from_str(value.trim()).ok()?,
+                        ))
+                    })
+                    .ok_or_else(|| {
+                        anyhow!("webhook auth header must be a single `Name: value` line")
+                    })?;
+                if url.scheme() != "https" && !is_loopback(&url) {
+                    bail!(
+                        "refusing to send the webhook auth header in cleartext to {}; use \
+                         https://, or a loopback host over http://",
+                        url.host_str().unwrap_or_default()
+                    );
+                }
+                Some((name, value))
+            }
+            None => None,
+        };
+        // Each attempt opens its own connection, and a redirect is not followed, so the auth
+        // header only ever goes to the URL given.
+        let client = Client::builder()
+            .connect_timeout(options.timeout)
+            .timeout(options.timeout)
+            .pool_max_idle_per_host(0)
+            .redirect(Policy::none())
+            .build()
+            .context("failed to build the webhook client")?;
+        let (queue, events) = mpsc::sync_channel::<String>(options.queue_capacity.max(1));
+        let worker = thread::spawn(move || {
+            let mut report = WebhookReport::default();
+            for body in events {
+                if deliver(&client, &url, auth.as_ref(), &options, body) {
+                    report.delivered += 1;
+                } else {
+                    report.failed += 1;
+                }
+            }
+            report
+        });
+        Ok(Self {
+            queue,
+            worker,
+            sent: 0,
+            dropped: 0,
+        })
+    }
+
+    /// Queues an event without blocking; drops it if the queue is full. Log lines name the
+    /// event by its number from 1, since the document path may itself hold PHI.
+    pub fn send(&mut self, event: &DocumentEvent<'_>) -> Result<()> {
+        let body = serde_json::to_string(event)?;
+        self.sent += 1;
+        match self.queue.try_send(body) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                log(format_args!(
+                    "webhook: queue full, dropping event {}",
+                    self.sent
+                ));
+                self.dropped += 1;
+            }
+            Err(TrySendError::Disconnected(_)) => bail!("webhook worker stopped"),
+        }
+        Ok(())
+    }
+
+    /// Waits for queued events to be delivered (or to fail) and reports the totals.
+    pub fn finish(self) -> WebhookReport {
+        drop(self.queue);
+        let report = self.worker.join().unwrap_or_default();
+        WebhookReport {
+            dropped: self.dropped,
+            ..report
+        }
+    }
+}
+
+/// Sends `body`, retrying with exponential backoff. Returns whether a 2xx response arrived.
+fn deliver(
+    client: &Client,
+    url: &Url,
+    auth: Option<&(HeaderName, HeaderValue)>,
+    options: &WebhookOptions,
+    body: String,
+) -> bool {
+    let mut delay = options.backoff;
+    for attempt in 0..=options.retries {
+        if attempt > 0 {
+            thread::sleep(delay);
+            delay *= 2;
+        }
+        let mut request = client
+            .post(url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some((name, value)) = auth {
+            request = request.header(name, value);
+        }
+        match request.send().map(|response| response.status().as_u16()) {
+            Ok(status) if (200..300).contains(&status) => return true,
+            Ok(status) => log(format_args!(
+                "webhook: {} responded with HTTP {}",
+                options.url, status
+            )),
+            Err(err) => log(format_args!("webhook: {} failed: {}", options.url, err)),
+        }
+    }
+    false
+}
+
+/// One line on stderr; a closed stderr is ignored rather than a panic in the worker.
+fn log(message: std::fmt::Arguments) {
+    writeln!(io::stderr().lock(), "{}", message).ok();
+}
+
+/// `url` if it is `http://` or `https://` with a host.
+fn parse_url(url: &str) -> Result<Url> {
+    let parsed = Url::parse(url).map_err(|err| anyhow!("invalid webhook URL {}: {}", url, err))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!(
+            "only http:// and https:// webhook URLs are supported: {}",
+            url
+        );
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        bail!("missing host in webhook URL: {}", url);
+    }
+    Ok(parsed)
+}
+
+/// `localhost` or a loopback IP, where cleartext never leaves the machine.
+fn is_loopback(url: &Url) -> bool {
+    let host = url.host_str().unwrap_or_default();
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    /// Accepts one connection per scripted status and returns each request's head and body.
+    fn serve(statuses: Vec<u16>) -> (String, JoinHandle<Vec<(String, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let url = format!("http://{}/events", listener.local_addr().expect("addr"));
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (stream, _) = listener.accept().expect("accept");
+                let mut reader = BufReader::new(stream);
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).expect("read header");
+                    if line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: "))
+                    .and_then(|value| value.trim().parse().ok())
+                    .expect("content length");
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).expect("read body");
+                write!(reader.get_mut(), "HTTP/1.1 {} Status\r\n\r\n", status).expect("respond");
+                requests.push((head, String::from_utf8(body).expect("utf8")));
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    fn event(stats: &ScrubStats) -> DocumentEvent<'static> {
+        DocumentEvent::new("-", stats, SchemaVersion::V2, "f", Duration::ZERO).expect("event")
+    }
+
+    fn options(url: &str) -> WebhookOptions {
+        WebhookOptions {
+            backoff: Duration::from_millis(10),
+            ..WebhookOptions::new(url)
+        }
+    }
+
+    #[test]
+    fn posts_stats_without_document_text() {
+        let (url, server) = serve(vec![200]);
+        let mut sender = WebhookSender::start(WebhookOptions {
+            auth_header: Some("Authorization: Bearer abc123".to_string()),
+            ..options(&url)
+        })
+        .expect("sender");
+        let stats = ScrubStats {
+            emails: 2,
+            spans_truncated: true,
+            ..Default::default()
+        };
+        let event = DocumentEvent::new(
+            "notes/a.txt",
+            &stats,
+            SchemaVersion::V1,
+            "0123abcd",
+            Duration::from_millis(7),
+        )
+        .expect("event");
+        sender.send(&event).expect("send");
+        assert_eq!(
+            sender.finish(),
+            WebhookReport {
+                delivered: 1,
+                ..Default::default()
+            }
+        );
+
+        let requests = server.join().expect("server");
+        let (head, body) = &requests[0];
+        assert!(head.starts_with("POST /events HTTP/1.1\r\n"));
+        assert!(head.contains("authorization: Bearer abc123\r\n"));
+        let json: serde_json::Value = serde_json::from_str(body).expect("json");
+        let keys: Vec<&str> = json
+            .as_object()
+            .expect("object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                "anomalies",
+                "config_fingerprint",
+                "document",
+                "duration_ms",
+                "schema_version",
+                "stats"
+            ]
+        );
+        assert_eq!(json["document"], "notes/a.txt");
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["stats"]["emails"], 2);
+        assert_eq!(json["anomalies"], serde_json::json!(["spans_truncated"]));
+        assert_eq!(json["duration_ms"], 7);
+    }
+
+    #[test]
+    fn retries_until_the_endpoint_accepts() {
+        let (url, server) = serve(vec![503, 500, 204]);
+        let mut sender = WebhookSender::start(options(&url)).expect("sender");
+        let stats = ScrubStats::default();
+        sender.send(&event(&stats)).expect("send");
+        assert_eq!(sender.finish().delivered, 1);
+        assert_eq!(server.join().expect("server").len(), 3);
+    }
+
+    #[test]
+    fn counts_failures_without_erroring() {
+        let (url, server) = serve(vec![500, 500]);
+        let mut sender = WebhookSender::start(WebhookOptions {
+            retries: 1,
+            ..options(&url)
+        })
+        .expect("sender");
+        let stats = ScrubStats::default();
+        sender.send(&event(&stats)).expect("send");
+        assert_eq!(sender.finish().failed, 1);
+        server.join().expect("server");
+    }
+
+    #[test]
+    fn parses_http_and_https_urls_only() {
+        for url in [
+            "http://hooks.local:8080/a/b",
+            "https://hooks.local",
+            "http://[::1]/hook",
+        ] {
+            assert!(parse_url(url).is_ok(), "{}", url);
+        }
+        assert!(is_loopback(&parse_url("http://[::1]/hook").expect("url")));
+        assert!(is_loopback(&parse_url("http://localhost:9").expect("url")));
+        assert!(!is_loopback(&parse_url("http://hooks.local").expect("url")));
+        assert!(parse_url("ftp://hooks.local").is_err());
+        assert!(parse_url("http://:80/").is_err());
+    }
+
+    #[test]
+    fn auth_headers_need_tls_or_a_loopback_host() {
+        let with_auth = |url: &str| {
+            WebhookSender::start(WebhookOptions {
+                auth_header: Some("Authorization: Bearer abc123".to_string()),
+                ..options(url)
+            })
+            .map(WebhookSender::finish)
+        };
+        let err = with_auth("http://hooks.example.com/events").expect_err("cleartext");
+        assert!(err.to_string().contains("cleartext"));
+        assert!(with_auth("https://hooks.example.com/events").is_ok());
+        assert!(with_auth("http://localhost:9/events").is_ok());
+        assert!(with_auth("http://[::1]/events").is_ok());
+        assert!(WebhookSender::start(options("http://hooks.example.com/events")).is_ok());
+        let err = WebhookSender::start(WebhookOptions {
+            auth_header: Some("Bearer abc123".to_string()),
+            ..options("https://hooks.example.com/events")
+        })
+        .map(WebhookSender::finish)
+        .expect_err("no header name");
+        assert!(err.to_string().contains("Name: value"));
+    }
+}
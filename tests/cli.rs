@@ -0,0 +1,2143 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+
+fn scrubber() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_clinical_scrubber"))
+}
+
+/// Identifiers planted in the fixtures and test inputs. None of these runs passes --log-phi, so
+/// none of them may reach stderr.
+const PLANTED_PHI: &[&str] = &[
+    "Maria Lopez",
+    "maria.lopez@example.com",
+    "Alan Brooks",
+    "Jane Doe",
+    "jane.doe@example.com",
+    "John Roe",
+    "4829130",
+    "5512093",
+    "312-555-0182",
+    "03/14/2024",
+    "ENC-2024-00123",
+    "ENC-00412",
+    "W123456789",
+];
+
+/// Fails the test if stderr echoes any planted identifier.
+fn checked(output: Output) -> Output {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if let Some(phi) = PLANTED_PHI.iter().find(|phi| stderr.contains(*phi)) {
+        panic!("stderr contains {:?}:\n{}", phi, stderr);
+    }
+    output
+}
+
+/// The only ways these tests collect a run's output, so every run's stderr is scanned by
+/// `checked`.
+trait Run {
+    fn checked_output(&mut self) -> Output;
+}
+
+impl Run for Command {
+    fn checked_output(&mut self) -> Output {
+        checked(self.output().expect("run"))
+    }
+}
+
+trait Finish {
+    fn checked_wait(self) -> Output;
+}
+
+impl Finish for Child {
+    fn checked_wait(self) -> Output {
+        checked(self.wait_with_output().expect("wait"))
+    }
+}
+
+#[test]
+fn closed_stdout_pipe_exits_cleanly() {
+    let input = "Reach me at jane.doe@example.com.\n".repeat(20_000);
+    let mut child = scrubber()
+        .args(["-i", "-", "-o", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn");
+
+    let mut stdin = child.stdin.take().expect("stdin");
+    let writer = thread::spawn(move || {
+        stdin.write_all(input.as_bytes()).ok();
+    });
+
+    // Read one line and hang up, as `head -n 1` would.
+    let mut first = String::new();
+    BufReader::new(child.stdout.take().expect("stdout"))
+        .read_line(&mut first)
+        .expect("read line");
+    assert_eq!(first, "Reach me at [EMAIL].\n");
+
+    let output = child.checked_wait();
+    writer.join().expect("writer");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "status {:?}: {}",
+        output.status,
+        stderr
+    );
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+    assert!(!stderr.contains("Error"), "{}", stderr);
+}
+
+#[test]
+fn closed_stderr_pipe_exits_cleanly() {
+    // As `2>&1 | head -1` leaves it once the first line is read: stderr has no reader.
+    let (reader, writer) = std::io::pipe().expect("pipe");
+    drop(reader);
+    let mut child = scrubber()
+        .args(["-i", "-", "-o", "-", "--stats-json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(writer)
+        .spawn()
+        .expect("spawn");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(b"Reach me at jane.doe@example.com.\n")
+        .expect("write");
+    // Nothing can read this run's stderr, so there is nothing for `checked` to scan.
+    let status = child.wait().expect("wait");
+    assert!(status.success(), "status {:?}", status);
+}
+
+#[test]
+fn stats_go_to_stderr_and_text_to_stdout() {
+    let mut child = scrubber()
+        .args(["-i", "-", "-o", "-", "--stats-json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(b"Reach me at jane.doe@example.com.\n")
+        .expect("write");
+
+    let output = child.checked_wait();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+        "Reach me at [EMAIL]."
+    );
+
+    let stats: serde_json::Value = serde_json::from_slice(&output.stderr).expect("stats json");
+    assert_eq!(stats["emails"], 1);
+}
+
+#[test]
+fn custom_categories_are_skipped_and_reported_by_name() {
+    let config = std::env::temp_dir().join(format!("custom-{}.json", std::process::id()));
+    std::fs::write(
+        &config,
+        r#"{"custom_patterns": [{"name": "encounter", "pattern": "\\bENC-\\d{5}\\b"}]}"#,
+    )
+    .expect("config");
+    let run = |extra: &[&str]| {
+        let mut child = scrubber()
+            .args([
+                "-i",
+                "-",
+                "-o",
+                "-",
+                "--stats-json",
+                "--schema-version",
+                "2",
+            ])
+            .arg("--config")
+            .arg(&config)
+            .args(extra)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(b"Visit ENC-00412 by jane.doe@example.com\n")
+            .expect("write");
+        child.checked_wait()
+    };
+
+    let output = run(&[]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+        "Visit [ENCOUNTER] by [EMAIL]"
+    );
+    let stats: serde_json::Value = serde_json::from_slice(&output.stderr).expect("stats json");
+    assert_eq!(stats["counts"]["custom:encounter"], 1);
+
+    let output = run(&["--skip", "custom:encounter"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+        "Visit ENC-00412 by [EMAIL]"
+    );
+
+    let output = run(&["--skip", "custom:unknown"]);
+    assert!(!output.status.success());
+    std::fs::remove_file(&config).ok();
+}
+
+#[test]
+fn repeated_configs_merge_in_order_and_print_config_shows_the_result() {
+    let dir = std::env::temp_dir().join(format!("merge-configs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("dir");
+    let base = dir.join("org.json");
+    let project = dir.join("project.json");
+    std::fs::write(&base, r#"{"names": ["Quaxley"], "mrn_min_length": 6}"#).expect("base");
+    std::fs::write(&project, r#"{"names": ["Zorbin"], "mrn_min_length": 8}"#).expect("project");
+
+    let output = scrubber()
+        .arg("--config")
+        .arg(&base)
+        .arg("--config")
+        .arg(&project)
+        .args(["--print-config", "--min-confidence", "medium"])
+        .checked_output();
+    assert!(output.status.success());
+    let config: serde_json::Value = serde_json::from_slice(&output.stdout).expect("config json");
+    assert_eq!(config["names"], serde_json::json!(["Quaxley", "Zorbin"]));
+    assert_eq!(config["mrn_min_length"], 8);
+    assert_eq!(config["min_confidence"], "medium");
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn config_skip_combines_with_skip_and_unskip() {
+    let config = std::env::temp_dir().join(format!("skip-{}.json", std::process::id()));
+    std::fs::write(&config, r#"{"skip": ["relative-date", "zip"]}"#).expect("config");
+    let run = |args: &[&str]| {
+        let mut child = scrubber()
+            .args(["-i", "-", "-o", "-", "--quiet"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(b"Seen yesterday, ZIP 60614.\n")
+            .expect("write");
+        let output = child.checked_wait();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string()
+    };
+    let config = config.to_str().expect("utf-8 path");
+
+    assert_eq!(run(&["--skip", "zip"]), "Seen [REL_DATE], ZIP 60614.");
+    assert_eq!(run(&["--config", config]), "Seen yesterday, ZIP 60614.");
+    assert_eq!(
+        run(&["--config", config, "--unskip", "zip"]),
+        "Seen yesterday, ZIP [ZIP]."
+    );
+    assert_eq!(
+        run(&["--config", config, "--enable", "zip", "--skip", "zip"]),
+        "Seen yesterday, ZIP 60614."
+    );
+    std::fs::remove_file(config).ok();
+}
+
+#[test]
+fn only_redacts_just_the_named_categories() {
+    let run = |args: &[&str]| {
+        let mut child = scrubber()
+            .args(["-i", "-", "-o", "-", "--quiet"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(b"SSN 123-45-6789, call 312-555-0182 or email jane.doe@example.com.\n")
+            .expect("write");
+        child.checked_wait()
+    };
+    let text = |output: Output| {
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string()
+    };
+
+    assert_eq!(
+        text(run(&["--only", "ssn"])),
+        "SSN [SSN], call 312-555-0182 or email jane.doe@example.com."
+    );
+    let commas = text(run(&["--only", "ssn,phone"]));
+    assert_eq!(
+        commas,
+        "SSN [SSN], call [PHONE] or email jane.doe@example.com."
+    );
+    assert_eq!(text(run(&["--only", "ssn", "--only", "phone"])), commas);
+    assert_eq!(
+        text(run(&["--skip", "ssn,phone"])),
+        "SSN 123-45-6789, call 312-555-0182 or email [EMAIL]."
+    );
+
+    let conflict = run(&["--only", "ssn", "--skip", "email"]);
+    assert!(!conflict.status.success());
+    let stderr = String::from_utf8_lossy(&conflict.stderr);
+    assert!(
+        stderr.contains("'--only <CATEGORY>' cannot be used with '--skip <SKIP>'"),
+        "{}",
+        stderr
+    );
+    let unknown = run(&["--only", "custom:encounter"]);
+    assert!(String::from_utf8_lossy(&unknown.stderr)
+        .contains("--only custom:encounter: no custom_patterns entry defines it"));
+}
+
+#[test]
+fn naming_age_runs_it_and_safe_harbor_only_categories_need_safe_harbor() {
+    let run = |args: &[&str]| {
+        let mut child = scrubber()
+            .args(["-i", "-", "-o", "-", "--quiet"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(b"A 92-year-old seen from 10.0.0.12, SSN 123-45-6789.\n")
+            .expect("write");
+        child.checked_wait()
+    };
+    let text = |output: Output| {
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string()
+    };
+
+    assert_eq!(
+        text(run(&[])),
+        "A 92-year-old seen from 10.0.0.12, SSN [SSN]."
+    );
+    assert_eq!(
+        text(run(&["--only", "age"])),
+        "A [AGE_OVER_89]-year-old seen from 10.0.0.12, SSN 123-45-6789."
+    );
+    assert_eq!(
+        text(run(&["--unskip", "age"])),
+        "A [AGE_OVER_89]-year-old seen from 10.0.0.12, SSN [SSN]."
+    );
+    assert_eq!(
+        text(run(&["--only", "ip", "--safe-harbor"])),
+        "A 92-year-old seen from [IP], SSN 123-45-6789."
+    );
+    for flag in ["--only", "--unskip"] {
+        let output = run(&[flag, "ip"]);
+        assert!(!output.status.success());
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains(&format!(
+                "{} ip: none of its detectors run with this mode and config",
+                flag
+            )),
+            "{:?}",
+            output
+        );
+        assert!(output.stdout.is_empty());
+    }
+}
+
+#[test]
+fn bad_custom_pattern_names_the_config_file_and_entry() {
+    let config = std::env::temp_dir().join(format!("bad-pattern-{}.json", std::process::id()));
+    std::fs::write(
+        &config,
+        r#"{"custom_patterns": [{"name": "study-id", "pattern": "STU-(\\d+"}]}"#,
+    )
+    .expect("config");
+    let output = scrubber()
+        .arg("--config")
+        .arg(&config)
+        .stdin(Stdio::null())
+        .checked_output();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("invalid config: {}", config.display())));
+    assert!(stderr.contains("invalid pattern for custom:study-id"));
+    std::fs::remove_file(&config).ok();
+}
+
+#[test]
+fn insurance_ids_are_counted_in_stats_json() {
+    let mut child = scrubber()
+        .args(["-i", "-", "-o", "-", "--safe-harbor", "--stats-json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(b"Aetna Member ID: W123456789, Group #: 48122-A\n")
+        .expect("write");
+
+    let output = child.checked_wait();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+        "Aetna [INSURANCE], [INSURANCE]"
+    );
+    let stats: serde_json::Value = serde_json::from_slice(&output.stderr).expect("stats json");
+    assert_eq!(stats["insurance_ids"], 2);
+}
+
+#[test]
+fn profile_detectors_prints_a_table_and_adds_timings_to_stats() {
+    let mut child = scrubber()
+        .args(["-i", "-", "-o", "-", "--stats-json", "--profile-detectors"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(b"Email jane.doe@example.com\n")
+        .expect("write");
+
+    let output = child.checked_wait();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let (json, table) = stderr.split_once("Detector timings:").expect("table");
+    let stats: serde_json::Value = serde_json::from_str(json).expect("stats json");
+    let timings = stats["detector_timings"].as_array().expect("timings");
+    let email = timings
+        .iter()
+        .find(|timing| timing["detector"] == "email")
+        .expect("email timing");
+    assert_eq!(email["matches"], 1);
+    assert!(table.lines().any(|line| line.starts_with("email ")));
+}
+
+#[test]
+fn repl_session_runs_from_a_script() {
+    let mut child = scrubber()
+        .args(["repl", "--skip", "date"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(
+            b"Seen 03/14/2024 by jane.doe@example.com\n:unskip date\nSeen 03/14/2024\n:quit\n",
+        )
+        .expect("write");
+
+    let output = child.checked_wait();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "> Seen 03/14/2024 by [EMAIL]\n  [EMAIL] <- email by email (user@domain.tld) «redacted»\n\
+         > ok\n\
+         > Seen [DATE]\n  [DATE] <- date by date (numeric, ISO, and month-name dates) «redacted»\n\
+         > "
+    );
+}
+
+#[test]
+fn dedupe_cache_reuses_output_for_identical_inputs() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/dedupe");
+    let out = std::env::temp_dir().join(format!("dedupe-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&out).expect("out dir");
+    let cache = out.join("cache.json");
+    let run = |name: &str| {
+        let output = scrubber()
+            .arg("-i")
+            .arg(fixtures.join(format!("{}.txt", name)))
+            .arg("-o")
+            .arg(out.join(format!("{}.out", name)))
+            .arg("--dedupe-cache")
+            .arg(&cache)
+            .checked_output();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    assert!(run("a").starts_with("Redactions applied"));
+    for name in ["b", "c"] {
+        let stderr = run(name);
+        assert!(stderr.starts_with("duplicate of "), "{}", stderr);
+        assert!(stderr.contains("a.txt"), "{}", stderr);
+    }
+    assert!(run("d").starts_with("Redactions applied"));
+    let read =
+        |name: &str| std::fs::read_to_string(out.join(format!("{}.out", name))).expect("out");
+    assert_eq!(read("b"), read("a"));
+    assert_eq!(read("c"), read("a"));
+    assert_ne!(read("d"), read("a"));
+
+    // A corrupt cache is reported and ignored, so the document is scrubbed again.
+    std::fs::write(&cache, "{not json").expect("corrupt");
+    let stderr = run("b");
+    assert!(
+        stderr.starts_with("warning: ignoring dedupe cache"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("Redactions applied"), "{}", stderr);
+    assert_eq!(read("b"), read("a"));
+    std::fs::remove_dir_all(&out).ok();
+}
+
+#[test]
+fn dedupe_scrubs_each_distinct_document_of_a_batch_once() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/dedupe");
+    let out = std::env::temp_dir().join(format!("dedupe-batch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&out);
+    let manifest = out.join("manifest.json");
+    let cache = out.join("cache.json");
+    let run = |extra: &[&str]| {
+        let output = scrubber()
+            .arg("--input-dir")
+            .arg(&fixtures)
+            .arg("--output-dir")
+            .arg(out.join("notes"))
+            .arg("--manifest")
+            .arg(&manifest)
+            .args(extra)
+            .checked_output();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    let stderr = run(&["--dedupe"]);
+    assert!(
+        stderr.contains("Documents: 2 unique, 2 duplicates\n"),
+        "{}",
+        stderr
+    );
+    assert_eq!(stderr.matches("duplicate of ").count(), 2, "{}", stderr);
+    let read = |name: &str| std::fs::read_to_string(out.join("notes").join(name)).expect("out");
+    assert_eq!(read("b.txt"), read("a.txt"));
+    assert_eq!(read("c.txt"), read("a.txt"));
+    assert_ne!(read("d.txt"), read("a.txt"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&manifest).expect("manifest")).expect("json");
+    assert_eq!(
+        json["dedupe"],
+        serde_json::json!({"unique": 2, "duplicates": 2})
+    );
+    let files = json["files"].as_array().expect("files");
+    let statuses: Vec<&str> = files
+        .iter()
+        .map(|file| file["status"].as_str().expect("status"))
+        .collect();
+    assert_eq!(statuses, ["scrubbed", "reused", "reused", "scrubbed"]);
+    for file in &files[1..3] {
+        let original = file["duplicate_of"].as_str().expect("duplicate_of");
+        assert!(original.ends_with("a.txt"), "{}", original);
+    }
+    assert!(files[0].get("duplicate_of").is_none());
+
+    // The cache is written when the run ends; the next run reuses every document.
+    let stderr = run(&[
+        "--dedupe-cache",
+        cache.to_str().expect("utf-8"),
+        "--stats-json",
+    ]);
+    assert!(cache.is_file());
+    let stats: serde_json::Value = serde_json::from_str(&stderr).expect("stats");
+    assert_eq!(
+        stats["dedupe"],
+        serde_json::json!({"unique": 2, "duplicates": 2})
+    );
+    let stderr = run(&[
+        "--dedupe-cache",
+        cache.to_str().expect("utf-8"),
+        "--stats-json",
+    ]);
+    let stats: serde_json::Value = serde_json::from_str(&stderr).expect("stats");
+    assert_eq!(
+        stats["dedupe"],
+        serde_json::json!({"unique": 0, "duplicates": 4})
+    );
+
+    let output = scrubber()
+        .arg("-i")
+        .arg(fixtures.join("a.txt"))
+        .args(["-o", "-", "--dedupe"])
+        .checked_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--dedupe needs several inputs"));
+    std::fs::remove_dir_all(&out).ok();
+}
+
+#[test]
+fn stderr_carries_no_phi_from_any_report() {
+    let note =
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sample_note.txt");
+    let spans = std::env::temp_dir().join(format!("phi-spans-{}.ndjson", std::process::id()));
+    for extra in [
+        &[][..],
+        &["--stats-json"],
+        &[
+            "--stats-json",
+            "--schema-version",
+            "2",
+            "--profile-detectors",
+        ],
+        &["--safe-harbor", "--skip", "person"],
+        &["--describe-rules"],
+    ] {
+        let output = scrubber()
+            .arg("-i")
+            .arg(&note)
+            .args(["-o", "-", "--spans-out"])
+            .arg(&spans)
+            .args(extra)
+            .checked_output();
+        assert!(output.status.success(), "{:?}", output);
+    }
+    std::fs::remove_file(&spans).ok();
+}
+
+#[test]
+fn safe_harbor_mode_adds_redactions_without_changing_standard_ones() {
+    let note = "Patient is a 92-year-old seen 03/14/2024, home ZIP 60614.\n\
+                Portal login from 10.42.7.19; License #: K774B21.\n\
+                VIN 1HGCM82633A004352 on the parking form.\n";
+    let run = |extra: &[&str]| {
+        let mut child = scrubber()
+            .args(["-i", "-", "-o", "-", "--quiet"])
+            .args(extra)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(note.as_bytes())
+            .expect("write");
+        let output = child.checked_wait();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).expect("utf8")
+    };
+
+    let standard = run(&[]);
+    assert_eq!(
+        standard,
+        "Patient is a 92-year-old seen [DATE], home ZIP [ZIP].\n\
+         Portal login from 10.42.7.19; License #: K774B21.\n\
+         VIN 1HGCM82633A004352 on the parking form.\n"
+    );
+    let safe_harbor = run(&["--safe-harbor"]);
+    assert_eq!(
+        safe_harbor,
+        "Patient is a [AGE_OVER_89]-year-old seen [DATE], home ZIP 606**.\n\
+         Portal login from [IP]; [LICENSE].\n\
+         VIN [VEHICLE] on the parking form.\n"
+    );
+}
+
+#[test]
+fn seed_shifts_dates_the_same_way_on_every_run() {
+    let config = std::env::temp_dir().join(format!("seed-shift-{}.json", std::process::id()));
+    std::fs::write(&config, r#"{"date_shift": {}}"#).expect("config");
+    let run = || {
+        let mut child = scrubber()
+            .args(["-i", "-", "-o", "-", "--quiet", "--seed", "42", "--config"])
+            .arg(&config)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(b"Seen 03/14/2024 and again 03/18/2024.\n")
+            .expect("write");
+        let output = child.checked_wait();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).expect("utf8")
+    };
+
+    let first = run();
+    assert_eq!(first, run());
+    assert!(
+        !first.contains("03/14/2024") && !first.contains("[DATE]"),
+        "{}",
+        first
+    );
+    std::fs::remove_file(&config).ok();
+}
+
+#[test]
+fn limited_dataset_profile_keeps_dates_and_zips_and_is_echoed_in_stats() {
+    let mut child = scrubber()
+        .args([
+            "-i",
+            "-",
+            "-o",
+            "-",
+            "--profile",
+            "limited-dataset",
+            "--stats-json",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(
+            b"Seen by Dr. Alan Brooks on 03/14/2024, MRN 4829130, cell 312-555-0182, ZIP 60614.\n",
+        )
+        .expect("write");
+
+    let output = child.checked_wait();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+        "Seen by [PERSON] on 03/14/2024, [MRN], cell [PHONE], ZIP 606**."
+    );
+    let stats: serde_json::Value = serde_json::from_slice(&output.stderr).expect("stats json");
+    assert_eq!(stats["profile"], "limited-dataset");
+    assert_eq!(
+        (
+            &stats["persons"],
+            &stats["mrn"],
+            &stats["phones"],
+            &stats["dates"]
+        ),
+        (&1.into(), &1.into(), &1.into(), &0.into())
+    );
+}
+
+#[test]
+fn audit_log_holds_removed_text_that_the_output_does_not() {
+    let dir = std::env::temp_dir().join(format!("audit-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("dir");
+    let input = dir.join("note.txt");
+    let audit = dir.join("audit.ndjson");
+    std::fs::write(&input, "Reach jane.doe@example.com or 312-555-0182.\n").expect("input");
+
+    let output = scrubber()
+        .arg("-i")
+        .arg(&input)
+        .args(["-o", "-", "--quiet", "--audit-log"])
+        .arg(&audit)
+        .checked_output();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim_end(), "Reach [EMAIL] or [PHONE].");
+
+    let log = std::fs::read_to_string(&audit).expect("audit log");
+    let records: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json line"))
+        .collect();
+    let originals: Vec<&str> = records
+        .iter()
+        .map(|record| record["original"].as_str().expect("original"))
+        .collect();
+    assert_eq!(originals, ["jane.doe@example.com", "312-555-0182"]);
+    assert_eq!(records[0]["category"], "email");
+    assert_eq!(records[0]["start"], 6);
+    assert_eq!(records[0]["source"], input.display().to_string());
+    assert_eq!(records[0]["schema_version"], 1);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&audit)
+            .expect("metadata")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let config = dir.join("capped.json");
+    std::fs::write(&config, r#"{"max_spans": 1}"#).expect("config");
+    let capped = scrubber()
+        .arg("-i")
+        .arg(&input)
+        .args(["-o", "-", "--quiet", "--schema-version", "2", "--config"])
+        .arg(&config)
+        .arg("--audit-log")
+        .arg(&audit)
+        .checked_output();
+    assert!(capped.status.success(), "{:?}", capped);
+    let log = std::fs::read_to_string(&audit).expect("audit log");
+    assert_eq!(log.lines().count(), 2);
+    assert!(log
+        .lines()
+        .all(|line| line.contains(r#""schema_version":2"#)));
+
+    let rejected = scrubber()
+        .arg("-i")
+        .arg(&input)
+        .args(["-o", "-", "--audit-log", "-"])
+        .checked_output();
+    assert!(!rejected.status.success());
+    assert!(rejected.stdout.is_empty());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn diff_shows_removed_phi_on_minus_lines_only_with_log_phi() {
+    let input = "Reach jane.doe@example.com or 312-555-0182.\nFollow up in clinic.\n";
+    let run = |extra: &[&str]| {
+        let mut child = scrubber()
+            .args(["-i", "-", "--quiet", "--diff", "--skip", "email"])
+            .args(extra)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(input.as_bytes())
+            .expect("write");
+        let output = child.checked_wait();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).expect("utf8")
+    };
+
+    let diff = run(&["--log-phi"]);
+    assert!(
+        diff.contains("\n-Reach jane.doe@example.com or 312-555-0182.\n"),
+        "{}",
+        diff
+    );
+    assert!(diff.contains("\n+Reach jane.doe@example.com or [PHONE].\n"));
+    assert!(diff.contains("\n Follow up in clinic.\n"));
+
+    let hidden = run(&[]);
+    assert!(!hidden.contains("312-555-0182"), "{}", hidden);
+    assert!(hidden.contains("\n-Reach jane.doe@example.com or «redacted».\n"));
+}
+
+#[test]
+fn stats_report_accumulates_files_and_a_total_across_runs() {
+    let dir = std::env::temp_dir().join(format!("stats-report-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("dir");
+    let report = dir.join("report.json");
+    let _ = std::fs::remove_file(&report);
+    let notes = [
+        ("a.txt", "Call 312-555-0182.\n"),
+        ("b.txt", "Email jane.doe@example.com or 312-555-0182.\n"),
+    ];
+    for (name, text) in notes {
+        let input = dir.join(name);
+        std::fs::write(&input, text).expect("input");
+        let output = scrubber()
+            .arg("-i")
+            .arg(&input)
+            .args([
+                "-o",
+                "-",
+                "--quiet",
+                "--schema-version",
+                "2",
+                "--stats-report",
+            ])
+            .arg(&report)
+            .checked_output();
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).expect("report")).expect("json");
+    let a = dir.join("a.txt").display().to_string();
+    assert_eq!(json["files"][&a]["counts"]["phone"], 1);
+    assert_eq!(json["total"]["counts"]["phone"], 2);
+    assert_eq!(json["total"]["counts"]["email"], 1);
+    assert_eq!(json["total"]["total"], 3);
+    assert!(json["generated_at"]
+        .as_str()
+        .expect("timestamp")
+        .ends_with('Z'));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn fail_on_phi_exits_3_only_for_the_listed_categories() {
+    let dir = std::env::temp_dir().join(format!("fail-on-phi-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("dir");
+    let clean = dir.join("clean.txt");
+    let phone = dir.join("phone.txt");
+    std::fs::write(&clean, "Vitals stable, follow up in clinic.\n").expect("clean");
+    std::fs::write(&phone, "Call 312-555-0182.\n").expect("phone");
+    let run = |input: &std::path::Path, extra: &[&str]| {
+        scrubber()
+            .arg("-i")
+            .arg(input)
+            .args(["-o", "-"])
+            .args(extra)
+            .checked_output()
+    };
+
+    let output = run(&clean, &["--fail-on-phi"]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+
+    let output = run(&phone, &["--fail-on-phi", "person,ssn"]);
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+
+    let output = run(&phone, &["--fail-on-phi", "ssn,phone"]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+        "Call [PHONE]."
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("phones         : 1"), "{}", stderr);
+    assert!(stderr.contains("failing per --fail-on-phi"), "{}", stderr);
+
+    let output = scrubber()
+        .arg("-i")
+        .arg(&phone)
+        .args(["--fail-on-phi", "--dry-run"])
+        .checked_output();
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    assert!(output.stdout.is_empty());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn stats_output_writes_json_stats_to_a_file_and_refuses_the_output_path() {
+    let dir = std::env::temp_dir().join(format!("stats-file-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("dir");
+    let input = dir.join("note.txt");
+    let stats = dir.join("stats.json");
+    std::fs::write(&input, "Call 312-555-0182.\n").expect("input");
+
+    let output = scrubber()
+        .arg("-i")
+        .arg(&input)
+        .args(["-o", "-", "--stats-json", "--stats-output"])
+        .arg(&stats)
+        .checked_output();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&stats).expect("stats")).expect("json");
+    assert_eq!(json["phones"], 1);
+    assert!(!dir.join("stats.json.partial").exists());
+
+    let output = scrubber()
+        .arg("-i")
+        .arg(&input)
+        .arg("-o")
+        .arg(dir.join("same.txt"))
+        .arg("--stats-output")
+        .arg(dir.join(".").join("same.txt"))
+        .checked_output();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("both point at"), "{}", stderr);
+    assert!(!dir.join("same.txt").exists());
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn csv_stats_for_the_sample_note() {
+    let output = scrubber()
+        .args(["-i", "tests/fixtures/sample_note.txt", "-o", "-"])
+        .args(["--stats-format", "csv"])
+        .checked_output();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "\
+file,category,count
+tests/fixtures/sample_note.txt,email,1
+tests/fixtures/sample_note.txt,phone,1
+tests/fixtures/sample_note.txt,date,1
+tests/fixtures/sample_note.txt,mrn,1
+tests/fixtures/sample_note.txt,zip,1
+tests/fixtures/sample_note.txt,person,3
+"
+    );
+}
+
+#[test]
+fn preview_marks_matches_in_the_original_text() {
+    let output = scrubber()
+        .args(["-i", "tests/fixtures/sample_note.txt", "--quiet"])
+        .args(["--preview", "--log-phi"])
+        .checked_output();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\
+Patient «PERSON:Maria» «PERSON:Lopez» («MRN:MRN 4829130») was seen on «DATE:03/14/2024».
+Call «PHONE:312-555-0182» or email «EMAIL:maria.lopez@example.com» with results.
+Encounter ENC-2024-«ZIP:00123» was closed by «PERSON:Dr. Alan Brooks».
+"
+    );
+
+    let refused = scrubber()
+        .args(["-i", "tests/fixtures/sample_note.txt", "--preview"])
+        .checked_output();
+    assert!(!refused.status.success());
+    assert!(refused.stdout.is_empty());
+}
+
+#[test]
+fn environment_fills_in_settings_the_command_line_leaves_unset() {
+    let config = std::env::temp_dir().join(format!("env-{}.json", std::process::id()));
+    std::fs::write(&config, r#"{"skip": ["email"]}"#).expect("config");
+    let run = |env: &[(&str, &str)], args: &[&str]| {
+        let mut child = scrubber()
+            .args(["-i", "-", "-o", "-", "--quiet"])
+            .args(args)
+            .envs(env.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(b"On 03/14/2024 zelda wrote jane.doe@example.com from ZIP 60614.\n")
+            .expect("write");
+        let output = child.checked_wait();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string()
+    };
+    let config = config.to_str().expect("utf-8 path");
+    let redacted = "On [DATE] zelda wrote [EMAIL] from ZIP [ZIP].";
+
+    assert_eq!(run(&[], &[]), redacted);
+    assert_eq!(
+        run(&[("CLINICAL_SCRUBBER_CONFIG", config)], &[]),
+        "On [DATE] zelda wrote jane.doe@example.com from ZIP [ZIP]."
+    );
+    assert_eq!(
+        run(&[("CLINICAL_SCRUBBER_SKIP", "zip, date")], &[]),
+        "On 03/14/2024 zelda wrote [EMAIL] from ZIP 60614."
+    );
+    assert_eq!(
+        run(&[("CLINICAL_SCRUBBER_SKIP", "zip")], &["--skip", "email"]),
+        "On [DATE] zelda wrote jane.doe@example.com from ZIP [ZIP]."
+    );
+    assert_eq!(
+        run(&[("CLINICAL_SCRUBBER_PROFILE", "limited-dataset")], &[]),
+        "On 03/14/2024 zelda wrote [EMAIL] from ZIP 606**."
+    );
+    assert_eq!(
+        run(
+            &[("CLINICAL_SCRUBBER_PROFILE", "limited-dataset")],
+            &["--regulation", "standard"]
+        ),
+        redacted
+    );
+    assert_eq!(
+        run(&[("CLINICAL_SCRUBBER_NAMES", "Zelda,Quinn")], &[]),
+        "On [DATE] [PERSON] wrote [EMAIL] from ZIP [ZIP]."
+    );
+    assert_eq!(
+        run(
+            &[
+                ("CLINICAL_SCRUBBER_CONFIG", config),
+                ("CLINICAL_SCRUBBER_NAMES", "Zelda"),
+            ],
+            &["--no-env"]
+        ),
+        redacted
+    );
+    std::fs::remove_file(config).ok();
+}
+
+#[test]
+fn invalid_environment_values_name_the_variable() {
+    let output = scrubber()
+        .args(["-i", "tests/fixtures/sample_note.txt", "-o", "-"])
+        .env("CLINICAL_SCRUBBER_PROFILE", "lenient")
+        .checked_output();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("CLINICAL_SCRUBBER_PROFILE"), "{}", stderr);
+}
+
+#[test]
+fn config_subcommand_prints_resolved_settings() {
+    for config in ["json", "yaml", "toml"] {
+        let output = scrubber()
+            .args(["config", "--config"])
+            .arg(format!("tests/fixtures/scrubber_config.{}", config))
+            .args(["--skip", "url"])
+            .checked_output();
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            include_str!("fixtures/resolved_config.json"),
+            "{}",
+            config
+        );
+    }
+}
+
+#[test]
+fn json_yaml_and_toml_configs_scrub_alike() {
+    let scrubbed: Vec<String> = ["json", "yaml", "toml"]
+        .iter()
+        .map(|config| {
+            let output = scrubber()
+                .args(["-i", "tests/fixtures/sample_note.txt", "-o", "-", "--quiet"])
+                .arg("--config")
+                .arg(format!("tests/fixtures/scrubber_config.{}", config))
+                .checked_output();
+            assert!(output.status.success(), "{:?}", output);
+            String::from_utf8(output.stdout).expect("utf-8")
+        })
+        .collect();
+    assert!(scrubbed[0].contains("[NAME]"), "{}", scrubbed[0]);
+    assert_eq!(scrubbed[1], scrubbed[0]);
+    assert_eq!(scrubbed[2], scrubbed[0]);
+}
+
+#[test]
+fn input_dir_scrubs_every_file_and_reports_a_failure_without_stopping() {
+    let dir = std::env::temp_dir().join(format!("batch-cli-{}", std::process::id()));
+    let input = dir.join("in");
+    let out = dir.join("out");
+    std::fs::create_dir_all(&input).expect("dir");
+    let _ = std::fs::remove_dir_all(&out);
+    std::fs::write(input.join("a.txt"), "Call 312-555-0182.\n").expect("input");
+    std::fs::write(input.join("b.bin"), b"\xff\xfe not utf-8\n").expect("input");
+    std::fs::write(input.join("c.txt"), "Email jane.doe@example.com.\n").expect("input");
+
+    let run = |extra: &[&str]| {
+        scrubber()
+            .arg("--input-dir")
+            .arg(&input)
+            .arg("--output-dir")
+            .arg(&out)
+            .args(extra)
+            .checked_output()
+    };
+    let stats = dir.join("stats.json");
+    let output = run(&[
+        "--schema-version",
+        "2",
+        "--stats-format",
+        "json",
+        "--stats-output",
+        stats.to_str().expect("path"),
+    ]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 of 3 files failed"), "{}", stderr);
+    assert_eq!(
+        std::fs::read_to_string(out.join("a.txt")).expect("a"),
+        "Call [PHONE].\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(out.join("c.txt")).expect("c"),
+        "Email [EMAIL].\n"
+    );
+    assert!(!out.join("b.bin").exists());
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&stats).expect("stats")).expect("json");
+    let a = input.join("a.txt").display().to_string();
+    assert_eq!(json["files"][&a]["counts"]["phone"], 1);
+    assert_eq!(json["total"]["total"], 2);
+    assert_eq!(json["failed"][0], input.join("b.bin").display().to_string());
+
+    std::fs::remove_dir_all(&out).ok();
+    let output = run(&["--fail-fast", "--quiet"]);
+    assert!(!output.status.success());
+    assert!(out.join("a.txt").exists());
+    assert!(!out.join("c.txt").exists());
+
+    let output = scrubber()
+        .arg("--input-dir")
+        .arg(&input)
+        .arg("--output-dir")
+        .arg(input.join("."))
+        .checked_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pick separate directories"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn several_input_files_and_patterns_need_an_output_dir() {
+    let dir = std::env::temp_dir().join(format!("multi-input-cli-{}", std::process::id()));
+    let out = dir.join("out");
+    let notes = dir.join("notes");
+    std::fs::create_dir_all(notes.join("more")).expect("dir");
+    std::fs::write(notes.join("b.txt"), "Call 312-555-0182.\n").expect("input");
+    std::fs::write(notes.join("a.txt"), "Email jane.doe@example.com.\n").expect("input");
+    std::fs::write(notes.join("more/c.txt"), "Seen 03/14/2024.\n").expect("input");
+
+    let output = scrubber()
+        .arg(notes.join("b.txt"))
+        .arg(notes.join("a.txt"))
+        .args(["-o", "-"])
+        .checked_output();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("2 inputs need --output-dir or --in-place"),
+        "{}",
+        stderr
+    );
+
+    let output = scrubber()
+        .arg(notes.join("b.txt"))
+        .arg(notes.join("a.txt"))
+        .arg("--output-dir")
+        .arg(&out)
+        .checked_output();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let (a, b) = (
+        stderr.find("a.txt: 1 redaction").expect("a listed"),
+        stderr.find("b.txt: 1 redaction").expect("b listed"),
+    );
+    assert!(a < b, "{}", stderr);
+    assert_eq!(
+        std::fs::read_to_string(out.join("b.txt")).expect("b"),
+        "Call [PHONE].\n"
+    );
+
+    // A quoted pattern is expanded by the tool, including `**` for subdirectories.
+    let output = scrubber()
+        .arg("--input")
+        .arg(format!("{}/**/*.txt", notes.display()))
+        .arg("--output-dir")
+        .arg(&out)
+        .arg("--quiet")
+        .checked_output();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        std::fs::read_to_string(out.join("c.txt")).expect("c"),
+        "Seen [DATE].\n"
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn in_place_renames_the_scrubbed_text_over_the_input_and_keeps_a_backup() {
+    let dir = std::env::temp_dir().join(format!("in-place-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("dir");
+    let note = dir.join("note.txt");
+    let original = "Call 312-555-0182.\r\n\tEmail jane.doe@example.com.\n";
+    std::fs::write(&note, original).expect("input");
+    // A hard link keeps the old file's contents only if the scrubbed text arrives by rename
+    // rather than by rewriting the file where it is.
+    let link = dir.join("link.txt");
+    let _ = std::fs::remove_file(&link);
+    std::fs::hard_link(&note, &link).expect("link");
+
+    let output = scrubber()
+        .arg(&note)
+        .args(["--in-place=.bak", "--quiet"])
+        .checked_output();
+    assert!(output.status.success(), "{:?}", output);
+    let scrubbed = std::fs::read_to_string(&note).expect("note");
+    assert!(scrubbed.contains("[PHONE]") && scrubbed.contains("[EMAIL]"));
+    assert_eq!(std::fs::read_to_string(&link).expect("link"), original);
+    assert_eq!(
+        std::fs::read(dir.join("note.txt.bak")).expect("backup"),
+        original.as_bytes()
+    );
+    let leftovers: Vec<_> = std::fs::read_dir(&dir)
+        .expect("dir")
+        .map(|entry| entry.expect("entry").file_name())
+        .filter(|name| name.to_string_lossy().ends_with(".partial"))
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+
+    let output = scrubber()
+        .arg(&note)
+        .args(["--in-place", "-o", "-"])
+        .checked_output();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--in-place") && stderr.contains("--output"),
+        "{}",
+        stderr
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn stream_output_and_stats_match_a_whole_document_scrub() {
+    let lines = [
+        "Call 312-555-0182 about the results.",
+        "  Email jane.doe@example.com or page Dr. Alan Brooks.",
+        "",
+        "Follow-up on 03/14/2024 for MRN: 4829130.",
+    ];
+    let input: String = (0..5_000)
+        .map(|i| format!("{}\n", lines[i % lines.len()]))
+        .collect();
+    let run = |extra: &[&str]| {
+        let mut child = scrubber()
+            .args([
+                "-i",
+                "-",
+                "-o",
+                "-",
+                "--stats-format",
+                "json",
+                "--seed",
+                "7",
+            ])
+            .args(extra)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        let mut stdin = child.stdin.take().expect("stdin");
+        let text = input.clone();
+        let writer = thread::spawn(move || {
+            stdin.write_all(text.as_bytes()).expect("write");
+        });
+        let output = child.checked_wait();
+        writer.join().expect("writer");
+        assert!(output.status.success(), "{:?}", output.status);
+        let stats: serde_json::Value = serde_json::from_slice(&output.stderr).expect("json stats");
+        (output.stdout, stats)
+    };
+
+    let (whole, whole_stats) = run(&[]);
+    let (streamed, stream_stats) = run(&["--stream"]);
+    assert!(streamed == whole, "streamed output differs");
+    assert_eq!(stream_stats, whole_stats);
+    assert_eq!(stream_stats["phones"], 1_250);
+}
+
+#[test]
+fn jsonl_scrubs_only_the_named_fields_and_passes_bad_lines_through() {
+    let input = concat!(
+        r#"{"note_id": 1, "payload": {"note": {"text": "Call Jane Doe at 312-555-0182."}}, "author": "Jane Doe"}"#,
+        "\n",
+        "{\"note_id\": 2, \"payload\": \n",
+        r#"{"note_id":3,"title":"Seen 03/14/2024","payload":{"note":{"text":"Email jane.doe@example.com"}}}"#,
+        "\n",
+    );
+    let mut child = scrubber()
+        .args(["-i", "-", "-o", "-", "--format", "jsonl"])
+        .args(["--field", "payload.note.text", "--field", "title"])
+        .args([
+            "--attach-stats",
+            "--schema-version",
+            "2",
+            "--stats-format",
+            "json",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(input.as_bytes())
+        .expect("write");
+    let output = child.checked_wait();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines[0],
+        r#"{"note_id": 1, "payload": {"note": {"text": "Call [PERSON] at [PHONE]."}}, "author": "Jane Doe","_scrub_stats":{"counts":{"person":1,"phone":1},"total":2}}"#
+    );
+    assert_eq!(lines[1], "{\"note_id\": 2, \"payload\": ");
+    assert_eq!(
+        lines[2],
+        r#"{"note_id":3,"title":"Seen [DATE]","payload":{"note":{"text":"Email [EMAIL]"}},"_scrub_stats":{"counts":{"date":1,"email":1},"total":2}}"#
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("-:2: not valid JSON"), "{}", stderr);
+    let json_start = stderr.find('{').expect("stats json");
+    let stats: serde_json::Value = serde_json::from_str(&stderr[json_start..]).expect("json");
+    assert_eq!(stats["total"], 4);
+    assert_eq!(stats["parse_errors"], 1);
+}
+
+#[test]
+fn csv_scrubs_the_named_columns_of_quoted_multi_line_fields() {
+    let dir = std::env::temp_dir().join(format!("csv-columns-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("dir");
+    let input = dir.join("notes.csv");
+    let output = dir.join("scrubbed.csv");
+    std::fs::write(
+        &input,
+        concat!(
+            "id,note_text,author,comments\n",
+            "1,\"Call Jane Doe, then\n312-555-0182.\",\"Doe, Jane\",\"Email jane.doe@example.com\"\n",
+            "2,Seen 03/14/2024,Dr. Smith,\n",
+        ),
+    )
+    .expect("input");
+
+    let run = |columns: &[&str]| {
+        let mut command = scrubber();
+        command
+            .arg("-i")
+            .arg(&input)
+            .arg("-o")
+            .arg(&output)
+            .args(["--format", "csv"]);
+        for column in columns {
+            command.args(["--column", column]);
+        }
+        command.checked_output()
+    };
+    let result = run(&["note_text", "3"]);
+    assert!(result.status.success(), "{:?}", result);
+    assert_eq!(
+        std::fs::read_to_string(&output).expect("output"),
+        concat!(
+            "id,note_text,author,comments\n",
+            "1,\"Call [PERSON], then\n[PHONE].\",\"Doe, Jane\",\"Email [EMAIL]\"\n",
+            "2,Seen [DATE],Dr. Smith,\n",
+        )
+    );
+
+    let missing = run(&["note_txt"]);
+    assert!(!missing.status.success());
+    let stderr = String::from_utf8_lossy(&missing.stderr);
+    assert!(
+        stderr.contains("column \"note_txt\" is not in the header, which has: id, note_text"),
+        "{}",
+        stderr
+    );
+    let out_of_range = run(&["4"]);
+    assert!(String::from_utf8_lossy(&out_of_range.stderr).contains("column 4 is out of range"));
+    let no_column = run(&[]);
+    assert!(String::from_utf8_lossy(&no_column.stderr)
+        .contains("--format csv needs at least one --column"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn parallel_batch_matches_a_serial_run_byte_for_byte() {
+    let dir = std::env::temp_dir().join(format!("batch-jobs-{}", std::process::id()));
+    let input = dir.join("in");
+    std::fs::create_dir_all(&input).expect("dir");
+    let notes = [
+        "Call Jane Doe at 312-555-0182.\n",
+        "Email jane.doe@example.com on 03/14/2024.\n",
+        "MRN: 4829130. Seen by Dr. Alan Brooks.\n",
+    ];
+    for index in 0..24 {
+        std::fs::write(
+            input.join(format!("note{:02}.txt", index)),
+            notes[index % notes.len()].repeat(index + 1),
+        )
+        .expect("input");
+    }
+    std::fs::write(input.join("note99.bin"), b"\xff not utf-8\n").expect("input");
+
+    let run = |jobs: &str| {
+        let out = dir.join(format!("out-{}", jobs));
+        let _ = std::fs::remove_dir_all(&out);
+        let stats = dir.join(format!("stats-{}.json", jobs));
+        let output = scrubber()
+            .arg("--input-dir")
+            .arg(&input)
+            .arg("--output-dir")
+            .arg(&out)
+            .args(["--jobs", jobs, "--seed", "7", "--stats-format", "json"])
+            .arg("--stats-output")
+            .arg(&stats)
+            .checked_output();
+        assert!(!output.status.success());
+        let mut files: Vec<(String, Vec<u8>)> = std::fs::read_dir(&out)
+            .expect("out")
+            .map(|entry| {
+                let path = entry.expect("entry").path();
+                let name = path.file_name().expect("name").to_string_lossy().into();
+                (name, std::fs::read(&path).expect("output"))
+            })
+            .collect();
+        files.sort();
+        (
+            files,
+            std::fs::read_to_string(&stats).expect("stats"),
+            output.stderr,
+        )
+    };
+    let serial = run("1");
+    assert_eq!(serial.0.len(), 24);
+    assert_eq!(run("4"), serial);
+    assert_eq!(run("32"), serial);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn watch_scrubs_files_dropped_into_the_input_dir_until_interrupted() {
+    let dir = std::env::temp_dir().join(format!("watch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let (input, out) = (dir.join("in"), dir.join("out"));
+    std::fs::create_dir_all(&input).expect("dir");
+    std::fs::write(input.join("early.txt"), "Call 312-555-0182.\n").expect("input");
+
+    let child = scrubber()
+        .arg("--input-dir")
+        .arg(&input)
+        .arg("--output-dir")
+        .arg(&out)
+        .args(["--watch", "--poll-interval", "50", "--move-processed"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn");
+    let wait_for = |path: &std::path::Path| {
+        for _ in 0..200 {
+            if path.exists() {
+                return;
+            }
+            thread::sleep(std::time::Duration::from_millis(25));
+        }
+        panic!("{} never appeared", path.display());
+    };
+
+    wait_for(&out.join("early.txt"));
+    std::fs::write(input.join("late.txt.tmp"), "Email jane.doe@example.com.\n").expect("tmp");
+    thread::sleep(std::time::Duration::from_millis(300));
+    assert!(!out.join("late.txt.tmp").exists());
+    std::fs::rename(input.join("late.txt.tmp"), input.join("late.txt")).expect("rename");
+    wait_for(&input.join("processed/late.txt"));
+
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("kill");
+    assert!(status.success());
+    let output = child.checked_wait();
+    assert!(output.status.success(), "{:?}", output);
+
+    assert_eq!(
+        std::fs::read_to_string(out.join("early.txt")).expect("early"),
+        "Call [PHONE].\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(out.join("late.txt")).expect("late"),
+        "Email [EMAIL].\n"
+    );
+    assert!(input.join("processed/early.txt").exists());
+    assert!(!input.join("early.txt").exists() && !input.join("late.txt").exists());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Total for 2 files:"), "{}", stderr);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn interrupted_batch_keeps_the_files_it_finished() {
+    let dir = std::env::temp_dir().join(format!("interrupt-batch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let input = dir.join("in");
+    std::fs::create_dir_all(&input).expect("dir");
+    let note =
+        "Seen by Dr. Alan Brooks on 03/14/2024, MRN 4829130, call 312-555-0182.\n".repeat(2000);
+    let files = 20;
+    for index in 0..files {
+        std::fs::write(input.join(format!("note{:02}.txt", index)), &note).expect("input");
+    }
+    // Sends SIGINT once note00 is written and returns the run and the outputs it kept.
+    let interrupt = |out: &std::path::Path, extra: &[&std::ffi::OsStr]| {
+        let child = scrubber()
+            .arg("--input-dir")
+            .arg(&input)
+            .arg("--output-dir")
+            .arg(out)
+            .args(["--jobs", "1", "--quiet"])
+            .args(extra)
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        for _ in 0..2000 {
+            if out.join("note00.txt").exists() {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let status = Command::new("kill")
+            .args(["-INT", &child.id().to_string()])
+            .status()
+            .expect("kill");
+        assert!(status.success());
+        let output = child.checked_wait();
+        assert_eq!(output.status.code(), Some(130), "{:?}", output);
+
+        let written: Vec<String> = std::fs::read_dir(out)
+            .expect("out")
+            .map(|entry| {
+                entry
+                    .expect("entry")
+                    .file_name()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        assert!(
+            !written.is_empty() && written.len() < files,
+            "{:?}",
+            written
+        );
+        for name in &written {
+            assert!(name.ends_with(".txt"), "{:?}", written);
+            let scrubbed = std::fs::read_to_string(out.join(name)).expect("output");
+            assert_eq!(scrubbed.lines().count(), 2000, "{}", name);
+            assert!(!scrubbed.contains("4829130"), "{}", name);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!(
+                "interrupted after {} of {} files; their output was kept",
+                written.len(),
+                files
+            )),
+            "{}",
+            stderr
+        );
+        written
+    };
+
+    // note00 was written before the signal and note01 was in flight, so both are kept.
+    let written = interrupt(&dir.join("out"), &[]);
+    assert!(written.len() >= 2, "{:?}", written);
+
+    // Hashing for the manifest can hold the signal off until note01 starts, so only the
+    // manifest is checked here.
+    let manifest = dir.join("manifest.json");
+    let written = interrupt(
+        &dir.join("out-manifest"),
+        &["--manifest".as_ref(), manifest.as_os_str()],
+    );
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&manifest).expect("manifest")).expect("json");
+    assert_eq!(manifest["interrupted"], true);
+    assert_eq!(
+        manifest["files"].as_array().expect("files").len(),
+        written.len()
+    );
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn dry_run_reports_counts_and_spans_without_touching_the_filesystem() {
+    let dir = std::env::temp_dir().join(format!("dry-run-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let notes = dir.join("notes");
+    std::fs::create_dir_all(&notes).expect("dir");
+    let note = notes.join("note.txt");
+    let text = "Call Jane Doe at 312-555-0182 or jane.doe@example.com.\n";
+    std::fs::write(&note, text).expect("input");
+    let listing = || {
+        let mut names: Vec<_> = std::fs::read_dir(&dir)
+            .expect("dir")
+            .chain(std::fs::read_dir(&notes).expect("notes"))
+            .map(|entry| entry.expect("entry").path())
+            .collect();
+        names.sort();
+        names
+    };
+    let before = listing();
+
+    let output = scrubber()
+        .arg("-i")
+        .arg(&note)
+        .args(["--dry-run", "--spans-out", "-", "--fail-on-phi"])
+        .args(["--schema-version", "2", "--stats-format", "json"])
+        .checked_output();
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let categories: Vec<String> = stdout
+        .lines()
+        .map(|line| {
+            let span: serde_json::Value = serde_json::from_str(line).expect("span");
+            span["category"].as_str().expect("category").to_string()
+        })
+        .collect();
+    assert_eq!(categories, ["person", "phone", "email"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stats: serde_json::Value =
+        serde_json::Deserializer::from_str(&stderr[stderr.find('{').expect("stats")..])
+            .into_iter()
+            .next()
+            .expect("stats")
+            .expect("json");
+    assert_eq!(stats["total"], 3);
+    assert_eq!(stats["counts"]["phone"], 1);
+
+    let batch = scrubber()
+        .arg("--input-dir")
+        .arg(&notes)
+        .arg("--output-dir")
+        .arg(dir.join("out"))
+        .args(["--dry-run", "--quiet"])
+        .checked_output();
+    assert!(batch.status.success(), "{:?}", batch);
+    assert_eq!(listing(), before);
+    assert_eq!(std::fs::read_to_string(&note).expect("note"), text);
+
+    for extra in [&["-o", "scrubbed.txt"][..], &["--in-place"][..]] {
+        let refused = scrubber()
+            .arg("-i")
+            .arg(&note)
+            .arg("--dry-run")
+            .args(extra)
+            .current_dir(&dir)
+            .checked_output();
+        assert!(!refused.status.success());
+        assert!(String::from_utf8_lossy(&refused.stderr).contains("cannot be used with"));
+    }
+    assert_eq!(listing(), before);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn recursive_input_dir_mirrors_the_tree_and_skips_binary_and_other_extensions() {
+    let dir = std::env::temp_dir().join(format!("recursive-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let (input, elsewhere) = (dir.join("notes"), dir.join("elsewhere"));
+    for sub in ["2023/01", "2023/02", "2024"] {
+        std::fs::create_dir_all(input.join(sub)).expect("dir");
+    }
+    std::fs::create_dir_all(&elsewhere).expect("dir");
+    std::fs::write(input.join("2023/01/visit.txt"), "Call 312-555-0182.\n").expect("txt");
+    std::fs::write(
+        input.join("2023/02/summary.MD"),
+        "Email jane.doe@example.com.\n",
+    )
+    .expect("md");
+    std::fs::write(input.join("intake.hl7"), "PID|||4829130\n").expect("hl7");
+    std::fs::write(input.join("2024/scan.pdf"), "%PDF-1.7 Jane Doe\n").expect("pdf");
+    std::fs::write(
+        input.join("2024/decoy.txt"),
+        b"Jane Doe\0\x01\x02 312-555-0182",
+    )
+    .expect("bin");
+    std::fs::write(elsewhere.join("linked.txt"), "Call 312-555-0182.\n").expect("linked");
+    std::os::unix::fs::symlink(&elsewhere, input.join("shared")).expect("symlink");
+
+    let run = |out: &std::path::Path, extra: &[&str]| {
+        scrubber()
+            .arg("--input-dir")
+            .arg(&input)
+            .arg("--output-dir")
+            .arg(out)
+            .args(["--recursive", "--ext", "txt,md,.hl7"])
+            .args(extra)
+            .checked_output()
+    };
+    let out = dir.join("out");
+    let output = run(&out, &["--quiet"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "warning: skipping {}: looks binary",
+            input.join("2024/decoy.txt").display()
+        )),
+        "{}",
+        stderr
+    );
+    assert_eq!(
+        std::fs::read_to_string(out.join("2023/01/visit.txt")).expect("visit"),
+        "Call [PHONE].\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(out.join("2023/02/summary.MD")).expect("summary"),
+        "Email [EMAIL].\n"
+    );
+    assert!(out.join("intake.hl7").exists());
+    assert!(!out.join("2024").exists());
+    assert!(!out.join("shared").exists());
+
+    let followed = dir.join("followed");
+    let output = run(&followed, &["--quiet", "--follow-symlinks"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        std::fs::read_to_string(followed.join("shared/linked.txt")).expect("linked"),
+        "Call [PHONE].\n"
+    );
+
+    let clash = dir.join("clash");
+    let output = scrubber()
+        .arg(input.join("2023/01/visit.txt"))
+        .arg(elsewhere.join("linked.txt"))
+        .arg(input.join("shared/linked.txt"))
+        .arg("--output-dir")
+        .arg(&clash)
+        .checked_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("would both be written to"));
+    assert_eq!(std::fs::read_dir(&clash).expect("clash").count(), 0);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn output_keeps_the_input_line_endings_unless_normalized() {
+    let run = |input: &str, extra: &[&str]| {
+        let mut child = scrubber()
+            .args(["-i", "-", "-o", "-", "--quiet"])
+            .args(extra)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(input.as_bytes())
+            .expect("write");
+        let output = child.checked_wait();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).expect("utf8")
+    };
+
+    let crlf = "Call 312-555-0182.\r\nEmail jane.doe@example.com\r\n";
+    assert_eq!(run(crlf, &[]), "Call [PHONE].\r\nEmail [EMAIL]\r\n");
+    assert_eq!(
+        run(crlf, &["--stream"]),
+        "Call [PHONE].\r\nEmail [EMAIL]\r\n"
+    );
+    assert_eq!(run("Call 312-555-0182.", &[]), "Call [PHONE].");
+    assert_eq!(run("", &[]), "");
+    assert_eq!(
+        run(crlf, &["--normalize-newlines"]),
+        "Call [PHONE].\r\nEmail [EMAIL]"
+    );
+}
+
+#[test]
+fn null_separated_records_are_scrubbed_apart_with_stats_per_record() {
+    let mut child = scrubber()
+        .args([
+            "-i",
+            "-",
+            "-o",
+            "-",
+            "--stream",
+            "--record-separator",
+            "null",
+        ])
+        .args(["--stats-per-record", "--quiet"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(b"Seen by Dr. Alan Brooks.\0Call 312-555-0182.\0Email jane.doe@example.com\0")
+        .expect("write");
+    let output = child.checked_wait();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Seen by [PERSON].\0Call [PHONE].\0Email [EMAIL]\0"
+    );
+    let records: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json line"))
+        .collect();
+    assert_eq!(records.len(), 3);
+    let fired: Vec<(u64, u64, u64, u64)> = records
+        .iter()
+        .map(|record| {
+            let stats = &record["stats"];
+            (
+                record["record"].as_u64().expect("record"),
+                stats["persons"].as_u64().unwrap_or(0),
+                stats["phones"].as_u64().unwrap_or(0),
+                stats["emails"].as_u64().unwrap_or(0),
+            )
+        })
+        .collect();
+    assert_eq!(fired, [(1, 1, 0, 0), (2, 0, 1, 0), (3, 0, 0, 1)]);
+}
+
+#[test]
+fn seed_makes_runs_repeatable_and_is_reported_when_drawn() {
+    let note = "Seen 03/14/2024 by Dr. Alan Brooks; follow-up 04/02/2024.\n";
+    let run = |extra: &[&str]| {
+        let mut child = scrubber()
+            .args(["-i", "-", "-o", "-", "--stats-json"])
+            .args(extra)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(note.as_bytes())
+            .expect("write");
+        let output = child.checked_wait();
+        assert!(output.status.success(), "{:?}", output);
+        let stats: serde_json::Value = serde_json::from_slice(&output.stderr).expect("json");
+        (output.stdout, stats)
+    };
+
+    // The seed only seeds what is randomized; without `date_shift`, dates stay redacted.
+    for mode in [&[][..], &["--safe-harbor"]] {
+        let (unseeded, _) = run(mode);
+        assert!(String::from_utf8_lossy(&unseeded).contains("follow-up [DATE]"));
+        assert_eq!(run(&[mode, &["--seed", "42"]].concat()).0, unseeded);
+    }
+
+    let config = std::env::temp_dir().join(format!("drawn-seed-{}.json", std::process::id()));
+    std::fs::write(&config, r#"{"date_shift": {}}"#).expect("config");
+    let config = config.to_str().expect("path");
+    let first = run(&["--config", config, "--seed", "42"]);
+    assert_eq!(run(&["--config", config, "--seed", "42"]), first);
+    assert_eq!(first.1["seed"], 42);
+    assert_ne!(run(&["--config", config, "--seed", "43"]).0, first.0);
+
+    // A config that shifts dates without a seed uses the one drawn for the run, and the stats
+    // report it so the run can be repeated.
+    let (output, drawn) = run(&["--config", config]);
+    assert!(!String::from_utf8_lossy(&output).contains("[DATE]"));
+    let seed = drawn["seed"].as_u64().expect("drawn seed").to_string();
+    assert_eq!(run(&["--config", config, "--seed", &seed]).0, output);
+    std::fs::remove_file(config).ok();
+}
+
+#[test]
+fn manifest_records_the_hashes_of_every_input_and_output() {
+    use clinical_scrubber::report::manifest::sha256_hex;
+
+    let dir = std::env::temp_dir().join(format!("manifest-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let (input, out) = (dir.join("in"), dir.join("out"));
+    std::fs::create_dir_all(&input).expect("dir");
+    std::fs::write(input.join("a.txt"), "Call 312-555-0182.\n").expect("input");
+    std::fs::write(input.join("b.txt"), "Email jane.doe@example.com.\n").expect("input");
+    std::fs::write(input.join("c.bin"), b"\x89PNG\0\0").expect("input");
+    let manifest = dir.join("manifest.json");
+    let run = |extra: &[&str]| {
+        scrubber()
+            .arg("--input-dir")
+            .arg(&input)
+            .arg("--output-dir")
+            .arg(&out)
+            .arg("--manifest")
+            .arg(&manifest)
+            .arg("--quiet")
+            .args(extra)
+            .checked_output()
+    };
+    let output = run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!dir.join("manifest.json.partial").exists());
+
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&manifest).expect("manifest")).expect("json");
+    assert_eq!(json["schema_version"], 1);
+    assert_eq!(json["tool_version"], env!("CARGO_PKG_VERSION"));
+    assert!(json["elapsed_ms"].is_u64());
+    let files = json["files"].as_array().expect("files");
+    assert_eq!(files.len(), 3);
+    let skipped = &files[2];
+    assert_eq!(skipped["status"], "skipped");
+    assert!(skipped["input"].as_str().expect("input").ends_with("c.bin"));
+    assert!(skipped.get("input_sha256").is_none() && skipped.get("output").is_none());
+    for file in &files[..2] {
+        assert_eq!(file["status"], "scrubbed");
+        let read = |key: &str| std::fs::read(file[key].as_str().expect(key)).expect("file");
+        assert_eq!(file["input_sha256"], sha256_hex(&read("input")));
+        assert_eq!(file["output_sha256"], sha256_hex(&read("output")));
+    }
+    assert_eq!(files[0]["stats"]["phones"], 1);
+    assert_eq!(json["total"]["phones"], 1);
+
+    // The config hash covers command-line overrides, not just the config files.
+    let config_hash = json["config_sha256"].clone();
+    assert_eq!(config_hash.as_str().map(str::len), Some(64));
+    assert!(run(&[]).status.success());
+    let again: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&manifest).expect("manifest")).expect("json");
+    assert_eq!(again["config_sha256"], config_hash);
+    assert!(run(&["--schema-version", "2"]).status.success());
+    let v2: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&manifest).expect("manifest")).expect("json");
+    assert_eq!(v2["schema_version"], 2);
+    assert!(run(&["--allow", "Ruth Hale"]).status.success());
+    let changed: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&manifest).expect("manifest")).expect("json");
+    assert_ne!(changed["config_sha256"], config_hash);
+    // So do the mode and the command line's skips, which apply after the config is merged.
+    for extra in [["--skip", "person"], ["--only", "phone"]] {
+        assert!(run(&extra).status.success());
+        let changed: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&manifest).expect("manifest"))
+                .expect("json");
+        assert_ne!(changed["config_sha256"], config_hash, "{:?}", extra);
+    }
+    assert!(run(&["--safe-harbor"]).status.success());
+    let changed: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&manifest).expect("manifest")).expect("json");
+    assert_ne!(changed["config_sha256"], config_hash);
+
+    let output = scrubber()
+        .args(["-i", "-", "-o", "-", "--manifest"])
+        .arg(&manifest)
+        .checked_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--manifest needs several inputs"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn batch_sends_one_webhook_event_per_file() {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    let dir = std::env::temp_dir().join(format!("webhook-batch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let (input, out) = (dir.join("in"), dir.join("out"));
+    std::fs::create_dir_all(&input).expect("dir");
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        std::fs::write(input.join(name), "Call 312-555-0182 for Jane Doe.\n").expect("input");
+    }
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let url = format!("http://{}/events", listener.local_addr().expect("addr"));
+    let server = thread::spawn(move || {
+        let mut bodies = Vec::new();
+        for _ in 0..3 {
+            let (stream, _) = listener.accept().expect("accept");
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).expect("read header");
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().expect("content length");
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).expect("read body");
+            write!(reader.get_mut(), "HTTP/1.1 204 No Content\r\n\r\n").expect("respond");
+            bodies.push(String::from_utf8(body).expect("utf8"));
+        }
+        bodies
+    });
+
+    let output = scrubber()
+        .arg("--input-dir")
+        .arg(&input)
+        .arg("--output-dir")
+        .arg(&out)
+        .args(["--quiet", "--webhook-url", &url])
+        .checked_output();
+    assert!(output.status.success(), "{:?}", output);
+
+    let mut documents: Vec<String> = server
+        .join()
+        .expect("server")
+        .iter()
+        .map(|body| {
+            assert!(!body.contains("Jane Doe") && !body.contains("312-555-0182"));
+            let event: serde_json::Value = serde_json::from_str(body).expect("json");
+            assert_eq!(event["stats"]["phones"], 1);
+            event["document"].as_str().expect("document").to_string()
+        })
+        .collect();
+    documents.sort();
+    let expected: Vec<String> = ["a.txt", "b.txt", "c.txt"]
+        .iter()
+        .map(|name| input.join(name).display().to_string())
+        .collect();
+    assert_eq!(documents, expected);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn interactive_review_refuses_stdin_input() {
+    let dir = std::env::temp_dir().join(format!("interactive-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("dir");
+    let note = dir.join("note.txt");
+    std::fs::write(&note, "Seen with Zorbin Quaxley.\n").expect("input");
+    let run =
+        |args: &[&std::ffi::OsStr]| scrubber().args(args).stdin(Stdio::null()).checked_output();
+
+    let output = run(&["--interactive".as_ref(), "-i".as_ref(), "-".as_ref()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("needs an --input file"));
+
+    // With the note in a file, answers still need a terminal on stdin.
+    let output = run(&["--interactive".as_ref(), "-i".as_ref(), note.as_os_str()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--interactive needs a terminal"));
+
+    let output = run(&["--save-decisions".as_ref(), dir.join("d.json").as_os_str()]);
+    assert!(!output.status.success());
+    std::fs::remove_dir_all(&dir).ok();
+}